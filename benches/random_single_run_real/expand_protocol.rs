@@ -44,6 +44,8 @@ fn main() {
     // WARM UP
     println!("--- START WARMUP ---");
     for _ in 0..N_WARMUP {
+        // seed of the run, such that a failing run can be replayed
+        let seed = rand::random();
         run_protocol_expand::<
             BasicConfig<InnerCurve, ZKPRng, ZKPScheme, RANDOMNESS_BYTES>,
             BasicConfigGadget<InnerCurve, InnerCurveVar>,
@@ -54,14 +56,16 @@ fn main() {
             RANDOMNESS_BYTES,
             K,
             IS_REAL_INPUT,
-        >(gamma.clone())
-        .unwrap()
+        >(gamma.clone(), Some(seed))
+        .unwrap_or_else(|e| panic!("Run with seed {seed} failed: {e}"))
     }
     println!("--- END WARMUP ---");
 
     // MEASUREMENTS
     println!("--- START MEASUREMENTS ---");
     for _ in 0..N_MEASURE {
+        // seed of the run, such that a failing run can be replayed
+        let seed = rand::random();
         run_protocol_expand::<
             BasicConfig<InnerCurve, ZKPRng, ZKPScheme, RANDOMNESS_BYTES>,
             BasicConfigGadget<InnerCurve, InnerCurveVar>,
@@ -72,8 +76,8 @@ fn main() {
             RANDOMNESS_BYTES,
             K,
             IS_REAL_INPUT,
-        >(gamma.clone())
-        .unwrap()
+        >(gamma.clone(), Some(seed))
+        .unwrap_or_else(|e| panic!("Run with seed {seed} failed: {e}"))
     }
     println!("--- END MEASUREMENTS ---");
 }
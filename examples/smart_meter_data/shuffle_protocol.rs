@@ -2,28 +2,25 @@
 use ark_bls12_381::Bls12_381;
 use ark_ed_on_bls12_381::constraints::EdwardsVar as JubJubVar;
 use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
-use ark_ff::{PrimeField, ToConstraintField};
+use ark_ff::ToConstraintField;
 use ark_groth16::Groth16;
 use ark_serialize::CanonicalDeserialize;
 use ark_std::Zero;
 use astro_float::{BigFloat, Consts, Radix, RoundingMode};
 use csv::StringRecord;
 use num_bigint::BigUint;
-use rand::{Rng, SeedableRng};
+use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
 use std::process;
 use std::str::FromStr;
-use vldp::circuits::CircuitShuffle;
-use vldp::client::ClientShuffle;
 use vldp::config::{BasicConfig, BasicConfigGadget, Config, ConfigGadget};
 use vldp::error::GenericError;
 use vldp::prelude::{
-    ClientCommitmentSchemeOutput, ClientSignatureSchemePublicKey, ClientSignatureSchemeSecretKey,
-    ConstraintField, CryptoRng, Error, PRFSchemeInput, ParametersShuffle, Proof, ProofSystemRng,
-    ProvingKey, ServerSignatureSchemePublicKey, ServerSignatureSchemeSignature, SignatureScheme,
-    ZKPRng,
+    ClientCommitmentSchemeOutput, ClientMerkleTreeRoot, ClientSignatureSchemePublicKey,
+    ConstraintField, Error, ParametersShuffle, Proof, RandomizedResponse,
+    ServerSignatureSchemePublicKey, ServerSignatureSchemeSignature,
 };
-use vldp::server::ServerShuffle;
+use vldp::run_random::{simulate_shuffle, SimulationSettings, TrueValueDistribution};
 
 const HOUSEHOLDS: u16 = 5566;
 const MAX_VALUE: f64 = 0.3527045043460217;
@@ -78,90 +75,39 @@ fn load_data() -> Result<Vec<SmartMeterRecord>, Error> {
     Ok(records)
 }
 
-fn setup<
-    Conf: Config,
-    ConfG: ConfigGadget<Conf>,
-    R: Rng + CryptoRng,
-    const INPUT_BYTES: usize,
-    const TIME_BYTES: usize,
-    const GAMMA_BYTES: usize,
-    const RANDOMNESS_BYTES: usize,
-    const K: u64,
-    const IS_REAL_INPUT: bool,
->(
-    gamma: BigFloat,
-    rng: &mut R,
-    zkp_rng: &mut ZKPRng<Conf>,
-) -> Result<
-    (
-        ParametersShuffle<Conf, GAMMA_BYTES>,
-        ProvingKey<Conf>,
-        ServerShuffle<
-            Conf,
-            INPUT_BYTES,
-            TIME_BYTES,
-            GAMMA_BYTES,
-            RANDOMNESS_BYTES,
-            K,
-            IS_REAL_INPUT,
-        >,
-        Vec<ClientSignatureSchemePublicKey<Conf>>,
-        Vec<ClientSignatureSchemeSecretKey<Conf>>,
-    ),
-    Error,
-> {
-    assert!(IS_REAL_INPUT || INPUT_BYTES + GAMMA_BYTES == RANDOMNESS_BYTES);
-    assert!(!IS_REAL_INPUT || 2 * INPUT_BYTES + GAMMA_BYTES == RANDOMNESS_BYTES);
-    assert!(K >= 2 && (K.ilog2() + 1) as usize <= INPUT_BYTES * 8);
-    assert!(INPUT_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
-    assert!(TIME_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
-
-    // setup
-    let parameters = ParametersShuffle::setup(gamma, rng)?;
-    let (proving_key, verifying_key) = CircuitShuffle::<
-        _,
-        ConfG,
-        INPUT_BYTES,
-        TIME_BYTES,
-        GAMMA_BYTES,
-        RANDOMNESS_BYTES,
-        K,
-        IS_REAL_INPUT,
-    >::keygen(parameters.clone(), zkp_rng)?;
-
-    // create server
-    let server = ServerShuffle::<
-        _,
-        INPUT_BYTES,
-        TIME_BYTES,
-        GAMMA_BYTES,
-        RANDOMNESS_BYTES,
-        K,
-        IS_REAL_INPUT,
-    >::new(parameters.clone(), verifying_key, rng)?;
-
-    // create clients
-    let mut client_public_keys = Vec::with_capacity(HOUSEHOLDS as usize);
-    let mut client_secret_keys = Vec::with_capacity(HOUSEHOLDS as usize);
-    for _ in 0..HOUSEHOLDS {
-        let (client_sig_pk, client_sig_sk) =
-            Conf::ClientSignatureScheme::keygen(&parameters.client_signature_scheme, rng)?;
-
-        client_public_keys.push(client_sig_pk);
-        client_secret_keys.push(client_sig_sk);
+/// Encode a (normalized) average energy in `[0, 1]` as an `INPUT_BYTES` wide real-valued input.
+fn encode_average_energy<const INPUT_BYTES: usize>(
+    average_energy: &BigFloat,
+) -> Result<BigUint, Error> {
+    if average_energy.is_zero() {
+        return Ok(BigUint::zero());
+    }
+    let precision = INPUT_BYTES * 8 * 2;
+    let input_as_string = average_energy
+        .mul_full_prec(
+            &BigFloat::from_u8(2, precision)
+                .powi(INPUT_BYTES * 8, precision, RoundingMode::Down)
+                .sub_full_prec(&BigFloat::from_u8(1, precision)),
+        )
+        .int()
+        .convert_to_radix(
+            Radix::Dec,
+            RoundingMode::None,
+            &mut Consts::new().expect("Constants cache initialization should not fail."),
+        )?
+        .1
+        .iter()
+        .map(|digit| digit.to_string())
+        .collect::<String>();
+    if input_as_string.is_empty() {
+        Ok(BigUint::zero())
+    } else {
+        Ok(BigUint::from_str(&input_as_string).expect("This parse should not fail."))
     }
-
-    Ok((
-        parameters,
-        proving_key,
-        server,
-        client_public_keys,
-        client_secret_keys,
-    ))
 }
 
 fn run<
-    Conf: Config,
+    Conf: Config<LdpMechanism = RandomizedResponse>,
     ConfG: ConfigGadget<Conf>,
     const INPUT_BYTES: usize,
     const TIME_BYTES: usize,
@@ -174,165 +120,52 @@ fn run<
 ) -> Result<(), Error>
 where
     ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
     ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
     ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
     ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     Proof<Conf>: CanonicalDeserialize,
 {
+    // true values per day and household
     let records = load_data()?;
-    // setup
-    let mut rng = ChaChaRng::from_entropy();
-    let mut zkp_rng = Conf::ZKPRng::new();
-    let (parameters, proving_key, server, client_public_keys, client_secret_keys) =
-        setup::<
-            Conf,
-            ConfG,
-            _,
-            INPUT_BYTES,
-            TIME_BYTES,
-            GAMMA_BYTES,
-            RANDOMNESS_BYTES,
-            K,
-            IS_REAL_INPUT,
-        >(gamma.clone(), &mut rng, &mut zkp_rng)?;
-    let server_sig_pk = server.get_signature_public_key();
-    let prf_eval_points = (0..((RANDOMNESS_BYTES - 1) / 32) + 1)
-        .map(|_| rng.gen::<PRFSchemeInput<Conf>>())
-        .collect::<Vec<_>>();
-
-    let mut ldp_values = vec![];
-
+    let days = records
+        .iter()
+        .map(|record| record.day as usize + 1)
+        .max()
+        .unwrap_or(0);
+    let mut values = vec![vec![BigUint::default(); HOUSEHOLDS as usize]; days];
     for record in records.iter() {
-        if record.household % 100 == 0 {
-            println!("{}", record.household)
-        }
-        let client_public_key = client_public_keys[record.household as usize].clone();
-        let client_secret_key = client_secret_keys[record.household as usize].clone();
-        let mut client = ClientShuffle::<
-            _,
-            INPUT_BYTES,
-            TIME_BYTES,
-            GAMMA_BYTES,
-            RANDOMNESS_BYTES,
-            K,
-            IS_REAL_INPUT,
-        >::new(
-            parameters.clone(),
-            server_sig_pk.clone(),
-            client_public_key,
-            proving_key.clone(),
-        )?;
-        // generate randomness
-        let gen_rand_client_message = client.generate_randomness_create(&mut rng)?;
-        let gen_rand_server_message =
-            server.generate_randomness_create(&gen_rand_client_message, &mut rng)?;
-        let result = client.generate_randomness_verify(&gen_rand_server_message)?;
-        assert!(
-            result,
-            "Verification of generate randomness protocol failed."
-        );
-
-        // input data from trusted environment
-        let input_value = if record.average_energy.is_zero() {
-            BigUint::zero()
-        } else {
-            let precision = INPUT_BYTES * 8 * 2;
-            let input_as_string = record
-                .average_energy
-                .mul_full_prec(
-                    &BigFloat::from_u8(2, precision)
-                        .powi(INPUT_BYTES * 8, precision, RoundingMode::Down)
-                        .sub_full_prec(&BigFloat::from_u8(1, precision)),
-                )
-                .int()
-                .convert_to_radix(
-                    Radix::Dec,
-                    RoundingMode::None,
-                    &mut Consts::new().expect("Constants cache initialization should not fail."),
-                )?
-                .1
-                .iter()
-                .map(|digit| digit.to_string())
-                .collect::<String>();
-            if input_as_string.is_empty() {
-                BigUint::zero()
-            } else {
-                BigUint::from_str(&input_as_string).expect("This parse should not fail.")
-            }
-        };
-
-        let mut input_value_time = [0; TIME_BYTES];
-        input_value_time[0] = record.day + 1;
-        let mut lower_bound_time = [0; TIME_BYTES];
-        lower_bound_time[0] = record.day;
-        let time_bounds = (lower_bound_time, input_value_time.clone());
-
-        let mut input_value_bytes = [0; INPUT_BYTES];
-        for (idx, byte) in input_value.to_bytes_le().iter().enumerate() {
-            input_value_bytes[idx] = *byte;
-        }
-
-        let mut message_bytes = input_value_bytes.to_vec();
-        message_bytes.extend_from_slice(&input_value_time);
-
-        let input_value_signature = Conf::ClientSignatureScheme::sign(
-            &parameters.client_signature_scheme,
-            &client_secret_key,
-            &message_bytes,
-            &mut rng,
-        )?;
+        values[record.day as usize][record.household as usize] =
+            encode_average_energy::<INPUT_BYTES>(&record.average_energy)?;
+    }
 
-        // verifiable randomizations
-        let ver_rand_client_message = client.verifiable_randomization_create::<ConfG>(
-            time_bounds,
-            input_value_time,
-            input_value.clone(),
-            input_value_signature,
-            &prf_eval_points,
-            &mut zkp_rng,
-            true,
-        )?;
-        let result = server.verifiable_randomization_verify::<ConfG>(
-            &ver_rand_client_message,
-            time_bounds,
-            &prf_eval_points,
-            &mut zkp_rng,
-            true,
-        )?;
-        assert!(
-            result.0,
-            "Verification of verifiable randomization protocol failed."
-        );
-        ldp_values.push(result.1);
+    // setup
+    let mut rng = ChaChaRng::from_entropy();
+    let parameters = ParametersShuffle::setup(gamma, &mut rng)?;
+
+    // simulate all households for all days
+    let settings = SimulationSettings {
+        clients: HOUSEHOLDS as usize,
+        epochs: days,
+        distribution: TrueValueDistribution::Data(values),
+        shuffler: true,
+        skip_proof: true,
+        seed: None,
+    };
+    let report = simulate_shuffle::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, &settings)?;
 
-        if record.household == HOUSEHOLDS - 1 {
-            let max_value = BigFloat::from_f64(MAX_VALUE, 100);
-            let sample_sum = ldp_values.iter().sum::<u64>() as f64;
-            let estimate = BigFloat::from_f64(sample_sum / K as f64, 100)
-                .sub(
-                    &gamma.mul(
-                        &BigFloat::from_f64(HOUSEHOLDS as f64 / 2.0, 100),
-                        100,
-                        RoundingMode::None,
-                    ),
-                    100,
-                    RoundingMode::None,
-                )
-                .div(
-                    &(BigFloat::from_u8(1, 100).sub(&gamma, 100, RoundingMode::None)),
-                    100,
-                    RoundingMode::None,
-                )
-                .div(
-                    &BigFloat::from_f64(HOUSEHOLDS as f64, 100),
-                    100,
-                    RoundingMode::None,
-                );
-            let estimate = estimate.mul(&max_value, 100, RoundingMode::None);
-            println!("Day {}:", record.day);
-            println!("Estimate: {estimate}");
-            ldp_values.clear();
-        }
+    for epoch in report.epochs {
+        println!("Day {}:", epoch.epoch);
+        println!("Estimate: {}", epoch.estimate.mean * MAX_VALUE);
     }
     Ok(())
 }
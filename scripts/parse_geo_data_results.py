@@ -29,12 +29,12 @@ if __name__ == "__main__":
             day_found = False
             for line in input_file.readlines():
                 if "Day" in line:
+                    if day_found:
+                        results_per_day_all.append(results_for_one_day)
+                        results_for_one_day = ["" for _ in range(len(bins) + 1)]
                     day_found = True
                     day_text = line.split(' ')[-1].rstrip()[:-1]
                     results_for_one_day[0] = day_text
-                elif line.rstrip() == '0' and day_found:
-                    results_per_day_all.append(results_for_one_day)
-                    results_for_one_day = ["" for _ in range(len(bins) + 1)]
                 else:
                     for i, bin_name in enumerate(bins):
                         if bin_name in line:
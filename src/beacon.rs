@@ -0,0 +1,77 @@
+//! Local stand-in for the public randomness beacon of the non-interactive Base scheme
+//!
+//! A randomness beacon publishes a fresh random output in every round, signed along with the time
+//! of the round. In the non-interactive Base scheme, the output of the first round after the time
+//! at which the client committed to its randomness replaces the server seed, such that the client
+//! does not need to contact the server before reporting. Since this round is fixed by the
+//! commitment, the client cannot pick a round of which the output suits it. In an actual deployment this is a public beacon
+//! that is trusted by the server, this stand-in publishes its rounds locally for tests and local
+//! runs.
+
+use crate::messages::base::*;
+use crate::prelude::*;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+/// Local randomness beacon of the non-interactive Base scheme
+pub struct LocalBeacon<Conf: Config> {
+    signature_scheme: ServerSignatureSchemeParameters<Conf>,
+    sig_pk: ServerSignatureSchemePublicKey<Conf>,
+    sig_sk: ServerSignatureSchemeSecretKey<Conf>,
+}
+
+impl<Conf: Config> LocalBeacon<Conf> {
+    /// Create a new beacon with the given system parameters, which signs its rounds using the
+    /// signature scheme of the server.
+    pub fn new<R: Rng + CryptoRng, const GAMMA_BYTES: usize>(
+        parameters: &ParametersBase<Conf, GAMMA_BYTES>,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        let (sig_pk, sig_sk) =
+            Conf::ServerSignatureScheme::keygen(&parameters.server_signature_scheme, rng)?;
+        Ok(Self {
+            signature_scheme: parameters.server_signature_scheme.clone(),
+            sig_pk,
+            sig_sk,
+        })
+    }
+
+    /// Get beacon's signature public key, under which the server and clients verify its rounds
+    pub fn get_signature_public_key(&self) -> ServerSignatureSchemePublicKey<Conf> {
+        self.sig_pk.clone()
+    }
+
+    /// Publish the round of the given time, which has a fresh random output.
+    pub fn publish<R: Rng + CryptoRng, const TIME_BYTES: usize>(
+        &self,
+        time: [u8; TIME_BYTES],
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Error>
+    where
+        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    {
+        // generate output
+        let mut output = PRFSchemeSeed::<Conf>::default();
+        rng.fill_bytes(&mut output);
+
+        // sign output along with the time of the round
+        let mut signature_input_bytes = Vec::new();
+        BeaconSignatureInputBase::<Conf, TIME_BYTES> { time, output }
+            .serialize_uncompressed(&mut signature_input_bytes)?;
+        let signature = Conf::ServerSignatureScheme::sign(
+            &self.signature_scheme,
+            &self.sig_sk,
+            &signature_input_bytes,
+            rng,
+        )?;
+
+        // return round
+        let mut serialized_round = vec![];
+        BeaconRoundBase::<Conf, TIME_BYTES> {
+            time,
+            output,
+            signature,
+        }
+        .serialize_compressed(&mut serialized_round)?;
+        Ok(serialized_round)
+    }
+}
@@ -0,0 +1,134 @@
+//! Command line interface to report the size of the ZKP circuits of the VLDP schemes, for the
+//! parameters given in the benchmark parameter files, without running the (expensive) key
+//! generation.
+//!
+//! Usage: `vldp report <base|expand|expand-seeded|shuffle> [histogram|real] [--gamma <value>]`
+
+use ark_bls12_381::Bls12_381;
+use ark_ed_on_bls12_381::constraints::EdwardsVar as JubJubVar;
+use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
+use ark_groth16::Groth16;
+use astro_float::BigFloat;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use std::env;
+use std::process;
+use vldp::circuits::{CircuitBase, CircuitExpand, CircuitShuffle, ConstraintReport};
+use vldp::config::{BasicConfig, BasicConfigGadget};
+use vldp::prelude::{Error, ParametersBase, ParametersExpand, ParametersShuffle};
+
+// protocol settings
+const INPUT_BYTES: usize = include!("../../benches/parameters/input_bytes");
+const TIME_BYTES: usize = include!("../../benches/parameters/time_bytes");
+const GAMMA_BYTES: usize = include!("../../benches/parameters/gamma_bytes");
+const RANDOMNESS_BYTES: usize = include!("../../benches/parameters/randomness_bytes");
+const MT_DEPTH: usize = include!("../../benches/parameters/mt_depth");
+const K_HISTOGRAM: u64 = 8;
+const K_REAL: u64 = 10;
+
+// curve selection
+type PairingCurve = Bls12_381;
+type InnerCurve = JubJub;
+type InnerCurveVar = JubJubVar;
+
+// zkp scheme selection
+type ZKPRng = ChaChaRng;
+type ZKPScheme = Groth16<PairingCurve>;
+
+type Conf<const NUM_COMM_BYTES: usize> = BasicConfig<InnerCurve, ZKPRng, ZKPScheme, NUM_COMM_BYTES>;
+type ConfG = BasicConfigGadget<InnerCurve, InnerCurveVar>;
+
+const USAGE: &str =
+    "Usage: vldp report <base|expand|expand-seeded|shuffle> [histogram|real] [--gamma <value>]";
+
+/// Report the size of the circuit of the given scheme.
+fn report<const K: u64, const IS_REAL_INPUT: bool>(
+    scheme: &str,
+    gamma: BigFloat,
+) -> Result<ConstraintReport, Error> {
+    let mut rng = ChaChaRng::from_entropy();
+    match scheme {
+        "base" => CircuitBase::<
+            Conf<RANDOMNESS_BYTES>,
+            ConfG,
+            INPUT_BYTES,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >::constraint_report(ParametersBase::setup(gamma, &mut rng)?),
+        "expand" => CircuitExpand::<
+            Conf<RANDOMNESS_BYTES>,
+            ConfG,
+            MT_DEPTH,
+            INPUT_BYTES,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >::constraint_report(ParametersExpand::setup(gamma, &mut rng)?),
+        // the leaves commit to a 32 byte seed
+        "expand-seeded" => {
+            CircuitExpand::<
+                Conf<32>,
+                ConfG,
+                MT_DEPTH,
+                INPUT_BYTES,
+                TIME_BYTES,
+                GAMMA_BYTES,
+                RANDOMNESS_BYTES,
+                K,
+                IS_REAL_INPUT,
+            >::constraint_report(ParametersExpand::setup_seeded(gamma, &mut rng)?)
+        }
+        "shuffle" => CircuitShuffle::<
+            Conf<32>,
+            ConfG,
+            INPUT_BYTES,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >::constraint_report(ParametersShuffle::setup(gamma, &mut rng)?),
+        _ => Err(format!("Unknown scheme: {scheme}."))?,
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Error> {
+    let mut args = args.iter().map(String::as_str);
+    if args.next() != Some("report") {
+        Err(USAGE)?
+    }
+    let scheme = args.next().ok_or(USAGE)?;
+
+    let mut is_real_input = false;
+    let mut gamma_value = 0.5;
+    while let Some(arg) = args.next() {
+        match arg {
+            "histogram" => is_real_input = false,
+            "real" => is_real_input = true,
+            "--gamma" => gamma_value = args.next().ok_or(USAGE)?.parse()?,
+            _ => Err(USAGE)?,
+        }
+    }
+    let gamma = BigFloat::from_f64(gamma_value, GAMMA_BYTES * 8);
+
+    let report = if is_real_input {
+        report::<K_REAL, true>(scheme, gamma)?
+    } else {
+        report::<K_HISTOGRAM, false>(scheme, gamma)?
+    };
+    print!("{report}");
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let Err(e) = run(&args) {
+        eprintln!("Error occurred: {e}");
+        process::exit(1);
+    }
+}
@@ -1,14 +1,12 @@
 //! Definitions of the R1CS ZKP circuits for the Base VLDP scheme.
 
+use crate::circuits::ConstraintReport;
 use crate::client::ClientBaseStorage;
 use crate::prelude::{constraints::*, *};
 use ark_ff::PrimeField;
 use ark_r1cs_std::fields::fp::FpVar;
-use ark_r1cs_std::ToConstraintFieldGadget;
+use ark_relations::ns;
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
-use ark_std::{One, Zero};
-use num_bigint::BigUint;
-use std::cmp::{min, Ordering};
 use std::marker::PhantomData;
 
 /// Struct for R1CS constraint generation for the Base scheme.
@@ -29,7 +27,7 @@ pub struct CircuitBase<
     // parameters
     params: ParametersBase<Conf, GAMMA_BYTES>,
     // public inputs
-    ldp_value: Option<u64>,
+    ldp_value: Option<LdpValue<Conf>>,
     time_bounds: Option<([u8; TIME_BYTES], [u8; TIME_BYTES])>,
     client_sig_pk: Option<ClientSignatureSchemePublicKey<Conf>>,
     client_randomness_commitment: Option<ClientCommitmentSchemeOutput<Conf>>,
@@ -64,11 +62,9 @@ impl<
         IS_REAL_INPUT,
     >
 {
-    pub fn keygen(
-        params: ParametersBase<Conf, GAMMA_BYTES>,
-        zkp_rng: &mut ZKPRng<Conf>,
-    ) -> Result<(ProvingKey<Conf>, VerifyingKey<Conf>), Error> {
-        let circuit = Self {
+    /// Circuit without any public inputs or witnesses, as used for key generation.
+    fn blank(params: ParametersBase<Conf, GAMMA_BYTES>) -> Self {
+        Self {
             _config_gadget: PhantomData,
             params,
             ldp_value: None,
@@ -81,14 +77,27 @@ impl<
             true_value_signature: None,
             client_randomness: None,
             client_randomness_commitment_randomness: None,
-        };
-        Conf::ZKPScheme::keygen(circuit, zkp_rng)
+        }
+    }
+
+    pub fn keygen(
+        params: ParametersBase<Conf, GAMMA_BYTES>,
+        zkp_rng: &mut ZKPRng<Conf>,
+    ) -> Result<(ProvingKey<Conf>, VerifyingKey<Conf>), Error> {
+        Conf::ZKPScheme::keygen(Self::blank(params), zkp_rng)
+    }
+
+    /// Report the size of the circuit for the given parameters, without running key generation.
+    pub fn constraint_report(
+        params: ParametersBase<Conf, GAMMA_BYTES>,
+    ) -> Result<ConstraintReport, Error> {
+        ConstraintReport::synthesize(Self::blank(params))
     }
 
     pub fn prove(
         proving_key: &ProvingKey<Conf>,
         params: ParametersBase<Conf, GAMMA_BYTES>,
-        ldp_value: u64,
+        ldp_value: LdpValue<Conf>,
         time_bounds: ([u8; TIME_BYTES], [u8; TIME_BYTES]),
         true_value: [u8; INPUT_BYTES],
         time: [u8; TIME_BYTES],
@@ -119,7 +128,7 @@ impl<
     pub fn verify(
         verifying_key: &VerifyingKey<Conf>,
         proof: &Proof<Conf>,
-        ldp_value: u64,
+        ldp_value: LdpValue<Conf>,
         time_bounds: ([u8; TIME_BYTES], [u8; TIME_BYTES]),
         client_sig_pk: &ClientSignatureSchemePublicKey<Conf>,
         client_randomness_commitment: ClientCommitmentSchemeOutput<Conf>,
@@ -133,12 +142,7 @@ impl<
         // convert inputs into correct format for proof verification
         let mut public_inputs = Vec::new();
 
-        public_inputs.extend_from_slice(
-            &ldp_value
-                .to_le_bytes()
-                .to_field_elements()
-                .ok_or(GenericError::ConversionError)?,
-        );
+        public_inputs.push(Conf::LdpMechanism::value_to_field(&ldp_value));
         public_inputs.extend_from_slice(
             &time_bounds
                 .0
@@ -208,13 +212,14 @@ impl<
         }
 
         // --- ALLOCATE VARIABLES ---
+        let namespace = ns!(cs, "allocation");
         // allocate constants
         let params = ParametersBaseVar::<_, ConfG>::new_constant(cs.clone(), &self.params)?;
 
         // allocate public inputs
         let ldp_value = FpVar::new_input(cs.clone(), || {
             self.ldp_value
-                .map(|x| ConstraintField::<Conf>::from(x))
+                .map(|x| Conf::LdpMechanism::value_to_field::<ConstraintField<Conf>>(&x))
                 .ok_or(SynthesisError::AssignmentMissing)
         })?;
         let time_lower_bound = FpVar::new_input(cs.clone(), || {
@@ -267,154 +272,48 @@ impl<
                 self.client_randomness_commitment_randomness
                     .ok_or(SynthesisError::AssignmentMissing)
             })?;
+        drop(namespace);
 
         // --- CONSTRAINTS ---
+        let namespace = ns!(cs, "randomness");
         // 1: randomness = client_randomness XOR server_randomness
-        let randomness = client_randomness
-            .iter()
-            .zip(server_randomness.iter())
-            .map(|(client_byte, server_byte)| client_byte.xor(server_byte))
-            .collect::<Result<Vec<_>, _>>()?;
+        let randomness = RandomnessDerivationGadget::xor(&client_randomness, &server_randomness)?;
+        drop(namespace);
 
+        let namespace = ns!(cs, "ldp_mechanism");
         // 2: ldp_value = LDP.Apply(true_value, randomness)
-        let k = FpVar::new_constant(cs.clone(), ConstraintField::<Conf>::from(K))?;
-        let max_bound = FpVar::new_constant(
-            cs.clone(),
-            ConstraintField::<Conf>::from_le_bytes_mod_order(&[u8::MAX; INPUT_BYTES]),
-        )?;
-        let boundary_gap = ConstraintField::<Conf>::from_le_bytes_mod_order(
-            &if IS_REAL_INPUT {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / (K + 1)
-            } else {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / K
-            }
-            .to_bytes_le(),
-        );
-        let computed_ldp_value = FpVar::new_witness(cs.clone(), || {
-            Ok(ConstraintField::<Conf>::from({
-                let mut randomness = self.server_randomness.unwrap();
-                randomness
-                    .iter_mut()
-                    .zip(self.client_randomness.unwrap())
-                    .for_each(|(client_byte, server_byte)| *client_byte ^= server_byte);
-                let boundary_gap = if IS_REAL_INPUT {
-                    BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / (K + 1)
-                } else {
-                    BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / K
-                };
-                let computed_ldp_value =
-                    BigUint::from_bytes_le(&randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES])
-                        / boundary_gap;
-                let computed_ldp_value = if computed_ldp_value.is_zero() {
-                    0
-                } else {
-                    computed_ldp_value.to_u64_digits()[0]
-                };
-                if IS_REAL_INPUT {
-                    min(computed_ldp_value, K)
-                } else {
-                    min(computed_ldp_value, K - 1) + 1
-                }
-            }))
-        })?;
-
-        let randomness_fp =
-            &randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES].to_constraint_field()?[0];
-        let lower_bound = if IS_REAL_INPUT {
-            computed_ldp_value.clone() * boundary_gap
-        } else {
-            (computed_ldp_value.clone() - ConstraintField::<Conf>::one()) * boundary_gap
-        };
-        let computed_upper_bound = if IS_REAL_INPUT {
-            (computed_ldp_value.clone() + ConstraintField::<Conf>::one()) * boundary_gap
-        } else {
-            computed_ldp_value.clone() * boundary_gap
-        };
-
-        // adjust the upper bound in case ldp_value == k;
-        let ldp_equal_to_k = k.is_eq(&computed_ldp_value)?;
-        let upper_bound = FpVar::new_witness(cs.clone(), || {
-            if ldp_equal_to_k.value().unwrap() {
-                max_bound.value()
-            } else {
-                computed_upper_bound.value()
-            }
-        })?;
-        upper_bound.conditional_enforce_equal(&max_bound, &ldp_equal_to_k)?;
-        upper_bound.conditional_enforce_equal(&computed_upper_bound, &ldp_equal_to_k.not())?;
-        // randomness >= lower_bound
-        let lower_bound_check =
-            randomness_fp.is_cmp_unchecked(&lower_bound, Ordering::Greater, true)?;
-        // randomness < upper_bound
-        let upper_bound_check =
-            randomness_fp.is_cmp_unchecked(&upper_bound, Ordering::Less, false)?;
-
-        let ldp_bit = params.gamma.compute_ldp_bit(&randomness[0..GAMMA_BYTES])?;
-
-        // cast true_value if is_real_input
-        let true_value_computed = if IS_REAL_INPUT {
-            let true_value_times_k = &true_value * k;
-            let multiplicand = FpVar::new_witness(cs.clone(), || {
-                Ok(ConstraintField::<Conf>::from_le_bytes_mod_order(
-                    &(BigUint::from_bytes_le(&self.true_value.unwrap()) * K
-                        / BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]))
-                    .to_bytes_le(),
-                ))
-            })?;
-            let remainder = FpVar::new_witness(cs.clone(), || {
-                Ok(true_value_times_k.value().unwrap()
-                    - multiplicand.value().unwrap()
-                        * ConstraintField::<Conf>::from_le_bytes_mod_order(&[u8::MAX; INPUT_BYTES]))
-            })?;
-            let true_value_randomness = Boolean::le_bits_to_fp_var(
-                &randomness[GAMMA_BYTES + INPUT_BYTES..GAMMA_BYTES + 2 * INPUT_BYTES]
-                    .to_bits_le()?,
-            )?;
-
-            // true_value_randomness <= remainder
-            let true_value_random_bit =
-                remainder.is_cmp_unchecked(&true_value_randomness, Ordering::Greater, true)?;
-            let true_value_computed = FpVar::new_witness(cs.clone(), || {
-                Ok(multiplicand.value().unwrap()
-                    + if true_value_random_bit.value().unwrap() {
-                        ConstraintField::<Conf>::one()
-                    } else {
-                        ConstraintField::<Conf>::zero()
-                    })
-            })?;
-
-            true_value_computed.conditional_enforce_equal(
-                &(&multiplicand + ConstraintField::<Conf>::one()),
-                &true_value_random_bit,
-            )?;
-            true_value_computed
-                .conditional_enforce_equal(&multiplicand, &true_value_random_bit.not())?;
-            remainder.enforce_equal(
-                &(true_value_times_k
-                    - multiplicand.clone()
-                        * ConstraintField::<Conf>::from_le_bytes_mod_order(
-                            &[u8::MAX; INPUT_BYTES],
-                        )),
+        // NOTE: correctness of the computation is checked at the end
+        let ldp_value_correct =
+            LdpApplyGadget::apply::<Conf, ConfG, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                &ldp_value,
+                &params.gamma,
+                None,
+                &true_value,
+                &randomness,
             )?;
-            true_value_computed
-        } else {
-            true_value.clone()
-        };
-        ldp_value.conditional_enforce_equal(&true_value_computed, &ldp_bit.not())?;
-        ldp_value.conditional_enforce_equal(&computed_ldp_value, &ldp_bit)?;
+        drop(namespace);
 
-        // 3: true_value_signature =?= ClientSig.Sign(client_sig_pk, true_value)
+        let namespace = ns!(cs, "authenticated_input");
+        // 3: true_value_signature =?= ClientSig.Sign(client_sig_pk, true_value || time) and
+        // true_value is in the input domain of the mechanism
         // NOTE: correctness of this constraint is checked at the end
-        let mut message_bytes = true_value.to_bytes()?[0..INPUT_BYTES].to_vec();
-        message_bytes.extend_from_slice(&time.to_bytes()?[0..TIME_BYTES]);
-
-        let true_value_signature_correct = ConfG::ClientSignatureVerifyGadget::verify(
+        let (_, true_value_correct) = AuthenticatedInputGadget::verify::<
+            Conf,
+            ConfG,
+            INPUT_BYTES,
+            TIME_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >(
             &params.client_signature_scheme,
             &client_sig_pk,
-            &message_bytes,
+            &true_value,
+            &time,
             &true_value_signature,
         )?;
+        drop(namespace);
 
+        let namespace = ns!(cs, "commitment");
         // 4: client_randomness_commitment =?= Comm(client_seed, client_seed_commitment_randomness)
         // NOTE: correctness of this constraint is checked at the end
         let client_randomness_commitment_computed = ConfG::ClientCommitmentVerifyGadget::commit(
@@ -424,25 +323,24 @@ impl<
         )?;
         let client_randomness_commitment_correct =
             client_randomness_commitment.is_eq(&client_randomness_commitment_computed)?;
+        drop(namespace);
 
+        let namespace = ns!(cs, "time_window");
         // 5: time_lower_bound < time <= time_upper_bound
-        // time_lower_bound < time
-        let time_lower_bound_check =
-            time_lower_bound.is_cmp_unchecked(&time, Ordering::Less, false)?;
-        // time <= time_upper_bound
-        let time_upper_bound_check =
-            time.is_cmp_unchecked(&time_upper_bound, Ordering::Less, true)?;
+        let time_correct =
+            TimeWindowGadget::contains::<_, TIME_BYTES>(time_lower_bound, time_upper_bound, time)?;
+        drop(namespace);
 
+        let namespace = ns!(cs, "result");
         // Check correctness of `=?=` constraints (i.e. 2, 3, 4, and 5)
         Boolean::kary_and(&[
-            true_value_signature_correct,
+            true_value_correct,
             client_randomness_commitment_correct,
-            lower_bound_check,
-            upper_bound_check,
-            time_lower_bound_check,
-            time_upper_bound_check,
+            ldp_value_correct,
+            time_correct,
         ])?
         .enforce_equal(&Boolean::TRUE)?;
+        drop(namespace);
 
         #[cfg(feature = "print-trace")]
         {
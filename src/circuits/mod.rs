@@ -8,3 +8,9 @@ pub use expand::*;
 
 pub mod shuffle;
 pub use shuffle::*;
+
+pub mod report;
+pub use report::*;
+
+#[cfg(test)]
+pub(crate) mod test;
@@ -0,0 +1,153 @@
+//! Reporting of the size of the R1CS ZKP circuits, to evaluate parameter choices without having to
+//! run the (expensive) key generation.
+
+use crate::prelude::*;
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{
+    ConstraintLayer, ConstraintSynthesizer, ConstraintSystem, SynthesisMode, TracingMode,
+};
+use std::fmt::{Display, Formatter};
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Name under which constraints outside of any namespace are reported.
+const UNNAMED_NAMESPACE: &str = "other";
+
+/// Size of the R1CS instance of a circuit
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstraintReport {
+    pub num_constraints: usize,
+    pub num_witness_variables: usize,
+    /// Number of public inputs (excluding the constant one).
+    pub num_public_inputs: usize,
+    /// Number of constraints per top-level namespace of the circuit, in order of appearance.
+    pub breakdown: Vec<(String, usize)>,
+}
+
+impl ConstraintReport {
+    /// Synthesize the circuit (in setup mode, i.e., without witnesses) and report its size.
+    pub fn synthesize<F: PrimeField, C: ConstraintSynthesizer<F>>(
+        circuit: C,
+    ) -> Result<Self, Error> {
+        // the constraint layer records the namespaces in which each constraint is generated
+        let subscriber = tracing_subscriber::Registry::default()
+            .with(ConstraintLayer::new(TracingMode::OnlyConstraints));
+        tracing::subscriber::with_default(subscriber, || {
+            let cs = ConstraintSystem::<F>::new_ref();
+            cs.set_mode(SynthesisMode::Setup);
+            circuit.generate_constraints(cs.clone())?;
+
+            let mut breakdown: Vec<(String, usize)> = Vec::new();
+            for name in cs.constraint_names().unwrap_or_default() {
+                let namespace = Self::top_level_namespace(&name);
+                match breakdown.iter_mut().find(|(other, _)| *other == namespace) {
+                    Some((_, count)) => *count += 1,
+                    None => breakdown.push((namespace, 1)),
+                }
+            }
+
+            Ok(Self {
+                num_constraints: cs.num_constraints(),
+                num_witness_variables: cs.num_witness_variables(),
+                num_public_inputs: cs.num_instance_variables() - 1,
+                breakdown,
+            })
+        })
+    }
+
+    /// Name of the top-level namespace of a constraint, given its full name (of the form
+    /// `/<module path>::<namespace>/...`).
+    fn top_level_namespace(name: &str) -> String {
+        name.trim_start_matches('/')
+            .split('/')
+            .next()
+            .and_then(|namespace| namespace.rsplit("::").next())
+            .filter(|namespace| !namespace.is_empty())
+            .unwrap_or(UNNAMED_NAMESPACE)
+            .to_string()
+    }
+}
+
+impl Display for ConstraintReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Number of constraints: {}", self.num_constraints)?;
+        writeln!(
+            f,
+            "Number of witness variables: {}",
+            self.num_witness_variables
+        )?;
+        writeln!(f, "Number of public inputs: {}", self.num_public_inputs)?;
+        writeln!(f, "Constraints per namespace:")?;
+        for (namespace, count) in &self.breakdown {
+            writeln!(f, "··{}: {}", namespace, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::circuits::{CircuitBase, CircuitExpand, CircuitShuffle, ConstraintReport};
+    use crate::config::{BasicConfig, BasicConfigGadget};
+    use crate::prelude::*;
+    use ark_bls12_381::Bls12_381;
+    use ark_ed_on_bls12_381::constraints::EdwardsVar as JubJubVar;
+    use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
+    use ark_groth16::Groth16;
+    use astro_float::BigFloat;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    type Conf = BasicConfig<JubJub, ChaChaRng, Groth16<Bls12_381>, 32>;
+    type ConfG = BasicConfigGadget<JubJub, JubJubVar>;
+
+    /// Check that the breakdown covers all constraints and contains the given namespaces.
+    fn check_breakdown(report: &ConstraintReport, namespaces: &[&str]) {
+        assert!(report.num_constraints > 0);
+        assert_eq!(
+            report
+                .breakdown
+                .iter()
+                .map(|(_, count)| count)
+                .sum::<usize>(),
+            report.num_constraints
+        );
+        for namespace in namespaces {
+            assert!(
+                report.breakdown.iter().any(|(name, _)| name == namespace),
+                "Namespace {} is missing from the breakdown.",
+                namespace
+            );
+        }
+    }
+
+    #[test]
+    fn constraint_report_test() {
+        let rng = &mut ChaChaRng::seed_from_u64(0);
+        let gamma = BigFloat::from_f64(0.5, 64);
+
+        let params = ParametersBase::<Conf, 8>::setup(gamma.clone(), rng).unwrap();
+        let report =
+            CircuitBase::<Conf, ConfG, 1, 1, 8, 24, 8, false>::constraint_report(params).unwrap();
+        check_breakdown(
+            &report,
+            &[
+                "ldp_mechanism",
+                "authenticated_input",
+                "commitment",
+                "time_window",
+            ],
+        );
+
+        let params = ParametersExpand::<Conf, 8>::setup(gamma.clone(), rng).unwrap();
+        let report =
+            CircuitExpand::<Conf, ConfG, 4, 1, 1, 8, 24, 8, false>::constraint_report(params)
+                .unwrap();
+        check_breakdown(&report, &["randomness", "merkle_membership"]);
+
+        let params = ParametersShuffle::<Conf, 8>::setup(gamma, rng).unwrap();
+        let report =
+            CircuitShuffle::<Conf, ConfG, 1, 1, 8, 24, 8, false>::constraint_report(params)
+                .unwrap();
+        check_breakdown(&report, &["seed", "server_signature"]);
+    }
+}
@@ -1,14 +1,15 @@
 //! Definitions of the R1CS ZKP circuits for the Shuffle VLDP scheme.
 
+use crate::circuits::ConstraintReport;
 use crate::client::ClientShuffleStorage;
+use crate::messages::BatchPath;
 use crate::prelude::{constraints::*, *};
+use crate::primitives::memoization::PermanentRandomnessGadget;
+use crate::primitives::multi_message::MultiMessageGadget;
 use ark_ff::PrimeField;
 use ark_r1cs_std::fields::fp::FpVar;
-use ark_r1cs_std::ToConstraintFieldGadget;
+use ark_relations::ns;
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
-use ark_std::{One, Zero};
-use num_bigint::BigUint;
-use std::cmp::{min, Ordering};
 use std::marker::PhantomData;
 
 /// Struct for R1CS constraint generation for the Shuffle scheme.
@@ -30,10 +31,13 @@ pub struct CircuitShuffle<
     params: ParametersShuffle<Conf, GAMMA_BYTES>,
 
     // public inputs
-    ldp_value: Option<u64>,
+    ldp_value: Option<LdpValue<Conf>>,
     time_bounds: Option<([u8; TIME_BYTES], [u8; TIME_BYTES])>,
     server_sig_pk: Option<ServerSignatureSchemePublicKey<Conf>>,
     prf_eval_points: Option<Vec<PRFSchemeInput<Conf>>>,
+    nullifier: Option<PRFSchemeOutput<Conf>>,
+    registry_root: Option<ClientMerkleTreeRoot<Conf>>,
+    message_index: Option<u8>,
 
     // private witnesses
     true_value: Option<[u8; INPUT_BYTES]>,
@@ -42,8 +46,12 @@ pub struct CircuitShuffle<
     client_sig_pk: Option<ClientSignatureSchemePublicKey<Conf>>,
     client_seed: Option<PRFSchemeSeed<Conf>>,
     client_seed_commitment_randomness: Option<ClientCommitmentSchemeRandomness<Conf>>,
+    nullifier_key: Option<PRFSchemeSeed<Conf>>,
+    nullifier_key_commitment_randomness: Option<ClientCommitmentSchemeRandomness<Conf>>,
     server_seed: Option<PRFSchemeSeed<Conf>>,
     server_signature: Option<ServerSignatureSchemeSignature<Conf>>,
+    registry_path: Option<ClientMerkleTreePath<Conf>>,
+    batch: Option<BatchPath<Conf>>,
 }
 
 impl<
@@ -67,35 +75,55 @@ impl<
         IS_REAL_INPUT,
     >
 {
-    pub fn keygen(
-        params: ParametersShuffle<Conf, GAMMA_BYTES>,
-        zkp_rng: &mut ZKPRng<Conf>,
-    ) -> Result<(ProvingKey<Conf>, VerifyingKey<Conf>), Error> {
-        let circuit = Self {
+    /// Circuit without any public inputs or witnesses, as used for key generation.
+    fn blank(params: ParametersShuffle<Conf, GAMMA_BYTES>) -> Self {
+        Self {
             _config_gadget: PhantomData,
             params,
             ldp_value: None,
             time_bounds: None,
             server_sig_pk: None,
             prf_eval_points: None,
+            nullifier: None,
+            registry_root: None,
+            message_index: None,
             true_value: None,
             time: None,
             true_value_signature: None,
             client_sig_pk: None,
             client_seed: None,
             client_seed_commitment_randomness: None,
+            nullifier_key: None,
+            nullifier_key_commitment_randomness: None,
             server_seed: None,
             server_signature: None,
-        };
-        Conf::ZKPScheme::keygen(circuit, zkp_rng)
+            registry_path: None,
+            batch: None,
+        }
+    }
+
+    pub fn keygen(
+        params: ParametersShuffle<Conf, GAMMA_BYTES>,
+        zkp_rng: &mut ZKPRng<Conf>,
+    ) -> Result<(ProvingKey<Conf>, VerifyingKey<Conf>), Error> {
+        Conf::ZKPScheme::keygen(Self::blank(params), zkp_rng)
+    }
+
+    /// Report the size of the circuit for the given parameters, without running key generation.
+    pub fn constraint_report(
+        params: ParametersShuffle<Conf, GAMMA_BYTES>,
+    ) -> Result<ConstraintReport, Error> {
+        ConstraintReport::synthesize(Self::blank(params))
     }
 
     pub fn prove(
         proving_key: &ProvingKey<Conf>,
         params: ParametersShuffle<Conf, GAMMA_BYTES>,
-        ldp_value: u64,
+        ldp_value: LdpValue<Conf>,
         server_sig_pk: ServerSignatureSchemePublicKey<Conf>,
         prf_eval_points: &[PRFSchemeInput<Conf>],
+        nullifier: PRFSchemeOutput<Conf>,
+        message_index: u8,
         time_bounds: ([u8; TIME_BYTES], [u8; TIME_BYTES]),
         true_value: [u8; INPUT_BYTES],
         time: [u8; TIME_BYTES],
@@ -111,14 +139,21 @@ impl<
             time_bounds: Some(time_bounds),
             server_sig_pk: Some(server_sig_pk),
             prf_eval_points: Some(prf_eval_points.to_vec()),
+            nullifier: Some(nullifier),
+            registry_root: client_storage.registry_root,
+            message_index: Some(message_index),
             true_value: Some(true_value),
             time: Some(time),
             true_value_signature: Some(true_value_signature),
             client_sig_pk: Some(client_sig_pk),
             client_seed: client_storage.client_seed,
             client_seed_commitment_randomness: client_storage.client_seed_commitment_randomness,
+            nullifier_key: client_storage.nullifier_key,
+            nullifier_key_commitment_randomness: client_storage.nullifier_key_commitment_randomness,
             server_seed: client_storage.server_seed,
             server_signature: client_storage.server_signature,
+            registry_path: client_storage.registry_path,
+            batch: client_storage.batch,
         };
         Conf::ZKPScheme::prove(proving_key, circuit, zkp_rng)
     }
@@ -126,24 +161,23 @@ impl<
     pub fn verify(
         verifying_key: &VerifyingKey<Conf>,
         proof: &Proof<Conf>,
-        ldp_value: u64,
+        ldp_value: LdpValue<Conf>,
         time_bounds: ([u8; TIME_BYTES], [u8; TIME_BYTES]),
         server_sig_pk: &ServerSignatureSchemePublicKey<Conf>,
         prf_eval_points: &[PRFSchemeInput<Conf>],
+        nullifier: &PRFSchemeOutput<Conf>,
+        registry_root: Option<&ClientMerkleTreeRoot<Conf>>,
+        message_index: Option<u8>,
         zkp_rng: &mut ZKPRng<Conf>,
     ) -> Result<bool, Error>
     where
         ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+        ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
     {
         // convert inputs into correct format for proof verification
         let mut public_inputs = Vec::new();
 
-        public_inputs.extend_from_slice(
-            &ldp_value
-                .to_le_bytes()
-                .to_field_elements()
-                .ok_or(GenericError::ConversionError)?,
-        );
+        public_inputs.push(Conf::LdpMechanism::value_to_field(&ldp_value));
         public_inputs.extend_from_slice(
             &time_bounds
                 .0
@@ -168,6 +202,25 @@ impl<
                     .ok_or(GenericError::ConversionError)?,
             );
         }
+        public_inputs.extend_from_slice(
+            &nullifier
+                .to_field_elements()
+                .ok_or(GenericError::ConversionError)?,
+        );
+        if let Some(registry_root) = registry_root {
+            public_inputs.extend_from_slice(
+                &registry_root
+                    .to_field_elements()
+                    .ok_or(GenericError::ConversionError)?,
+            );
+        }
+        if let Some(message_index) = message_index {
+            public_inputs.extend_from_slice(
+                &[message_index]
+                    .to_field_elements()
+                    .ok_or(GenericError::ConversionError)?,
+            );
+        }
 
         Conf::ZKPScheme::verify(verifying_key, &public_inputs, proof, zkp_rng)
     }
@@ -204,21 +257,26 @@ impl<
                 || self.prf_eval_points.is_none()
                 || self.true_value.is_none()
                 || self.true_value_signature.is_none()
+                || self.nullifier.is_none()
                 || self.client_seed.is_none()
+                || self.nullifier_key.is_none()
                 || self.server_seed.is_none()
-                || self.server_signature.is_none())
+                || self.server_signature.is_none()
+                || (self.params.registry_depth().is_some() && self.registry_path.is_none())
+                || (self.params.batch_depth().is_some() && self.batch.is_none()))
         {
             Err(SynthesisError::AssignmentMissing)?
         }
 
         // --- ALLOCATE VARIABLES ---
+        let namespace = ns!(cs, "allocation");
         // allocate constants
         let params = ParametersShuffleVar::<_, ConfG>::new_constant(cs.clone(), &self.params)?;
 
         // allocate public inputs
         let ldp_value = FpVar::new_input(cs.clone(), || {
             self.ldp_value
-                .map(|x| ConstraintField::<Conf>::from(x))
+                .map(|x| Conf::LdpMechanism::value_to_field::<ConstraintField<Conf>>(&x))
                 .ok_or(SynthesisError::AssignmentMissing)
         })?;
         let time_lower_bound = FpVar::new_input(cs.clone(), || {
@@ -249,6 +307,21 @@ impl<
                 )
             })
             .collect::<Result<Vec<_>, _>>()?;
+        let nullifier = UInt8::new_input_vec(cs.clone(), &self.nullifier.unwrap_or_default())?;
+        // the registry root (and path) is only allocated if a registry of client keys is used
+        let registry_root = match self.params.registry_depth() {
+            Some(_) => Some(ClientMerkleTreeRootVar::<_, ConfG>::new_input(
+                cs.clone(),
+                || self.registry_root.ok_or(SynthesisError::AssignmentMissing),
+            )?),
+            None => None,
+        };
+        // the message index is only allocated if clients send multiple messages
+        let message_index = if self.params.messages() > 1 {
+            UInt8::new_input_vec(cs.clone(), &[self.message_index.unwrap_or_default()])?[0].clone()
+        } else {
+            UInt8::constant(0)
+        };
 
         // allocate witnesses
         let true_value = FpVar::new_witness(cs.clone(), || {
@@ -269,218 +342,255 @@ impl<
             ClientSignatureSchemePublicKeyVar::<_, ConfG>::new_witness(cs.clone(), || {
                 self.client_sig_pk.ok_or(SynthesisError::AssignmentMissing)
             })?;
-        let client_seed = UInt8::new_witness_vec(
-            cs.clone(),
-            &self.client_seed.unwrap_or(PRFSchemeSeed::<Conf>::default()),
-        )?;
+        let client_seed =
+            UInt8::new_witness_vec(cs.clone(), &self.client_seed.unwrap_or_default())?;
         let client_seed_commitment_randomness =
             ClientCommitmentSchemeRandomnessVar::<_, ConfG>::new_witness(cs.clone(), || {
                 self.client_seed_commitment_randomness
                     .ok_or(SynthesisError::AssignmentMissing)
             })?;
-        let server_seed = UInt8::new_witness_vec(
-            cs.clone(),
-            &self.server_seed.unwrap_or(PRFSchemeSeed::<Conf>::default()),
-        )?;
+        let nullifier_key =
+            UInt8::new_witness_vec(cs.clone(), &self.nullifier_key.unwrap_or_default())?;
+        let nullifier_key_commitment_randomness =
+            ClientCommitmentSchemeRandomnessVar::<_, ConfG>::new_witness(cs.clone(), || {
+                self.nullifier_key_commitment_randomness
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+        let server_seed =
+            UInt8::new_witness_vec(cs.clone(), &self.server_seed.unwrap_or_default())?;
         let server_signature =
             ServerSignatureSchemeSignatureVar::<_, ConfG>::new_witness(cs.clone(), || {
                 Ok(self.server_signature.unwrap_or_default())
             })?;
+        let registry_path = match self.params.registry_depth() {
+            Some(registry_depth) => Some(ClientMerkleTreePathVar::<_, ConfG>::new_witness(
+                cs.clone(),
+                || {
+                    Ok(self.registry_path.unwrap_or(
+                        ClientMerkleTree::<Conf>::blank(
+                            &self.params.client_merkle_tree_scheme.leaf_crh_params,
+                            &self.params.client_merkle_tree_scheme.two_to_one_crh_params,
+                            registry_depth,
+                        )
+                        .and_then(|mt| mt.generate_proof(0))
+                        .map_err(|_| SynthesisError::AssignmentMissing)?,
+                    ))
+                },
+            )?),
+            None => None,
+        };
+        // the batch root and path are only allocated if the parameters use batches
+        let batch = match self.params.batch_depth() {
+            Some(batch_depth) => {
+                let (batch_root, batch_path) = match self.batch {
+                    Some(batch) => (batch.root, batch.path),
+                    None => (
+                        ClientMerkleTreeRoot::<Conf>::default(),
+                        ClientMerkleTree::<Conf>::blank(
+                            &self.params.client_merkle_tree_scheme.leaf_crh_params,
+                            &self.params.client_merkle_tree_scheme.two_to_one_crh_params,
+                            batch_depth,
+                        )
+                        .and_then(|mt| mt.generate_proof(0))
+                        .map_err(|_| SynthesisError::AssignmentMissing)?,
+                    ),
+                };
+                Some((
+                    ClientMerkleTreeRootVar::<_, ConfG>::new_witness(cs.clone(), || {
+                        Ok(batch_root)
+                    })?,
+                    ClientMerkleTreePathVar::<_, ConfG>::new_witness(cs.clone(), || {
+                        Ok(batch_path)
+                    })?,
+                ))
+            }
+            None => None,
+        };
+        drop(namespace);
 
         // --- CONSTRAINTS ---
+        let namespace = ns!(cs, "seed");
         // 1: seed = client_seed XOR server_seed
-        let seed = client_seed
-            .iter()
-            .zip(server_seed.iter())
-            .map(|(client_byte, server_byte)| client_byte.xor(server_byte))
-            .collect::<Result<Vec<_>, _>>()?;
+        let seed = RandomnessDerivationGadget::xor(&client_seed, &server_seed)?;
+        drop(namespace);
 
+        let namespace = ns!(cs, "randomness");
         // 2: randomness = PRF(seed, prf_eval_point)
-        let randomness = prf_eval_points
-            .iter()
-            .flat_map(|prf_eval_point| {
-                ConfG::PRFVerifyGadget::evaluate(&seed, &prf_eval_point).map(|x| x.to_bytes())
-            })
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .flatten()
-            .collect::<Vec<_>>();
-
-        // 3: ldp_value = LDP.Apply(true_value, randomness)
-        let k = FpVar::new_constant(cs.clone(), ConstraintField::<Conf>::from(K))?;
-        let max_bound = FpVar::new_constant(
-            cs.clone(),
-            ConstraintField::<Conf>::from_le_bytes_mod_order(&[u8::MAX; INPUT_BYTES]),
-        )?;
-        let boundary_gap = ConstraintField::<Conf>::from_le_bytes_mod_order(
-            &if IS_REAL_INPUT {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / (K + 1)
-            } else {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / K
-            }
-            .to_bytes_le(),
-        );
-        let computed_ldp_value = FpVar::new_witness(cs.clone(), || {
-            Ok(ConstraintField::<Conf>::from({
-                let boundary_gap = if IS_REAL_INPUT {
-                    BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / (K + 1)
-                } else {
-                    BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / K
-                };
-                let computed_ldp_value = BigUint::from_bytes_le(
-                    &randomness
-                        .iter()
-                        .map(|x| x.value().unwrap())
-                        .collect::<Vec<_>>()[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES],
-                ) / boundary_gap;
-                let computed_ldp_value = if computed_ldp_value.is_zero() {
-                    0
-                } else {
-                    computed_ldp_value.to_u64_digits()[0]
-                };
-                if IS_REAL_INPUT {
-                    min(computed_ldp_value, K)
-                } else {
-                    min(computed_ldp_value, K - 1) + 1
-                }
-            }))
-        })?;
-
-        let randomness_fp =
-            &randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES].to_constraint_field()?[0];
-        let lower_bound = if IS_REAL_INPUT {
-            computed_ldp_value.clone() * boundary_gap
-        } else {
-            (computed_ldp_value.clone() - ConstraintField::<Conf>::one()) * boundary_gap
-        };
-        let computed_upper_bound = if IS_REAL_INPUT {
-            (computed_ldp_value.clone() + ConstraintField::<Conf>::one()) * boundary_gap
+        // with multiple messages: the last byte of every prf_eval_point is XOR-ed with message_index
+        let prf_eval_points = if self.params.messages() > 1 {
+            MultiMessageGadget::eval_points(&prf_eval_points, &message_index)?
         } else {
-            computed_ldp_value.clone() * boundary_gap
+            prf_eval_points
         };
+        let randomness = RandomnessDerivationGadget::expand::<
+            Conf::PRFScheme,
+            ConfG::PRFVerifyGadget,
+            _,
+            RANDOMNESS_BYTES,
+        >(&seed, &prf_eval_points)?;
+        drop(namespace);
 
-        // adjust the upper bound in case ldp_value == k;
-        let ldp_equal_to_k = k.is_eq(&computed_ldp_value)?;
-        let upper_bound = FpVar::new_witness(cs.clone(), || {
-            if ldp_equal_to_k.value().unwrap() {
-                max_bound.value()
-            } else {
-                computed_upper_bound.value()
-            }
-        })?;
-        upper_bound.conditional_enforce_equal(&max_bound, &ldp_equal_to_k)?;
-        upper_bound.conditional_enforce_equal(&computed_upper_bound, &ldp_equal_to_k.not())?;
-        // randomness >= lower_bound
-        let lower_bound_check =
-            randomness_fp.is_cmp_unchecked(&lower_bound, Ordering::Greater, true)?;
-        // randomness < upper_bound
-        let upper_bound_check =
-            randomness_fp.is_cmp_unchecked(&upper_bound, Ordering::Less, false)?;
-
-        let ldp_bit = params.gamma.compute_ldp_bit(&randomness[0..GAMMA_BYTES])?;
-
-        // cast true_value if is_real_input
-        let true_value_computed = if IS_REAL_INPUT {
-            let true_value_times_k = &true_value * k;
-            let multiplicand = FpVar::new_witness(cs.clone(), || {
-                Ok(ConstraintField::<Conf>::from_le_bytes_mod_order(
-                    &(BigUint::from_bytes_le(&self.true_value.unwrap()) * K
-                        / BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]))
-                    .to_bytes_le(),
-                ))
-            })?;
-            let remainder = FpVar::new_witness(cs.clone(), || {
-                Ok(true_value_times_k.value().unwrap()
-                    - multiplicand.value().unwrap()
-                        * ConstraintField::<Conf>::from_le_bytes_mod_order(&[u8::MAX; INPUT_BYTES]))
-            })?;
-            let true_value_randomness = Boolean::le_bits_to_fp_var(
-                &randomness[GAMMA_BYTES + INPUT_BYTES..GAMMA_BYTES + 2 * INPUT_BYTES]
-                    .to_bits_le()?,
-            )?;
-
-            // true_value_randomness <= remainder
-            let true_value_random_bit =
-                remainder.is_cmp_unchecked(&true_value_randomness, Ordering::Greater, true)?;
-            let true_value_computed = FpVar::new_witness(cs.clone(), || {
-                Ok(multiplicand.value().unwrap()
-                    + if true_value_random_bit.value().unwrap() {
-                        ConstraintField::<Conf>::one()
-                    } else {
-                        ConstraintField::<Conf>::zero()
-                    })
-            })?;
-
-            true_value_computed.conditional_enforce_equal(
-                &(&multiplicand + ConstraintField::<Conf>::one()),
-                &true_value_random_bit,
-            )?;
-            true_value_computed
-                .conditional_enforce_equal(&multiplicand, &true_value_random_bit.not())?;
-            remainder.enforce_equal(
-                &(true_value_times_k
-                    - multiplicand.clone()
-                        * ConstraintField::<Conf>::from_le_bytes_mod_order(
-                            &[u8::MAX; INPUT_BYTES],
-                        )),
-            )?;
-            true_value_computed
-        } else {
-            true_value.clone()
-        };
-        ldp_value.conditional_enforce_equal(&true_value_computed, &ldp_bit.not())?;
-        ldp_value.conditional_enforce_equal(&computed_ldp_value, &ldp_bit)?;
-
-        // 4: true_value_signature =?= ClientSig.Sign(client_sig_pk, true_value)
+        let namespace = ns!(cs, "authenticated_input");
+        // 3: true_value_signature =?= ClientSig.Sign(client_sig_pk, true_value || time) and
+        // true_value is in the input domain of the mechanism
         // NOTE: correctness of this constraint is checked at the end
-        let mut message_bytes = true_value.to_bytes()?[0..INPUT_BYTES].to_vec();
-        message_bytes.extend_from_slice(&time.to_bytes()?[0..TIME_BYTES]);
-
-        let true_value_signature_correct = ConfG::ClientSignatureVerifyGadget::verify(
+        let (true_value_bytes, true_value_correct) = AuthenticatedInputGadget::verify::<
+            Conf,
+            ConfG,
+            INPUT_BYTES,
+            TIME_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >(
             &params.client_signature_scheme,
             &client_sig_pk,
-            &message_bytes,
+            &true_value,
+            &time,
             &true_value_signature,
         )?;
+        drop(namespace);
 
+        let namespace = ns!(cs, "ldp_mechanism");
+        // 4: ldp_value = LDP.Apply(true_value, randomness)
+        // with memoization: ldp_value = LDP.Apply(LDP.Apply(true_value, permanent_randomness), randomness)
+        // where permanent_randomness is derived from nullifier_key and true_value
+        // with multiple messages: gamma is replaced by the blanket gamma, unless message_index is
+        // the index of the real message (i.e., PRF(seed, REAL_INDEX_EVAL_POINT) mod messages)
+        // NOTE: correctness of the computation is checked at the end
+        let permanent_randomness = match &params.permanent_gamma {
+            Some(_) => Some(PermanentRandomnessGadget::derive::<
+                Conf::PRFScheme,
+                ConfG::PRFVerifyGadget,
+                _,
+                INPUT_BYTES,
+                RANDOMNESS_BYTES,
+            >(
+                &nullifier_key, &true_value_bytes[0..INPUT_BYTES]
+            )?),
+            None => None,
+        };
+        let permanent = params
+            .permanent_gamma
+            .as_ref()
+            .zip(permanent_randomness.as_deref());
+        let gamma = match &params.blanket_gamma {
+            Some(blanket_gamma) => MultiMessageGadget::is_real::<
+                Conf::PRFScheme,
+                ConfG::PRFVerifyGadget,
+                _,
+            >(&seed, &message_index, self.params.messages())?
+            .select(&params.gamma, blanket_gamma)?,
+            None => params.gamma.clone(),
+        };
+        let ldp_value_correct = LdpApplyGadget::apply::<
+            Conf,
+            ConfG,
+            INPUT_BYTES,
+            GAMMA_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >(&ldp_value, &gamma, permanent, &true_value, &randomness)?;
+        drop(namespace);
+
+        let namespace = ns!(cs, "commitment");
         // 5: client_seed_commitment = Comm(client_seed, client_seed_commitment_randomness)
-        // NOTE: correctness of this constraint is checked at the end
         let client_seed_commitment = ConfG::ClientCommitmentVerifyGadget::commit(
             &params.client_commitment_scheme,
             &client_seed,
             &client_seed_commitment_randomness,
         )?;
+        // 6: nullifier_key_commitment = Comm(nullifier_key, nullifier_key_commitment_randomness)
+        let nullifier_key_commitment = ConfG::ClientCommitmentVerifyGadget::commit(
+            &params.client_commitment_scheme,
+            &nullifier_key,
+            &nullifier_key_commitment_randomness,
+        )?;
+        drop(namespace);
 
-        // 6: server_signature =?= ServerSig.Sign(server_sig_pk, client_seed_commitment || client_sig_pk || server_seed)
+        let namespace = ns!(cs, "server_signature");
+        // 7: server_signature =?= ServerSig.Sign(server_sig_pk, client_seed_commitment || nullifier_key_commitment || client_sig_pk || server_seed)
+        // with batches: server_signature =?= ServerSig.Sign(server_sig_pk, batch_root) and
+        // Comm(digest(client_seed_commitment || ... || server_seed)) =?= leaf in the merkle tree
+        // with root batch_root
         // NOTE: correctness of this constraint is checked at the end
         let mut signature_input_bytes = client_seed_commitment.to_bytes()?;
+        signature_input_bytes.extend_from_slice(&nullifier_key_commitment.to_bytes()?);
         signature_input_bytes.extend_from_slice(&client_sig_pk.to_bytes()?);
         signature_input_bytes.extend_from_slice(&server_seed);
-        let server_signature_correct = ConfG::ServerSignatureVerifyGadget::verify(
-            &params.server_signature_scheme,
-            &server_sig_pk,
-            &signature_input_bytes,
-            &server_signature,
-        )?;
+        let server_signature_correct = match &batch {
+            Some((batch_root, batch_path)) => {
+                let is_in_batch = batch_path.verify_membership(
+                    &params.client_merkle_tree_scheme.leaf_crh_scheme,
+                    &params.client_merkle_tree_scheme.two_to_one_crh_scheme,
+                    batch_root,
+                    &SignedBatchGadget::leaf::<Conf, ConfG>(
+                        &params.client_commitment_scheme,
+                        &signature_input_bytes,
+                    )?,
+                )?;
+                is_in_batch.and(&ConfG::ServerSignatureVerifyGadget::verify(
+                    &params.server_signature_scheme,
+                    &server_sig_pk,
+                    &batch_root.to_bytes()?,
+                    &server_signature,
+                )?)?
+            }
+            None => ConfG::ServerSignatureVerifyGadget::verify(
+                &params.server_signature_scheme,
+                &server_sig_pk,
+                &signature_input_bytes,
+                &server_signature,
+            )?,
+        };
+        drop(namespace);
 
-        // 7: time_lower_bound < time <= time_upper_bound
-        // time_lower_bound < time
-        let time_lower_bound_check =
-            time_lower_bound.is_cmp_unchecked(&time, Ordering::Less, false)?;
-        // time <= time_upper_bound
-        let time_upper_bound_check =
-            time.is_cmp_unchecked(&time_upper_bound, Ordering::Less, true)?;
+        let namespace = ns!(cs, "nullifier");
+        // 8: nullifier =?= PRF(nullifier_key, time_upper_bound || message_index)
+        // NOTE: correctness of this constraint is checked at the end
+        let nullifier_correct = NullifierGadget::derive::<
+            Conf::PRFScheme,
+            ConfG::PRFVerifyGadget,
+            _,
+        >(&nullifier_key, &time_upper_bound, &message_index)?
+        .is_eq(&nullifier)?;
+        drop(namespace);
+
+        let namespace = ns!(cs, "time_window");
+        // 9: time_lower_bound < time <= time_upper_bound
+        let time_correct =
+            TimeWindowGadget::contains::<_, TIME_BYTES>(time_lower_bound, time_upper_bound, time)?;
+        drop(namespace);
+
+        let namespace = ns!(cs, "registry");
+        // 10: with a registry: Comm(digest(client_sig_pk)) =?= leaf in the merkle tree with root
+        // registry_root
+        // NOTE: correctness of this constraint is checked at the end
+        let is_registered = match registry_root.as_ref().zip(registry_path.as_ref()) {
+            Some((registry_root, registry_path)) => registry_path.verify_membership(
+                &params.client_merkle_tree_scheme.leaf_crh_scheme,
+                &params.client_merkle_tree_scheme.two_to_one_crh_scheme,
+                registry_root,
+                &RegisteredKeyGadget::leaf::<Conf, ConfG>(
+                    &params.client_commitment_scheme,
+                    &client_sig_pk,
+                )?,
+            )?,
+            None => Boolean::TRUE,
+        };
+        drop(namespace);
 
-        // Check correctness of `=?=` constraints (i.e. 2, 4, 6, and 7)
+        let namespace = ns!(cs, "result");
+        // Check correctness of `=?=` constraints (i.e. 3, 4, 7, 8, 9, and 10)
         Boolean::kary_and(&[
-            true_value_signature_correct,
+            true_value_correct,
             server_signature_correct,
-            lower_bound_check,
-            upper_bound_check,
-            time_lower_bound_check,
-            time_upper_bound_check,
+            ldp_value_correct,
+            nullifier_correct,
+            time_correct,
+            is_registered,
         ])?
         .enforce_equal(&Boolean::TRUE)?;
+        drop(namespace);
 
         #[cfg(feature = "print-trace")]
         {
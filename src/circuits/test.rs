@@ -0,0 +1,510 @@
+//! Differential tests between the native randomization of the clients and the witness computation
+//! inside the ZKP circuits of all three VLDP schemes, along with the test configurations and the
+//! setup of every scheme that are shared with the tests of the servers, the shuffler and the
+//! population simulator.
+
+use crate::client::{ClientBase, ClientExpand, ClientShuffle};
+use crate::config::{BasicConfig, BasicConfigGadget};
+use crate::prelude::*;
+use crate::run_random::*;
+use crate::server::{ServerBase, ServerExpand, ServerShuffle};
+use crate::shuffler::Shuffler;
+use ark_ed_on_bls12_381::constraints::EdwardsVar as JubJubVar;
+use ark_ed_on_bls12_381::{EdwardsProjective as JubJub, Fq};
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::test_rng;
+use astro_float::BigFloat;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// Proof system that checks the constraints of a circuit, instead of proving them.
+///
+/// Proving synthesizes the circuit with the witnesses computed by the client, and fails if these
+/// do not satisfy the constraints, or if the constraints are still satisfied after altering the
+/// claimed LDP value (the first public input of all circuits). The "proof" is the list of public
+/// inputs, such that verification checks that the server reconstructs the same public inputs.
+pub(crate) struct ConstraintChecker<F: PrimeField>(PhantomData<F>);
+
+impl<F: PrimeField, R: ProofSystemRng> ProofSystem<R> for ConstraintChecker<F> {
+    type ConstraintField = F;
+    type ProvingKey = ();
+    type VerifyingKey = ();
+    type Proof = Vec<F>;
+
+    fn keygen<C: ConstraintSynthesizer<F> + Clone>(
+        _circuit: C,
+        _rng: &mut R,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), Error> {
+        Ok(((), ()))
+    }
+
+    fn prove<C: ConstraintSynthesizer<F>>(
+        _proving_key: &Self::ProvingKey,
+        circuit: C,
+        _rng: &mut R,
+    ) -> Result<Self::Proof, Error> {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone())?;
+        if let Some(constraint) = cs.which_is_unsatisfied()? {
+            Err(format!(
+                "Honest witnesses do not satisfy constraint {}.",
+                constraint
+            ))?
+        }
+        let public_inputs = cs
+            .borrow()
+            .ok_or("Constraint system is unavailable.")?
+            .instance_assignment[1..]
+            .to_vec();
+
+        // alter the claimed LDP value
+        cs.borrow_mut()
+            .ok_or("Constraint system is unavailable.")?
+            .instance_assignment[1] += F::one();
+        if cs.is_satisfied()? {
+            Err("An altered LDP value satisfies the constraints.")?
+        }
+        Ok(public_inputs)
+    }
+
+    fn verify(
+        _verifying_key: &Self::VerifyingKey,
+        public_inputs: &[F],
+        proof: &Self::Proof,
+        _rng: &mut R,
+    ) -> Result<bool, Error> {
+        Ok(public_inputs == proof.as_slice())
+    }
+
+    fn rerandomize_proof(
+        _verifying_key: &Self::VerifyingKey,
+        proof: &Self::Proof,
+        _rng: &mut R,
+    ) -> Result<Self::Proof, Error> {
+        Ok(proof.clone())
+    }
+}
+
+type TestConfig<M> = BasicConfig<JubJub, ChaChaRng, ConstraintChecker<Fq>, 64, M>;
+type TestConfigGadget<MG> = BasicConfigGadget<JubJub, JubJubVar, MG>;
+pub(crate) type RrConf = TestConfig<RandomizedResponse>;
+pub(crate) type RrConfG = TestConfigGadget<RandomizedResponseGadget>;
+type PwConf = TestConfig<Piecewise>;
+type PwConfG = TestConfigGadget<PiecewiseGadget>;
+type DlConf = TestConfig<DiscreteLaplace<16>>;
+type DlConfG = TestConfigGadget<DiscreteLaplaceGadget<16>>;
+
+const RUNS: usize = 8;
+const MT_DEPTH: usize = 3;
+pub(crate) const TIME_BYTES: usize = 2;
+pub(crate) const GAMMA_BYTES: usize = 4;
+pub(crate) const RANDOMNESS_BYTES: usize = 64;
+
+pub(crate) type BaseServer =
+    ServerBase<RrConf, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 2, false>;
+pub(crate) type BaseClient =
+    ClientBase<RrConf, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 2, false>;
+pub(crate) type ExpandServer<const MT_DEPTH: usize> =
+    ServerExpand<RrConf, MT_DEPTH, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 7, false>;
+pub(crate) type ExpandClient<const MT_DEPTH: usize> =
+    ClientExpand<RrConf, MT_DEPTH, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 7, false>;
+pub(crate) type ShuffleServer =
+    ServerShuffle<RrConf, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 7, false>;
+pub(crate) type ShuffleClient =
+    ClientShuffle<RrConf, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 7, false>;
+pub(crate) type ClientKeys = (
+    ClientSignatureSchemePublicKey<RrConf>,
+    ClientSignatureSchemeSecretKey<RrConf>,
+);
+
+/// Signature key pairs of the given number of clients (as held by their trusted environments).
+fn client_keys(
+    parameters: &ClientSignatureSchemeParameters<RrConf>,
+    clients: usize,
+    rng: &mut ChaChaRng,
+) -> Vec<ClientKeys> {
+    (0..clients)
+        .map(|_| <RrConf as Config>::ClientSignatureScheme::keygen(parameters, rng).unwrap())
+        .collect()
+}
+
+/// Random PRF evaluation points for `RANDOMNESS_BYTES` of server randomness.
+fn prf_eval_points(rng: &mut ChaChaRng) -> Vec<PRFSchemeInput<RrConf>> {
+    (0..((RANDOMNESS_BYTES - 1) / 32) + 1)
+        .map(|_| rng.gen::<PRFSchemeInput<RrConf>>())
+        .collect()
+}
+
+/// Server of the Base scheme, along with clients that each have their own signature key pair.
+pub(crate) struct BaseSetup {
+    pub(crate) server: BaseServer,
+    pub(crate) clients: Vec<BaseClient>,
+    pub(crate) client_keys: Vec<ClientKeys>,
+}
+
+/// Set up the Base scheme with the given parameters and number of clients.
+pub(crate) fn setup_base(
+    parameters: &ParametersBase<RrConf, GAMMA_BYTES>,
+    clients: usize,
+    rng: &mut ChaChaRng,
+) -> BaseSetup {
+    let client_keys = client_keys(&parameters.client_signature_scheme, clients, rng);
+    let server = BaseServer::new(parameters.clone(), (), rng).unwrap();
+    let clients = client_keys
+        .iter()
+        .map(|(client_sig_pk, _)| {
+            BaseClient::new(
+                parameters.clone(),
+                server.get_signature_public_key(),
+                *client_sig_pk,
+                (),
+            )
+            .unwrap()
+        })
+        .collect();
+    BaseSetup {
+        server,
+        clients,
+        client_keys,
+    }
+}
+
+/// Server of the Expand scheme, along with clients that each have their own signature key pair,
+/// and the PRF evaluation points of the server randomness.
+pub(crate) struct ExpandSetup<const MT_DEPTH: usize> {
+    pub(crate) server: ExpandServer<MT_DEPTH>,
+    pub(crate) clients: Vec<ExpandClient<MT_DEPTH>>,
+    pub(crate) client_keys: Vec<ClientKeys>,
+    pub(crate) prf_eval_points: Vec<PRFSchemeInput<RrConf>>,
+}
+
+/// Set up the Expand scheme with the given parameters and number of clients.
+pub(crate) fn setup_expand<const MT_DEPTH: usize>(
+    parameters: &ParametersExpand<RrConf, GAMMA_BYTES>,
+    clients: usize,
+    rng: &mut ChaChaRng,
+) -> ExpandSetup<MT_DEPTH> {
+    let client_keys = client_keys(&parameters.client_signature_scheme, clients, rng);
+    let prf_eval_points = prf_eval_points(rng);
+    let server = ExpandServer::new(parameters.clone(), (), rng).unwrap();
+    let clients = client_keys
+        .iter()
+        .map(|(client_sig_pk, _)| {
+            ExpandClient::new(
+                parameters.clone(),
+                server.get_signature_public_key(),
+                *client_sig_pk,
+                (),
+            )
+            .unwrap()
+        })
+        .collect();
+    ExpandSetup {
+        server,
+        clients,
+        client_keys,
+        prf_eval_points,
+    }
+}
+
+/// Server and shuffler of the Shuffle scheme, along with clients that each have their own
+/// signature key pair, and the PRF evaluation points of the server randomness.
+pub(crate) struct ShuffleSetup {
+    pub(crate) server: ShuffleServer,
+    pub(crate) shuffler: Shuffler<RrConf>,
+    pub(crate) clients: Vec<ShuffleClient>,
+    pub(crate) client_keys: Vec<ClientKeys>,
+    pub(crate) prf_eval_points: Vec<PRFSchemeInput<RrConf>>,
+}
+
+impl ShuffleSetup {
+    /// Create a client with the given signature public key.
+    pub(crate) fn new_client(
+        &self,
+        parameters: &ParametersShuffle<RrConf, GAMMA_BYTES>,
+        client_sig_pk: ClientSignatureSchemePublicKey<RrConf>,
+    ) -> ShuffleClient {
+        ShuffleClient::new(
+            parameters.clone(),
+            self.server.get_signature_public_key(),
+            self.server.get_encryption_public_key(),
+            self.shuffler.get_encryption_public_key(),
+            client_sig_pk,
+            (),
+        )
+        .unwrap()
+    }
+}
+
+/// Set up the Shuffle scheme with the given parameters and number of clients.
+pub(crate) fn setup_shuffle(
+    parameters: &ParametersShuffle<RrConf, GAMMA_BYTES>,
+    clients: usize,
+    rng: &mut ChaChaRng,
+) -> ShuffleSetup {
+    let client_keys = client_keys(&parameters.client_signature_scheme, clients, rng);
+    let prf_eval_points = prf_eval_points(rng);
+    let server = ShuffleServer::new(parameters.clone(), (), rng).unwrap();
+    let shuffler = Shuffler::new(parameters, server.get_encryption_public_key(), (), rng).unwrap();
+    let mut setup = ShuffleSetup {
+        server,
+        shuffler,
+        clients: vec![],
+        client_keys,
+        prf_eval_points,
+    };
+    setup.clients = setup
+        .client_keys
+        .iter()
+        .map(|(client_sig_pk, _)| setup.new_client(parameters, *client_sig_pk))
+        .collect();
+    setup
+}
+
+/// Random gammas within the given range, along with a seed for each run such that a failing run
+/// can be replayed.
+fn gammas(range: Range<f64>) -> Vec<(BigFloat, Option<u64>)> {
+    let rng = &mut test_rng();
+    (0..RUNS)
+        .map(|_| {
+            (
+                BigFloat::from_f64(rng.gen_range(range.clone()), 8 * GAMMA_BYTES),
+                Some(rng.gen()),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn base_differential_test() {
+    for (gamma, seed) in gammas(0.05..0.95) {
+        run_protocol_base::<RrConf, RrConfG, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 2, false>(
+            gamma.clone(),
+            seed,
+        )
+        .unwrap();
+        run_protocol_base::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            200,
+            false,
+        >(gamma.clone(), seed)
+        .unwrap();
+        run_protocol_base::<RrConf, RrConfG, 2, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 10, true>(
+            gamma.clone(),
+            seed,
+        )
+        .unwrap();
+        run_protocol_base::<DlConf, DlConfG, 2, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 10, false>(
+            gamma.clone(),
+            seed,
+        )
+        .unwrap();
+        run_protocol_base_stateless::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            2,
+            false,
+        >(gamma.clone(), seed)
+        .unwrap();
+        run_protocol_base_beacon::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            2,
+            false,
+        >(gamma.clone(), seed)
+        .unwrap();
+        run_protocol_base_batch::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            2,
+            false,
+        >(gamma, seed)
+        .unwrap();
+    }
+    for (gamma, seed) in gammas(0.05..0.45) {
+        run_protocol_base::<PwConf, PwConfG, 2, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 10, true>(gamma, seed)
+            .unwrap();
+    }
+    // the piecewise mechanism requires gamma to be smaller than 1/2
+    let rng = &mut ChaChaRng::seed_from_u64(0);
+    for gamma in [0.6, 1.0] {
+        let gamma = BigFloat::from_f64(gamma, 8 * GAMMA_BYTES);
+        assert!(ParametersBase::<PwConf, GAMMA_BYTES>::setup(gamma.clone(), rng).is_err());
+        assert!(ParametersBase::<RrConf, GAMMA_BYTES>::setup(gamma, rng).is_ok());
+    }
+}
+
+#[test]
+fn expand_differential_test() {
+    for (gamma, seed) in gammas(0.05..0.95) {
+        run_protocol_expand::<
+            RrConf,
+            RrConfG,
+            MT_DEPTH,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(gamma.clone(), seed)
+        .unwrap();
+        run_protocol_expand::<
+            RrConf,
+            RrConfG,
+            MT_DEPTH,
+            2,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            3,
+            true,
+        >(gamma.clone(), seed)
+        .unwrap();
+        run_protocol_expand_seeded::<
+            RrConf,
+            RrConfG,
+            MT_DEPTH,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(gamma.clone(), seed)
+        .unwrap();
+        run_protocol_expand_epoch_bound::<
+            RrConf,
+            RrConfG,
+            MT_DEPTH,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(gamma.clone(), seed)
+        .unwrap();
+        run_protocol_expand_batch::<
+            RrConf,
+            RrConfG,
+            MT_DEPTH,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(gamma, seed)
+        .unwrap();
+    }
+}
+
+#[test]
+fn shuffle_differential_test() {
+    for (gamma, seed) in gammas(0.05..0.95) {
+        run_protocol_shuffle::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(gamma.clone(), seed)
+        .unwrap();
+        run_protocol_shuffle::<
+            RrConf,
+            RrConfG,
+            2,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            10,
+            true,
+        >(gamma.clone(), seed)
+        .unwrap();
+        run_protocol_shuffle_memoized::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(
+            gamma.clone(),
+            BigFloat::from_f64(0.25, 8 * GAMMA_BYTES),
+            seed,
+        )
+        .unwrap();
+        run_protocol_shuffle_multi_message::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(gamma.clone(), 3, seed)
+        .unwrap();
+        run_protocol_shuffle_blanket_noise::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(gamma.clone(), 4, seed)
+        .unwrap();
+        run_protocol_shuffle_batch::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(gamma, 3, seed)
+        .unwrap();
+    }
+    for (gamma, seed) in gammas(0.05..0.45) {
+        run_protocol_shuffle::<
+            PwConf,
+            PwConfG,
+            2,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            10,
+            true,
+        >(gamma, seed)
+        .unwrap();
+    }
+}
@@ -2,11 +2,10 @@
 
 use crate::circuits::CircuitBase;
 use crate::messages::base::*;
+use crate::messages::{verify_server_signature, BatchPath};
 use crate::prelude::*;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::Zero;
 use num_bigint::BigUint;
-use std::cmp::min;
 
 /// Storage of values between steps for a client in the Base scheme
 #[derive(Clone)]
@@ -46,6 +45,10 @@ pub struct ClientBase<
     client_sig_pk: ClientSignatureSchemePublicKey<Conf>,
     proving_key: ProvingKey<Conf>,
     storage: ClientBaseStorage<Conf, RANDOMNESS_BYTES>,
+    commitment_time: Option<[u8; TIME_BYTES]>,
+    commitment_signature: Option<ClientSignatureSchemeSignature<Conf>>,
+    beacon_time: Option<[u8; TIME_BYTES]>,
+    batch: Option<BatchPath<Conf>>,
 }
 
 impl<
@@ -71,34 +74,27 @@ impl<
             client_sig_pk,
             proving_key,
             storage: ClientBaseStorage::new(),
+            commitment_time: None,
+            commitment_signature: None,
+            beacon_time: None,
+            batch: None,
         })
     }
 
-    /// Given the current time step, perform the first part of the `Generate Randomness` step of the client.
-    pub fn generate_randomness_create<R: Rng + CryptoRng>(
+    /// Generate fresh client randomness and commit to it.
+    fn commit_randomness<R: Rng + CryptoRng>(
         &mut self,
-        time: [u8; TIME_BYTES],
         rng: &mut R,
-    ) -> Result<Vec<u8>, Error>
-    where
-        ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
-        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
-    {
+    ) -> Result<ClientCommitmentSchemeOutput<Conf>, Error> {
         // generate a seed
         let mut client_seed = PRFSchemeSeed::<Conf>::default();
         rng.fill_bytes(&mut client_seed);
 
         // generate client randomness from seed
-        let mut client_randomness = [0; RANDOMNESS_BYTES];
-        for (index, chunk) in client_randomness.chunks_mut(32).enumerate() {
-            let mut eval_point = [0; 32];
-            for (new_byte, old_byte) in index.to_le_bytes().into_iter().zip(eval_point.iter_mut()) {
-                *old_byte = new_byte;
-            }
-            chunk.copy_from_slice(
-                &Conf::PRFScheme::evaluate(&client_seed, &eval_point)?[0..chunk.len()],
-            );
-        }
+        let client_randomness = RandomnessDerivation::expand::<Conf::PRFScheme, RANDOMNESS_BYTES>(
+            &client_seed,
+            &RandomnessDerivation::index_eval_points::<RANDOMNESS_BYTES>(0),
+        )?;
 
         // commit to client randomness
         let commitment_randomness = ClientCommitmentSchemeRandomness::<Conf>::rand(rng);
@@ -112,6 +108,24 @@ impl<
         self.storage.client_randomness = Some(client_randomness);
         self.storage.client_randomness_commitment_randomness = Some(commitment_randomness);
         self.storage.client_randomness_commitment = Some(client_randomness_commitment.clone());
+        self.beacon_time = None;
+        self.batch = None;
+
+        Ok(client_randomness_commitment)
+    }
+
+    /// Given the current time step, perform the first part of the `Generate Randomness` step of the client.
+    pub fn generate_randomness_create<R: Rng + CryptoRng>(
+        &mut self,
+        time: [u8; TIME_BYTES],
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Error>
+    where
+        ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    {
+        // commit to fresh client randomness
+        let client_randomness_commitment = self.commit_randomness(rng)?;
 
         // return message
         let mut serialized_message = vec![];
@@ -125,6 +139,9 @@ impl<
     }
 
     /// Perform the second part of the `Generate Randomness` step of the client.
+    ///
+    /// If the step is part of a batch, the server signature is verified on the root of the batch,
+    /// which must contain the signature input of the client.
     pub fn generate_randomness_verify(&mut self, server_message: &[u8]) -> Result<bool, Error>
     where
         ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
@@ -145,19 +162,104 @@ impl<
             client_signature_public_key: self.client_sig_pk.clone(),
             server_seed: server_message.server_seed.clone(),
         };
-        let mut signature_input_bytes = Vec::new();
-        signature_input.serialize_uncompressed(&mut signature_input_bytes)?;
 
         // verify signature
-        if Conf::ServerSignatureScheme::verify(
+        if verify_server_signature::<Conf>(
+            &self.parameters.client_commitment_scheme,
+            &self.parameters.client_merkle_tree_scheme,
             &self.parameters.server_signature_scheme,
             &self.server_sig_pk,
-            &signature_input_bytes,
+            &signature_input,
             &server_message.server_signature,
+            server_message.batch.as_ref(),
         )? {
             // storage
             self.storage.server_seed = Some(server_message.server_seed);
             self.storage.server_signature = Some(server_message.server_signature);
+            self.batch = server_message.batch;
+
+            // return success
+            Ok(true)
+        } else {
+            // signature verification failed
+            Ok(false)
+        }
+    }
+
+    /// Given the current time step, perform the first part of the non-interactive `Generate
+    /// Randomness` step of the client, in which the server seed is replaced by the output of a
+    /// round of the randomness beacon. Returns the message that is to be signed by the trusted
+    /// environment, which attests that the client randomness was committed to at this time.
+    pub fn generate_randomness_commit<R: Rng + CryptoRng>(
+        &mut self,
+        time: [u8; TIME_BYTES],
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Error>
+    where
+        ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+    {
+        // commit to fresh client randomness
+        let client_randomness_commitment = self.commit_randomness(rng)?;
+
+        // storage
+        self.commitment_time = Some(time);
+        self.commitment_signature = None;
+
+        // return signature input
+        let mut signature_input_bytes = Vec::new();
+        CommitmentSignatureInputBase::<Conf, TIME_BYTES> {
+            client_randomness_commitment,
+            time,
+        }
+        .serialize_uncompressed(&mut signature_input_bytes)?;
+        Ok(signature_input_bytes)
+    }
+
+    /// Given the public key of the randomness beacon, a round of the beacon, and the signature of
+    /// the trusted environment on the commitment, perform the second part of the non-interactive
+    /// `Generate Randomness` step of the client. The round must be the first round after the time
+    /// of the commitment, and only one round is accepted per commitment.
+    pub fn generate_randomness_beacon(
+        &mut self,
+        beacon_sig_pk: &ServerSignatureSchemePublicKey<Conf>,
+        beacon_round: &[u8],
+        commitment_signature: ClientSignatureSchemeSignature<Conf>,
+    ) -> Result<bool, Error>
+    where
+        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    {
+        // deserialize beacon round
+        let beacon_round =
+            BeaconRoundBase::<Conf, TIME_BYTES>::deserialize_compressed(beacon_round)?;
+
+        // check that the round is the first round after the commitment to the client randomness,
+        // and that no round was accepted for this commitment yet
+        let commitment_time = self.commitment_time.ok_or(ClientError::UnobtainedValue)?;
+        if self.beacon_time.is_some() || !TimeWindow::is_next(&commitment_time, &beacon_round.time)
+        {
+            return Ok(false);
+        }
+
+        // reconstruct signature input
+        let mut signature_input_bytes = Vec::new();
+        BeaconSignatureInputBase::<Conf, TIME_BYTES> {
+            time: beacon_round.time,
+            output: beacon_round.output,
+        }
+        .serialize_uncompressed(&mut signature_input_bytes)?;
+
+        // verify signature
+        if Conf::ServerSignatureScheme::verify(
+            &self.parameters.server_signature_scheme,
+            beacon_sig_pk,
+            &signature_input_bytes,
+            &beacon_round.signature,
+        )? {
+            // storage
+            self.storage.server_seed = Some(beacon_round.output);
+            self.storage.server_signature = Some(beacon_round.signature);
+            self.commitment_signature = Some(commitment_signature);
+            self.beacon_time = Some(beacon_round.time);
 
             // return success
             Ok(true)
@@ -187,88 +289,43 @@ impl<
         Proof<Conf>: CanonicalDeserialize,
         ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
         ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     {
+        // check that the input value is in the input domain of the mechanism
+        let input_value_bytes =
+            AuthenticatedInput::encode::<Conf::LdpMechanism, INPUT_BYTES, K, IS_REAL_INPUT>(
+                &input_value,
+            )?;
+        // check that the input value was created within the time bounds
+        if !TimeWindow::contains(&time_bounds, &input_value_time) {
+            Err(ClientError::InvalidTime)?
+        }
+
         // compute server randomness
         let server_seed = self
             .storage
             .server_seed
             .ok_or(ClientError::UnobtainedValue)?;
-        let mut server_randomness = [0; RANDOMNESS_BYTES];
-        for (index, chunk) in server_randomness.chunks_mut(32).enumerate() {
-            let mut eval_point = [0; 32];
-            for (new_byte, old_byte) in index.to_le_bytes().into_iter().zip(eval_point.iter_mut()) {
-                *old_byte = new_byte;
-            }
-            chunk.copy_from_slice(
-                &Conf::PRFScheme::evaluate(&server_seed, &eval_point)?[0..chunk.len()],
-            );
-        }
+        let server_randomness = RandomnessDerivation::expand::<Conf::PRFScheme, RANDOMNESS_BYTES>(
+            &server_seed,
+            &RandomnessDerivation::index_eval_points::<RANDOMNESS_BYTES>(0),
+        )?;
         // compute full randomness from client and server part
-        let mut randomness = server_randomness.clone();
-        randomness
-            .iter_mut()
-            .zip(
-                self.storage
-                    .client_randomness
-                    .ok_or(ClientError::UnobtainedValue)?,
-            )
-            .for_each(|(client_byte, server_byte)| *client_byte ^= server_byte);
+        let randomness = RandomnessDerivation::xor(
+            &server_randomness,
+            &self
+                .storage
+                .client_randomness
+                .ok_or(ClientError::UnobtainedValue)?,
+        );
 
         // apply LDP
-        let ldp_bit = {
-            (BigUint::from_bytes_le(&randomness[0..GAMMA_BYTES])
-                <= BigUint::from_bytes_le(&self.parameters.gamma_as_bytes()?)) as u8
-        };
-
-        let ldp_value = if ldp_bit == 0 {
-            if IS_REAL_INPUT {
-                let input_value_times_k = &input_value * K;
-                let multiplicand =
-                    &input_value_times_k / BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]);
-                let remainder = &input_value_times_k
-                    - &multiplicand * BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]);
-                let random_input_bytes =
-                    &randomness[GAMMA_BYTES + INPUT_BYTES..GAMMA_BYTES + 2 * INPUT_BYTES];
-                let random_input_bit =
-                    (BigUint::from_bytes_le(&random_input_bytes) <= remainder) as u64;
-                if multiplicand.is_zero() {
-                    random_input_bit
-                } else {
-                    multiplicand.to_u64_digits()[0] + random_input_bit
-                }
-            } else {
-                if (&input_value).is_zero() {
-                    0
-                } else {
-                    (&input_value).to_u64_digits()[0]
-                }
-            }
-        } else {
-            // ldp_bit == 1
-            let boundary_gap = if IS_REAL_INPUT {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / (K + 1)
-            } else {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / K
-            };
-            let computed_ldp_value =
-                BigUint::from_bytes_le(&randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES])
-                    / boundary_gap;
-            let computed_ldp_value = if computed_ldp_value.is_zero() {
-                0
-            } else {
-                computed_ldp_value.to_u64_digits()[0]
-            };
-            if IS_REAL_INPUT {
-                min(computed_ldp_value, K)
-            } else {
-                min(computed_ldp_value, K - 1) + 1
-            }
-        };
-
-        let mut input_value_bytes = [0; INPUT_BYTES];
-        for (idx, byte) in input_value.to_bytes_le().iter().enumerate() {
-            input_value_bytes[idx] = *byte;
-        }
+        let ldp_value = LdpApply::apply::<Conf, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+            &self.parameters.gamma_as_bytes()?,
+            None,
+            &input_value,
+            &randomness,
+        );
 
         // create proof
         let proof = if skip_proof {
@@ -298,9 +355,22 @@ impl<
             )?
         };
 
+        // attest the ordering of the commitment and the beacon round, if the beacon is used
+        let beacon = match self.beacon_time {
+            Some(beacon_time) => Some(BeaconAttestationBase {
+                commitment_time: self.commitment_time.ok_or(ClientError::UnobtainedValue)?,
+                commitment_signature: self
+                    .commitment_signature
+                    .clone()
+                    .ok_or(ClientError::UnobtainedValue)?,
+                beacon_time,
+            }),
+            None => None,
+        };
+
         // return message
         let mut serialized_message = vec![];
-        VerifiableRandomizationMessageBase::<Conf, INPUT_BYTES> {
+        VerifiableRandomizationMessageBase::<Conf, INPUT_BYTES, TIME_BYTES> {
             client_sig_pk: self.client_sig_pk.clone(),
             client_randomness_commitment: self
                 .storage
@@ -313,6 +383,8 @@ impl<
                 .server_signature
                 .clone()
                 .ok_or(ClientError::UnobtainedValue)?,
+            beacon,
+            batch: self.batch.clone(),
             proof,
             ldp_value,
         }
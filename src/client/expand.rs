@@ -2,22 +2,28 @@
 
 use crate::circuits::CircuitExpand;
 use crate::messages::expand::*;
+use crate::messages::{verify_server_signature, BatchPath};
 use crate::prelude::*;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::Zero;
+use ark_std::cfg_into_iter;
 use num_bigint::BigUint;
 use rand::{RngCore, SeedableRng};
 use rand_chacha::ChaChaRng;
-use std::cmp::min;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::ops::Range;
 
 /// Storage of values between steps for a client in the Expand scheme
 #[derive(Clone)]
 pub struct ClientExpandStorage<Conf: Config> {
     pub generator_seed: Option<[u8; 32]>,
     pub index: usize,
-    pub merkle_tree: Option<ClientMerkleTree<Conf>>,
+    pub merkle_tree_root: Option<ClientMerkleTreeRoot<Conf>>,
+    pub previous_merkle_tree_root: Option<ClientMerkleTreeRoot<Conf>>,
+    pub start_epoch: Option<u64>,
     pub server_seed: Option<PRFSchemeSeed<Conf>>,
     pub server_signature: Option<ServerSignatureSchemeSignature<Conf>>,
+    pub batch: Option<BatchPath<Conf>>,
 }
 
 impl<Conf: Config> ClientExpandStorage<Conf> {
@@ -26,9 +32,12 @@ impl<Conf: Config> ClientExpandStorage<Conf> {
         Self {
             generator_seed: None,
             index: 0,
-            merkle_tree: None,
+            merkle_tree_root: None,
+            previous_merkle_tree_root: None,
+            start_epoch: None,
             server_seed: None,
             server_signature: None,
+            batch: None,
         }
     }
 }
@@ -49,6 +58,7 @@ pub struct ClientExpand<
     client_sig_pk: ClientSignatureSchemePublicKey<Conf>,
     proving_key: ProvingKey<Conf>,
     storage: ClientExpandStorage<Conf>,
+    next_start_epoch: Option<u64>,
 }
 
 impl<
@@ -85,75 +95,149 @@ impl<
             client_sig_pk,
             proving_key,
             storage: ClientExpandStorage::new(),
+            next_start_epoch: None,
         })
     }
 
-    /// Perform the first part of the `Generate Randomness` step of the client.
+    /// Set the epoch (i.e., upper time bound) in which the first leaf of the next merkle tree is
+    /// used, this is required before every `Generate Randomness` step (including renewals) if the
+    /// parameters bind the index to the epoch.
+    pub fn set_start_epoch(&mut self, start_epoch: [u8; TIME_BYTES]) -> Result<(), Error> {
+        self.next_start_epoch = Some(
+            u64::try_from(BigUint::from_bytes_le(&start_epoch))
+                .map_err(|_| ClientError::InvalidTime)?,
+        );
+        Ok(())
+    }
+
+    /// Given the signature secret key of the client perform the first part of the `Generate
+    /// Randomness` step of the client.
     pub fn generate_randomness_create<R: Rng + CryptoRng>(
         &mut self,
+        client_sig_sk: &ClientSignatureSchemeSecretKey<Conf>,
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Error>
+    where
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+        ClientCommitmentSchemeParameters<Conf>: Sync,
+        ClientCommitmentSchemeOutput<Conf>: Send,
+    {
+        self.generate_randomness_message(None, client_sig_sk, rng)
+    }
+
+    /// Perform the first part of a renewal of the `Generate Randomness` step of the client, which
+    /// replaces the current merkle tree (e.g., when it is exhausted) by a new one that is linked to
+    /// it. The second part is the same as for the regular `Generate Randomness` step.
+    ///
+    /// The server only accepts a renewal once all leaves of the current merkle tree are used, or,
+    /// if the index is bound to the epoch, if the new start epoch is after its last epoch.
+    pub fn generate_randomness_renew_create<R: Rng + CryptoRng>(
+        &mut self,
+        client_sig_sk: &ClientSignatureSchemeSecretKey<Conf>,
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Error>
+    where
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+        ClientCommitmentSchemeParameters<Conf>: Sync,
+        ClientCommitmentSchemeOutput<Conf>: Send,
+    {
+        // only a merkle tree that has been signed by the server can be renewed
+        if self.storage.server_signature.is_none() {
+            Err(ClientError::UnobtainedValue)?
+        }
+        let previous_merkle_tree_root = self
+            .storage
+            .merkle_tree_root
+            .clone()
+            .ok_or(ClientError::UnobtainedValue)?;
+        self.generate_randomness_message(Some(previous_merkle_tree_root), client_sig_sk, rng)
+    }
+
+    /// Whether all leaves of the current merkle tree have been used, i.e., whether it has to be
+    /// renewed before the next `Randomize` step.
+    pub fn is_merkle_tree_exhausted(&self) -> bool {
+        self.storage.index >= self.merkle_tree().num_leaves()
+    }
+
+    /// Create a new merkle tree (linked to the previous one, if any) and the corresponding client
+    /// message of the `Generate Randomness` step, which is signed by the client.
+    fn generate_randomness_message<R: Rng + CryptoRng>(
+        &mut self,
+        previous_merkle_tree_root: Option<ClientMerkleTreeRoot<Conf>>,
+        client_sig_sk: &ClientSignatureSchemeSecretKey<Conf>,
         rng: &mut R,
     ) -> Result<Vec<u8>, Error>
     where
         ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+        ClientCommitmentSchemeParameters<Conf>: Sync,
+        ClientCommitmentSchemeOutput<Conf>: Send,
     {
+        // the start epoch is only used (and required) if the index is bound to the epoch
+        let start_epoch = if self.parameters.has_epoch_bound_index() {
+            Some(
+                self.next_start_epoch
+                    .take()
+                    .ok_or(ClientError::UnobtainedValue)?,
+            )
+        } else {
+            None
+        };
+
         // make a new rng and store its seed, so we do not have to store the entire merkle tree in memory
-        let mut generator = ChaChaRng::from_rng(rng)?;
-        let generator_seed = generator.get_seed();
+        let generator_seed = ChaChaRng::from_rng(&mut *rng)?.get_seed();
 
-        // create the merkle tree
-        let mut client_seed = PRFSchemeSeed::<Conf>::default();
-        generator.fill_bytes(&mut client_seed);
-        let leaves = (0..2_usize.pow((MT_DEPTH - 1) as u32))
-            .map(|index| {
-                let mut client_randomness = [0; RANDOMNESS_BYTES];
-                let num_evals = ((RANDOMNESS_BYTES - 1) / 32) + 1;
-                for (inner_index, chunk) in client_randomness.chunks_mut(32).enumerate() {
-                    let eval_index = index * num_evals + inner_index;
-                    let mut eval_point = [0; 32];
-                    for (new_byte, old_byte) in eval_index
-                        .to_le_bytes()
-                        .into_iter()
-                        .zip(eval_point.iter_mut())
-                    {
-                        *old_byte = new_byte;
-                    }
-                    chunk.copy_from_slice(
-                        &Conf::PRFScheme::evaluate(&client_seed, &eval_point)?[0..chunk.len()],
-                    );
-                }
-                let client_randomness_commitment_randomness =
-                    ClientCommitmentSchemeRandomness::<Conf>::rand(&mut generator);
-                Conf::ClientCommitmentScheme::commit(
-                    &self.parameters.client_commitment_scheme,
-                    &client_randomness,
-                    &client_randomness_commitment_randomness,
-                )
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-        let merkle_tree = ClientMerkleTree::<Conf>::new(
-            &self.parameters.client_merkle_tree_scheme.leaf_crh_params,
-            &self
-                .parameters
-                .client_merkle_tree_scheme
-                .two_to_one_crh_params,
-            leaves,
-        )?;
+        // compute the root of the merkle tree, generating its leaves on the fly
+        let merkle_tree_root = self
+            .merkle_tree()
+            .root(|batch| self.leaves(&generator_seed, batch))?;
 
-        // storage
-        self.storage.generator_seed = Some(generator_seed);
-        self.storage.merkle_tree = Some(merkle_tree.clone());
+        // storage (the values of the previous merkle tree are no longer valid)
+        self.storage = ClientExpandStorage {
+            generator_seed: Some(generator_seed),
+            index: 0,
+            merkle_tree_root: Some(merkle_tree_root.clone()),
+            previous_merkle_tree_root: previous_merkle_tree_root.clone(),
+            start_epoch,
+            server_seed: None,
+            server_signature: None,
+            batch: None,
+        };
+
+        // sign the (renewed) merkle tree
+        let mut client_signature_input_bytes = Vec::new();
+        MerkleTreeSignatureInputExpand::<Conf> {
+            client_merkle_tree_root: merkle_tree_root.clone(),
+            previous_client_merkle_tree_root: previous_merkle_tree_root.clone(),
+            start_epoch,
+        }
+        .serialize_uncompressed(&mut client_signature_input_bytes)?;
+        let client_signature = Conf::ClientSignatureScheme::sign(
+            &self.parameters.client_signature_scheme,
+            client_sig_sk,
+            &client_signature_input_bytes,
+            rng,
+        )?;
 
         // return message
         let mut serialized_message = vec![];
         GenerateRandomnessMessageClientExpand::<Conf> {
-            client_merkle_tree_root: merkle_tree.root(),
+            client_merkle_tree_root: merkle_tree_root,
             client_signature_public_key: self.client_sig_pk.clone(),
+            previous_client_merkle_tree_root: previous_merkle_tree_root,
+            start_epoch,
+            client_signature,
         }
         .serialize_compressed(&mut serialized_message)?;
         Ok(serialized_message)
     }
 
     /// Perform the second part of the `Generate Randomness` step of the client.
+    ///
+    /// If the step is part of a batch, the server signature is verified on the root of the batch,
+    /// which must contain the signature input of the client.
     pub fn generate_randomness_verify(&mut self, server_message: &[u8]) -> Result<bool, Error>
     where
         ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
@@ -167,26 +251,29 @@ impl<
         let signature_input = GenerateRandomnessSignatureInputExpand::<Conf> {
             client_merkle_tree_root: self
                 .storage
-                .merkle_tree
-                .as_ref()
-                .map(|mt| mt.root())
+                .merkle_tree_root
+                .clone()
                 .ok_or(ClientError::UnobtainedValue)?,
             client_signature_public_key: self.client_sig_pk.clone(),
             server_seed: server_message.server_seed.clone(),
+            previous_client_merkle_tree_root: self.storage.previous_merkle_tree_root.clone(),
+            start_epoch: self.storage.start_epoch,
         };
-        let mut signature_input_bytes = Vec::new();
-        signature_input.serialize_uncompressed(&mut signature_input_bytes)?;
 
         // verify signature
-        if Conf::ServerSignatureScheme::verify(
+        if verify_server_signature::<Conf>(
+            &self.parameters.client_commitment_scheme,
+            &self.parameters.client_merkle_tree_scheme,
             &self.parameters.server_signature_scheme,
             &self.server_sig_pk,
-            &signature_input_bytes,
+            &signature_input,
             &server_message.server_signature,
+            server_message.batch.as_ref(),
         )? {
             // storage
             self.storage.server_seed = Some(server_message.server_seed);
             self.storage.server_signature = Some(server_message.server_signature);
+            self.storage.batch = server_message.batch;
 
             // return success
             Ok(true)
@@ -200,9 +287,13 @@ impl<
     /// and its signature, along with the list of public `prf_eval_points` (s in the paper) and
     /// current `index` (j in the paper) perform the `Randomize` step of the client.
     ///
+    /// If the parameters bind the index to the epoch, the given index is ignored, and the index is
+    /// derived from the time bounds instead (see `TimeWindow::epoch_index`).
+    ///
     /// The `skip_proof` flag can be set to `true` to do a faster test run of this function that
     /// only executes the randomization (without proof generation).
     /// Note: in actual usage this should be set to `false`.
+    #[allow(clippy::too_many_arguments)]
     pub fn verifiable_randomization_create<ConfG: ConfigGadget<Conf>>(
         &mut self,
         time_bounds: ([u8; TIME_BYTES], [u8; TIME_BYTES]),
@@ -218,114 +309,65 @@ impl<
         Proof<Conf>: CanonicalDeserialize,
         ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
         ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ClientCommitmentSchemeParameters<Conf>: Sync,
+        ClientCommitmentSchemeOutput<Conf>: Send,
     {
-        // reconstruct the generator that was used to create this entry of the merkle tree
-        let mut generator = ChaChaRng::from_seed(
-            self.storage
-                .generator_seed
-                .ok_or(ClientError::UnobtainedValue)?,
-        );
-
-        // compute the client seed, client randomness and commitment randomness again
-        let mut client_seed = PRFSchemeSeed::<Conf>::default();
-        generator.fill_bytes(&mut client_seed);
-        let mut client_randomness = [0; RANDOMNESS_BYTES];
-        let num_evals = ((RANDOMNESS_BYTES - 1) / 32) + 1;
-        for (inner_index, chunk) in client_randomness.chunks_mut(32).enumerate() {
-            let eval_index = index * num_evals + inner_index;
-            let mut eval_point = [0; 32];
-            for (new_byte, old_byte) in eval_index
-                .to_le_bytes()
-                .into_iter()
-                .zip(eval_point.iter_mut())
-            {
-                *old_byte = new_byte;
-            }
-            chunk.copy_from_slice(
-                &Conf::PRFScheme::evaluate(&client_seed, &eval_point)?[0..chunk.len()],
-            );
+        // check that the input value is in the input domain of the mechanism
+        let input_value_bytes =
+            AuthenticatedInput::encode::<Conf::LdpMechanism, INPUT_BYTES, K, IS_REAL_INPUT>(
+                &input_value,
+            )?;
+        // check that the input value was created within the time bounds
+        if !TimeWindow::contains(&time_bounds, &input_value_time) {
+            Err(ClientError::InvalidTime)?
         }
-        let client_randomness_commitment_randomness =
-            ClientCommitmentSchemeRandomness::<Conf>::rand(&mut generator);
+        // derive the index from the epoch, if it is bound to the epoch
+        let index = match self.storage.start_epoch {
+            Some(start_epoch) => TimeWindow::epoch_index(start_epoch, &time_bounds.1)
+                .ok_or(ClientError::InvalidTime)?,
+            None => index,
+        };
+        // check that the merkle tree has a leaf left for this index
+        if self.is_merkle_tree_exhausted() || index >= self.merkle_tree().num_leaves() {
+            Err(ClientError::MerkleTreeExhausted)?
+        }
+
+        // compute the client randomness and commitment randomness of this entry of the merkle tree again
+        let generator_seed = self
+            .storage
+            .generator_seed
+            .ok_or(ClientError::UnobtainedValue)?;
+        let (client_leaf_seed, client_randomness, client_randomness_commitment_randomness) =
+            Self::leaf_randomness(&generator_seed, index, self.parameters.has_seeded_leaves())?;
 
         // compute server randomness
         let server_seed = self
             .storage
             .server_seed
             .ok_or(ClientError::UnobtainedValue)?;
-        let mut server_randomness = [0; RANDOMNESS_BYTES];
-        for (chunk, prf_eval_point) in server_randomness.chunks_mut(32).zip(prf_eval_points) {
-            chunk.copy_from_slice(
-                &Conf::PRFScheme::evaluate(&server_seed, &prf_eval_point)?[0..chunk.len()],
-            );
-        }
+        let server_randomness = RandomnessDerivation::expand::<Conf::PRFScheme, RANDOMNESS_BYTES>(
+            &server_seed,
+            prf_eval_points,
+        )?;
         // compute full randomness from client and server part
-        let mut randomness = server_randomness.clone();
-        randomness
-            .iter_mut()
-            .zip(client_randomness)
-            .for_each(|(client_byte, server_byte)| *client_byte ^= server_byte);
+        let randomness = RandomnessDerivation::xor(&server_randomness, &client_randomness);
 
         // apply LDP
-        let ldp_bit = {
-            (BigUint::from_bytes_le(&randomness[0..GAMMA_BYTES])
-                <= BigUint::from_bytes_le(&self.parameters.gamma_as_bytes()?)) as u8
-        };
-
-        let ldp_value = if ldp_bit == 0 {
-            if IS_REAL_INPUT {
-                let input_value_times_k = &input_value * K;
-                let multiplicand =
-                    &input_value_times_k / BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]);
-                let remainder = &input_value_times_k
-                    - &multiplicand * BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]);
-                let random_input_bytes =
-                    &randomness[GAMMA_BYTES + INPUT_BYTES..GAMMA_BYTES + 2 * INPUT_BYTES];
-                let random_input_bit =
-                    (BigUint::from_bytes_le(&random_input_bytes) <= remainder) as u64;
-                if multiplicand.is_zero() {
-                    random_input_bit
-                } else {
-                    multiplicand.to_u64_digits()[0] + random_input_bit
-                }
-            } else {
-                if (&input_value).is_zero() {
-                    0
-                } else {
-                    (&input_value).to_u64_digits()[0]
-                }
-            }
-        } else {
-            // ldp_bit == 1
-            let boundary_gap = if IS_REAL_INPUT {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / (K + 1)
-            } else {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / K
-            };
-            let computed_ldp_value =
-                BigUint::from_bytes_le(&randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES])
-                    / boundary_gap;
-            let computed_ldp_value = if computed_ldp_value.is_zero() {
-                0
-            } else {
-                computed_ldp_value.to_u64_digits()[0]
-            };
-            if IS_REAL_INPUT {
-                min(computed_ldp_value, K)
-            } else {
-                min(computed_ldp_value, K - 1) + 1
-            }
-        };
-
-        let mut input_value_bytes = [0; INPUT_BYTES];
-        for (idx, byte) in input_value.to_bytes_le().iter().enumerate() {
-            input_value_bytes[idx] = *byte;
-        }
+        let ldp_value = LdpApply::apply::<Conf, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+            &self.parameters.gamma_as_bytes()?,
+            None,
+            &input_value,
+            &randomness,
+        );
 
         // create proof
         let proof = if skip_proof {
             Proof::<Conf>::default()
         } else {
+            // recompute the authentication path of this entry of the merkle tree
+            let merkle_path = self
+                .merkle_tree()
+                .generate_proof(index, |batch| self.leaves(&generator_seed, batch))?;
             CircuitExpand::<
                 _,
                 ConfG,
@@ -346,16 +388,20 @@ impl<
                 input_value_signature,
                 self.client_sig_pk.clone(),
                 server_randomness,
+                client_leaf_seed,
                 client_randomness,
                 client_randomness_commitment_randomness,
                 self.storage.clone(),
+                merkle_path,
                 zkp_rng,
             )?
         };
 
-        // store the seed after the generator has been used
-        self.storage.generator_seed = Some(generator.get_seed());
-        self.storage.index += 1;
+        // leaves before the used leaf can no longer be used if the index is bound to the epoch
+        self.storage.index = match self.storage.start_epoch {
+            Some(_) => index + 1,
+            None => self.storage.index + 1,
+        };
 
         // return message
         let mut serialized_message = vec![];
@@ -363,20 +409,120 @@ impl<
             client_sig_pk: self.client_sig_pk.clone(),
             client_merkle_tree_root: self
                 .storage
-                .merkle_tree
-                .as_ref()
-                .map(|mt| mt.root())
+                .merkle_tree_root
+                .clone()
                 .ok_or(ClientError::UnobtainedValue)?,
+            previous_client_merkle_tree_root: self.storage.previous_merkle_tree_root.clone(),
+            start_epoch: self.storage.start_epoch,
             server_seed,
             server_signature: self
                 .storage
                 .server_signature
                 .clone()
                 .ok_or(ClientError::UnobtainedValue)?,
+            batch: self.storage.batch.clone(),
             proof,
             ldp_value,
         }
         .serialize_compressed(&mut serialized_message)?;
         Ok(serialized_message)
     }
+
+    /// The client merkle tree, which does not store any nodes (its leaves are generated on demand).
+    fn merkle_tree(&self) -> ClientStreamingMerkleTree<Conf> {
+        ClientStreamingMerkleTree::<Conf>::new(
+            &self.parameters.client_merkle_tree_scheme.leaf_crh_params,
+            &self
+                .parameters
+                .client_merkle_tree_scheme
+                .two_to_one_crh_params,
+            MT_DEPTH,
+        )
+    }
+
+    /// Generate the leaves at the indices in `batch` of the merkle tree, i.e., the commitments to
+    /// the client randomness (or the leaf seeds, if seeded leaves are used) of those indices.
+    ///
+    /// The leaves are generated in parallel if the `parallel` feature is enabled, since every leaf
+    /// is derived from its own index the result does not depend on the number of threads.
+    fn leaves(
+        &self,
+        generator_seed: &[u8; 32],
+        batch: Range<usize>,
+    ) -> Result<Vec<ClientCommitmentSchemeOutput<Conf>>, Error>
+    where
+        ClientCommitmentSchemeParameters<Conf>: Sync,
+        ClientCommitmentSchemeOutput<Conf>: Send,
+    {
+        let commitment_parameters = &self.parameters.client_commitment_scheme;
+        let seeded_leaves = self.parameters.has_seeded_leaves();
+        let leaf = |index: usize| -> Result<ClientCommitmentSchemeOutput<Conf>, Error> {
+            let (client_leaf_seed, client_randomness, client_randomness_commitment_randomness) =
+                Self::leaf_randomness(generator_seed, index, seeded_leaves)?;
+            Conf::ClientCommitmentScheme::commit(
+                commitment_parameters,
+                client_leaf_seed
+                    .as_ref()
+                    .map_or(client_randomness.as_slice(), |seed| seed.as_slice()),
+                &client_randomness_commitment_randomness,
+            )
+        };
+        // errors are not thread-safe, so they are converted to their message inside the iterator
+        Ok(cfg_into_iter!(batch)
+            .map(|index| leaf(index).map_err(|error| error.to_string()))
+            .collect::<Result<Vec<_>, String>>()?)
+    }
+
+    /// Derive the client randomness for the leaf at `index` of the merkle tree, along with the
+    /// randomness of its commitment. With seeded leaves, this also returns the seed of the leaf
+    /// from which the client randomness is derived.
+    ///
+    /// The commitment randomness of each leaf is drawn from its own stream of the generator, such
+    /// that any leaf can be derived without deriving the leaves before it.
+    #[allow(clippy::type_complexity)]
+    fn leaf_randomness(
+        generator_seed: &[u8; 32],
+        index: usize,
+        seeded_leaves: bool,
+    ) -> Result<
+        (
+            Option<PRFSchemeSeed<Conf>>,
+            [u8; RANDOMNESS_BYTES],
+            ClientCommitmentSchemeRandomness<Conf>,
+        ),
+        Error,
+    > {
+        let mut generator = ChaChaRng::from_seed(*generator_seed);
+        let mut client_seed = PRFSchemeSeed::<Conf>::default();
+        generator.fill_bytes(&mut client_seed);
+        let (client_leaf_seed, client_randomness) = if seeded_leaves {
+            let client_leaf_seed = Conf::PRFScheme::evaluate(
+                &client_seed,
+                &RandomnessDerivation::index_eval_point(index),
+            )?;
+            let client_randomness =
+                RandomnessDerivation::expand::<Conf::PRFScheme, RANDOMNESS_BYTES>(
+                    &client_leaf_seed,
+                    &RandomnessDerivation::index_eval_points::<RANDOMNESS_BYTES>(0),
+                )?;
+            (Some(client_leaf_seed), client_randomness)
+        } else {
+            let client_randomness =
+                RandomnessDerivation::expand::<Conf::PRFScheme, RANDOMNESS_BYTES>(
+                    &client_seed,
+                    &RandomnessDerivation::index_eval_points::<RANDOMNESS_BYTES>(index),
+                )?;
+            (None, client_randomness)
+        };
+
+        let mut leaf_generator = ChaChaRng::from_seed(*generator_seed);
+        leaf_generator.set_stream(index as u64 + 1);
+        let client_randomness_commitment_randomness =
+            ClientCommitmentSchemeRandomness::<Conf>::rand(&mut leaf_generator);
+        Ok((
+            client_leaf_seed,
+            client_randomness,
+            client_randomness_commitment_randomness,
+        ))
+    }
 }
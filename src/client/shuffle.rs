@@ -2,20 +2,32 @@
 
 use crate::circuits::CircuitShuffle;
 use crate::messages::shuffle::*;
+use crate::messages::{verify_server_signature, BatchPath};
 use crate::prelude::*;
+use crate::primitives::memoization::PermanentRandomness;
+use crate::primitives::multi_message::MultiMessage;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::Zero;
 use num_bigint::BigUint;
-use std::cmp::min;
 
 /// Storage of values between steps for a client in the Shuffle scheme
+///
+/// The nullifier key (and its commitment) is generated in the first `Generate Randomness` step,
+/// and is kept for all later steps. The registry root and path are only used if the parameters use
+/// a registry of client keys, and the batch root and path are only used if the parameters use
+/// batches of `Generate Randomness` steps.
 #[derive(Clone)]
 pub struct ClientShuffleStorage<Conf: Config> {
     pub client_seed: Option<PRFSchemeSeed<Conf>>,
     pub client_seed_commitment_randomness: Option<ClientCommitmentSchemeRandomness<Conf>>,
     pub client_seed_commitment: Option<ClientCommitmentSchemeOutput<Conf>>,
+    pub nullifier_key: Option<PRFSchemeSeed<Conf>>,
+    pub nullifier_key_commitment_randomness: Option<ClientCommitmentSchemeRandomness<Conf>>,
+    pub nullifier_key_commitment: Option<ClientCommitmentSchemeOutput<Conf>>,
     pub server_seed: Option<PRFSchemeSeed<Conf>>,
     pub server_signature: Option<ServerSignatureSchemeSignature<Conf>>,
+    pub registry_root: Option<ClientMerkleTreeRoot<Conf>>,
+    pub registry_path: Option<ClientMerkleTreePath<Conf>>,
+    pub batch: Option<BatchPath<Conf>>,
 }
 
 impl<Conf: Config> ClientShuffleStorage<Conf> {
@@ -25,8 +37,14 @@ impl<Conf: Config> ClientShuffleStorage<Conf> {
             client_seed: None,
             client_seed_commitment_randomness: None,
             client_seed_commitment: None,
+            nullifier_key: None,
+            nullifier_key_commitment_randomness: None,
+            nullifier_key_commitment: None,
             server_seed: None,
             server_signature: None,
+            registry_root: None,
+            registry_path: None,
+            batch: None,
         }
     }
 }
@@ -43,6 +61,8 @@ pub struct ClientShuffle<
 > {
     parameters: ParametersShuffle<Conf, GAMMA_BYTES>,
     server_sig_pk: ServerSignatureSchemePublicKey<Conf>,
+    server_enc_pk: ReportEncryptionSchemePublicKey<Conf>,
+    shuffler_enc_pk: ReportEncryptionSchemePublicKey<Conf>,
     client_sig_pk: ClientSignatureSchemePublicKey<Conf>,
     proving_key: ProvingKey<Conf>,
     storage: ClientShuffleStorage<Conf>,
@@ -59,22 +79,80 @@ impl<
     >
     ClientShuffle<Conf, INPUT_BYTES, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, K, IS_REAL_INPUT>
 {
-    /// Create a new client with the given system parameters, signature public keys (server and client) and proof generation key.
+    /// Create a new client with the given system parameters, signature public keys (server and client), encryption public keys (server and shuffler) and proof generation key.
     pub fn new(
         parameters: ParametersShuffle<Conf, GAMMA_BYTES>,
         server_sig_pk: ServerSignatureSchemePublicKey<Conf>,
+        server_enc_pk: ReportEncryptionSchemePublicKey<Conf>,
+        shuffler_enc_pk: ReportEncryptionSchemePublicKey<Conf>,
         client_sig_pk: ClientSignatureSchemePublicKey<Conf>,
         proving_key: ProvingKey<Conf>,
     ) -> Result<Self, Error> {
+        if parameters.permanent_gamma_as_bytes()?.is_some() {
+            PermanentRandomness::check::<INPUT_BYTES, TIME_BYTES, RANDOMNESS_BYTES>()?;
+        }
         Ok(Self {
             parameters,
             server_sig_pk,
+            server_enc_pk,
+            shuffler_enc_pk,
             client_sig_pk,
             proving_key,
             storage: ClientShuffleStorage::new(),
         })
     }
 
+    /// Store the current root of the registry of client keys, and the path of the client key for
+    /// this root (as obtained from the server), which are needed to prove that the client key is
+    /// registered.
+    pub fn set_registration(
+        &mut self,
+        registry_root: ClientMerkleTreeRoot<Conf>,
+        registry_path: ClientMerkleTreePath<Conf>,
+    ) {
+        self.storage.registry_root = Some(registry_root);
+        self.storage.registry_path = Some(registry_path);
+    }
+
+    /// Compute the permanent randomized value of the given true value if the parameters use
+    /// memoization, i.e., the value to which the mechanism is applied in every report of this true
+    /// value. Since it is derived from the nullifier key, it is the same after every later
+    /// `Generate Randomness` step.
+    pub fn permanent_value(&self, input_value: &BigUint) -> Result<Option<LdpValue<Conf>>, Error> {
+        let permanent_gamma = match self.parameters.permanent_gamma_as_bytes()? {
+            Some(permanent_gamma) => permanent_gamma,
+            None => return Ok(None),
+        };
+        let input_value_bytes =
+            AuthenticatedInput::encode::<Conf::LdpMechanism, INPUT_BYTES, K, IS_REAL_INPUT>(
+                input_value,
+            )?;
+        Ok(Some(Conf::LdpMechanism::apply::<
+            INPUT_BYTES,
+            GAMMA_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >(
+            &permanent_gamma,
+            input_value,
+            &self.permanent_randomness(&input_value_bytes)?,
+        )))
+    }
+
+    /// Derive the permanent randomness of the given true value from the nullifier key.
+    fn permanent_randomness(
+        &self,
+        input_value_bytes: &[u8; INPUT_BYTES],
+    ) -> Result<[u8; RANDOMNESS_BYTES], Error> {
+        PermanentRandomness::derive::<Conf::PRFScheme, INPUT_BYTES, RANDOMNESS_BYTES>(
+            &self
+                .storage
+                .nullifier_key
+                .ok_or(ClientError::UnobtainedValue)?,
+            input_value_bytes,
+        )
+    }
+
     /// Perform the first part of the `Generate Randomness` step of the client.
     pub fn generate_randomness_create<R: Rng + CryptoRng>(
         &mut self,
@@ -93,6 +171,21 @@ impl<
             &commitment_randomness,
         )?;
 
+        // generate a nullifier key, if this is the first `Generate Randomness` step
+        if self.storage.nullifier_key.is_none() {
+            let mut nullifier_key = PRFSchemeSeed::<Conf>::default();
+            rng.fill_bytes(&mut nullifier_key);
+            let commitment_randomness = ClientCommitmentSchemeRandomness::<Conf>::rand(rng);
+            let nullifier_key_commitment = Conf::ClientCommitmentScheme::commit(
+                &self.parameters.client_commitment_scheme,
+                &nullifier_key,
+                &commitment_randomness,
+            )?;
+            self.storage.nullifier_key = Some(nullifier_key);
+            self.storage.nullifier_key_commitment_randomness = Some(commitment_randomness);
+            self.storage.nullifier_key_commitment = Some(nullifier_key_commitment);
+        }
+
         // storage
         self.storage.client_seed = Some(client_seed);
         self.storage.client_seed_commitment_randomness = Some(commitment_randomness);
@@ -102,6 +195,11 @@ impl<
         let mut serialized_message = vec![];
         GenerateRandomnessMessageClientShuffle::<Conf> {
             client_seed_commitment,
+            nullifier_key_commitment: self
+                .storage
+                .nullifier_key_commitment
+                .clone()
+                .ok_or(ClientError::UnobtainedValue)?,
             client_signature_public_key: self.client_sig_pk.clone(),
         }
         .serialize_compressed(&mut serialized_message)?;
@@ -109,6 +207,9 @@ impl<
     }
 
     /// Perform the second part of the `Generate Randomness` step of the client.
+    ///
+    /// If the parameters use batches, the server signature is verified on the root of the batch,
+    /// which must contain the signature input of the client.
     pub fn generate_randomness_verify(&mut self, server_message: &[u8]) -> Result<bool, Error>
     where
         ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
@@ -126,22 +227,38 @@ impl<
                 .client_seed_commitment
                 .clone()
                 .ok_or(ClientError::UnobtainedValue)?,
+            nullifier_key_commitment: self
+                .storage
+                .nullifier_key_commitment
+                .clone()
+                .ok_or(ClientError::UnobtainedValue)?,
             client_signature_public_key: self.client_sig_pk.clone(),
             server_seed: server_message.server_seed.clone(),
         };
-        let mut signature_input_bytes = Vec::new();
-        signature_input.serialize_uncompressed(&mut signature_input_bytes)?;
+
+        // the path must be of the batch depth if (and only if) the parameters use batches
+        let batch_height = server_message
+            .batch
+            .as_ref()
+            .map(|batch| batch.path.auth_path.len() + 2);
+        if batch_height != self.parameters.batch_depth() {
+            return Ok(false);
+        }
 
         // verify signature
-        if Conf::ServerSignatureScheme::verify(
+        if verify_server_signature::<Conf>(
+            &self.parameters.client_commitment_scheme,
+            &self.parameters.client_merkle_tree_scheme,
             &self.parameters.server_signature_scheme,
             &self.server_sig_pk,
-            &signature_input_bytes,
+            &signature_input,
             &server_message.server_signature,
+            server_message.batch.as_ref(),
         )? {
             // storage
             self.storage.server_seed = Some(server_message.server_seed);
             self.storage.server_signature = Some(server_message.server_signature);
+            self.storage.batch = server_message.batch;
 
             // return success
             Ok(true)
@@ -154,6 +271,16 @@ impl<
     /// Given the time bounds of the current step, the true input value, the time it was created,
     /// and its signature, along with the list of public `prf_eval_points` (s in the paper) and
     /// current `index` (j in the paper) perform the `Randomize` step of the client.
+    /// If the parameters use memoization, the true value is first replaced by its permanent
+    /// randomized value, which is the same for every report of the same true value (see
+    /// `permanent_value`).
+    /// The report carries the nullifier of the client for the epoch that ends at the upper time
+    /// bound, and is encrypted under the encryption public key of the server (such that the
+    /// shuffler cannot read it). The encrypted report and the proof are then encrypted under the
+    /// encryption public key of the shuffler, which rerandomizes the proof before forwarding it
+    /// (such that the messages on either side of the shuffler cannot be linked).
+    /// If the parameters use multiple messages, `verifiable_randomization_create_messages` must be
+    /// used instead.
     ///
     /// The `skip_proof` flag can be set to `true` to do a faster test run of this function that
     /// only executes the randomization (without proof generation).
@@ -171,116 +298,198 @@ impl<
     where
         Proof<Conf>: CanonicalDeserialize,
     {
-        // compute full seed from client and server part
-        let mut seed = self
-            .storage
-            .client_seed
-            .ok_or(ClientError::UnobtainedValue)?;
-        seed.iter_mut()
-            .zip(
-                self.storage
-                    .server_seed
-                    .ok_or(ClientError::UnobtainedValue)?,
-            )
-            .for_each(|(client_byte, server_byte)| *client_byte ^= server_byte);
+        if self.parameters.messages() > 1 {
+            Err("The parameters use multiple messages per report.")?
+        }
+        let mut messages = self.verifiable_randomization_create_messages::<ConfG>(
+            time_bounds,
+            input_value_time,
+            input_value,
+            input_value_signature,
+            prf_eval_points,
+            zkp_rng,
+            skip_proof,
+        )?;
+        Ok(messages.remove(0))
+    }
 
-        // compute randomness from seeds
-        let mut randomness = [0; RANDOMNESS_BYTES];
-        for (chunk, prf_eval_point) in randomness.chunks_mut(32).zip(prf_eval_points) {
-            chunk.copy_from_slice(
-                &Conf::PRFScheme::evaluate(&seed, &prf_eval_point)?[0..chunk.len()],
-            );
+    /// Perform the `Randomize` step of the client (see `verifiable_randomization_create`), which
+    /// returns one message per message index if the parameters use multiple messages.
+    /// Each message has its own randomness, nullifier and proof. One of these messages contains
+    /// the randomized true value, and all other messages contain blanket noise (see
+    /// `MultiMessage`).
+    #[allow(clippy::too_many_arguments)]
+    pub fn verifiable_randomization_create_messages<ConfG: ConfigGadget<Conf>>(
+        &self,
+        time_bounds: ([u8; TIME_BYTES], [u8; TIME_BYTES]),
+        input_value_time: [u8; TIME_BYTES],
+        input_value: BigUint,
+        input_value_signature: ClientSignatureSchemeSignature<Conf>,
+        prf_eval_points: &[PRFSchemeInput<Conf>],
+        zkp_rng: &mut ZKPRng<Conf>,
+        skip_proof: bool,
+    ) -> Result<Vec<Vec<u8>>, Error>
+    where
+        Proof<Conf>: CanonicalDeserialize,
+    {
+        // check that the input value is in the input domain of the mechanism
+        let input_value_bytes =
+            AuthenticatedInput::encode::<Conf::LdpMechanism, INPUT_BYTES, K, IS_REAL_INPUT>(
+                &input_value,
+            )?;
+        // check that the input value was created within the time bounds
+        if !TimeWindow::contains(&time_bounds, &input_value_time) {
+            Err(ClientError::InvalidTime)?
         }
 
-        // apply LDP
-        let ldp_bit = {
-            (BigUint::from_bytes_le(&randomness[0..GAMMA_BYTES])
-                <= BigUint::from_bytes_le(&self.parameters.gamma_as_bytes()?)) as u8
+        // compute full seed from client and server part
+        let seed = RandomnessDerivation::xor(
+            &self
+                .storage
+                .client_seed
+                .ok_or(ClientError::UnobtainedValue)?,
+            &self
+                .storage
+                .server_seed
+                .ok_or(ClientError::UnobtainedValue)?,
+        );
+
+        // permanent randomness (memoization), this only depends on the nullifier key and the input
+        // value
+        let permanent_gamma = self.parameters.permanent_gamma_as_bytes()?;
+        let permanent_randomness = match permanent_gamma {
+            Some(_) => Some(self.permanent_randomness(&input_value_bytes)?),
+            None => None,
         };
 
-        let ldp_value = if ldp_bit == 0 {
-            if IS_REAL_INPUT {
-                let input_value_times_k = &input_value * K;
-                let multiplicand =
-                    &input_value_times_k / BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]);
-                let remainder = &input_value_times_k
-                    - &multiplicand * BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]);
-                let random_input_bytes =
-                    &randomness[GAMMA_BYTES + INPUT_BYTES..GAMMA_BYTES + 2 * INPUT_BYTES];
-                let random_input_bit =
-                    (BigUint::from_bytes_le(&random_input_bytes) <= remainder) as u64;
-                if multiplicand.is_zero() {
-                    random_input_bit
+        // index of the real message, all other messages use the blanket gamma
+        let messages = self.parameters.messages();
+        let real_index = MultiMessage::real_index::<Conf::PRFScheme>(&seed, messages)?;
+        let gamma = self.parameters.gamma_as_bytes()?;
+        let blanket_gamma = Conf::LdpMechanism::blanket_gamma::<GAMMA_BYTES>();
+
+        (0..messages)
+            .map(|message_index| {
+                let message_index = message_index as u8;
+
+                // compute randomness from seeds
+                let randomness = if messages > 1 {
+                    RandomnessDerivation::expand::<Conf::PRFScheme, RANDOMNESS_BYTES>(
+                        &seed,
+                        &MultiMessage::eval_points(prf_eval_points, message_index),
+                    )?
                 } else {
-                    multiplicand.to_u64_digits()[0] + random_input_bit
-                }
-            } else {
-                if (&input_value).is_zero() {
-                    0
+                    RandomnessDerivation::expand::<Conf::PRFScheme, RANDOMNESS_BYTES>(
+                        &seed,
+                        prf_eval_points,
+                    )?
+                };
+
+                // apply LDP
+                let message_gamma = if message_index == real_index {
+                    gamma
                 } else {
-                    (&input_value).to_u64_digits()[0]
-                }
-            }
-        } else {
-            // ldp_bit == 1
-            let boundary_gap = if IS_REAL_INPUT {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / (K + 1)
-            } else {
-                BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / K
-            };
-            let computed_ldp_value =
-                BigUint::from_bytes_le(&randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES])
-                    / boundary_gap;
-            let computed_ldp_value = if computed_ldp_value.is_zero() {
-                0
-            } else {
-                computed_ldp_value.to_u64_digits()[0]
-            };
-            if IS_REAL_INPUT {
-                min(computed_ldp_value, K)
-            } else {
-                min(computed_ldp_value, K - 1) + 1
-            }
-        };
+                    blanket_gamma.ok_or("The mechanism has no blanket gamma.")?
+                };
+                let ldp_value = LdpApply::apply::<Conf, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                    &message_gamma,
+                    permanent_gamma
+                        .as_ref()
+                        .zip(permanent_randomness.as_ref().map(|x| x.as_slice())),
+                    &input_value,
+                    &randomness,
+                );
 
-        let mut input_value_bytes = [0; INPUT_BYTES];
-        for (idx, byte) in input_value.to_bytes_le().iter().enumerate() {
-            input_value_bytes[idx] = *byte;
-        }
+                // nullifier of the client for the current epoch and message
+                let nullifier = Nullifier::derive::<Conf::PRFScheme, TIME_BYTES>(
+                    &self
+                        .storage
+                        .nullifier_key
+                        .ok_or(ClientError::UnobtainedValue)?,
+                    &time_bounds.1,
+                    message_index,
+                )?;
 
-        // create proof
-        let proof = if skip_proof {
-            Proof::<Conf>::default()
-        } else {
-            CircuitShuffle::<
-                _,
-                ConfG,
-                INPUT_BYTES,
-                TIME_BYTES,
-                GAMMA_BYTES,
-                RANDOMNESS_BYTES,
-                K,
-                IS_REAL_INPUT,
-            >::prove(
-                &self.proving_key,
-                self.parameters.clone(),
-                ldp_value,
-                self.server_sig_pk.clone(),
-                prf_eval_points,
-                time_bounds,
-                input_value_bytes,
-                input_value_time,
-                input_value_signature,
-                self.client_sig_pk.clone(),
-                self.storage.clone(),
-                zkp_rng,
-            )?
-        };
+                // create proof
+                let proof = if skip_proof {
+                    Proof::<Conf>::default()
+                } else {
+                    CircuitShuffle::<
+                        _,
+                        ConfG,
+                        INPUT_BYTES,
+                        TIME_BYTES,
+                        GAMMA_BYTES,
+                        RANDOMNESS_BYTES,
+                        K,
+                        IS_REAL_INPUT,
+                    >::prove(
+                        &self.proving_key,
+                        self.parameters.clone(),
+                        ldp_value,
+                        self.server_sig_pk.clone(),
+                        prf_eval_points,
+                        nullifier,
+                        message_index,
+                        time_bounds,
+                        input_value_bytes,
+                        input_value_time,
+                        input_value_signature.clone(),
+                        self.client_sig_pk.clone(),
+                        self.storage.clone(),
+                        zkp_rng,
+                    )?
+                };
 
-        // return message
+                self.encrypt_message(
+                    ReportShuffle::<Conf, INPUT_BYTES> {
+                        ldp_value,
+                        nullifier,
+                        message_index,
+                    },
+                    proof,
+                    zkp_rng,
+                )
+            })
+            .collect()
+    }
+
+    /// Encrypt the report for the server, and the encrypted report along with its proof for the
+    /// shuffler.
+    fn encrypt_message(
+        &self,
+        report: ReportShuffle<Conf, INPUT_BYTES>,
+        proof: Proof<Conf>,
+        zkp_rng: &mut ZKPRng<Conf>,
+    ) -> Result<Vec<u8>, Error>
+    where
+        Proof<Conf>: CanonicalDeserialize,
+    {
+        // encrypt report for the server
+        let mut serialized_report = vec![];
+        ReportPayloadShuffle::Client(report).serialize_compressed(&mut serialized_report)?;
+        let encrypted_report = EncryptedReportShuffle::<Conf>::encrypt(
+            &self.parameters.report_encryption_scheme,
+            &self.server_enc_pk,
+            &serialized_report,
+            zkp_rng,
+        )?;
+
+        // encrypt message for the shuffler and return it
+        let mut serialized_shuffler_message = vec![];
+        VerifiableRandomizationMessageShuffle::<Conf> {
+            proof,
+            encrypted_report,
+        }
+        .serialize_compressed(&mut serialized_shuffler_message)?;
         let mut serialized_message = vec![];
-        VerifiableRandomizationMessageShuffle::<Conf, INPUT_BYTES> { proof, ldp_value }
-            .serialize_compressed(&mut serialized_message)?;
+        EncryptedReportShuffle::<Conf>::encrypt(
+            &self.parameters.report_encryption_scheme,
+            &self.shuffler_enc_pk,
+            &serialized_shuffler_message,
+            zkp_rng,
+        )?
+        .serialize_compressed(&mut serialized_message)?;
         Ok(serialized_message)
     }
 }
@@ -8,6 +8,7 @@ use super::windows::{ClientCommitmentSchemeWindow, PedersenMerkleTreeWindow};
 use crate::config::merkle_tree::{PedersenMerkleTreeConfig, PedersenMerkleTreeConfigGadget};
 use crate::prelude::{constraints::*, *};
 use crate::primitives::crh;
+use crate::primitives::encryption::Ecies;
 use crate::primitives::signature::schnorr::constraints::SchnorrSignatureVerifyGadget;
 use crate::primitives::signature::Schnorr;
 use ark_crypto_primitives::commitment::pedersen::constraints::CommGadget;
@@ -22,6 +23,7 @@ pub struct BasicConfig<
     R: ProofSystemRng,
     PS: ProofSystem<R, ConstraintField = ConstraintF<C>>,
     const NUM_COMM_BYTES: usize,
+    M: Mechanism = RandomizedResponse,
 > {
     /// Needed to bind these generic types to the struct.
     #[doc(hidden)]
@@ -30,6 +32,8 @@ pub struct BasicConfig<
     _rng: PhantomData<R>,
     #[doc(hidden)]
     _proof_system: PhantomData<PS>,
+    #[doc(hidden)]
+    _mechanism: PhantomData<M>,
 }
 
 // We need to manually implement clone due to the const generic.
@@ -38,13 +42,15 @@ impl<
         R: ProofSystemRng,
         PS: ProofSystem<R, ConstraintField = ConstraintF<C>>,
         const NUM_COMM_BYTES: usize,
-    > Clone for BasicConfig<C, R, PS, NUM_COMM_BYTES>
+        M: Mechanism,
+    > Clone for BasicConfig<C, R, PS, NUM_COMM_BYTES, M>
 {
     fn clone(&self) -> Self {
         Self {
             _curve_group: PhantomData,
             _rng: PhantomData,
             _proof_system: PhantomData,
+            _mechanism: PhantomData,
         }
     }
 }
@@ -55,7 +61,8 @@ impl<
         R: ProofSystemRng,
         PS: ProofSystem<R, ConstraintField = ConstraintF<C>>,
         const NUM_COMM_BYTES: usize,
-    > Config for BasicConfig<C, R, PS, NUM_COMM_BYTES>
+        M: Mechanism,
+    > Config for BasicConfig<C, R, PS, NUM_COMM_BYTES, M>
 {
     type ZKPRng = R;
     type ZKPScheme = PS;
@@ -65,15 +72,22 @@ impl<
     type ClientSignatureScheme = Schnorr<C, crh::Blake2s256>;
     type ClientMerkleTreeConfig =
         PedersenMerkleTreeConfig<C, ClientCommitmentSchemeOutput<Self>, PedersenMerkleTreeWindow>;
+    type LdpMechanism = M;
+    type ReportEncryptionScheme = Ecies<C, crh::Blake2s256>;
 }
 
 /// Basic configuration struct for the R1CS part of the configuration as used for the experiments
 /// in the paper.
 #[derive(Clone)]
-pub struct BasicConfigGadget<C: CurveGroup, CG: CurveVar<C, ConstraintF<C>>> {
+pub struct BasicConfigGadget<
+    C: CurveGroup,
+    CG: CurveVar<C, ConstraintF<C>>,
+    MG = RandomizedResponseGadget,
+> {
     #[doc(hidden)]
     _curve_group: PhantomData<C>,
     _curve_group_gadget: PhantomData<CG>,
+    _mechanism_gadget: PhantomData<MG>,
 }
 
 // HERE IS THE ACTUAL DEFINITION OF THE USED GADGETS
@@ -83,7 +97,9 @@ impl<
         PS: ProofSystem<R, ConstraintField = ConstraintF<C>>,
         CG: CurveVar<C, ConstraintF<C>>,
         const NUM_COMM_BYTES: usize,
-    > ConfigGadget<BasicConfig<C, R, PS, NUM_COMM_BYTES>> for BasicConfigGadget<C, CG>
+        M: Mechanism,
+        MG: MechanismGadget<M, ConstraintF<C>> + Clone,
+    > ConfigGadget<BasicConfig<C, R, PS, NUM_COMM_BYTES, M>> for BasicConfigGadget<C, CG, MG>
 where
     for<'a> &'a CG: GroupOpsBounds<'a, C, CG>,
 {
@@ -105,6 +121,7 @@ where
     type ClientMerkleTreeConfigGadget = PedersenMerkleTreeConfigGadget<
         C,
         CG,
-        ClientCommitmentSchemeOutputVar<BasicConfig<C, R, PS, NUM_COMM_BYTES>, Self>,
+        ClientCommitmentSchemeOutputVar<BasicConfig<C, R, PS, NUM_COMM_BYTES, M>, Self>,
     >;
+    type LdpMechanismGadget = MG;
 }
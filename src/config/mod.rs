@@ -23,6 +23,8 @@ pub trait Config: Clone {
     type ClientMerkleTreeConfig: ark_crypto_primitives::merkle_tree::Config<
         Leaf = ClientCommitmentSchemeOutput<Self>,
     >;
+    type LdpMechanism: Mechanism;
+    type ReportEncryptionScheme: EncryptionScheme;
 }
 
 /// Subtrait of the `Config` trait to additionally describe all options that are to be defined
@@ -47,4 +49,5 @@ pub trait ConfigGadget<Conf: Config>: Clone {
         ConstraintField<Conf>,
         Leaf = ClientCommitmentSchemeOutputVar<Conf, Self>,
     >;
+    type LdpMechanismGadget: MechanismGadget<Conf::LdpMechanism, ConstraintField<Conf>>;
 }
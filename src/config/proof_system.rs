@@ -11,13 +11,21 @@ use rand_chacha::ChaChaRng;
 
 /// Some proof systems need a specifically defined RNG, this trait helps to make that generic.
 pub trait ProofSystemRng: RngCore {
+    /// Create a new RNG seeded from entropy.
     fn new() -> Self;
+
+    /// Create a new RNG from an explicit seed, such that its output can be reproduced.
+    fn with_seed(seed: u64) -> Self;
 }
 
 impl ProofSystemRng for ChaChaRng {
     fn new() -> Self {
         Self::from_entropy()
     }
+
+    fn with_seed(seed: u64) -> Self {
+        Self::seed_from_u64(seed)
+    }
 }
 
 /// Generic trait for a ZKP scheme. This makes it easier to switch out different ZKP schemes without
@@ -45,6 +53,14 @@ pub trait ProofSystem<R: ProofSystemRng> {
         proof: &Self::Proof,
         rng: &mut R,
     ) -> Result<bool, Error>;
+
+    /// Rerandomize a valid proof, such that it still verifies for the same public inputs but
+    /// cannot be linked to the original proof.
+    fn rerandomize_proof(
+        verifying_key: &Self::VerifyingKey,
+        proof: &Self::Proof,
+        rng: &mut R,
+    ) -> Result<Self::Proof, Error>;
 }
 
 // HERE WE IMPLEMENT THE GENERIC TRAIT FOR GROTH16 (as used in our experiments)
@@ -94,4 +110,66 @@ impl<E: Pairing, QAP: ark_groth16::r1cs_to_qap::R1CSToQAP, R: ProofSystemRng + C
             proof,
         )?)
     }
+
+    fn rerandomize_proof(
+        verifying_key: &Self::VerifyingKey,
+        proof: &Self::Proof,
+        rng: &mut R,
+    ) -> Result<Self::Proof, Error> {
+        Ok(ark_groth16::Groth16::<E, QAP>::rerandomize_proof(
+            &verifying_key.vk,
+            proof,
+            rng,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_relations::lc;
+    use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+
+    /// Circuit that proves knowledge of two factors of the public input.
+    #[derive(Clone)]
+    struct FactorCircuit(Fr, Fr);
+
+    impl ConstraintSynthesizer<Fr> for FactorCircuit {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let product = cs.new_input_variable(|| Ok(self.0 * self.1))?;
+            let a = cs.new_witness_variable(|| Ok(self.0))?;
+            let b = cs.new_witness_variable(|| Ok(self.1))?;
+            cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + product)
+        }
+    }
+
+    #[test]
+    fn groth16_rerandomization_test() {
+        type ZKPScheme = Groth16<Bls12_381>;
+        let rng = &mut ChaChaRng::seed_from_u64(0);
+        let circuit = FactorCircuit(Fr::from(3u8), Fr::from(5u8));
+
+        let (pk, vk) = <ZKPScheme as ProofSystem<_>>::keygen(circuit.clone(), rng).unwrap();
+        let proof = <ZKPScheme as ProofSystem<_>>::prove(&pk, circuit, rng).unwrap();
+        let rerandomized_proof =
+            <ZKPScheme as ProofSystem<_>>::rerandomize_proof(&vk, &proof, rng).unwrap();
+
+        assert!(proof != rerandomized_proof);
+        assert!(<ZKPScheme as ProofSystem<_>>::verify(
+            &vk,
+            &[Fr::from(15u8)],
+            &rerandomized_proof,
+            rng
+        )
+        .unwrap());
+        assert!(!<ZKPScheme as ProofSystem<_>>::verify(
+            &vk,
+            &[Fr::from(16u8)],
+            &rerandomized_proof,
+            rng
+        )
+        .unwrap());
+    }
 }
@@ -5,10 +5,12 @@ use std::fmt::{Display, Formatter};
 /// Generic error class capturing all VLDP errors:
 /// - Conversion error from value to field elemnt
 /// - Parsing related error
+/// - Invalid gamma: gamma is not in the range for which the LDP mechanism is defined
 #[derive(Debug)]
 pub enum GenericError {
     ConversionError,
     ParseError(String),
+    InvalidGamma,
 }
 impl Display for GenericError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -18,6 +20,10 @@ impl Display for GenericError {
                 "You tried to convert a value to a field element, but this failed!"
             ),
             GenericError::ParseError(e) => write!(f, "An error occured during parsing: {}", e),
+            GenericError::InvalidGamma => write!(
+                f,
+                "The value of gamma is not supported by the LDP mechanism."
+            ),
         }
     }
 }
@@ -26,9 +32,15 @@ impl std::error::Error for GenericError {}
 
 /// Class capturing client side errors:
 /// - Unobtained value: tried to use a value from storage that has not yet been computed
+/// - Invalid input: the input value is not in the input domain of the LDP mechanism
+/// - Invalid time: the creation time of the input value is not within the time bounds
+/// - Merkle tree exhausted: all leaves of the merkle tree (Expand) have been used
 #[derive(Debug)]
 pub enum ClientError {
     UnobtainedValue,
+    InvalidInput,
+    InvalidTime,
+    MerkleTreeExhausted,
 }
 
 impl Display for ClientError {
@@ -38,8 +50,91 @@ impl Display for ClientError {
                 f,
                 "You tried to use a value from storage, but this value has not yet been obtained."
             ),
+            ClientError::InvalidInput => write!(
+                f,
+                "The input value is not in the input domain of the LDP mechanism."
+            ),
+            ClientError::InvalidTime => write!(
+                f,
+                "The creation time of the input value is not within the time bounds."
+            ),
+            ClientError::MerkleTreeExhausted => write!(
+                f,
+                "All leaves of the Merkle tree have been used, the Merkle tree has to be renewed."
+            ),
         }
     }
 }
 
 impl std::error::Error for ClientError {}
+
+/// Class capturing server side errors:
+/// - Budget exhausted: the client has no privacy budget left in the current window
+/// - Invalid renewal: the client (Expand) already has a merkle tree, or the renewed merkle tree is
+///   not its latest merkle tree, or the renewed merkle tree has unused leaves
+/// - Invalid nullifier key: the nullifier key commitment (Shuffle) differs from the registered one
+/// - Registry full: all leaves of the registry of client keys (Shuffle) have been used
+/// - Unregistered key: the client key is not in the registry of client keys (Shuffle)
+/// - Invalid start epoch: the start epoch (Expand) is missing, not used by the parameters, or
+///   within the epochs of the renewed merkle tree
+/// - Invalid client signature: the client message is not signed by the client (Expand)
+/// - Batch too large: a batch of `GenRand` steps has more clients than its merkle tree has leaves
+/// - Excess dummy reports: the shuffler (Shuffle) added more dummy reports to an epoch than the
+///   minimum batch size minus the number of client reports
+#[derive(Debug)]
+pub enum ServerError {
+    BudgetExhausted,
+    InvalidRenewal,
+    InvalidNullifierKey,
+    RegistryFull,
+    UnregisteredKey,
+    InvalidStartEpoch,
+    InvalidClientSignature,
+    BatchTooLarge,
+    ExcessDummyReports,
+}
+
+impl Display for ServerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerError::BudgetExhausted => write!(
+                f,
+                "The client has exhausted its privacy budget for the current window."
+            ),
+            ServerError::InvalidRenewal => write!(
+                f,
+                "The client already has a Merkle tree, or the renewed Merkle tree is not its latest Merkle tree or has unused leaves."
+            ),
+            ServerError::InvalidNullifierKey => write!(
+                f,
+                "The nullifier key of the client differs from the one it registered before."
+            ),
+            ServerError::RegistryFull => write!(
+                f,
+                "All leaves of the registry of client keys have been used."
+            ),
+            ServerError::UnregisteredKey => write!(
+                f,
+                "The public key of the client is not in the registry of client keys."
+            ),
+            ServerError::InvalidStartEpoch => write!(
+                f,
+                "The start epoch of the Merkle tree is missing, not used by the parameters, or within the epochs of the renewed Merkle tree."
+            ),
+            ServerError::InvalidClientSignature => write!(
+                f,
+                "The message is not signed by the client."
+            ),
+            ServerError::BatchTooLarge => write!(
+                f,
+                "The batch contains more client messages than the Merkle tree of the batch has leaves."
+            ),
+            ServerError::ExcessDummyReports => write!(
+                f,
+                "The epoch contains more dummy reports than the minimum batch size allows."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
@@ -1,5 +1,8 @@
-//! All server and client functionalities for our 3 different VLDP schemes (Base, Expand, Shuffle)
+//! All server and client functionalities for our 3 different VLDP schemes (Base, Expand, Shuffle),
+//! the shuffler of the Shuffle scheme, and a local randomness beacon for the non-interactive Base
+//! scheme
 
+pub mod beacon;
 pub mod circuits;
 pub mod client;
 pub mod config;
@@ -9,3 +12,4 @@ pub mod prelude;
 mod primitives;
 pub mod run_random;
 pub mod server;
+pub mod shuffler;
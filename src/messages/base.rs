@@ -1,6 +1,7 @@
 //! Structs and basic logic for all messages that are sent between clients and server in the
 //! Base VLDP scheme.
 
+use crate::messages::BatchPath;
 use crate::prelude::*;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
@@ -17,6 +18,9 @@ where
 }
 
 /// Message sent by the server in the `GenRand` step of the paper.
+///
+/// If the step is part of a batch, the server signature is the signature on the root of the merkle
+/// tree of the batch, and the message also contains this root and the path of the client leaf.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct GenerateRandomnessMessageServerBase<Conf: Config>
 where
@@ -24,6 +28,7 @@ where
 {
     pub server_seed: PRFSchemeSeed<Conf>,
     pub server_signature: ServerSignatureSchemeSignature<Conf>,
+    pub batch: Option<BatchPath<Conf>>,
 }
 
 /// Message that is to be signed by the server as part of the `GenRand` step of the paper.
@@ -56,19 +61,87 @@ where
     }
 }
 
-/// Message sent by the client as part of the `Randomize` step of the paper.
+/// Round of the public randomness beacon, of which the output replaces the server seed in the
+/// non-interactive `GenRand` step.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
-pub struct VerifiableRandomizationMessageBase<Conf: Config, const INPUT_BYTES: usize>
+pub struct BeaconRoundBase<Conf: Config, const TIME_BYTES: usize>
 where
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+{
+    pub time: [u8; TIME_BYTES],
+    pub output: PRFSchemeSeed<Conf>,
+    pub signature: ServerSignatureSchemeSignature<Conf>,
+}
+
+/// Message that is to be signed by the randomness beacon for every round.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct BeaconSignatureInputBase<Conf: Config, const TIME_BYTES: usize> {
+    pub time: [u8; TIME_BYTES],
+    pub output: PRFSchemeSeed<Conf>,
+}
+
+/// Message that is to be signed by the trusted environment of the client as part of the
+/// non-interactive `GenRand` step, which attests the time at which the client randomness was
+/// committed to.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct CommitmentSignatureInputBase<Conf: Config, const TIME_BYTES: usize>
+where
+    ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+{
+    pub client_randomness_commitment: ClientCommitmentSchemeOutput<Conf>,
+    pub time: [u8; TIME_BYTES],
+}
+
+/// Attestation that the client randomness was committed to before the round of the randomness
+/// beacon that replaces the server seed (non-interactive `GenRand` step).
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BeaconAttestationBase<Conf: Config, const TIME_BYTES: usize>
+where
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+{
+    pub commitment_time: [u8; TIME_BYTES],
+    pub commitment_signature: ClientSignatureSchemeSignature<Conf>,
+    pub beacon_time: [u8; TIME_BYTES],
+}
+
+/// Input from which the server seed is derived, if the server derives its seeds deterministically
+/// instead of storing them (see `ServerBase::set_seed_key`).
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct ServerSeedInputBase<Conf: Config, const TIME_BYTES: usize>
+where
+    ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+{
+    pub client_randomness_commitment: ClientCommitmentSchemeOutput<Conf>,
+    pub client_signature_public_key: ClientSignatureSchemePublicKey<Conf>,
+    pub epoch: [u8; TIME_BYTES],
+}
+
+/// Message sent by the client as part of the `Randomize` step of the paper.
+///
+/// If the server seed is the output of a round of the randomness beacon, the server signature is
+/// the signature of the beacon on this round, and the message also contains the attestation that
+/// the client randomness was committed to before this round. If the server seed was obtained in a
+/// batch, the server signature is the signature on the root of the batch, and the message also
+/// contains this root and the path of the client leaf.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct VerifiableRandomizationMessageBase<
+    Conf: Config,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+> where
     Proof<Conf>: CanonicalDeserialize,
     ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
     ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
 {
     pub client_sig_pk: ClientSignatureSchemePublicKey<Conf>,
     pub client_randomness_commitment: ClientCommitmentSchemeOutput<Conf>,
     pub server_seed: PRFSchemeSeed<Conf>,
     pub server_signature: ServerSignatureSchemeSignature<Conf>,
+    pub beacon: Option<BeaconAttestationBase<Conf, TIME_BYTES>>,
+    pub batch: Option<BatchPath<Conf>>,
     pub proof: Proof<Conf>,
-    pub ldp_value: u64,
+    pub ldp_value: LdpValue<Conf>,
 }
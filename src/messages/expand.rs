@@ -1,20 +1,41 @@
 //! Structs and basic logic for all messages that are sent between clients and server in the
 //! Expand VLDP scheme.
 
+use crate::messages::BatchPath;
 use crate::prelude::*;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
 /// Message sent by client in the `GenRand` step of the paper.
+///
+/// When renewing an exhausted merkle tree, this also contains the root of the tree that is renewed.
+/// If the index is bound to the epoch, this also contains the start epoch of the merkle tree.
+/// The client signs these values (see `MerkleTreeSignatureInputExpand`), such that only the client
+/// can start or extend its chain of merkle trees.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct GenerateRandomnessMessageClientExpand<Conf: Config>
 where
     ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
 {
     pub client_merkle_tree_root: ClientMerkleTreeRoot<Conf>,
     pub client_signature_public_key: ClientSignatureSchemePublicKey<Conf>,
+    pub previous_client_merkle_tree_root: Option<ClientMerkleTreeRoot<Conf>>,
+    pub start_epoch: Option<u64>,
+    pub client_signature: ClientSignatureSchemeSignature<Conf>,
+}
+
+/// Message that is to be signed by the client as part of the `GenRand` step of the paper.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerkleTreeSignatureInputExpand<Conf: Config> {
+    pub client_merkle_tree_root: ClientMerkleTreeRoot<Conf>,
+    pub previous_client_merkle_tree_root: Option<ClientMerkleTreeRoot<Conf>>,
+    pub start_epoch: Option<u64>,
 }
 
 /// Message sent by the server in the `GenRand` step of the paper.
+///
+/// If the step is part of a batch, the server signature is the signature on the root of the merkle
+/// tree of the batch, and the message also contains this root and the path of the client leaf.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct GenerateRandomnessMessageServerExpand<Conf: Config>
 where
@@ -22,9 +43,13 @@ where
 {
     pub server_seed: PRFSchemeSeed<Conf>,
     pub server_signature: ServerSignatureSchemeSignature<Conf>,
+    pub batch: Option<BatchPath<Conf>>,
 }
 
 /// Message that is to be signed by the server as part of the `GenRand` step of the paper.
+///
+/// Including the root of the renewed merkle tree (if any) links the signed root to its predecessor,
+/// and including the start epoch (if any) fixes the leaf that is used in every epoch.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct GenerateRandomnessSignatureInputExpand<Conf: Config>
 where
@@ -33,11 +58,14 @@ where
     pub client_merkle_tree_root: ClientMerkleTreeRoot<Conf>,
     pub client_signature_public_key: ClientSignatureSchemePublicKey<Conf>,
     pub server_seed: PRFSchemeSeed<Conf>,
+    pub previous_client_merkle_tree_root: Option<ClientMerkleTreeRoot<Conf>>,
+    pub start_epoch: Option<u64>,
 }
 
 impl<Conf: Config> GenerateRandomnessSignatureInputExpand<Conf>
 where
     ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
 {
     /// Create signature input message from the received client message and the server seed.
     pub fn new(
@@ -48,11 +76,16 @@ where
             client_merkle_tree_root: client_message.client_merkle_tree_root,
             client_signature_public_key: client_message.client_signature_public_key,
             server_seed,
+            previous_client_merkle_tree_root: client_message.previous_client_merkle_tree_root,
+            start_epoch: client_message.start_epoch,
         }
     }
 }
 
 /// Message sent by the client as part of the `Randomize` step of the paper.
+///
+/// If the server seed was obtained in a batch, this also contains the root of the batch (on which
+/// the server signature is) and the path of the client leaf.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct VerifiableRandomizationMessageExpand<Conf: Config, const INPUT_BYTES: usize>
 where
@@ -62,8 +95,11 @@ where
 {
     pub client_sig_pk: ClientSignatureSchemePublicKey<Conf>,
     pub client_merkle_tree_root: ClientMerkleTreeRoot<Conf>,
+    pub previous_client_merkle_tree_root: Option<ClientMerkleTreeRoot<Conf>>,
+    pub start_epoch: Option<u64>,
     pub server_seed: PRFSchemeSeed<Conf>,
     pub server_signature: ServerSignatureSchemeSignature<Conf>,
+    pub batch: Option<BatchPath<Conf>>,
     pub proof: Proof<Conf>,
-    pub ldp_value: u64,
+    pub ldp_value: LdpValue<Conf>,
 }
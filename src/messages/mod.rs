@@ -1,6 +1,58 @@
 //! Structs and basic logic for all messages that are sent between clients and server in either of
 //! the VLDP schemes (Base, Expand, and Shuffle).
 
+use crate::prelude::*;
+use crate::primitives::parameters::ClientMerkleTreeParameters;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
 pub mod base;
 pub mod expand;
 pub mod shuffle;
+
+/// Root of the merkle tree of a batch of `GenRand` steps, which is signed by the server instead of
+/// the signature input of the client, along with the path of the leaf of the client (see
+/// `SignedBatch`).
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BatchPath<Conf: Config> {
+    pub root: ClientMerkleTreeRoot<Conf>,
+    pub path: ClientMerkleTreePath<Conf>,
+}
+
+/// Verify the server signature of a `GenRand` step on the given signature input: either directly,
+/// or if the step is part of a batch, on the root of the batch of which the signature input is a
+/// leaf.
+pub fn verify_server_signature<Conf: Config>(
+    client_commitment_scheme: &ClientCommitmentSchemeParameters<Conf>,
+    client_merkle_tree_scheme: &ClientMerkleTreeParameters<Conf>,
+    server_signature_scheme: &ServerSignatureSchemeParameters<Conf>,
+    server_sig_pk: &ServerSignatureSchemePublicKey<Conf>,
+    signature_input: &impl CanonicalSerialize,
+    server_signature: &ServerSignatureSchemeSignature<Conf>,
+    batch: Option<&BatchPath<Conf>>,
+) -> Result<bool, Error> {
+    let signature_input_bytes = match batch {
+        Some(batch) => {
+            if !SignedBatch::contains::<Conf>(
+                client_commitment_scheme,
+                client_merkle_tree_scheme,
+                signature_input,
+                &batch.root,
+                &batch.path,
+            )? {
+                return Ok(false);
+            }
+            SignedBatch::message::<Conf>(&batch.root)?
+        }
+        None => {
+            let mut signature_input_bytes = Vec::new();
+            signature_input.serialize_uncompressed(&mut signature_input_bytes)?;
+            signature_input_bytes
+        }
+    };
+    Conf::ServerSignatureScheme::verify(
+        server_signature_scheme,
+        server_sig_pk,
+        &signature_input_bytes,
+        server_signature,
+    )
+}
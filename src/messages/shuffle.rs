@@ -1,10 +1,21 @@
 //! Structs and basic logic for all messages that are sent between clients and server in the
 //! Shuffle VLDP scheme.
 
+use crate::messages::BatchPath;
 use crate::prelude::*;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate,
+};
+use std::io::{Read, Write};
+
+/// Serialized messages are padded to a multiple of this number of bytes before encryption, such
+/// that all encrypted reports on either side of the shuffler have the same length.
+pub const REPORT_BLOCK_BYTES: usize = 256;
 
 /// Message sent by client in the `GenRand` step of the paper.
+///
+/// This also contains the commitment to the nullifier key of the client, which must be the same in
+/// every `GenRand` step of the client.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct GenerateRandomnessMessageClientShuffle<Conf: Config>
 where
@@ -12,10 +23,14 @@ where
     ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
 {
     pub client_seed_commitment: ClientCommitmentSchemeOutput<Conf>,
+    pub nullifier_key_commitment: ClientCommitmentSchemeOutput<Conf>,
     pub client_signature_public_key: ClientSignatureSchemePublicKey<Conf>,
 }
 
 /// Message sent by the server in the `GenRand` step of the paper.
+///
+/// If the parameters use batches, the server signature is the signature on the root of the merkle
+/// tree of the batch, and the message also contains this root and the path of the client leaf.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct GenerateRandomnessMessageServerShuffle<Conf: Config>
 where
@@ -23,6 +38,7 @@ where
 {
     pub server_seed: PRFSchemeSeed<Conf>,
     pub server_signature: ServerSignatureSchemeSignature<Conf>,
+    pub batch: Option<BatchPath<Conf>>,
 }
 
 /// Message that is to be signed by the server as part of the `GenRand` step of the paper.
@@ -33,6 +49,7 @@ where
     ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
 {
     pub client_seed_commitment: ClientCommitmentSchemeOutput<Conf>,
+    pub nullifier_key_commitment: ClientCommitmentSchemeOutput<Conf>,
     pub client_signature_public_key: ClientSignatureSchemePublicKey<Conf>,
     pub server_seed: PRFSchemeSeed<Conf>,
 }
@@ -49,18 +66,166 @@ where
     ) -> Self {
         Self {
             client_seed_commitment: client_message.client_seed_commitment,
+            nullifier_key_commitment: client_message.nullifier_key_commitment,
             client_signature_public_key: client_message.client_signature_public_key,
             server_seed,
         }
     }
 }
 
-/// Message sent by the client as part of the `Randomize` step of the paper.
+/// Contents of the report of a client in the `Randomize` step of the paper, which can only be
+/// read by the server.
+///
+/// The nullifier is the same for all reports of a client within an epoch (with the same message
+/// index), but cannot be linked to the client or to its reports in other epochs. The message index
+/// is 0 unless the parameters use multiple messages.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
-pub struct VerifiableRandomizationMessageShuffle<Conf: Config, const INPUT_BYTES: usize>
+pub struct ReportShuffle<Conf: Config, const INPUT_BYTES: usize> {
+    pub ldp_value: LdpValue<Conf>,
+    pub nullifier: PRFSchemeOutput<Conf>,
+    pub message_index: u8,
+}
+
+/// Message sent by the client as part of the `Randomize` step of the paper, as forwarded by the
+/// shuffler to the server.
+///
+/// The report (inside a `ReportPayloadShuffle`) is encrypted under the encryption public key of
+/// the server, while the proof can be rerandomized by the shuffler. A dummy report of the shuffler
+/// is forwarded as a message of the same shape.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct VerifiableRandomizationMessageShuffle<Conf: Config>
 where
     Proof<Conf>: CanonicalDeserialize,
 {
     pub proof: Proof<Conf>,
-    pub ldp_value: u64,
+    pub encrypted_report: EncryptedReportShuffle<Conf>,
+}
+
+/// Contents of a dummy report that is injected by the shuffler as blanket noise, of which the LDP
+/// value is derived from the public seed, the epoch and the index (see `BlanketNoise`).
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct DummyReportShuffle {
+    pub index: u32,
+}
+
+/// Contents of an encrypted report of the Shuffle scheme: either the report of a client, or a
+/// dummy report that is injected by the shuffler.
+///
+/// The payload is serialized with a tag byte: `0` for a client report, and `1` for a dummy report.
+pub enum ReportPayloadShuffle<Conf: Config, const INPUT_BYTES: usize> {
+    Client(ReportShuffle<Conf, INPUT_BYTES>),
+    Dummy(DummyReportShuffle),
+}
+
+impl<Conf: Config, const INPUT_BYTES: usize> Valid for ReportPayloadShuffle<Conf, INPUT_BYTES> {
+    fn check(&self) -> Result<(), SerializationError> {
+        match self {
+            Self::Client(report) => report.check(),
+            Self::Dummy(report) => report.check(),
+        }
+    }
+}
+
+impl<Conf: Config, const INPUT_BYTES: usize> CanonicalSerialize
+    for ReportPayloadShuffle<Conf, INPUT_BYTES>
+{
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        match self {
+            Self::Client(report) => {
+                0u8.serialize_with_mode(&mut writer, compress)?;
+                report.serialize_with_mode(writer, compress)
+            }
+            Self::Dummy(report) => {
+                1u8.serialize_with_mode(&mut writer, compress)?;
+                report.serialize_with_mode(writer, compress)
+            }
+        }
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        1 + match self {
+            Self::Client(report) => report.serialized_size(compress),
+            Self::Dummy(report) => report.serialized_size(compress),
+        }
+    }
+}
+
+impl<Conf: Config, const INPUT_BYTES: usize> CanonicalDeserialize
+    for ReportPayloadShuffle<Conf, INPUT_BYTES>
+{
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        match u8::deserialize_with_mode(&mut reader, compress, validate)? {
+            0 => Ok(Self::Client(ReportShuffle::deserialize_with_mode(
+                reader, compress, validate,
+            )?)),
+            1 => Ok(Self::Dummy(DummyReportShuffle::deserialize_with_mode(
+                reader, compress, validate,
+            )?)),
+            _ => Err(SerializationError::InvalidData),
+        }
+    }
+}
+
+/// Encrypted message of the Shuffle scheme, i.e., a `ReportPayloadShuffle` that is encrypted under
+/// the encryption public key of the server, or a `VerifiableRandomizationMessageShuffle` that is
+/// encrypted under the encryption public key of the shuffler (as sent by the client).
+///
+/// The serialized message is padded with a single `0x80` byte followed by zero bytes up to a
+/// multiple of `REPORT_BLOCK_BYTES` before encryption.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct EncryptedReportShuffle<Conf: Config> {
+    pub ciphertext: ReportEncryptionSchemeCiphertext<Conf>,
+}
+
+impl<Conf: Config> EncryptedReportShuffle<Conf> {
+    /// Pad and encrypt the serialized message under the given public key.
+    pub fn encrypt<R: Rng>(
+        parameters: &ReportEncryptionSchemeParameters<Conf>,
+        pk: &ReportEncryptionSchemePublicKey<Conf>,
+        message: &[u8],
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        let mut padded_message = message.to_vec();
+        padded_message.push(0x80);
+        padded_message.resize(
+            padded_message.len().div_ceil(REPORT_BLOCK_BYTES) * REPORT_BLOCK_BYTES,
+            0,
+        );
+        Ok(Self {
+            ciphertext: Conf::ReportEncryptionScheme::encrypt(
+                parameters,
+                pk,
+                &padded_message,
+                rng,
+            )?,
+        })
+    }
+
+    /// Decrypt and unpad the serialized message with the given secret key, returns `None` if the
+    /// ciphertext is not authentic.
+    pub fn decrypt(
+        &self,
+        parameters: &ReportEncryptionSchemeParameters<Conf>,
+        sk: &ReportEncryptionSchemeSecretKey<Conf>,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        Ok(
+            Conf::ReportEncryptionScheme::decrypt(parameters, sk, &self.ciphertext)?.and_then(
+                |mut padded_message| {
+                    let padding_start = padded_message.iter().rposition(|byte| *byte != 0)?;
+                    (padded_message[padding_start] == 0x80).then(|| {
+                        padded_message.truncate(padding_start);
+                        padded_message
+                    })
+                },
+            ),
+        )
+    }
 }
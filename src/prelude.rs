@@ -6,6 +6,18 @@ use ark_ec::CurveGroup;
 // convenient re-exports
 pub use crate::config::{Config, ConfigGadget, ProofSystem, ProofSystemRng};
 pub use crate::error::*;
+pub use crate::primitives::blanket_noise::BlanketNoise;
+pub use crate::primitives::components::{
+    AuthenticatedInput, LdpApply, Nullifier, RandomnessDerivation, RegisteredKey, SignedBatch,
+    TimeWindow,
+};
+pub use crate::primitives::encryption::EncryptionScheme;
+pub use crate::primitives::mechanism::{
+    DiscreteLaplace, DiscreteLaplaceGadget, Estimate, Mechanism, MechanismGadget, Piecewise,
+    PiecewiseGadget, RandomizedResponse, RandomizedResponseGadget, SignedValue,
+};
+pub use crate::primitives::merkle_tree::StreamingMerkleTree;
+pub use crate::primitives::multi_message::MultiMessage;
 pub use crate::primitives::parameters::{ParametersBase, ParametersExpand, ParametersShuffle};
 pub use crate::primitives::signature::SignatureScheme;
 pub use ark_crypto_primitives::commitment::CommitmentScheme;
@@ -32,6 +44,8 @@ pub type ProvingKey<Conf> =
     <<Conf as Config>::ZKPScheme as ProofSystem<<Conf as Config>::ZKPRng>>::ProvingKey;
 pub type VerifyingKey<Conf> =
     <<Conf as Config>::ZKPScheme as ProofSystem<<Conf as Config>::ZKPRng>>::VerifyingKey;
+// ldp mechanism
+pub type LdpValue<Conf> = <<Conf as Config>::LdpMechanism as Mechanism>::Value;
 // client commitment scheme
 pub type ClientCommitmentSchemeParameters<Conf> =
     <<Conf as Config>::ClientCommitmentScheme as CommitmentScheme>::Parameters;
@@ -48,9 +62,19 @@ pub type ServerSignatureSchemeSecretKey<Conf> =
     <<Conf as Config>::ServerSignatureScheme as SignatureScheme>::SecretKey;
 pub type ServerSignatureSchemeSignature<Conf> =
     <<Conf as Config>::ServerSignatureScheme as SignatureScheme>::Signature;
+// report encryption scheme
+pub type ReportEncryptionSchemeParameters<Conf> =
+    <<Conf as Config>::ReportEncryptionScheme as EncryptionScheme>::Parameters;
+pub type ReportEncryptionSchemePublicKey<Conf> =
+    <<Conf as Config>::ReportEncryptionScheme as EncryptionScheme>::PublicKey;
+pub type ReportEncryptionSchemeSecretKey<Conf> =
+    <<Conf as Config>::ReportEncryptionScheme as EncryptionScheme>::SecretKey;
+pub type ReportEncryptionSchemeCiphertext<Conf> =
+    <<Conf as Config>::ReportEncryptionScheme as EncryptionScheme>::Ciphertext;
 // client PRF
 pub type PRFSchemeInput<Conf> = <<Conf as Config>::PRFScheme as PRF>::Input;
 pub type PRFSchemeSeed<Conf> = <<Conf as Config>::PRFScheme as PRF>::Seed;
+pub type PRFSchemeOutput<Conf> = <<Conf as Config>::PRFScheme as PRF>::Output;
 // client signature scheme
 pub type ClientSignatureSchemeParameters<Conf> =
     <<Conf as Config>::ClientSignatureScheme as SignatureScheme>::Parameters;
@@ -63,6 +87,7 @@ pub type ClientSignatureSchemeSignature<Conf> =
 // client merkle tree
 pub type ClientMerkleTreeConfig<Conf> = <Conf as Config>::ClientMerkleTreeConfig;
 pub type ClientMerkleTree<Conf> = MerkleTree<ClientMerkleTreeConfig<Conf>>;
+pub type ClientStreamingMerkleTree<Conf> = StreamingMerkleTree<ClientMerkleTreeConfig<Conf>>;
 pub type ClientMerkleTreeLeafHash<Conf> =
     <ClientMerkleTreeConfig<Conf> as merkle_tree::Config>::LeafHash;
 pub type ClientMerkleTreeLeafHashParameters<Conf> =
@@ -78,6 +103,11 @@ pub type ClientMerkleTreePath<Conf> = Path<ClientMerkleTreeConfig<Conf>>;
 pub(crate) mod constraints {
     use super::*;
     // convenient re-exports
+    pub use crate::primitives::comparison::BoundedFpVar;
+    pub use crate::primitives::components::{
+        AuthenticatedInputGadget, LdpApplyGadget, NullifierGadget, RandomnessDerivationGadget,
+        RegisteredKeyGadget, SignedBatchGadget, TimeWindowGadget,
+    };
     pub use crate::primitives::parameters::{
         ParametersBaseVar, ParametersExpandVar, ParametersShuffleVar,
     };
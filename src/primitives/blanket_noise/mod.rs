@@ -0,0 +1,193 @@
+//! Blanket noise that is injected by the shuffler in the Shuffle scheme, such that every epoch
+//! contains a minimum number of reports.
+//!
+//! If fewer client reports than the minimum batch size are received in an epoch, the shuffler adds
+//! dummy reports, which are tagged as such inside the encrypted report. A dummy report has the
+//! same shape as a forwarded client message (its proof is a rerandomized proof of another message
+//! of the epoch), such that an observer of the shuffler cannot count the client reports.
+//!
+//! The LDP value of a dummy report is the output of the mechanism using its blanket gamma (i.e.,
+//! it is uniformly random), with randomness that is derived from a public seed, the epoch and the
+//! index of the dummy report. Hence, anyone can recompute the dummy reports of an epoch from their
+//! number. The server accepts at most the minimum batch size minus the number of client reports as
+//! dummy reports, and the estimator accounts for them by this number and their uniform
+//! distribution.
+
+use crate::prelude::*;
+use crate::primitives::mechanism::{gamma_to_probability, probability_to_gamma};
+use num_bigint::BigUint;
+
+/// Public parameters of the blanket noise that is injected by the shuffler
+#[derive(Clone, Debug)]
+pub struct BlanketNoise {
+    /// Minimum number of reports per epoch, including dummy reports.
+    pub min_batch_size: usize,
+    /// Public seed from which the randomness of all dummy reports is derived.
+    pub seed: [u8; 32],
+}
+
+impl BlanketNoise {
+    /// Number of dummy reports that are to be added to the given number of client reports.
+    pub fn dummies(&self, reports: usize) -> usize {
+        self.min_batch_size.saturating_sub(reports)
+    }
+
+    /// Derive the randomness of the dummy report with the given index in the given epoch, where
+    /// block `b` of 32 bytes is `PRF(seed, index || b || epoch)`. The epoch can be at most 24
+    /// bytes.
+    pub fn randomness<P: PRF<Input = [u8; 32], Seed = [u8; 32], Output = [u8; 32]>>(
+        &self,
+        epoch: &[u8],
+        index: u32,
+        randomness_bytes: usize,
+    ) -> Result<Vec<u8>, Error> {
+        if epoch.len() > 24 {
+            Err("The epoch of a dummy report should be at most 24 bytes.")?
+        }
+        let mut randomness = vec![];
+        for block in 0..randomness_bytes.div_ceil(32) as u32 {
+            let mut eval_point = [0; 32];
+            eval_point[0..4].copy_from_slice(&index.to_le_bytes());
+            eval_point[4..8].copy_from_slice(&block.to_le_bytes());
+            eval_point[8..8 + epoch.len()].copy_from_slice(epoch);
+            randomness.extend(P::evaluate(&self.seed, &eval_point)?);
+        }
+        randomness.truncate(randomness_bytes);
+        Ok(randomness)
+    }
+
+    /// Compute the LDP value of the dummy report with the given index in the given epoch.
+    pub fn ldp_value<
+        Conf: Config,
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        &self,
+        epoch: &[u8],
+        index: u32,
+    ) -> Result<LdpValue<Conf>, Error> {
+        let blanket_gamma = Conf::LdpMechanism::blanket_gamma::<GAMMA_BYTES>()
+            .ok_or("The mechanism has no blanket gamma.")?;
+        let randomness = self.randomness::<Conf::PRFScheme>(
+            epoch,
+            index,
+            Conf::LdpMechanism::randomness_bytes::<INPUT_BYTES, GAMMA_BYTES, IS_REAL_INPUT>(),
+        )?;
+        // the output does not depend on the input value for the blanket gamma
+        Ok(Conf::LdpMechanism::apply::<
+            INPUT_BYTES,
+            GAMMA_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >(&blanket_gamma, &BigUint::from(1u8), &randomness))
+    }
+
+    /// Estimate the population statistics from the accepted values of an epoch, of which the given
+    /// number are dummy reports (see `MultiMessage::estimate`).
+    ///
+    /// A client message is uniformly random with probability `(gamma + messages - 1) / messages`
+    /// and a dummy report always is, so the estimator of the mechanism is applied with the
+    /// effective gamma of all values, after which the estimated histogram is scaled down to the
+    /// number of clients. If there are no client messages, the estimate is empty (i.e., the
+    /// default estimate).
+    pub fn estimate<
+        M: Mechanism,
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        messages: usize,
+        values: &[M::Value],
+        dummies: usize,
+    ) -> Estimate {
+        if dummies >= values.len() {
+            return Estimate::default();
+        }
+        if dummies == 0 {
+            return MultiMessage::estimate::<M, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                gamma, messages, values,
+            );
+        }
+        let client_messages = (values.len() - dummies) as f64;
+        let message_probability =
+            (gamma_to_probability(gamma) + (messages - 1) as f64) / messages as f64;
+        let effective_gamma = probability_to_gamma::<GAMMA_BYTES>(
+            (client_messages * message_probability + dummies as f64) / values.len() as f64,
+        );
+        let mut estimate =
+            M::estimate::<INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(&effective_gamma, values);
+        if let Some(histogram) = &mut estimate.histogram {
+            histogram.iter_mut().for_each(|estimate| {
+                *estimate *= client_messages / (messages * values.len()) as f64
+            });
+        }
+        estimate
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::primitives::mechanism::probability_to_gamma;
+    use ark_crypto_primitives::prf::Blake2s;
+    use num_bigint::BigUint;
+    use rand::{RngCore, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn blanket_noise_estimate_test() {
+        const K: u64 = 4;
+        let rng = &mut ChaChaRng::seed_from_u64(0);
+        let gamma = probability_to_gamma::<4>(0.25);
+        let blanket_gamma = RandomizedResponse::blanket_gamma::<4>().unwrap();
+
+        // 10000 clients with true value 2, along with 30000 dummy reports
+        let (clients, dummies) = (10000, 30000);
+        let mut values = vec![];
+        for index in 0..clients + dummies {
+            let mut randomness = [0; 8];
+            rng.fill_bytes(&mut randomness);
+            let gamma = if index < clients {
+                &gamma
+            } else {
+                &blanket_gamma
+            };
+            values.push(RandomizedResponse::apply::<4, 4, K, false>(
+                gamma,
+                &BigUint::from(2u8),
+                &randomness,
+            ));
+        }
+
+        // the dummy reports are only accounted for by their number
+        let estimate = BlanketNoise::estimate::<RandomizedResponse, 4, 4, K, false>(
+            &gamma, 1, &values, dummies,
+        );
+        let histogram = estimate.histogram.unwrap();
+        assert!((histogram[1] / clients as f64 - 1.0).abs() < 0.1);
+        assert!((estimate.mean - 2.0).abs() < 0.1);
+
+        // an epoch with only dummy reports has an empty estimate
+        let estimate = BlanketNoise::estimate::<RandomizedResponse, 4, 4, K, false>(
+            &gamma,
+            1,
+            &values[clients..],
+            dummies,
+        );
+        assert_eq!(estimate, Estimate::default());
+
+        // the randomness of a dummy report is only derived for an epoch of at most 24 bytes
+        let blanket_noise = BlanketNoise {
+            min_batch_size: 1,
+            seed: [0; 32],
+        };
+        assert!(blanket_noise.randomness::<Blake2s>(&[0; 24], 0, 32).is_ok());
+        assert!(blanket_noise
+            .randomness::<Blake2s>(&[0; 25], 0, 32)
+            .is_err());
+    }
+}
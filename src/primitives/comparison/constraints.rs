@@ -0,0 +1,200 @@
+//! R1CS constraint generation for range checks and comparisons.
+
+use crate::primitives::comparison::fits_in_bits;
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+use std::cmp::Ordering;
+
+/// R1CS variable for a field element that is enforced to be at most `num_bits` wide.
+///
+/// Since both operands of a comparison are known to be small, comparisons between these variables
+/// are sound (as opposed to `FpVar::is_cmp_unchecked`).
+#[derive(Clone)]
+pub struct BoundedFpVar<ConstraintF: PrimeField> {
+    value: FpVar<ConstraintF>,
+    num_bits: usize,
+}
+
+impl<ConstraintF: PrimeField> BoundedFpVar<ConstraintF> {
+    /// Enforce that `value` is at most `num_bits` wide.
+    ///
+    /// Note: for constants this is checked outside of the circuit (and panics if it does not hold).
+    pub fn new(value: FpVar<ConstraintF>, num_bits: usize) -> Result<Self, SynthesisError> {
+        Self::enforce_bits(&value, num_bits)?;
+        Ok(Self { value, num_bits })
+    }
+
+    /// Create a bounded variable from its little-endian bits, this requires no extra constraints.
+    pub fn from_bits_le(bits: &[Boolean<ConstraintF>]) -> Result<Self, SynthesisError> {
+        Ok(Self {
+            value: Boolean::le_bits_to_fp_var(bits)?,
+            num_bits: bits.len(),
+        })
+    }
+
+    /// Create a bounded variable from its little-endian bytes, this requires no extra constraints.
+    pub fn from_bytes_le(bytes: &[UInt8<ConstraintF>]) -> Result<Self, SynthesisError> {
+        Self::from_bits_le(&bytes.to_bits_le()?)
+    }
+
+    /// Create a bounded constant, its bit length is the bit length of `value`.
+    pub fn constant(value: ConstraintF) -> Self {
+        Self {
+            value: FpVar::constant(value),
+            num_bits: value.into_bigint().num_bits() as usize,
+        }
+    }
+
+    /// The underlying field element variable.
+    pub fn as_fp_var(&self) -> &FpVar<ConstraintF> {
+        &self.value
+    }
+
+    /// Compare `self` to `other`, i.e., compute `self <ordering> other` (or `self <ordering>=
+    /// other` if `should_also_check_equality` is set).
+    pub fn is_cmp(
+        &self,
+        other: &Self,
+        ordering: Ordering,
+        should_also_check_equality: bool,
+    ) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        match (ordering, should_also_check_equality) {
+            (Ordering::Equal, _) => self.value.is_eq(&other.value),
+            (Ordering::Greater, true) => self.is_ge(other),
+            (Ordering::Greater, false) => Ok(other.is_ge(self)?.not()),
+            (Ordering::Less, true) => other.is_ge(self),
+            (Ordering::Less, false) => Ok(self.is_ge(other)?.not()),
+        }
+    }
+
+    /// Compute `self >= other`: for `n` bit operands `self - other + 2^n` is in `1..2^(n+1)`, and
+    /// its most significant bit is set iff `self >= other`.
+    fn is_ge(&self, other: &Self) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        let num_bits = self.num_bits.max(other.num_bits);
+        let offset = ConstraintF::from(2u8).pow([num_bits as u64]);
+        let difference = &self.value - &other.value + offset;
+        Ok(Self::enforce_bits(&difference, num_bits + 1)?[num_bits].clone())
+    }
+
+    /// Enforce that `value` is at most `num_bits` wide, returns its little-endian bits.
+    fn enforce_bits(
+        value: &FpVar<ConstraintF>,
+        num_bits: usize,
+    ) -> Result<Vec<Boolean<ConstraintF>>, SynthesisError> {
+        assert!(
+            num_bits < ConstraintF::MODULUS_BIT_SIZE as usize,
+            "Bounded variables must be smaller than the modulus."
+        );
+        match value {
+            FpVar::Constant(constant) => {
+                assert!(
+                    fits_in_bits(constant, num_bits),
+                    "Constant does not fit in the given number of bits."
+                );
+                Ok(constant.into_bigint().to_bits_le()[0..num_bits]
+                    .iter()
+                    .map(|bit| Boolean::constant(*bit))
+                    .collect())
+            }
+            FpVar::Var(_) => {
+                let bits = (0..num_bits)
+                    .map(|index| {
+                        Boolean::new_witness(value.cs(), || {
+                            Ok(value.value()?.into_bigint().get_bit(index))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(value)?;
+                Ok(bits)
+            }
+        }
+    }
+}
+
+// conditional selection between two bounded variables, the result is bounded by the widest of both
+impl<ConstraintF: PrimeField> CondSelectGadget<ConstraintF> for BoundedFpVar<ConstraintF> {
+    fn conditionally_select(
+        cond: &Boolean<ConstraintF>,
+        true_value: &Self,
+        false_value: &Self,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Self {
+            value: cond.select(&true_value.value, &false_value.value)?,
+            num_bits: true_value.num_bits.max(false_value.num_bits),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::comparison::BoundedFpVar;
+    use ark_ed_on_bls12_381::Fq as Fr;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::test_rng;
+    use rand::Rng;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn bounded_comparison_test() {
+        let rng = &mut test_rng();
+
+        for _ in 0..20 {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let a = rng.gen::<u16>();
+            let b = rng.gen_range(0..4) * (u16::MAX / 4);
+            let a_var = BoundedFpVar::new(
+                FpVar::new_witness(cs.clone(), || Ok(Fr::from(a))).unwrap(),
+                16,
+            )
+            .unwrap();
+            let b_var = BoundedFpVar::constant(Fr::from(b));
+
+            for (ordering, should_also_check_equality, expected) in [
+                (Ordering::Less, false, a < b),
+                (Ordering::Less, true, a <= b),
+                (Ordering::Greater, false, a > b),
+                (Ordering::Greater, true, a >= b),
+                (Ordering::Equal, false, a == b),
+            ] {
+                let result = a_var
+                    .is_cmp(&b_var, ordering, should_also_check_equality)
+                    .unwrap();
+                assert_eq!(result.value().unwrap(), expected);
+            }
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn bounded_malicious_witness_test() {
+        // a value that is too wide is rejected
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let value = FpVar::new_witness(cs.clone(), || Ok(Fr::from(1u32 << 16))).unwrap();
+        BoundedFpVar::new(value, 16).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // a "negative" value (i.e., one that wraps around the modulus) is rejected, while it passes
+        // an unchecked comparison
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let value = FpVar::new_witness(cs.clone(), || Ok(-Fr::from(1u8))).unwrap();
+        let bound = FpVar::constant(Fr::from(10u8));
+        assert!(value
+            .is_cmp_unchecked(&bound, Ordering::Less, false)
+            .unwrap()
+            .value()
+            .unwrap());
+        let value = BoundedFpVar::new(value, 16).unwrap();
+        let _ = value
+            .is_cmp(
+                &BoundedFpVar::constant(Fr::from(10u8)),
+                Ordering::Less,
+                false,
+            )
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,17 @@
+//! Range checks and comparisons of field elements with a known bit length, also contains the
+//! constraints for usage inside ZKP circuits.
+//!
+//! Comparing two field elements only makes sense when both are known to represent small
+//! integers. Inside ZKP circuits this has to be enforced explicitly for every witness, otherwise a
+//! malicious prover can use a field element that "wraps around" the modulus to pass a comparison.
+
+use ark_ff::{BigInteger, PrimeField};
+
+// ZKP constraints
+pub mod constraints;
+pub use constraints::*;
+
+/// Check whether `value` (as an integer in `0..p`) fits in `num_bits` bits.
+pub fn fits_in_bits<F: PrimeField>(value: &F, num_bits: usize) -> bool {
+    value.into_bigint().num_bits() as usize <= num_bits
+}
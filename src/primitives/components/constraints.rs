@@ -0,0 +1,320 @@
+//! R1CS constraint generation for the reusable components of the VLDP schemes.
+
+use crate::prelude::{constraints::*, *};
+use crate::primitives::parameters::GammaVar;
+use ark_ff::PrimeField;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_relations::r1cs::SynthesisError;
+use std::cmp::Ordering;
+
+/// Gadget for the derivation of the randomness from the client and server contributions
+pub struct RandomnessDerivationGadget;
+
+impl RandomnessDerivationGadget {
+    /// Combine two contributions by XOR-ing them inside the ZKP circuit.
+    pub fn xor<ConstraintF: PrimeField>(
+        left: &[UInt8<ConstraintF>],
+        right: &[UInt8<ConstraintF>],
+    ) -> Result<Vec<UInt8<ConstraintF>>, SynthesisError> {
+        left.iter()
+            .zip(right.iter())
+            .map(|(left_byte, right_byte)| left_byte.xor(right_byte))
+            .collect()
+    }
+
+    /// Expand a seed to `RANDOMNESS_BYTES` random bytes inside the ZKP circuit, by evaluating the
+    /// PRF on one evaluation point per 32 bytes.
+    pub fn expand<
+        P: PRF,
+        PG: PRFGadget<P, ConstraintF>,
+        ConstraintF: PrimeField,
+        const RANDOMNESS_BYTES: usize,
+    >(
+        seed: &[UInt8<ConstraintF>],
+        eval_points: &[Vec<UInt8<ConstraintF>>],
+    ) -> Result<Vec<UInt8<ConstraintF>>, SynthesisError> {
+        let mut randomness = Vec::with_capacity(RANDOMNESS_BYTES);
+        for eval_point in eval_points {
+            randomness.extend(PG::evaluate(seed, eval_point)?.to_bytes()?);
+        }
+        randomness.truncate(RANDOMNESS_BYTES);
+        Ok(randomness)
+    }
+}
+
+/// Gadget for the authentication of the true value by the data source
+pub struct AuthenticatedInputGadget;
+
+impl AuthenticatedInputGadget {
+    /// Check that `signature` is a valid signature of the client on `true_value || time`, and that
+    /// the true value is an `INPUT_BYTES` wide value in the input domain of the mechanism. Returns
+    /// the (little-endian) bytes of the true value along with the result of the checks.
+    #[allow(clippy::type_complexity)]
+    pub fn verify<
+        Conf: Config,
+        ConfG: ConfigGadget<Conf>,
+        const INPUT_BYTES: usize,
+        const TIME_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        client_signature_scheme: &ClientSignatureSchemeParametersVar<Conf, ConfG>,
+        client_sig_pk: &ClientSignatureSchemePublicKeyVar<Conf, ConfG>,
+        true_value: &FpVar<ConstraintField<Conf>>,
+        time: &FpVar<ConstraintField<Conf>>,
+        signature: &ClientSignatureSchemeSignatureVar<Conf, ConfG>,
+    ) -> Result<
+        (
+            Vec<UInt8<ConstraintField<Conf>>>,
+            Boolean<ConstraintField<Conf>>,
+        ),
+        SynthesisError,
+    > {
+        // signature =?= ClientSig.Sign(client_sig_pk, true_value || time)
+        let true_value_bytes = true_value.to_bytes()?;
+        let mut message_bytes = true_value_bytes[0..INPUT_BYTES].to_vec();
+        message_bytes.extend_from_slice(&time.to_bytes()?[0..TIME_BYTES]);
+        let signature_correct = ConfG::ClientSignatureVerifyGadget::verify(
+            client_signature_scheme,
+            client_sig_pk,
+            &message_bytes,
+            signature,
+        )?;
+
+        // true_value is INPUT_BYTES wide
+        let range_check = Boolean::kary_and(
+            &true_value_bytes[INPUT_BYTES..]
+                .iter()
+                .map(|byte| byte.is_eq(&UInt8::constant(0)))
+                .collect::<Result<Vec<_>, _>>()?,
+        )?;
+        // true_value is a valid input of the mechanism (e.g., in 1..=K for categorical inputs)
+        let domain_check =
+            ConfG::LdpMechanismGadget::is_valid_input::<INPUT_BYTES, K, IS_REAL_INPUT>(true_value)?;
+
+        Ok((
+            true_value_bytes,
+            Boolean::kary_and(&[signature_correct, range_check, domain_check])?,
+        ))
+    }
+}
+
+/// Gadget for the time window of the current step
+pub struct TimeWindowGadget;
+
+impl TimeWindowGadget {
+    /// Compute `time_lower_bound < time <= time_upper_bound` inside the ZKP circuit, where all
+    /// times are enforced to be `TIME_BYTES` wide.
+    pub fn contains<ConstraintF: PrimeField, const TIME_BYTES: usize>(
+        time_lower_bound: FpVar<ConstraintF>,
+        time_upper_bound: FpVar<ConstraintF>,
+        time: FpVar<ConstraintF>,
+    ) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        let time_lower_bound = BoundedFpVar::new(time_lower_bound, 8 * TIME_BYTES)?;
+        let time_upper_bound = BoundedFpVar::new(time_upper_bound, 8 * TIME_BYTES)?;
+        let time = BoundedFpVar::new(time, 8 * TIME_BYTES)?;
+        // time_lower_bound < time
+        let lower_bound_check = time_lower_bound.is_cmp(&time, Ordering::Less, false)?;
+        // time <= time_upper_bound
+        let upper_bound_check = time.is_cmp(&time_upper_bound, Ordering::Less, true)?;
+        lower_bound_check.and(&upper_bound_check)
+    }
+}
+
+/// Gadget for the nullifier of a report
+pub struct NullifierGadget;
+
+impl NullifierGadget {
+    /// Derive the nullifier from the nullifier key of the client inside the ZKP circuit, for the
+    /// epoch that ends at the given time (which is less than 32 bytes wide) and the given message
+    /// index.
+    pub fn derive<P: PRF, PG: PRFGadget<P, ConstraintF>, ConstraintF: PrimeField>(
+        nullifier_key: &[UInt8<ConstraintF>],
+        epoch: &FpVar<ConstraintF>,
+        message_index: &UInt8<ConstraintF>,
+    ) -> Result<Vec<UInt8<ConstraintF>>, SynthesisError> {
+        let mut eval_point = epoch.to_bytes()?;
+        eval_point.resize(31, UInt8::constant(0));
+        eval_point.push(message_index.clone());
+        PG::evaluate(nullifier_key, &eval_point)?.to_bytes()
+    }
+}
+
+/// Gadget for the leaf of a client key in the registry of client keys (Shuffle)
+pub struct RegisteredKeyGadget;
+
+impl RegisteredKeyGadget {
+    /// Compress the given bytes to 32 bytes inside the ZKP circuit, by chaining the PRF over
+    /// zero-padded chunks of 32 bytes (see `RegisteredKey::digest`).
+    pub fn digest<Conf: Config, ConfG: ConfigGadget<Conf>>(
+        bytes: &[UInt8<ConstraintField<Conf>>],
+    ) -> Result<Vec<UInt8<ConstraintField<Conf>>>, SynthesisError> {
+        let mut chunks = bytes.chunks(32).map(|chunk| {
+            let mut padded_chunk = chunk.to_vec();
+            padded_chunk.resize(32, UInt8::constant(0));
+            padded_chunk
+        });
+        let first_chunk = chunks
+            .next()
+            .unwrap_or_else(|| vec![UInt8::constant(0); 32]);
+        chunks.try_fold(first_chunk, |digest, chunk| {
+            ConfG::PRFVerifyGadget::evaluate(&digest, &chunk)?.to_bytes()
+        })
+    }
+
+    /// Compute the leaf of the public key in the registry inside the ZKP circuit, i.e., the
+    /// commitment (with default randomness) to the digest of the public key.
+    pub fn leaf<Conf: Config, ConfG: ConfigGadget<Conf>>(
+        client_commitment_scheme: &ClientCommitmentSchemeParametersVar<Conf, ConfG>,
+        client_sig_pk: &ClientSignatureSchemePublicKeyVar<Conf, ConfG>,
+    ) -> Result<ClientCommitmentSchemeOutputVar<Conf, ConfG>, SynthesisError> {
+        let pk_bytes = client_sig_pk.to_bytes()?;
+        let digest = Self::digest::<Conf, ConfG>(&pk_bytes)?;
+        let randomness = ClientCommitmentSchemeRandomnessVar::<Conf, ConfG>::new_constant(
+            pk_bytes.cs(),
+            ClientCommitmentSchemeRandomness::<Conf>::default(),
+        )?;
+        ConfG::ClientCommitmentVerifyGadget::commit(client_commitment_scheme, &digest, &randomness)
+    }
+}
+
+/// Gadget for the leaf of a signature input in the merkle tree of a batch of `Generate Randomness`
+/// steps
+pub struct SignedBatchGadget;
+
+impl SignedBatchGadget {
+    /// Compute the leaf of the signature input (given as bytes) in the merkle tree of a batch
+    /// inside the ZKP circuit, i.e., the commitment (with default randomness) to its digest.
+    pub fn leaf<Conf: Config, ConfG: ConfigGadget<Conf>>(
+        client_commitment_scheme: &ClientCommitmentSchemeParametersVar<Conf, ConfG>,
+        signature_input_bytes: &[UInt8<ConstraintField<Conf>>],
+    ) -> Result<ClientCommitmentSchemeOutputVar<Conf, ConfG>, SynthesisError> {
+        let digest = RegisteredKeyGadget::digest::<Conf, ConfG>(signature_input_bytes)?;
+        let randomness = ClientCommitmentSchemeRandomnessVar::<Conf, ConfG>::new_constant(
+            signature_input_bytes.cs(),
+            ClientCommitmentSchemeRandomness::<Conf>::default(),
+        )?;
+        ConfG::ClientCommitmentVerifyGadget::commit(client_commitment_scheme, &digest, &randomness)
+    }
+}
+
+/// Permanent gamma and randomness of the memoized application of the LDP mechanism
+pub type PermanentVar<'a, ConstraintF> = (&'a GammaVar<ConstraintF>, &'a [UInt8<ConstraintF>]);
+
+/// Gadget for the application of the LDP mechanism of a configuration
+pub struct LdpApplyGadget;
+
+impl LdpApplyGadget {
+    /// Enforce that `ldp_value` is the output of the LDP mechanism on the true value. With
+    /// memoization (i.e., if `permanent` contains the permanent gamma and randomness), the
+    /// mechanism is first applied using the permanent randomness, after which the mechanism is
+    /// applied to its output. Returns whether the computations of the mechanism are correct.
+    pub fn apply<
+        Conf: Config,
+        ConfG: ConfigGadget<Conf>,
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        ldp_value: &FpVar<ConstraintField<Conf>>,
+        gamma: &GammaVar<ConstraintField<Conf>>,
+        permanent: Option<PermanentVar<ConstraintField<Conf>>>,
+        true_value: &FpVar<ConstraintField<Conf>>,
+        randomness: &[UInt8<ConstraintField<Conf>>],
+    ) -> Result<Boolean<ConstraintField<Conf>>, SynthesisError> {
+        let (ldp_input, permanent_value_correct) = match permanent {
+            Some((permanent_gamma, permanent_randomness)) => {
+                assert!(
+                    Conf::LdpMechanism::output_is_valid_input::<IS_REAL_INPUT>(),
+                    "Memoization requires the outputs of the mechanism to be valid inputs."
+                );
+                ConfG::LdpMechanismGadget::apply::<INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                    permanent_gamma,
+                    true_value,
+                    permanent_randomness,
+                )?
+            }
+            None => (true_value.clone(), Boolean::TRUE),
+        };
+        let (computed_ldp_value, ldp_value_correct) =
+            ConfG::LdpMechanismGadget::apply::<INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                gamma, &ldp_input, randomness,
+            )?;
+        ldp_value.enforce_equal(&computed_ldp_value)?;
+        permanent_value_correct.and(&ldp_value_correct)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::components::{
+        RandomnessDerivation, RandomnessDerivationGadget, TimeWindow, TimeWindowGadget,
+    };
+    use ark_crypto_primitives::prf::blake2s::constraints::Blake2sGadget;
+    use ark_crypto_primitives::prf::Blake2s;
+    use ark_ed_on_bls12_381::Fq as Fr;
+    use ark_ff::PrimeField;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::test_rng;
+    use rand::Rng;
+
+    #[test]
+    fn components_native_gadget_test() {
+        const RANDOMNESS_BYTES: usize = 40;
+        const TIME_BYTES: usize = 2;
+        let rng = &mut test_rng();
+
+        for _ in 0..10 {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let client_seed = rng.gen::<[u8; 32]>();
+            let server_seed = rng.gen::<[u8; 32]>();
+            let eval_points = RandomnessDerivation::index_eval_points::<RANDOMNESS_BYTES>(3);
+
+            // randomness derivation
+            let seed = RandomnessDerivation::xor(&client_seed, &server_seed);
+            let randomness =
+                RandomnessDerivation::expand::<Blake2s, RANDOMNESS_BYTES>(&seed, &eval_points)
+                    .unwrap();
+
+            let client_seed_var = UInt8::new_witness_vec(cs.clone(), &client_seed).unwrap();
+            let server_seed_var = UInt8::new_input_vec(cs.clone(), &server_seed).unwrap();
+            let eval_points_var = eval_points
+                .iter()
+                .map(|eval_point| UInt8::new_input_vec(cs.clone(), eval_point))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let seed_var =
+                RandomnessDerivationGadget::xor(&client_seed_var, &server_seed_var).unwrap();
+            let randomness_var =
+                RandomnessDerivationGadget::expand::<Blake2s, Blake2sGadget, Fr, RANDOMNESS_BYTES>(
+                    &seed_var,
+                    &eval_points_var,
+                )
+                .unwrap();
+            assert_eq!(randomness_var.value().unwrap(), randomness.to_vec());
+
+            // time window
+            let time_bounds = (rng.gen::<[u8; TIME_BYTES]>(), rng.gen::<[u8; TIME_BYTES]>());
+            let time = rng.gen::<[u8; TIME_BYTES]>();
+            let [lower_bound_var, upper_bound_var, time_var] = [time_bounds.0, time_bounds.1, time]
+                .map(|bytes| {
+                    FpVar::new_witness(cs.clone(), || Ok(Fr::from_le_bytes_mod_order(&bytes)))
+                        .unwrap()
+                });
+            let contains_var = TimeWindowGadget::contains::<_, TIME_BYTES>(
+                lower_bound_var,
+                upper_bound_var,
+                time_var,
+            )
+            .unwrap();
+            assert_eq!(
+                contains_var.value().unwrap(),
+                TimeWindow::contains(&time_bounds, &time)
+            );
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+}
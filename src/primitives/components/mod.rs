@@ -0,0 +1,354 @@
+//! Reusable components of the three VLDP schemes (Base, Expand, and Shuffle), also contains the
+//! constraints for usage inside ZKP circuits.
+//!
+//! Every scheme is a composition of the same steps: the randomness is derived from a client and a
+//! server contribution, the true value is authenticated by the signature of the data source on the
+//! value and its creation time, the creation time must lie within the current time window, and
+//! the LDP mechanism is applied to the true value. The native implementations of these steps are
+//! used by the clients and servers, and match the gadgets used inside the ZKP circuits.
+
+use crate::prelude::*;
+use crate::primitives::mechanism::field_to_biguint;
+use crate::primitives::parameters::ClientMerkleTreeParameters;
+use ark_serialize::CanonicalSerialize;
+use num_bigint::BigUint;
+
+// ZKP constraints
+pub mod constraints;
+pub use constraints::*;
+
+/// Derivation of the randomness from the client and server contributions
+pub struct RandomnessDerivation;
+
+impl RandomnessDerivation {
+    /// Evaluation point of the PRF for the given index: the index as little-endian bytes.
+    pub fn index_eval_point(index: usize) -> [u8; 32] {
+        let mut eval_point = [0; 32];
+        for (new_byte, old_byte) in index.to_le_bytes().into_iter().zip(eval_point.iter_mut()) {
+            *old_byte = new_byte;
+        }
+        eval_point
+    }
+
+    /// Evaluation points of the PRF for expanding a seed to the `index`-th block of
+    /// `RANDOMNESS_BYTES` bytes, i.e., the block uses the indices `index * n..(index + 1) * n`
+    /// where `n` is the number of 32 byte chunks per block.
+    pub fn index_eval_points<const RANDOMNESS_BYTES: usize>(index: usize) -> Vec<[u8; 32]> {
+        let num_evals = ((RANDOMNESS_BYTES - 1) / 32) + 1;
+        (index * num_evals..(index + 1) * num_evals)
+            .map(Self::index_eval_point)
+            .collect()
+    }
+
+    /// Combine two contributions (of which at least one is uniformly random) by XOR-ing them.
+    pub fn xor<const N: usize>(left: &[u8; N], right: &[u8; N]) -> [u8; N] {
+        let mut result = *left;
+        result
+            .iter_mut()
+            .zip(right)
+            .for_each(|(left_byte, right_byte)| *left_byte ^= right_byte);
+        result
+    }
+
+    /// Expand a seed to `RANDOMNESS_BYTES` random bytes, by evaluating the PRF on one evaluation
+    /// point per 32 bytes.
+    pub fn expand<
+        P: PRF<Input = [u8; 32], Seed = [u8; 32], Output = [u8; 32]>,
+        const RANDOMNESS_BYTES: usize,
+    >(
+        seed: &[u8; 32],
+        eval_points: &[[u8; 32]],
+    ) -> Result<[u8; RANDOMNESS_BYTES], Error> {
+        let mut randomness = [0; RANDOMNESS_BYTES];
+        for (chunk, eval_point) in randomness.chunks_mut(32).zip(eval_points) {
+            chunk.copy_from_slice(&P::evaluate(seed, eval_point)?[0..chunk.len()]);
+        }
+        Ok(randomness)
+    }
+
+    /// Derive a seed deterministically from a secret key and the given input, by splitting the
+    /// (uncompressed) serialization of the input in zero-padded chunks of 32 bytes and chaining
+    /// the PRF: the key is the initial seed, and every chunk is used as evaluation point.
+    pub fn derive_seed<P: PRF<Input = [u8; 32], Seed = [u8; 32], Output = [u8; 32]>>(
+        key: &[u8; 32],
+        input: &impl CanonicalSerialize,
+    ) -> Result<[u8; 32], Error> {
+        let mut input_bytes = Vec::new();
+        input.serialize_uncompressed(&mut input_bytes)?;
+        input_bytes.chunks(32).try_fold(*key, |seed, chunk| {
+            let mut padded_chunk = [0; 32];
+            padded_chunk[..chunk.len()].copy_from_slice(chunk);
+            Ok(P::evaluate(&seed, &padded_chunk)?)
+        })
+    }
+}
+
+/// Authentication of the true value by the data source
+pub struct AuthenticatedInput;
+
+impl AuthenticatedInput {
+    /// Encode the true value as `INPUT_BYTES` little-endian bytes, after checking that it is in
+    /// the input domain of the mechanism.
+    pub fn encode<
+        M: Mechanism,
+        const INPUT_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        input_value: &BigUint,
+    ) -> Result<[u8; INPUT_BYTES], Error> {
+        if input_value.bits() > 8 * INPUT_BYTES as u64
+            || !M::is_valid_input::<INPUT_BYTES, K, IS_REAL_INPUT>(input_value)
+        {
+            Err(ClientError::InvalidInput)?
+        }
+        let mut input_value_bytes = [0; INPUT_BYTES];
+        for (idx, byte) in input_value.to_bytes_le().iter().enumerate() {
+            input_value_bytes[idx] = *byte;
+        }
+        Ok(input_value_bytes)
+    }
+
+    /// Message that is signed by the data source: the true value followed by its creation time.
+    pub fn message<const INPUT_BYTES: usize, const TIME_BYTES: usize>(
+        input_value: &[u8; INPUT_BYTES],
+        time: &[u8; TIME_BYTES],
+    ) -> Vec<u8> {
+        let mut message_bytes = input_value.to_vec();
+        message_bytes.extend_from_slice(time);
+        message_bytes
+    }
+}
+
+/// Time window of the current step
+pub struct TimeWindow;
+
+impl TimeWindow {
+    /// Check whether `time_lower_bound < time <= time_upper_bound`, where all times are
+    /// little-endian integers.
+    pub fn contains<const TIME_BYTES: usize>(
+        time_bounds: &([u8; TIME_BYTES], [u8; TIME_BYTES]),
+        time: &[u8; TIME_BYTES],
+    ) -> bool {
+        let time = BigUint::from_bytes_le(time);
+        BigUint::from_bytes_le(&time_bounds.0) < time
+            && time <= BigUint::from_bytes_le(&time_bounds.1)
+    }
+
+    /// Check whether `other_time = time + 1`, i.e., whether `other_time` is the first time step
+    /// after `time`, where both times are little-endian integers.
+    pub fn is_next<const TIME_BYTES: usize>(
+        time: &[u8; TIME_BYTES],
+        other_time: &[u8; TIME_BYTES],
+    ) -> bool {
+        BigUint::from_bytes_le(time) + 1u8 == BigUint::from_bytes_le(other_time)
+    }
+
+    /// Index of the given epoch (i.e., upper time bound) relative to the given start epoch, i.e.,
+    /// `epoch - start_epoch`, or `None` if the epoch is before the start epoch.
+    pub fn epoch_index<const TIME_BYTES: usize>(
+        start_epoch: u64,
+        epoch: &[u8; TIME_BYTES],
+    ) -> Option<usize> {
+        let epoch = BigUint::from_bytes_le(epoch);
+        let start_epoch = BigUint::from(start_epoch);
+        if epoch < start_epoch {
+            return None;
+        }
+        usize::try_from(epoch - start_epoch).ok()
+    }
+}
+
+/// Nullifier of a report, which is the same for all reports of a client within the same epoch (and
+/// with the same message index, in the multi-message Shuffle scheme)
+pub struct Nullifier;
+
+impl Nullifier {
+    /// Evaluation point of the PRF for the epoch that ends at the given time: the time as
+    /// little-endian bytes, with the message index as the last byte.
+    pub fn eval_point<const TIME_BYTES: usize>(
+        epoch: &[u8; TIME_BYTES],
+        message_index: u8,
+    ) -> [u8; 32] {
+        assert!(TIME_BYTES < 32);
+        let mut eval_point = [0; 32];
+        for (new_byte, old_byte) in epoch.iter().zip(eval_point.iter_mut()) {
+            *old_byte = *new_byte;
+        }
+        eval_point[31] = message_index;
+        eval_point
+    }
+
+    /// Derive the nullifier from the nullifier key of the client, for the epoch that ends at the
+    /// given time (i.e., the upper bound of the time bounds) and the given message index (which is
+    /// 0 if a single message is sent per epoch).
+    pub fn derive<
+        P: PRF<Input = [u8; 32], Seed = [u8; 32], Output = [u8; 32]>,
+        const TIME_BYTES: usize,
+    >(
+        nullifier_key: &[u8; 32],
+        epoch: &[u8; TIME_BYTES],
+        message_index: u8,
+    ) -> Result<[u8; 32], Error> {
+        Ok(P::evaluate(
+            nullifier_key,
+            &Self::eval_point(epoch, message_index),
+        )?)
+    }
+}
+
+/// Leaf of a client key in the registry of client keys (Shuffle)
+pub struct RegisteredKey;
+
+impl RegisteredKey {
+    /// Compress the (uncompressed) serialization of the public key to 32 bytes, by splitting it in
+    /// zero-padded chunks of 32 bytes and chaining the PRF: the first chunk is the initial seed,
+    /// and every next chunk is used as evaluation point.
+    pub fn digest<P: PRF<Input = [u8; 32], Seed = [u8; 32], Output = [u8; 32]>>(
+        client_sig_pk: &impl CanonicalSerialize,
+    ) -> Result<[u8; 32], Error> {
+        let mut pk_bytes = Vec::new();
+        client_sig_pk.serialize_uncompressed(&mut pk_bytes)?;
+        let mut chunks = pk_bytes.chunks(32).map(|chunk| {
+            let mut padded_chunk = [0; 32];
+            padded_chunk[..chunk.len()].copy_from_slice(chunk);
+            padded_chunk
+        });
+        let first_chunk = chunks.next().unwrap_or_default();
+        chunks.try_fold(first_chunk, |digest, chunk| {
+            Ok(P::evaluate(&digest, &chunk)?)
+        })
+    }
+
+    /// Leaf of the public key in the registry: a commitment to its digest with fixed (default)
+    /// randomness, such that it can be recomputed from the public key only.
+    pub fn leaf<Conf: Config>(
+        client_commitment_scheme: &ClientCommitmentSchemeParameters<Conf>,
+        client_sig_pk: &ClientSignatureSchemePublicKey<Conf>,
+    ) -> Result<ClientCommitmentSchemeOutput<Conf>, Error> {
+        Conf::ClientCommitmentScheme::commit(
+            client_commitment_scheme,
+            &Self::digest::<Conf::PRFScheme>(client_sig_pk)?,
+            &ClientCommitmentSchemeRandomness::<Conf>::default(),
+        )
+    }
+}
+
+/// Batch of `Generate Randomness` steps, in which the server signs the root of a merkle tree over
+/// the signature inputs of all clients in the batch instead of every signature input, and every
+/// client obtains the path of its leaf
+pub struct SignedBatch;
+
+impl SignedBatch {
+    /// Leaf of a signature input in the merkle tree of a batch: a commitment (with default
+    /// randomness) to the digest of its (uncompressed) serialization (see `RegisteredKey::digest`).
+    pub fn leaf<Conf: Config>(
+        client_commitment_scheme: &ClientCommitmentSchemeParameters<Conf>,
+        signature_input: &impl CanonicalSerialize,
+    ) -> Result<ClientCommitmentSchemeOutput<Conf>, Error> {
+        Conf::ClientCommitmentScheme::commit(
+            client_commitment_scheme,
+            &RegisteredKey::digest::<Conf::PRFScheme>(signature_input)?,
+            &ClientCommitmentSchemeRandomness::<Conf>::default(),
+        )
+    }
+
+    /// Height of the smallest merkle tree that holds a batch of the given size (and at least two
+    /// leaves).
+    pub fn height(batch_size: usize) -> usize {
+        batch_size.max(2).next_power_of_two().trailing_zeros() as usize + 1
+    }
+
+    /// Merkle tree of the given height over the leaves of the given signature inputs, in order.
+    /// Unused leaves contain the default leaf (which does not correspond to any signature input).
+    pub fn tree<Conf: Config>(
+        client_commitment_scheme: &ClientCommitmentSchemeParameters<Conf>,
+        client_merkle_tree_scheme: &ClientMerkleTreeParameters<Conf>,
+        signature_inputs: &[impl CanonicalSerialize],
+        height: usize,
+    ) -> Result<ClientMerkleTree<Conf>, Error> {
+        if signature_inputs.len() > 1 << (height - 1) {
+            Err(ServerError::BatchTooLarge)?
+        }
+        let mut leaves = signature_inputs
+            .iter()
+            .map(|signature_input| Self::leaf::<Conf>(client_commitment_scheme, signature_input))
+            .collect::<Result<Vec<_>, _>>()?;
+        leaves.resize(
+            1 << (height - 1),
+            ClientCommitmentSchemeOutput::<Conf>::default(),
+        );
+        ClientMerkleTree::<Conf>::new(
+            &client_merkle_tree_scheme.leaf_crh_params,
+            &client_merkle_tree_scheme.two_to_one_crh_params,
+            &leaves,
+        )
+    }
+
+    /// Message that is signed by the server for a batch: the (uncompressed) serialization of the
+    /// root of its merkle tree.
+    pub fn message<Conf: Config>(root: &ClientMerkleTreeRoot<Conf>) -> Result<Vec<u8>, Error> {
+        let mut message_bytes = Vec::new();
+        root.serialize_uncompressed(&mut message_bytes)?;
+        Ok(message_bytes)
+    }
+
+    /// Check whether the leaf of the signature input is in the merkle tree with the given root.
+    pub fn contains<Conf: Config>(
+        client_commitment_scheme: &ClientCommitmentSchemeParameters<Conf>,
+        client_merkle_tree_scheme: &ClientMerkleTreeParameters<Conf>,
+        signature_input: &impl CanonicalSerialize,
+        root: &ClientMerkleTreeRoot<Conf>,
+        path: &ClientMerkleTreePath<Conf>,
+    ) -> Result<bool, Error> {
+        path.verify(
+            &client_merkle_tree_scheme.leaf_crh_params,
+            &client_merkle_tree_scheme.two_to_one_crh_params,
+            root,
+            Self::leaf::<Conf>(client_commitment_scheme, signature_input)?,
+        )
+    }
+}
+
+/// Application of the LDP mechanism of a configuration
+pub struct LdpApply;
+
+impl LdpApply {
+    /// Apply the LDP mechanism to the true value. With memoization (i.e., if `permanent` contains
+    /// the permanent gamma and randomness), the mechanism is first applied using the permanent
+    /// randomness, after which the mechanism is applied to its output.
+    pub fn apply<
+        Conf: Config,
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        permanent: Option<(&[u8; GAMMA_BYTES], &[u8])>,
+        input_value: &BigUint,
+        randomness: &[u8],
+    ) -> LdpValue<Conf> {
+        let ldp_input = match permanent {
+            Some((permanent_gamma, permanent_randomness)) => {
+                assert!(
+                    Conf::LdpMechanism::output_is_valid_input::<IS_REAL_INPUT>(),
+                    "Memoization requires the outputs of the mechanism to be valid inputs."
+                );
+                let permanent_value =
+                    Conf::LdpMechanism::apply::<INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                        permanent_gamma,
+                        input_value,
+                        permanent_randomness,
+                    );
+                field_to_biguint(Conf::LdpMechanism::value_to_field::<ConstraintField<Conf>>(
+                    &permanent_value,
+                ))
+            }
+            None => input_value.clone(),
+        };
+        Conf::LdpMechanism::apply::<INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+            gamma, &ldp_input, randomness,
+        )
+    }
+}
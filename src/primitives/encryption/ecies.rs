@@ -0,0 +1,208 @@
+//! Implementation of a hybrid ECIES encryption scheme
+//!
+//! The sender combines an ephemeral key pair with the public key of the receiver into a shared
+//! point, from which an encryption key and a MAC key are derived with the hash function. The
+//! message is encrypted with the hash function in counter mode (encrypt-then-MAC).
+
+use crate::prelude::*;
+use ark_crypto_primitives::crh::CRHScheme;
+use ark_ec::CurveGroup;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{end_timer, marker::PhantomData, start_timer};
+use derivative::Derivative;
+use std::ops::Mul;
+
+/// Hybrid ECIES Encryption Scheme
+pub struct Ecies<C: CurveGroup, H: CRHScheme> {
+    #[doc(hidden)]
+    _group: PhantomData<C>,
+    #[doc(hidden)]
+    _hash: PhantomData<H>,
+}
+
+/// Parameters for the ECIES scheme:
+/// - Parameters for the hash function (key derivation, encryption, and MAC)
+/// - Group generator
+#[derive(Derivative)]
+#[derivative(Clone(
+    bound = "C: CurveGroup, H: CRHScheme, <H as CRHScheme>::Parameters: Send + Sync"
+))]
+pub struct Parameters<C: CurveGroup, H: CRHScheme>
+where
+    <H as CRHScheme>::Parameters: Send + Sync,
+{
+    pub hash_params: H::Parameters,
+    pub generator: C::Affine,
+}
+
+// Public key is simply an alias to an affine group element (struct is overkill)
+pub type PublicKey<C> = <C as CurveGroup>::Affine;
+
+/// Secret Key for ECIES (contains only a scalar)
+#[derive(Clone, Default, Debug, CanonicalSerialize)]
+pub struct SecretKey<C: CurveGroup>(pub C::ScalarField);
+
+/// ECIES ciphertext: the ephemeral public key, the encrypted message, and its MAC
+#[derive(Clone, Default, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Ciphertext<C: CurveGroup> {
+    pub ephemeral_key: C::Affine,
+    pub body: Vec<u8>,
+    pub tag: Vec<u8>,
+}
+
+impl<C: CurveGroup, H: CRHScheme<Input = [u8], Output = Vec<u8>>> Ecies<C, H>
+where
+    <H as CRHScheme>::Parameters: Send + Sync,
+{
+    /// Derive the (encryption key, MAC key) from the ephemeral key and the shared point.
+    fn derive_keys(
+        parameters: &Parameters<C, H>,
+        ephemeral_key: &C::Affine,
+        shared_point: &C::Affine,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let mut kdf_input = vec![0];
+        ephemeral_key.serialize_compressed(&mut kdf_input)?;
+        shared_point.serialize_compressed(&mut kdf_input)?;
+        let encryption_key = H::evaluate(&parameters.hash_params, kdf_input.as_slice())?;
+        kdf_input[0] = 1;
+        let mac_key = H::evaluate(&parameters.hash_params, kdf_input.as_slice())?;
+        Ok((encryption_key, mac_key))
+    }
+
+    /// XOR the input with the key stream H(encryption_key || counter), this both encrypts and
+    /// decrypts.
+    fn apply_key_stream(
+        parameters: &Parameters<C, H>,
+        encryption_key: &[u8],
+        input: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::with_capacity(input.len());
+        let mut counter = 0u64;
+        while output.len() < input.len() {
+            let block = H::evaluate(
+                &parameters.hash_params,
+                [encryption_key, &counter.to_le_bytes()].concat().as_slice(),
+            )?;
+            output.extend(
+                input[output.len()..]
+                    .iter()
+                    .zip(block.iter())
+                    .map(|(input_byte, key_byte)| input_byte ^ key_byte),
+            );
+            counter += 1;
+        }
+        Ok(output)
+    }
+
+    /// MAC of the encrypted message: H(mac_key || body).
+    fn tag(parameters: &Parameters<C, H>, mac_key: &[u8], body: &[u8]) -> Result<Vec<u8>, Error> {
+        H::evaluate(&parameters.hash_params, [mac_key, body].concat().as_slice())
+    }
+
+    /// Compare two tags in constant time (for tags of equal length), such that the timing of
+    /// decryption does not reveal how many leading bytes of a forged tag are correct.
+    fn tags_equal(tag: &[u8], other_tag: &[u8]) -> bool {
+        tag.len() == other_tag.len()
+            && tag
+                .iter()
+                .zip(other_tag)
+                .fold(0u8, |difference, (byte, other_byte)| {
+                    difference | (byte ^ other_byte)
+                })
+                == 0
+    }
+}
+
+impl<C: CurveGroup, H: CRHScheme<Input = [u8], Output = Vec<u8>>> EncryptionScheme for Ecies<C, H>
+where
+    <H as CRHScheme>::Parameters: Send + Sync,
+{
+    type Parameters = Parameters<C, H>;
+    type PublicKey = PublicKey<C>;
+    type SecretKey = SecretKey<C>;
+    type Ciphertext = Ciphertext<C>;
+
+    fn setup<R: Rng>(rng: &mut R) -> Result<Self::Parameters, Error> {
+        let setup_time = start_timer!(|| "Ecies::Setup");
+
+        let hash_params = H::setup(rng)?;
+        let generator = C::rand(rng).into();
+
+        end_timer!(setup_time);
+        Ok(Parameters {
+            hash_params,
+            generator,
+        })
+    }
+
+    fn keygen<R: Rng>(
+        parameters: &Self::Parameters,
+        rng: &mut R,
+    ) -> Result<(Self::PublicKey, Self::SecretKey), Error> {
+        let keygen_time = start_timer!(|| "Ecies::KeyGen");
+
+        // Secret key is a random scalar x
+        // the public key is y = xG
+        let secret_key = C::ScalarField::rand(rng);
+        let public_key = parameters.generator.mul(secret_key).into();
+
+        end_timer!(keygen_time);
+        Ok((public_key, SecretKey(secret_key)))
+    }
+
+    fn encrypt<R: Rng>(
+        parameters: &Self::Parameters,
+        pk: &Self::PublicKey,
+        message: &[u8],
+        rng: &mut R,
+    ) -> Result<Self::Ciphertext, Error> {
+        let encrypt_time = start_timer!(|| "Ecies::Encrypt");
+
+        // ephemeral key R = rG and shared point rY
+        let ephemeral_secret = C::ScalarField::rand(rng);
+        let ephemeral_key = parameters.generator.mul(ephemeral_secret).into_affine();
+        let shared_point = pk.mul(ephemeral_secret).into_affine();
+
+        let (encryption_key, mac_key) =
+            Self::derive_keys(parameters, &ephemeral_key, &shared_point)?;
+        let body = Self::apply_key_stream(parameters, &encryption_key, message)?;
+        let tag = Self::tag(parameters, &mac_key, &body)?;
+
+        end_timer!(encrypt_time);
+        Ok(Ciphertext {
+            ephemeral_key,
+            body,
+            tag,
+        })
+    }
+
+    fn decrypt(
+        parameters: &Self::Parameters,
+        sk: &Self::SecretKey,
+        ciphertext: &Self::Ciphertext,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let decrypt_time = start_timer!(|| "Ecies::Decrypt");
+
+        // shared point xR = rY
+        let shared_point = ciphertext.ephemeral_key.mul(sk.0).into_affine();
+
+        let (encryption_key, mac_key) =
+            Self::derive_keys(parameters, &ciphertext.ephemeral_key, &shared_point)?;
+        // the ciphertext is only decrypted if its MAC is valid
+        let message = if Self::tags_equal(
+            &Self::tag(parameters, &mac_key, &ciphertext.body)?,
+            &ciphertext.tag,
+        ) {
+            Some(Self::apply_key_stream(
+                parameters,
+                &encryption_key,
+                &ciphertext.body,
+            )?)
+        } else {
+            None
+        };
+
+        end_timer!(decrypt_time);
+        Ok(message)
+    }
+}
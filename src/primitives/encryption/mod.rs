@@ -0,0 +1,102 @@
+//! Generic definition of a public key encryption scheme.
+//!
+//! Encryption only happens outside of the ZKP circuits (it hides the reports from the shuffler, and
+//! the messages that pass the shuffler from each other, in the Shuffle scheme), so no constraints
+//! are defined for these schemes.
+
+use crate::prelude::*;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+// ECIES scheme
+pub mod ecies;
+pub use ecies::*;
+
+/// Generic definition of a (public key) Encryption Scheme
+pub trait EncryptionScheme {
+    type Parameters: Clone + Send + Sync;
+    type PublicKey: CanonicalSerialize + Eq + Clone + Default + Send + Sync;
+    type SecretKey: CanonicalSerialize + Clone + Default;
+    type Ciphertext: CanonicalSerialize + CanonicalDeserialize + Clone + Default;
+
+    fn setup<R: Rng>(rng: &mut R) -> Result<Self::Parameters, Error>;
+
+    fn keygen<R: Rng>(
+        pp: &Self::Parameters,
+        rng: &mut R,
+    ) -> Result<(Self::PublicKey, Self::SecretKey), Error>;
+
+    fn encrypt<R: Rng>(
+        pp: &Self::Parameters,
+        pk: &Self::PublicKey,
+        message: &[u8],
+        rng: &mut R,
+    ) -> Result<Self::Ciphertext, Error>;
+
+    /// Decrypt the ciphertext, returns `None` if the ciphertext is not authentic.
+    fn decrypt(
+        pp: &Self::Parameters,
+        sk: &Self::SecretKey,
+        ciphertext: &Self::Ciphertext,
+    ) -> Result<Option<Vec<u8>>, Error>;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::EncryptionScheme;
+    use crate::primitives::crh::Blake2s256;
+    use crate::primitives::encryption::Ecies;
+    use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
+    use ark_std::test_rng;
+
+    fn encrypt_and_decrypt<E: EncryptionScheme>(message: &[u8]) {
+        let rng = &mut test_rng();
+        let parameters = E::setup::<_>(rng).unwrap();
+        let (pk, sk) = E::keygen(&parameters, rng).unwrap();
+        let ciphertext = E::encrypt(&parameters, &pk, message, rng).unwrap();
+        assert_eq!(
+            E::decrypt(&parameters, &sk, &ciphertext).unwrap().unwrap(),
+            message
+        );
+    }
+
+    fn failed_decryption<E: EncryptionScheme>(message: &[u8]) {
+        let rng = &mut test_rng();
+        let parameters = E::setup::<_>(rng).unwrap();
+        let (pk, _) = E::keygen(&parameters, rng).unwrap();
+        let (_, other_sk) = E::keygen(&parameters, rng).unwrap();
+        let ciphertext = E::encrypt(&parameters, &pk, message, rng).unwrap();
+        assert!(E::decrypt(&parameters, &other_sk, &ciphertext)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn ecies_tampering_test() {
+        let rng = &mut test_rng();
+        let parameters = Ecies::<JubJub, Blake2s256>::setup(rng).unwrap();
+        let (pk, sk) = Ecies::<JubJub, Blake2s256>::keygen(&parameters, rng).unwrap();
+        let ciphertext =
+            Ecies::<JubJub, Blake2s256>::encrypt(&parameters, &pk, b"report", rng).unwrap();
+
+        // a tampered body, a tampered tag, or a truncated tag is rejected
+        let mut tampered_ciphertexts = vec![ciphertext.clone(); 3];
+        tampered_ciphertexts[0].body[0] ^= 1;
+        *tampered_ciphertexts[1].tag.last_mut().unwrap() ^= 1;
+        tampered_ciphertexts[2].tag.pop();
+        for tampered_ciphertext in &tampered_ciphertexts {
+            assert!(
+                Ecies::<JubJub, Blake2s256>::decrypt(&parameters, &sk, tampered_ciphertext)
+                    .unwrap()
+                    .is_none()
+            );
+        }
+    }
+
+    #[test]
+    fn ecies_encryption_test() {
+        let message = "Hi, I am an ECIES ciphertext that is longer than a single block!";
+        encrypt_and_decrypt::<Ecies<JubJub, Blake2s256>>(message.as_bytes());
+        encrypt_and_decrypt::<Ecies<JubJub, Blake2s256>>(&[]);
+        failed_decryption::<Ecies<JubJub, Blake2s256>>(message.as_bytes());
+    }
+}
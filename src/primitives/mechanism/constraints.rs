@@ -0,0 +1,35 @@
+//! Generic definition of the R1CS gadget for applying an LDP mechanism inside ZKP circuits.
+
+use crate::primitives::mechanism::Mechanism;
+use crate::primitives::parameters::GammaVar;
+use ark_ff::PrimeField;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+
+/// R1CS gadget for applying an LDP mechanism inside ZKP circuits.
+pub trait MechanismGadget<M: Mechanism, ConstraintF: PrimeField> {
+    /// Compute the output of the mechanism on `true_value` using the given `randomness` inside the
+    /// ZKP circuit.
+    ///
+    /// Returns the computed LDP value along with a `Boolean` that is only `TRUE` when all
+    /// (in)equality checks that were needed for the computation hold. It is up to the caller to
+    /// enforce this `Boolean` (this allows combining all checks of a circuit at once).
+    fn apply<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &GammaVar<ConstraintF>,
+        true_value: &FpVar<ConstraintF>,
+        randomness: &[UInt8<ConstraintF>],
+    ) -> Result<(FpVar<ConstraintF>, Boolean<ConstraintF>), SynthesisError>;
+
+    /// Check whether `true_value` is in the input domain of the mechanism inside the ZKP circuit,
+    /// this matches `Mechanism::is_valid_input`. The caller has to ensure that `true_value` is at
+    /// most `INPUT_BYTES` wide.
+    fn is_valid_input<const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        true_value: &FpVar<ConstraintF>,
+    ) -> Result<Boolean<ConstraintF>, SynthesisError>;
+}
@@ -0,0 +1,209 @@
+//! R1CS constraint generation for the discrete Laplace mechanism.
+
+use crate::primitives::comparison::BoundedFpVar;
+use crate::primitives::mechanism::{
+    biguint_to_field, field_to_biguint, DiscreteLaplace, MechanismGadget,
+};
+use crate::primitives::parameters::GammaVar;
+use ark_ff::PrimeField;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+use num_bigint::BigUint;
+use std::cmp::Ordering;
+
+/// Gadget for generating R1CS constraints for the discrete Laplace mechanism
+#[derive(Clone)]
+pub struct DiscreteLaplaceGadget<const NOISE_BOUND: u64>;
+
+impl<const NOISE_BOUND: u64> DiscreteLaplaceGadget<NOISE_BOUND> {
+    /// Sample a (truncated) geometric random variable from the uniformly random bytes, by counting
+    /// the number of thresholds that the random bytes are below.
+    fn sample_geometric<ConstraintF: PrimeField>(
+        uniform: &[UInt8<ConstraintF>],
+        thresholds: &[BigUint],
+    ) -> Result<FpVar<ConstraintF>, SynthesisError> {
+        let uniform = BoundedFpVar::from_bytes_le(uniform)?;
+        let mut geometric = FpVar::zero();
+        // thresholds are decreasing, so all comparisons after a zero threshold are false
+        for threshold in thresholds
+            .iter()
+            .take_while(|threshold| **threshold > BigUint::from(0u8))
+        {
+            let threshold = BoundedFpVar::constant(biguint_to_field::<ConstraintF>(threshold));
+            geometric += FpVar::from(uniform.is_cmp(&threshold, Ordering::Less, false)?);
+        }
+        Ok(geometric)
+    }
+
+    /// Decode the signed true value from its `INPUT_BYTES` wide two's complement encoding, i.e.,
+    /// subtract `2^(8*INPUT_BYTES)` if its most significant bit is set. The caller has to ensure
+    /// that `true_value` is at most `INPUT_BYTES` wide.
+    fn decode_input<ConstraintF: PrimeField, const INPUT_BYTES: usize>(
+        true_value: &FpVar<ConstraintF>,
+    ) -> Result<FpVar<ConstraintF>, SynthesisError> {
+        if !(1..=8).contains(&INPUT_BYTES) {
+            // such inputs are rejected by `is_valid_input`
+            return Ok(true_value.clone());
+        }
+        let sign = &true_value.to_bits_le()?[8 * INPUT_BYTES - 1];
+        let modulus = FpVar::constant(ConstraintF::from(1u128 << (8 * INPUT_BYTES)));
+        Ok(true_value - FpVar::from(sign.clone()) * modulus)
+    }
+}
+
+impl<ConstraintF: PrimeField, const NOISE_BOUND: u64>
+    MechanismGadget<DiscreteLaplace<NOISE_BOUND>, ConstraintF>
+    for DiscreteLaplaceGadget<NOISE_BOUND>
+{
+    /// Note: gamma has to be allocated as a constant, as the thresholds of the inverse CDF are
+    /// computed from it outside of the circuit.
+    fn apply<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &GammaVar<ConstraintF>,
+        true_value: &FpVar<ConstraintF>,
+        randomness: &[UInt8<ConstraintF>],
+    ) -> Result<(FpVar<ConstraintF>, Boolean<ConstraintF>), SynthesisError> {
+        // the mechanism is only defined for integer inputs, and requires gamma to be a constant
+        if IS_REAL_INPUT || !gamma.as_fp_var().is_constant() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        // compute the thresholds of the inverse CDF
+        let gamma = field_to_biguint(gamma.as_fp_var().value()?).to_bytes_le();
+        let mut gamma_bytes = [0; GAMMA_BYTES];
+        gamma_bytes[..gamma.len()].copy_from_slice(&gamma);
+        let thresholds = DiscreteLaplace::<NOISE_BOUND>::thresholds(&gamma_bytes);
+
+        // noise = G1 - G2
+        let positive_noise = Self::sample_geometric(&randomness[0..GAMMA_BYTES], &thresholds)?;
+        let negative_noise =
+            Self::sample_geometric(&randomness[GAMMA_BYTES..2 * GAMMA_BYTES], &thresholds)?;
+
+        Ok((
+            Self::decode_input::<ConstraintF, INPUT_BYTES>(true_value)? + positive_noise
+                - negative_noise,
+            Boolean::TRUE,
+        ))
+    }
+
+    fn is_valid_input<const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        _true_value: &FpVar<ConstraintF>,
+    ) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        // any INPUT_BYTES wide value is the encoding of a valid signed input, real-valued inputs
+        // are not supported
+        Ok(Boolean::constant(
+            !IS_REAL_INPUT && (1..=8).contains(&INPUT_BYTES),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::mechanism::{
+        DiscreteLaplace, DiscreteLaplaceGadget, Mechanism, MechanismGadget,
+    };
+    use crate::primitives::parameters::GammaVar;
+    use ark_ed_on_bls12_381::Fq as Fr;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::test_rng;
+    use rand::Rng;
+
+    const GAMMA_BYTES: usize = 4;
+    const NOISE_BOUND: u64 = 16;
+
+    /// Check that the gadget matches the native mechanism on the given signed true value.
+    fn check_signed_value<const INPUT_BYTES: usize>(value: i64, randomness: &[u8]) {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let gamma = (u32::MAX / 3 * 2).to_le_bytes();
+        let true_value =
+            DiscreteLaplace::<NOISE_BOUND>::encode_input::<INPUT_BYTES>(value).unwrap();
+        let ldp_value = DiscreteLaplace::<NOISE_BOUND>::apply::<INPUT_BYTES, GAMMA_BYTES, 0, false>(
+            &gamma,
+            &true_value,
+            randomness,
+        );
+
+        let gamma_var = GammaVar::new_constant(cs.clone(), gamma).unwrap();
+        let true_value_var = FpVar::new_witness(cs.clone(), || {
+            Ok(Fr::from(
+                true_value.to_u64_digits().first().copied().unwrap_or(0),
+            ))
+        })
+        .unwrap();
+        let randomness_var = UInt8::new_witness_vec(cs.clone(), randomness).unwrap();
+        let (ldp_value_var, correct) = <DiscreteLaplaceGadget<NOISE_BOUND> as MechanismGadget<
+            DiscreteLaplace<NOISE_BOUND>,
+            Fr,
+        >>::apply::<INPUT_BYTES, GAMMA_BYTES, 0, false>(
+            &gamma_var,
+            &true_value_var,
+            &randomness_var,
+        )
+        .unwrap();
+
+        correct.enforce_equal(&Boolean::TRUE).unwrap();
+        assert_eq!(
+            ldp_value_var.value().unwrap(),
+            DiscreteLaplace::<NOISE_BOUND>::value_to_field::<Fr>(&ldp_value)
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn discrete_laplace_gadget_test() {
+        let rng = &mut test_rng();
+
+        // small (negative) true values, such that LDP values of both signs occur
+        for _ in 0..20 {
+            let randomness = rng.gen::<[u8; 2 * GAMMA_BYTES]>();
+            check_signed_value::<1>(rng.gen_range(-4..4), &randomness);
+        }
+
+        // extreme true values, with maximal noise in both directions
+        for value in [i64::MIN, -1, i64::MAX] {
+            for randomness in [
+                [[0; GAMMA_BYTES], [u8::MAX; GAMMA_BYTES]].concat(),
+                [[u8::MAX; GAMMA_BYTES], [0; GAMMA_BYTES]].concat(),
+            ] {
+                check_signed_value::<8>(value, &randomness);
+            }
+        }
+
+        // real-valued inputs and non-constant gamma are rejected without panicking
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let gamma = (u32::MAX / 3 * 2).to_le_bytes();
+        let true_value = FpVar::new_witness(cs.clone(), || Ok(Fr::from(1u8))).unwrap();
+        let randomness = UInt8::new_witness_vec(cs.clone(), &[0; 2 * GAMMA_BYTES]).unwrap();
+        for (gamma_var, is_real_input) in [
+            (GammaVar::new_constant(cs.clone(), gamma).unwrap(), true),
+            (
+                GammaVar::new_witness(cs.clone(), || Ok(gamma)).unwrap(),
+                false,
+            ),
+        ] {
+            let result = if is_real_input {
+                <DiscreteLaplaceGadget<NOISE_BOUND> as MechanismGadget<
+                    DiscreteLaplace<NOISE_BOUND>,
+                    Fr,
+                >>::apply::<1, GAMMA_BYTES, 0, true>(
+                    &gamma_var, &true_value, &randomness
+                )
+            } else {
+                <DiscreteLaplaceGadget<NOISE_BOUND> as MechanismGadget<
+                    DiscreteLaplace<NOISE_BOUND>,
+                    Fr,
+                >>::apply::<1, GAMMA_BYTES, 0, false>(
+                    &gamma_var, &true_value, &randomness
+                )
+            };
+            assert!(result.is_err());
+        }
+    }
+}
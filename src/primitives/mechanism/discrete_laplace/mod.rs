@@ -0,0 +1,311 @@
+//! Implementation of the discrete Laplace (two-sided geometric) mechanism for integer inputs.
+//!
+//! The true value `v` (a signed integer in `-2^(8*INPUT_BYTES-1)..2^(8*INPUT_BYTES-1)`, which is
+//! encoded in two's complement, see `encode_input`) is reported as `v + Z`, where `P(Z = z)` is
+//! proportional to `alpha^|z|` with `alpha = e^(-eps)` (for sensitivity 1). Here, gamma is used to
+//! encode alpha, i.e., gamma must be set to `e^(-eps)`. Only inputs of at most 8 bytes are valid,
+//! such that `v + Z` is computed exactly (without overflow) as it is inside the ZKP circuit.
+//!
+//! The noise is sampled as `Z = G1 - G2`, where `G1` and `G2` are independent geometric random
+//! variables with `P(G >= g) = alpha^g`. Each geometric variable is sampled via its inverse CDF
+//! from `GAMMA_BYTES` random bytes: `G` equals the number of thresholds `T_g ~ alpha^g *
+//! 2^(8*GAMMA_BYTES)` (for `g` in `1..=NOISE_BOUND`) that the random bytes are below.
+//!
+//! Note that this truncates each geometric variable to `NOISE_BOUND`. As a result the mechanism
+//! satisfies (eps, delta)-LDP with delta in the order of `alpha^NOISE_BOUND`, so `NOISE_BOUND`
+//! should be chosen such that this is negligible.
+
+use crate::primitives::mechanism::{biguint_to_u64, gamma_to_probability, Estimate, Mechanism};
+use ark_ff::PrimeField;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate,
+};
+use astro_float::{BigFloat, Consts, RoundingMode};
+use num_bigint::BigUint;
+use std::io::{Read, Write};
+
+pub mod constraints;
+pub use constraints::*;
+
+/// Signed integer output of the discrete Laplace mechanism.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SignedValue(pub i128);
+
+impl Valid for SignedValue {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalSerialize for SignedValue {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.0.to_le_bytes().serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.0.to_le_bytes().serialized_size(compress)
+    }
+}
+
+impl CanonicalDeserialize for SignedValue {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self(i128::from_le_bytes(
+            <[u8; 16]>::deserialize_with_mode(reader, compress, validate)?,
+        )))
+    }
+}
+
+/// Discrete Laplace mechanism with noise bounded by `NOISE_BOUND` in both directions
+#[derive(Clone)]
+pub struct DiscreteLaplace<const NOISE_BOUND: u64>;
+
+impl<const NOISE_BOUND: u64> DiscreteLaplace<NOISE_BOUND> {
+    /// Encode a signed true value as the `INPUT_BYTES` wide two's complement that is used as input
+    /// value of the mechanism, returns `None` if the value does not fit in `INPUT_BYTES`.
+    pub fn encode_input<const INPUT_BYTES: usize>(value: i64) -> Option<BigUint> {
+        if !(1..=8).contains(&INPUT_BYTES) {
+            return None;
+        }
+        let bits = 8 * INPUT_BYTES as u32;
+        let value = value as i128;
+        if value < -(1 << (bits - 1)) || value >= 1 << (bits - 1) {
+            return None;
+        }
+        Some(BigUint::from(value.rem_euclid(1 << bits) as u128))
+    }
+
+    /// Decode the signed true value from its `INPUT_BYTES` wide two's complement encoding.
+    pub fn decode_input<const INPUT_BYTES: usize>(input_value: &BigUint) -> i128 {
+        assert!(
+            (1..=8).contains(&INPUT_BYTES),
+            "The discrete Laplace mechanism is only defined for inputs of at most 8 bytes."
+        );
+        let bits = 8 * INPUT_BYTES as u32;
+        let value = biguint_to_u64(input_value) as i128;
+        if value >> (bits - 1) == 1 {
+            value - (1 << bits)
+        } else {
+            value
+        }
+    }
+
+    /// Compute alpha (`e^(-eps)`) for the given value of epsilon, to be used as gamma.
+    pub fn alpha_from_epsilon(epsilon: &BigFloat, precision: usize) -> BigFloat {
+        epsilon.neg().exp(
+            precision,
+            RoundingMode::None,
+            &mut Consts::new().expect("Constants cache initialization should not fail."),
+        )
+    }
+
+    /// Thresholds `T_1, ..., T_NOISE_BOUND` of the inverse CDF of the geometric distribution,
+    /// given alpha in its byte encoding. Here, `T_0 = 2^(8*GAMMA_BYTES)` and
+    /// `T_g = floor(T_(g-1) * gamma / (2^(8*GAMMA_BYTES) - 1))`.
+    pub fn thresholds<const GAMMA_BYTES: usize>(gamma: &[u8; GAMMA_BYTES]) -> Vec<BigUint> {
+        let gamma = BigUint::from_bytes_le(gamma);
+        let max_gamma = BigUint::from_bytes_le(&[u8::MAX; GAMMA_BYTES]);
+        let mut threshold = BigUint::from(1u8) << (8 * GAMMA_BYTES);
+        (0..NOISE_BOUND)
+            .map(|_| {
+                threshold = &threshold * &gamma / &max_gamma;
+                threshold.clone()
+            })
+            .collect()
+    }
+
+    /// Sample a (truncated) geometric random variable from the uniformly random bytes.
+    pub fn sample_geometric(uniform: &[u8], thresholds: &[BigUint]) -> u64 {
+        let uniform = BigUint::from_bytes_le(uniform);
+        thresholds
+            .iter()
+            .filter(|threshold| uniform < **threshold)
+            .count() as u64
+    }
+
+    /// Sample the two-sided geometric noise from the given randomness.
+    pub fn sample_noise<const GAMMA_BYTES: usize>(
+        gamma: &[u8; GAMMA_BYTES],
+        randomness: &[u8],
+    ) -> i128 {
+        let thresholds = Self::thresholds(gamma);
+        Self::sample_geometric(&randomness[0..GAMMA_BYTES], &thresholds) as i128
+            - Self::sample_geometric(&randomness[GAMMA_BYTES..2 * GAMMA_BYTES], &thresholds) as i128
+    }
+
+    /// Unbiased estimator of the mean of the true values from the outputs of the mechanism.
+    pub fn estimate_mean(values: &[SignedValue]) -> f64 {
+        values.iter().map(|value| value.0 as f64).sum::<f64>() / values.len() as f64
+    }
+}
+
+impl<const NOISE_BOUND: u64> Mechanism for DiscreteLaplace<NOISE_BOUND> {
+    type Value = SignedValue;
+
+    fn randomness_bytes<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const IS_REAL_INPUT: bool,
+    >() -> usize {
+        2 * GAMMA_BYTES
+    }
+
+    fn apply<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        input_value: &BigUint,
+        randomness: &[u8],
+    ) -> Self::Value {
+        if IS_REAL_INPUT {
+            // such inputs are rejected by `is_valid_input`
+            return SignedValue::default();
+        }
+        // |v| < 2^63 and |Z| <= NOISE_BOUND < 2^64, so the sum cannot overflow
+        SignedValue(
+            Self::decode_input::<INPUT_BYTES>(input_value) + Self::sample_noise(gamma, randomness),
+        )
+    }
+
+    fn value_to_field<F: PrimeField>(value: &Self::Value) -> F {
+        if value.0 < 0 {
+            -F::from(value.0.unsigned_abs())
+        } else {
+            F::from(value.0 as u128)
+        }
+    }
+
+    fn is_valid_input<const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        _input_value: &BigUint,
+    ) -> bool {
+        // any INPUT_BYTES wide value is the encoding of a valid signed input, real-valued inputs
+        // are not supported
+        !IS_REAL_INPUT && (1..=8).contains(&INPUT_BYTES)
+    }
+
+    fn output_is_valid_input<const IS_REAL_INPUT: bool>() -> bool {
+        // outputs can be negative
+        false
+    }
+
+    fn blanket_gamma<const GAMMA_BYTES: usize>() -> Option<[u8; GAMMA_BYTES]> {
+        // the output is centered around the input for every gamma
+        None
+    }
+
+    fn is_valid_gamma<const GAMMA_BYTES: usize>(_gamma: &[u8; GAMMA_BYTES]) -> bool {
+        // any gamma in (0, 1] is valid
+        true
+    }
+
+    fn epsilon<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+    ) -> f64 {
+        // alpha = e^(-eps), for sensitivity 1
+        -gamma_to_probability(gamma).ln()
+    }
+
+    fn estimate<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        _gamma: &[u8; GAMMA_BYTES],
+        values: &[Self::Value],
+    ) -> Estimate {
+        Estimate {
+            mean: Self::estimate_mean(values),
+            counts: None,
+            histogram: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::mechanism::{DiscreteLaplace, Mechanism, SignedValue};
+    use ark_std::test_rng;
+    use num_bigint::BigUint;
+    use rand::Rng;
+
+    #[test]
+    fn discrete_laplace_noise_test() {
+        const GAMMA_BYTES: usize = 4;
+        const N: usize = 20000;
+        let rng = &mut test_rng();
+        // alpha = 0.5, i.e., eps = ln(2)
+        let gamma = (u32::MAX / 2).to_le_bytes();
+
+        let values = (0..N)
+            .map(|_| {
+                let randomness = rng.gen::<[u8; 2 * GAMMA_BYTES]>();
+                DiscreteLaplace::<32>::apply::<2, GAMMA_BYTES, 0, false>(
+                    &gamma,
+                    &BigUint::from(100u8),
+                    &randomness,
+                )
+            })
+            .collect::<Vec<SignedValue>>();
+        let mean = DiscreteLaplace::<32>::estimate_mean(&values);
+        let variance = values
+            .iter()
+            .map(|value| (value.0 as f64 - mean).powi(2))
+            .sum::<f64>()
+            / N as f64;
+
+        // the two-sided geometric distribution has variance 2 * alpha / (1 - alpha)^2
+        assert!((mean - 100.0).abs() < 0.05);
+        assert!((variance - 4.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn discrete_laplace_signed_input_test() {
+        const GAMMA_BYTES: usize = 4;
+        type Dl = DiscreteLaplace<32>;
+        let rng = &mut test_rng();
+        let gamma = (u32::MAX / 2).to_le_bytes();
+
+        // negative values are encoded in two's complement
+        assert_eq!(Dl::encode_input::<1>(-1), Some(BigUint::from(u8::MAX)));
+        assert_eq!(Dl::decode_input::<1>(&BigUint::from(u8::MAX)), -1);
+        assert_eq!(Dl::encode_input::<1>(128), None);
+        assert_eq!(Dl::encode_input::<1>(-129), None);
+        assert!(!Dl::is_valid_input::<9, 0, false>(&BigUint::from(0u8)));
+        assert!(!Dl::is_valid_input::<8, 0, true>(&BigUint::from(0u8)));
+
+        // the output stays centered around negative and extreme inputs, without overflow
+        for value in [-100, i64::MIN, i64::MAX] {
+            let input_value = Dl::encode_input::<8>(value).unwrap();
+            assert_eq!(Dl::decode_input::<8>(&input_value), value as i128);
+            let mut outputs = (0..2000).map(|_| {
+                let randomness = rng.gen::<[u8; 2 * GAMMA_BYTES]>();
+                Dl::apply::<8, GAMMA_BYTES, 0, false>(&gamma, &input_value, &randomness).0
+                    - value as i128
+            });
+            assert!(outputs.all(|noise| noise.abs() <= 32));
+        }
+        // the maximal positive noise on the maximal input exceeds the range of the input
+        let ldp_value = Dl::apply::<8, GAMMA_BYTES, 0, false>(
+            &gamma,
+            &Dl::encode_input::<8>(i64::MAX).unwrap(),
+            &[[0; GAMMA_BYTES], [u8::MAX; GAMMA_BYTES]].concat(),
+        );
+        assert!(ldp_value.0 > i64::MAX as i128);
+    }
+}
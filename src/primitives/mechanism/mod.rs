@@ -0,0 +1,171 @@
+//! Generic definition of an LDP mechanism (i.e., the randomizer that is applied to the true value of
+//! a client), also contains the constraints for usage inside ZKP circuits.
+//!
+//! A mechanism is applied to the true input value using the randomness that was generated in the
+//! `GenRand` step. Since this randomness is verifiable, the application of the mechanism can be
+//! proven inside the ZKP circuits of all three VLDP schemes.
+
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use num_bigint::BigUint;
+use std::fmt::Debug;
+
+// ZKP constraints
+pub mod constraints;
+pub use constraints::*;
+
+// Randomized response (as used in the paper)
+pub mod randomized_response;
+pub use randomized_response::*;
+
+// Piecewise mechanism
+pub mod piecewise;
+pub use piecewise::*;
+
+// Discrete Laplace (two-sided geometric) mechanism
+pub mod discrete_laplace;
+pub use discrete_laplace::*;
+
+/// Population statistics that are estimated from the outputs of a mechanism.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Estimate {
+    /// Estimate of the mean of the true values (on the same scale as the true values, i.e., in
+    /// `[0, 1]` for real-valued inputs).
+    pub mean: f64,
+    /// Number of outputs per category `1..=K`, for mechanisms with categorical outputs.
+    pub counts: Option<Vec<u64>>,
+    /// Estimate of the number of true values per category `1..=K`, for mechanisms with
+    /// categorical outputs.
+    pub histogram: Option<Vec<f64>>,
+}
+
+/// Generic definition of an LDP mechanism.
+///
+/// All functions are generic over the protocol settings, such that each mechanism can decide for
+/// itself which settings are relevant (e.g., `K` is the domain size for randomized response).
+pub trait Mechanism {
+    /// Type of the randomized values that are output by the mechanism.
+    type Value: Copy
+        + Default
+        + Debug
+        + PartialEq
+        + CanonicalSerialize
+        + CanonicalDeserialize
+        + Send
+        + Sync;
+
+    /// Number of random bytes that are consumed when applying the mechanism.
+    fn randomness_bytes<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const IS_REAL_INPUT: bool,
+    >() -> usize;
+
+    /// Apply the mechanism to `input_value` using the given `randomness`, where `gamma` is the byte
+    /// encoding of gamma as given by `gamma_as_bytes`.
+    fn apply<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        input_value: &BigUint,
+        randomness: &[u8],
+    ) -> Self::Value;
+
+    /// Encode an output value as a field element, such that it can be used as public input.
+    fn value_to_field<F: PrimeField>(value: &Self::Value) -> F;
+
+    /// Whether `input_value` (which is at most `INPUT_BYTES` wide) is in the input domain of the
+    /// mechanism.
+    fn is_valid_input<const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        input_value: &BigUint,
+    ) -> bool;
+
+    /// Whether the outputs of the mechanism are valid inputs, i.e., whether the mechanism can be
+    /// applied to its own output (as is needed for memoization).
+    fn output_is_valid_input<const IS_REAL_INPUT: bool>() -> bool;
+
+    /// Encoding of gamma for which the output of the mechanism does not depend on the input, i.e.,
+    /// is drawn from the uniform blanket distribution, if the mechanism has such a gamma. This is
+    /// used for the blanket noise messages of the multi-message Shuffle scheme.
+    fn blanket_gamma<const GAMMA_BYTES: usize>() -> Option<[u8; GAMMA_BYTES]>;
+
+    /// Whether `gamma` (the byte encoding as given by `gamma_as_bytes`) is in the range of gamma
+    /// for which the mechanism is defined, this is checked during the setup of the parameters.
+    fn is_valid_gamma<const GAMMA_BYTES: usize>(gamma: &[u8; GAMMA_BYTES]) -> bool;
+
+    /// Privacy loss (epsilon) of a single application of the mechanism, where `gamma` is the byte
+    /// encoding of gamma as given by `gamma_as_bytes`.
+    fn epsilon<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+    ) -> f64;
+
+    /// Estimate the population statistics from the outputs `values` of the mechanism, where
+    /// `gamma` is the byte encoding of gamma as given by `gamma_as_bytes`.
+    fn estimate<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        values: &[Self::Value],
+    ) -> Estimate;
+}
+
+/// Compute Ber(gamma) from the first `GAMMA_BYTES` of the given randomness, this matches
+/// `GammaVar::compute_ldp_bit` inside the ZKP circuit.
+pub fn compute_ldp_bit<const GAMMA_BYTES: usize>(
+    gamma: &[u8; GAMMA_BYTES],
+    randomness: &[u8],
+) -> bool {
+    BigUint::from_bytes_le(&randomness[0..GAMMA_BYTES]) <= BigUint::from_bytes_le(gamma)
+}
+
+/// Decode the byte encoding of gamma to the probability it represents, i.e.,
+/// `gamma / (2^(8*GAMMA_BYTES) - 1)`.
+pub fn gamma_to_probability<const GAMMA_BYTES: usize>(gamma: &[u8; GAMMA_BYTES]) -> f64 {
+    let to_f64 = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .rev()
+            .fold(0.0, |acc, byte| acc * 256.0 + *byte as f64)
+    };
+    to_f64(gamma) / to_f64(&[u8::MAX; GAMMA_BYTES])
+}
+
+/// Encode a probability in `[0, 1]` as gamma, i.e., the inverse of `gamma_to_probability` (up to
+/// the precision of an `f64`).
+pub fn probability_to_gamma<const GAMMA_BYTES: usize>(probability: f64) -> [u8; GAMMA_BYTES] {
+    let mut scaled = probability.clamp(0.0, 1.0) * (256.0f64.powi(GAMMA_BYTES as i32) - 1.0);
+    let mut gamma = [0; GAMMA_BYTES];
+    for (index, byte) in gamma.iter_mut().enumerate().rev() {
+        let place = 256.0f64.powi(index as i32);
+        let digit = (scaled / place).floor().min(u8::MAX as f64);
+        *byte = digit as u8;
+        scaled -= digit * place;
+    }
+    gamma
+}
+
+/// Convert a (small) big integer to a `u64`, truncating it when it does not fit.
+pub(crate) fn biguint_to_u64(value: &BigUint) -> u64 {
+    value.to_u64_digits().first().copied().unwrap_or(0)
+}
+
+/// Convert a field element to a big integer (for native witness computations).
+pub(crate) fn field_to_biguint<F: PrimeField>(value: F) -> BigUint {
+    value.into()
+}
+
+/// Convert a big integer to a field element (for native witness computations).
+pub(crate) fn biguint_to_field<F: PrimeField>(value: &BigUint) -> F {
+    F::from_le_bytes_mod_order(&value.to_bytes_le())
+}
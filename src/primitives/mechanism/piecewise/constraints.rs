@@ -0,0 +1,273 @@
+//! R1CS constraint generation for the piecewise mechanism.
+
+use crate::primitives::comparison::BoundedFpVar;
+use crate::primitives::mechanism::{
+    biguint_to_field, field_to_biguint, MechanismGadget, Piecewise,
+};
+use crate::primitives::parameters::GammaVar;
+use ark_ff::PrimeField;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+use num_bigint::BigUint;
+use std::cmp::Ordering;
+
+/// Gadget for generating R1CS constraints for the piecewise mechanism
+#[derive(Clone)]
+pub struct PiecewiseGadget;
+
+impl PiecewiseGadget {
+    /// Compute `floor(numerator / divisor)` inside the circuit, where the quotient and the
+    /// divisor are at most `num_bits` wide. The quotient is allocated as a witness (computed by
+    /// `quotient`), or as a constant if both inputs are constants, and the returned `Boolean`
+    /// checks that the remainder is smaller than the divisor.
+    fn floor_division<ConstraintF: PrimeField>(
+        numerator: &FpVar<ConstraintF>,
+        divisor: &BoundedFpVar<ConstraintF>,
+        num_bits: usize,
+        quotient: impl FnOnce() -> Result<BigUint, SynthesisError>,
+    ) -> Result<(BoundedFpVar<ConstraintF>, Boolean<ConstraintF>), SynthesisError> {
+        // quotient * divisor + remainder must not wrap around the modulus
+        if 2 * num_bits + 1 >= ConstraintF::MODULUS_BIT_SIZE as usize {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let cs = numerator.cs().or(divisor.as_fp_var().cs());
+        let mode = if cs.is_none() {
+            AllocationMode::Constant
+        } else {
+            AllocationMode::Witness
+        };
+        let quotient = BoundedFpVar::new(
+            FpVar::new_variable(
+                cs,
+                || quotient().map(|x| biguint_to_field::<ConstraintF>(&x)),
+                mode,
+            )?,
+            num_bits,
+        )?;
+        let remainder = BoundedFpVar::new(
+            numerator - quotient.as_fp_var() * divisor.as_fp_var(),
+            num_bits,
+        )?;
+        // remainder < divisor
+        let remainder_check = remainder.is_cmp(divisor, Ordering::Less, false)?;
+        Ok((quotient, remainder_check))
+    }
+}
+
+impl<ConstraintF: PrimeField> MechanismGadget<Piecewise, ConstraintF> for PiecewiseGadget {
+    fn apply<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &GammaVar<ConstraintF>,
+        true_value: &FpVar<ConstraintF>,
+        randomness: &[UInt8<ConstraintF>],
+    ) -> Result<(FpVar<ConstraintF>, Boolean<ConstraintF>), SynthesisError> {
+        // the mechanism is only defined for real-valued inputs that are at most 8 bytes wide
+        if !IS_REAL_INPUT || !(1..=8).contains(&INPUT_BYTES) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        // all intermediate values (and divisors) are at most num_bits wide
+        let num_bits = 8 * INPUT_BYTES.max(GAMMA_BYTES) + 1;
+        let max_value = BoundedFpVar::constant(ConstraintF::from_le_bytes_mod_order(
+            &[u8::MAX; INPUT_BYTES],
+        ));
+        let max_gamma = BoundedFpVar::constant(ConstraintF::from_le_bytes_mod_order(
+            &[u8::MAX; GAMMA_BYTES],
+        ));
+        let uniform_range =
+            BoundedFpVar::constant(ConstraintF::from(BigUint::from(1u8) << (8 * INPUT_BYTES)));
+
+        // ldp_bit = Ber(gamma)
+        let ldp_bit = gamma.compute_ldp_bit(&randomness[0..GAMMA_BYTES])?;
+
+        // width = floor(M * gamma / G)
+        let (width, width_check) = Self::floor_division(
+            &(max_value.as_fp_var() * gamma.as_fp_var()),
+            &max_gamma,
+            num_bits,
+            || {
+                let gamma = field_to_biguint(gamma.as_fp_var().value()?).to_bytes_le();
+                let mut gamma_bytes = [0; GAMMA_BYTES];
+                gamma_bytes[..gamma.len()].copy_from_slice(&gamma);
+                Ok(Piecewise::interval_width::<INPUT_BYTES, GAMMA_BYTES>(
+                    &gamma_bytes,
+                ))
+            },
+        )?;
+        let width = width.as_fp_var();
+
+        // left_bound = floor(true_value * (M - width) / M)
+        let (left_bound, left_bound_check) = Self::floor_division(
+            &(true_value * (max_value.as_fp_var() - width)),
+            &max_value,
+            num_bits,
+            || {
+                Ok(Piecewise::interval_left_bound::<INPUT_BYTES>(
+                    &field_to_biguint(true_value.value()?),
+                    &field_to_biguint(width.value()?),
+                ))
+            },
+        )?;
+
+        // uniformly random value from the randomness
+        let uniform_bytes = &randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES];
+        let uniform = Boolean::le_bits_to_fp_var(&uniform_bytes.to_bits_le()?)?;
+
+        // ldp_bit == 0: sample inside the interval [left_bound, left_bound + width)
+        let (inside, inside_check) =
+            Self::floor_division(&(&uniform * width), &uniform_range, num_bits, || {
+                Ok(Piecewise::scale_uniform::<INPUT_BYTES>(
+                    &field_to_biguint(uniform.value()?),
+                    &field_to_biguint(width.value()?),
+                ))
+            })?;
+        let inside = left_bound.as_fp_var() + inside.as_fp_var();
+
+        // ldp_bit == 1: sample outside the interval [left_bound, left_bound + width)
+        let outside_length = max_value.as_fp_var() + ConstraintF::from(1u8) - width;
+        let (outside, outside_check) = Self::floor_division(
+            &(&uniform * &outside_length),
+            &uniform_range,
+            num_bits,
+            || {
+                Ok(Piecewise::scale_uniform::<INPUT_BYTES>(
+                    &field_to_biguint(uniform.value()?),
+                    &field_to_biguint(outside_length.value()?),
+                ))
+            },
+        )?;
+        // outside < left_bound
+        let is_left = outside.is_cmp(&left_bound, Ordering::Less, false)?;
+        let outside = outside.as_fp_var();
+        let outside = is_left.select(outside, &(outside + width))?;
+
+        Ok((
+            ldp_bit.select(&outside, &inside)?,
+            Boolean::kary_and(&[width_check, left_bound_check, inside_check, outside_check])?,
+        ))
+    }
+
+    fn is_valid_input<const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        _true_value: &FpVar<ConstraintF>,
+    ) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        // any INPUT_BYTES wide value is in 0..=M, categorical inputs are not supported
+        Ok(Boolean::constant(
+            IS_REAL_INPUT && (1..=8).contains(&INPUT_BYTES),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::comparison::BoundedFpVar;
+    use crate::primitives::mechanism::{
+        field_to_biguint, Mechanism, MechanismGadget, Piecewise, PiecewiseGadget,
+    };
+    use crate::primitives::parameters::GammaVar;
+    use ark_ed_on_bls12_381::Fq as Fr;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::test_rng;
+    use num_bigint::BigUint;
+    use rand::Rng;
+
+    #[test]
+    fn piecewise_malicious_quotient_test() {
+        // floor(100 / 7) = 14, the last quotient satisfies quotient * 7 + 3 = 100 modulo p
+        let wrapping_quotient = field_to_biguint(Fr::from(97u8) / Fr::from(7u8));
+        for (quotient, is_valid) in [
+            (BigUint::from(14u8), true),
+            (BigUint::from(13u8), false),
+            (BigUint::from(15u8), false),
+            (wrapping_quotient, false),
+        ] {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let numerator = FpVar::new_witness(cs.clone(), || Ok(Fr::from(100u8))).unwrap();
+            let (_, correct) = PiecewiseGadget::floor_division(
+                &numerator,
+                &BoundedFpVar::constant(Fr::from(7u8)),
+                16,
+                || Ok(quotient),
+            )
+            .unwrap();
+
+            correct.enforce_equal(&Boolean::TRUE).unwrap();
+            assert_eq!(cs.is_satisfied().unwrap(), is_valid);
+        }
+    }
+
+    #[test]
+    fn piecewise_gadget_test() {
+        const INPUT_BYTES: usize = 8;
+        const GAMMA_BYTES: usize = 8;
+        let rng = &mut test_rng();
+        let gamma = (u64::MAX / 5).to_le_bytes();
+
+        for _ in 0..20 {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let true_value = rng.gen::<u64>();
+            let randomness = rng.gen::<[u8; GAMMA_BYTES + INPUT_BYTES]>();
+            let ldp_value = Piecewise::apply::<INPUT_BYTES, GAMMA_BYTES, 0, true>(
+                &gamma,
+                &BigUint::from(true_value),
+                &randomness,
+            );
+
+            let gamma_var = GammaVar::new_constant(cs.clone(), gamma).unwrap();
+            let true_value_var =
+                FpVar::new_witness(cs.clone(), || Ok(Fr::from(true_value))).unwrap();
+            let randomness_var = UInt8::new_witness_vec(cs.clone(), &randomness).unwrap();
+            let (ldp_value_var, correct) =
+                <PiecewiseGadget as MechanismGadget<Piecewise, Fr>>::apply::<
+                    INPUT_BYTES,
+                    GAMMA_BYTES,
+                    0,
+                    true,
+                >(&gamma_var, &true_value_var, &randomness_var)
+                .unwrap();
+
+            correct.enforce_equal(&Boolean::TRUE).unwrap();
+            assert_eq!(ldp_value_var.value().unwrap(), Fr::from(ldp_value));
+            assert!(cs.is_satisfied().unwrap());
+        }
+
+        // wide and categorical inputs are rejected without panicking
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let gamma_var = GammaVar::new_constant(cs.clone(), gamma).unwrap();
+        let true_value_var = FpVar::new_witness(cs.clone(), || Ok(Fr::from(1u8))).unwrap();
+        let randomness_var = UInt8::new_witness_vec(cs.clone(), &[0; 2 * GAMMA_BYTES + 1]).unwrap();
+        assert!(
+            <PiecewiseGadget as MechanismGadget<Piecewise, Fr>>::apply::<
+                { INPUT_BYTES + 1 },
+                GAMMA_BYTES,
+                0,
+                true,
+            >(&gamma_var, &true_value_var, &randomness_var)
+            .is_err()
+        );
+        assert!(
+            <PiecewiseGadget as MechanismGadget<Piecewise, Fr>>::apply::<
+                INPUT_BYTES,
+                GAMMA_BYTES,
+                0,
+                false,
+            >(&gamma_var, &true_value_var, &randomness_var)
+            .is_err()
+        );
+        assert!(
+            !<PiecewiseGadget as MechanismGadget<Piecewise, Fr>>::is_valid_input::<
+                { INPUT_BYTES + 1 },
+                0,
+                true,
+            >(&true_value_var)
+            .unwrap()
+            .value()
+            .unwrap()
+        );
+    }
+}
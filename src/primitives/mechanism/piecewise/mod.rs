@@ -0,0 +1,267 @@
+//! Implementation of the piecewise mechanism of Wang et al. ("Collecting and Analyzing
+//! Multidimensional Data with Local Differential Privacy", ICDE 2019) for real-valued inputs.
+//!
+//! The original mechanism maps a value `t` in `[-1, 1]` to a value in `[-C, C]`, with
+//! `C = (e^(eps/2) + 1) / (e^(eps/2) - 1)`. With probability `e^(eps/2) / (e^(eps/2) + 1)` the
+//! output is sampled uniformly from the interval `[l(t), r(t)]` of length `C - 1` around `t`,
+//! otherwise it is sampled uniformly from the remainder of `[-C, C]`.
+//!
+//! We use a fixed-point encoding of both the input and output: the true value is an integer
+//! `v` in `0..=M` representing `v / M` in `[0, 1]`, and the output is an integer `Y` in `0..=M`
+//! representing `(2 * Y / M - 1) * C`, where `M = 2^(8 * INPUT_BYTES) - 1`. Under this encoding
+//! gamma equals `1 / (e^(eps/2) + 1)`, i.e., gamma is the probability of sampling outside of the
+//! interval around the true value, and the interval has width `gamma * M`. Hence, gamma must be
+//! in `(0, 1/2)`, which is checked during the setup of the parameters (see `is_valid_gamma`). The
+//! outputs are `u64` values, so inputs are at most 8 bytes wide.
+
+use crate::primitives::mechanism::{
+    biguint_to_u64, compute_ldp_bit, gamma_to_probability, Estimate, Mechanism,
+};
+use ark_ff::PrimeField;
+use astro_float::{BigFloat, Consts, RoundingMode};
+use num_bigint::BigUint;
+
+pub mod constraints;
+pub use constraints::*;
+
+/// Piecewise mechanism for real-valued inputs
+#[derive(Clone)]
+pub struct Piecewise;
+
+impl Piecewise {
+    /// Compute gamma (`1 / (e^(eps/2) + 1)`) for the given value of epsilon.
+    pub fn gamma_from_epsilon(epsilon: &BigFloat, precision: usize) -> BigFloat {
+        let exp_half_epsilon = epsilon
+            .div(
+                &BigFloat::from_u8(2, precision),
+                precision,
+                RoundingMode::None,
+            )
+            .exp(
+                precision,
+                RoundingMode::None,
+                &mut Consts::new().expect("Constants cache initialization should not fail."),
+            );
+        BigFloat::from_u8(1, precision).div(
+            &exp_half_epsilon.add(
+                &BigFloat::from_u8(1, precision),
+                precision,
+                RoundingMode::None,
+            ),
+            precision,
+            RoundingMode::None,
+        )
+    }
+
+    /// Width of the interval around the true value: `floor(M * gamma)`, where gamma is given in
+    /// its byte encoding.
+    pub fn interval_width<const INPUT_BYTES: usize, const GAMMA_BYTES: usize>(
+        gamma: &[u8; GAMMA_BYTES],
+    ) -> BigUint {
+        BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) * BigUint::from_bytes_le(gamma)
+            / BigUint::from_bytes_le(&[u8::MAX; GAMMA_BYTES])
+    }
+
+    /// Left bound of the interval around the true value: `floor(v * (M - width) / M)`.
+    pub fn interval_left_bound<const INPUT_BYTES: usize>(
+        input_value: &BigUint,
+        width: &BigUint,
+    ) -> BigUint {
+        let max_value = BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]);
+        input_value * (&max_value - width) / max_value
+    }
+
+    /// Scale a uniformly random value in `0..2^(8*INPUT_BYTES)` to a uniformly random value in
+    /// `0..length`: `floor(uniform * length / 2^(8*INPUT_BYTES))`.
+    pub fn scale_uniform<const INPUT_BYTES: usize>(uniform: &BigUint, length: &BigUint) -> BigUint {
+        (uniform * length) >> (8 * INPUT_BYTES)
+    }
+
+    /// Decode an output of the mechanism to its (real) value in `[-C, C]`. Note that this value
+    /// is an unbiased estimate of `2t - 1` where `t` is the true value in `[0, 1]`.
+    pub fn decode<const INPUT_BYTES: usize, const GAMMA_BYTES: usize>(
+        value: u64,
+        gamma: &[u8; GAMMA_BYTES],
+    ) -> f64 {
+        let (max_value, gamma) = Self::effective_parameters::<INPUT_BYTES, GAMMA_BYTES>(gamma);
+        (2.0 * value as f64 / max_value - 1.0) / (1.0 - 2.0 * gamma)
+    }
+
+    /// Unbiased estimator of the mean of the true values (in `[0, 1]`) from the outputs of the
+    /// mechanism.
+    pub fn estimate_mean<const INPUT_BYTES: usize, const GAMMA_BYTES: usize>(
+        values: &[u64],
+        gamma: &[u8; GAMMA_BYTES],
+    ) -> f64 {
+        let decoded_sum = values
+            .iter()
+            .map(|value| Self::decode::<INPUT_BYTES, GAMMA_BYTES>(*value, gamma))
+            .sum::<f64>();
+        (decoded_sum / values.len() as f64 + 1.0) / 2.0
+    }
+
+    /// The value of `M` and the value of gamma that are effectively used by the fixed-point
+    /// encoding (i.e., taking rounding of the interval width into account).
+    fn effective_parameters<const INPUT_BYTES: usize, const GAMMA_BYTES: usize>(
+        gamma: &[u8; GAMMA_BYTES],
+    ) -> (f64, f64) {
+        let max_value = biguint_to_u64(&BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES])) as f64;
+        let width = biguint_to_u64(&Self::interval_width::<INPUT_BYTES, GAMMA_BYTES>(gamma)) as f64;
+        (max_value, width / max_value)
+    }
+}
+
+impl Mechanism for Piecewise {
+    type Value = u64;
+
+    fn randomness_bytes<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const IS_REAL_INPUT: bool,
+    >() -> usize {
+        GAMMA_BYTES + INPUT_BYTES
+    }
+
+    fn apply<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        input_value: &BigUint,
+        randomness: &[u8],
+    ) -> Self::Value {
+        if !Self::is_valid_input::<INPUT_BYTES, K, IS_REAL_INPUT>(input_value) {
+            // such inputs are rejected by `is_valid_input`
+            return 0;
+        }
+        let width = Self::interval_width::<INPUT_BYTES, GAMMA_BYTES>(gamma);
+        let left_bound = Self::interval_left_bound::<INPUT_BYTES>(input_value, &width);
+        let uniform = BigUint::from_bytes_le(&randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES]);
+
+        let ldp_value = if compute_ldp_bit(gamma, randomness) {
+            // sample outside the interval [left_bound, left_bound + width)
+            let outside_length = BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) + 1u8 - &width;
+            let outside = Self::scale_uniform::<INPUT_BYTES>(&uniform, &outside_length);
+            if outside < left_bound {
+                outside
+            } else {
+                outside + width
+            }
+        } else {
+            // sample inside the interval [left_bound, left_bound + width)
+            left_bound + Self::scale_uniform::<INPUT_BYTES>(&uniform, &width)
+        };
+        biguint_to_u64(&ldp_value)
+    }
+
+    fn value_to_field<F: PrimeField>(value: &Self::Value) -> F {
+        F::from(*value)
+    }
+
+    fn is_valid_input<const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        _input_value: &BigUint,
+    ) -> bool {
+        // any INPUT_BYTES wide value is in 0..=M, categorical inputs are not supported
+        IS_REAL_INPUT && (1..=8).contains(&INPUT_BYTES)
+    }
+
+    fn output_is_valid_input<const IS_REAL_INPUT: bool>() -> bool {
+        // outputs are in 0..=M, just like the inputs
+        true
+    }
+
+    fn blanket_gamma<const GAMMA_BYTES: usize>() -> Option<[u8; GAMMA_BYTES]> {
+        // gamma must be smaller than 1/2, so the output always depends on the input
+        None
+    }
+
+    fn is_valid_gamma<const GAMMA_BYTES: usize>(gamma: &[u8; GAMMA_BYTES]) -> bool {
+        // 0 < gamma < 1/2, otherwise epsilon is not positive and the outputs cannot be decoded
+        let gamma = BigUint::from_bytes_le(gamma);
+        gamma > BigUint::from(0u8) && 2u8 * gamma < BigUint::from_bytes_le(&[u8::MAX; GAMMA_BYTES])
+    }
+
+    fn epsilon<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+    ) -> f64 {
+        // inverse of gamma = 1 / (e^(eps/2) + 1)
+        let gamma = gamma_to_probability(gamma);
+        2.0 * ((1.0 - gamma) / gamma).ln()
+    }
+
+    fn estimate<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        values: &[Self::Value],
+    ) -> Estimate {
+        Estimate {
+            mean: Self::estimate_mean::<INPUT_BYTES, GAMMA_BYTES>(values, gamma),
+            counts: None,
+            histogram: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::mechanism::{Mechanism, Piecewise};
+    use ark_std::test_rng;
+    use num_bigint::BigUint;
+    use rand::Rng;
+
+    #[test]
+    fn piecewise_estimator_test() {
+        const INPUT_BYTES: usize = 4;
+        const GAMMA_BYTES: usize = 4;
+        const N: usize = 20000;
+        let rng = &mut test_rng();
+        // gamma = 0.25, i.e., eps = 2 ln(3)
+        let gamma = (u32::MAX / 4).to_le_bytes();
+
+        for true_mean in [0.1, 0.5, 0.8] {
+            let values = (0..N)
+                .map(|_| {
+                    // true values are spread around the true mean
+                    let true_value = (true_mean + rng.gen_range(-0.1..0.1)) * u32::MAX as f64;
+                    let randomness = rng.gen::<[u8; GAMMA_BYTES + INPUT_BYTES]>();
+                    Piecewise::apply::<INPUT_BYTES, GAMMA_BYTES, 0, true>(
+                        &gamma,
+                        &BigUint::from(true_value as u32),
+                        &randomness,
+                    )
+                })
+                .collect::<Vec<_>>();
+            let estimate = Piecewise::estimate_mean::<INPUT_BYTES, GAMMA_BYTES>(&values, &gamma);
+            assert!((estimate - true_mean).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn piecewise_validity_test() {
+        // gamma must be in (0, 1/2)
+        assert!(Piecewise::is_valid_gamma(&(u32::MAX / 4).to_le_bytes()));
+        assert!(Piecewise::is_valid_gamma(&(u32::MAX / 2).to_le_bytes()));
+        assert!(!Piecewise::is_valid_gamma(
+            &(u32::MAX / 2 + 1).to_le_bytes()
+        ));
+        assert!(!Piecewise::is_valid_gamma(&[0u8; 4]));
+        assert!(!Piecewise::is_valid_gamma(&[u8::MAX; 4]));
+
+        // inputs must be real-valued and at most 8 bytes wide
+        let input_value = BigUint::from(1u8);
+        assert!(Piecewise::is_valid_input::<8, 0, true>(&input_value));
+        assert!(!Piecewise::is_valid_input::<9, 0, true>(&input_value));
+        assert!(!Piecewise::is_valid_input::<8, 0, false>(&input_value));
+    }
+}
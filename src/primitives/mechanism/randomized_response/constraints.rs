@@ -0,0 +1,250 @@
+//! R1CS constraint generation for the randomized response mechanism.
+
+use crate::primitives::comparison::BoundedFpVar;
+use crate::primitives::mechanism::{
+    biguint_to_field, field_to_biguint, MechanismGadget, RandomizedResponse,
+};
+use crate::primitives::parameters::GammaVar;
+use ark_ff::PrimeField;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+use std::cmp::Ordering;
+
+/// Gadget for generating R1CS constraints for the randomized response mechanism
+#[derive(Clone)]
+pub struct RandomizedResponseGadget;
+
+impl<ConstraintF: PrimeField> MechanismGadget<RandomizedResponse, ConstraintF>
+    for RandomizedResponseGadget
+{
+    fn apply<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &GammaVar<ConstraintF>,
+        true_value: &FpVar<ConstraintF>,
+        randomness: &[UInt8<ConstraintF>],
+    ) -> Result<(FpVar<ConstraintF>, Boolean<ConstraintF>), SynthesisError> {
+        let cs = true_value.cs().or(randomness.cs());
+
+        // ldp_bit = Ber(gamma)
+        let ldp_bit = gamma.compute_ldp_bit(&randomness[0..GAMMA_BYTES])?;
+
+        // compute the uniformly random value, used if ldp_bit == 1
+        let k = FpVar::constant(ConstraintF::from(K));
+        let max_bound = FpVar::constant(ConstraintF::from_le_bytes_mod_order(
+            &[u8::MAX; INPUT_BYTES],
+        ));
+        let boundary_gap = biguint_to_field::<ConstraintF>(&RandomizedResponse::boundary_gap::<
+            INPUT_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >());
+        // the computed ldp value is at most K
+        let k_bits = (u64::BITS - K.leading_zeros()) as usize;
+        let computed_ldp_value = BoundedFpVar::new(
+            FpVar::new_witness(cs.clone(), || {
+                Ok(ConstraintF::from(RandomizedResponse::random_value::<
+                    INPUT_BYTES,
+                    GAMMA_BYTES,
+                    K,
+                    IS_REAL_INPUT,
+                >(&randomness.value()?)))
+            })?,
+            k_bits,
+        )?;
+        let computed_ldp_value = computed_ldp_value.as_fp_var();
+
+        let randomness_fp =
+            BoundedFpVar::from_bytes_le(&randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES])?;
+        let lower_bound = if IS_REAL_INPUT {
+            computed_ldp_value.clone() * boundary_gap
+        } else {
+            (computed_ldp_value.clone() - ConstraintF::one()) * boundary_gap
+        };
+        let computed_upper_bound = if IS_REAL_INPUT {
+            (computed_ldp_value.clone() + ConstraintF::one()) * boundary_gap
+        } else {
+            computed_ldp_value.clone() * boundary_gap
+        };
+
+        // adjust the upper bound in case ldp_value == k, such that it includes max_bound
+        let ldp_equal_to_k = k.is_eq(computed_ldp_value)?;
+        let upper_bound = ldp_equal_to_k.select(
+            &(max_bound.clone() + ConstraintF::one()),
+            &computed_upper_bound,
+        )?;
+        // the lower bound is INPUT_BYTES wide, the upper bound can be one bit wider
+        let lower_bound = BoundedFpVar::new(lower_bound, 8 * INPUT_BYTES)?;
+        let upper_bound = BoundedFpVar::new(upper_bound, 8 * INPUT_BYTES + 1)?;
+        // randomness >= lower_bound
+        let lower_bound_check = randomness_fp.is_cmp(&lower_bound, Ordering::Greater, true)?;
+        // randomness < upper_bound
+        let upper_bound_check = randomness_fp.is_cmp(&upper_bound, Ordering::Less, false)?;
+
+        // encode the true value, used if ldp_bit == 0 (stochastic rounding if is_real_input)
+        let (true_value_computed, remainder_check) = if IS_REAL_INPUT {
+            let true_value_times_k = true_value * &k;
+            // the multiplicand is at most K
+            let multiplicand = BoundedFpVar::new(
+                FpVar::new_witness(cs.clone(), || {
+                    Ok(biguint_to_field::<ConstraintF>(
+                        &RandomizedResponse::stochastic_rounding_division::<INPUT_BYTES, K>(
+                            &field_to_biguint(true_value.value()?),
+                        )
+                        .0,
+                    ))
+                })?,
+                k_bits,
+            )?;
+            let multiplicand = multiplicand.as_fp_var();
+            let remainder = BoundedFpVar::new(
+                true_value_times_k - multiplicand * &max_bound,
+                8 * INPUT_BYTES,
+            )?;
+            // remainder < max_bound
+            let remainder_check = remainder.is_cmp(
+                &BoundedFpVar::new(max_bound, 8 * INPUT_BYTES)?,
+                Ordering::Less,
+                false,
+            )?;
+            let true_value_randomness = BoundedFpVar::from_bytes_le(
+                &randomness[GAMMA_BYTES + INPUT_BYTES..GAMMA_BYTES + 2 * INPUT_BYTES],
+            )?;
+
+            // true_value_randomness <= remainder
+            let true_value_random_bit =
+                remainder.is_cmp(&true_value_randomness, Ordering::Greater, true)?;
+            (
+                multiplicand + FpVar::from(true_value_random_bit),
+                remainder_check,
+            )
+        } else {
+            (true_value.clone(), Boolean::TRUE)
+        };
+
+        Ok((
+            ldp_bit.select(computed_ldp_value, &true_value_computed)?,
+            Boolean::kary_and(&[lower_bound_check, upper_bound_check, remainder_check])?,
+        ))
+    }
+
+    fn is_valid_input<const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        true_value: &FpVar<ConstraintF>,
+    ) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        if IS_REAL_INPUT {
+            // real inputs can be any INPUT_BYTES wide value
+            Ok(Boolean::TRUE)
+        } else {
+            // 1 <= true_value <= K
+            let true_value = BoundedFpVar::new(true_value.clone(), 8 * INPUT_BYTES)?;
+            let lower_bound_check = true_value.is_cmp(
+                &BoundedFpVar::constant(ConstraintF::one()),
+                Ordering::Greater,
+                true,
+            )?;
+            let upper_bound_check = true_value.is_cmp(
+                &BoundedFpVar::constant(ConstraintF::from(K)),
+                Ordering::Less,
+                true,
+            )?;
+            lower_bound_check.and(&upper_bound_check)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::mechanism::{
+        Mechanism, MechanismGadget, RandomizedResponse, RandomizedResponseGadget,
+    };
+    use crate::primitives::parameters::GammaVar;
+    use ark_ed_on_bls12_381::Fq as Fr;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn randomized_response_valid_input_test() {
+        const INPUT_BYTES: usize = 1;
+        const K: u64 = 4;
+
+        for true_value in 0..=K + 1 {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let is_valid = RandomizedResponse::is_valid_input::<INPUT_BYTES, K, false>(
+                &BigUint::from(true_value),
+            );
+            assert_eq!(is_valid, (1..=K).contains(&true_value));
+
+            let true_value_var =
+                FpVar::new_witness(cs.clone(), || Ok(Fr::from(true_value))).unwrap();
+            let is_valid_var = <RandomizedResponseGadget as MechanismGadget<
+                RandomizedResponse,
+                Fr,
+            >>::is_valid_input::<INPUT_BYTES, K, false>(
+                &true_value_var
+            )
+            .unwrap();
+            assert_eq!(is_valid_var.value().unwrap(), is_valid);
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn randomized_response_maximal_randomness_test() {
+        const INPUT_BYTES: usize = 1;
+        const GAMMA_BYTES: usize = 1;
+        const K: u64 = 2;
+
+        // the ldp bit is set and the random value is sampled from the maximal randomness
+        let randomness = [0, u8::MAX, u8::MAX];
+        for is_real_input in [false, true] {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let gamma = [u8::MAX / 2];
+            let true_value = 1u8;
+            let ldp_value = if is_real_input {
+                RandomizedResponse::apply::<INPUT_BYTES, GAMMA_BYTES, K, true>(
+                    &gamma,
+                    &BigUint::from(true_value),
+                    &randomness,
+                )
+            } else {
+                RandomizedResponse::apply::<INPUT_BYTES, GAMMA_BYTES, K, false>(
+                    &gamma,
+                    &BigUint::from(true_value),
+                    &randomness,
+                )
+            };
+            assert_eq!(ldp_value, K);
+
+            let gamma_var = GammaVar::new_constant(cs.clone(), gamma).unwrap();
+            let true_value_var =
+                FpVar::new_witness(cs.clone(), || Ok(Fr::from(true_value))).unwrap();
+            let randomness_var = UInt8::new_witness_vec(cs.clone(), &randomness).unwrap();
+            let (ldp_value_var, correct) = if is_real_input {
+                <RandomizedResponseGadget as MechanismGadget<RandomizedResponse, Fr>>::apply::<
+                    INPUT_BYTES,
+                    GAMMA_BYTES,
+                    K,
+                    true,
+                >(&gamma_var, &true_value_var, &randomness_var)
+            } else {
+                <RandomizedResponseGadget as MechanismGadget<RandomizedResponse, Fr>>::apply::<
+                    INPUT_BYTES,
+                    GAMMA_BYTES,
+                    K,
+                    false,
+                >(&gamma_var, &true_value_var, &randomness_var)
+            }
+            .unwrap();
+
+            correct.enforce_equal(&Boolean::TRUE).unwrap();
+            assert_eq!(ldp_value_var.value().unwrap(), Fr::from(K));
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Implementation of the randomized response mechanism as used in the paper (Sec. 4.1).
+//!
+//! For categorical inputs (`IS_REAL_INPUT = false`) the true value is in `1..=K` and is replaced by
+//! a uniformly random value from `1..=K` with probability gamma. For real-valued inputs
+//! (`IS_REAL_INPUT = true`) the true value is first stochastically rounded to one of the `K + 1`
+//! levels `0..=K`, after which the same randomization is applied.
+
+use crate::primitives::mechanism::{
+    biguint_to_u64, compute_ldp_bit, gamma_to_probability, Estimate, Mechanism,
+};
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use std::cmp::min;
+
+pub mod constraints;
+pub use constraints::*;
+
+/// Randomized response mechanism
+#[derive(Clone)]
+pub struct RandomizedResponse;
+
+impl RandomizedResponse {
+    /// Size of each of the `K` (or `K + 1`) equally sized intervals that the random bytes are
+    /// divided into, in order to sample a uniformly random value.
+    pub fn boundary_gap<const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+    ) -> BigUint {
+        if IS_REAL_INPUT {
+            BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / (K + 1)
+        } else {
+            BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]) / K
+        }
+    }
+
+    /// Sample a uniformly random value from the output domain (this is the output when the
+    /// LDP bit equals 1).
+    pub fn random_value<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        randomness: &[u8],
+    ) -> u64 {
+        let computed_ldp_value =
+            BigUint::from_bytes_le(&randomness[GAMMA_BYTES..GAMMA_BYTES + INPUT_BYTES])
+                / Self::boundary_gap::<INPUT_BYTES, K, IS_REAL_INPUT>();
+        let computed_ldp_value = biguint_to_u64(&computed_ldp_value);
+        if IS_REAL_INPUT {
+            min(computed_ldp_value, K)
+        } else {
+            min(computed_ldp_value, K - 1) + 1
+        }
+    }
+
+    /// Stochastic rounding of a real input value (encoded as integer in `0..2^(8*INPUT_BYTES)`)
+    /// to one of the levels `0..=K`. Returns the rounded down level (`multiplicand`) and the
+    /// `remainder` of the division, the input is rounded up iff the random bytes are at most
+    /// the remainder.
+    pub fn stochastic_rounding_division<const INPUT_BYTES: usize, const K: u64>(
+        input_value: &BigUint,
+    ) -> (BigUint, BigUint) {
+        let input_value_times_k = input_value * K;
+        let multiplicand = &input_value_times_k / BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]);
+        let remainder =
+            &input_value_times_k - &multiplicand * BigUint::from_bytes_le(&[u8::MAX; INPUT_BYTES]);
+        (multiplicand, remainder)
+    }
+
+    /// Encode the true value (this is the output when the LDP bit equals 0). For real inputs
+    /// this applies stochastic rounding, for categorical inputs this is the identity.
+    pub fn encode_true_value<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        input_value: &BigUint,
+        randomness: &[u8],
+    ) -> u64 {
+        if IS_REAL_INPUT {
+            let (multiplicand, remainder) =
+                Self::stochastic_rounding_division::<INPUT_BYTES, K>(input_value);
+            let random_input_bytes =
+                &randomness[GAMMA_BYTES + INPUT_BYTES..GAMMA_BYTES + 2 * INPUT_BYTES];
+            let random_input_bit = (BigUint::from_bytes_le(random_input_bytes) <= remainder) as u64;
+            biguint_to_u64(&multiplicand) + random_input_bit
+        } else {
+            biguint_to_u64(input_value)
+        }
+    }
+}
+
+impl Mechanism for RandomizedResponse {
+    type Value = u64;
+
+    fn randomness_bytes<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const IS_REAL_INPUT: bool,
+    >() -> usize {
+        if IS_REAL_INPUT {
+            GAMMA_BYTES + 2 * INPUT_BYTES
+        } else {
+            GAMMA_BYTES + INPUT_BYTES
+        }
+    }
+
+    fn apply<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        input_value: &BigUint,
+        randomness: &[u8],
+    ) -> Self::Value {
+        if compute_ldp_bit(gamma, randomness) {
+            Self::random_value::<INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(randomness)
+        } else {
+            Self::encode_true_value::<INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                input_value,
+                randomness,
+            )
+        }
+    }
+
+    fn value_to_field<F: PrimeField>(value: &Self::Value) -> F {
+        F::from(*value)
+    }
+
+    fn is_valid_input<const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        input_value: &BigUint,
+    ) -> bool {
+        // categorical inputs are in 1..=K, real inputs can be any INPUT_BYTES wide value
+        IS_REAL_INPUT || (*input_value >= BigUint::from(1u8) && *input_value <= BigUint::from(K))
+    }
+
+    fn output_is_valid_input<const IS_REAL_INPUT: bool>() -> bool {
+        // categorical outputs are in 1..=K, but real-valued outputs are levels in 0..=K
+        !IS_REAL_INPUT
+    }
+
+    fn blanket_gamma<const GAMMA_BYTES: usize>() -> Option<[u8; GAMMA_BYTES]> {
+        // the ldp bit is always set, so the output is a uniformly random value
+        Some([u8::MAX; GAMMA_BYTES])
+    }
+
+    fn is_valid_gamma<const GAMMA_BYTES: usize>(_gamma: &[u8; GAMMA_BYTES]) -> bool {
+        // any gamma in (0, 1] is valid
+        true
+    }
+
+    fn epsilon<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+    ) -> f64 {
+        // ln(((1 - gamma) + gamma / L) / (gamma / L)) for L output levels
+        let levels = if IS_REAL_INPUT { K + 1 } else { K } as f64;
+        let gamma = gamma_to_probability(gamma);
+        (1.0 + levels * (1.0 - gamma) / gamma).ln()
+    }
+
+    fn estimate<
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        values: &[Self::Value],
+    ) -> Estimate {
+        let gamma = gamma_to_probability(gamma);
+        let n = values.len() as f64;
+        if IS_REAL_INPUT {
+            // random levels are uniform in 0..=K, i.e., have mean 1/2 after scaling to [0, 1]
+            let sample_mean = values.iter().sum::<u64>() as f64 / (n * K as f64);
+            Estimate {
+                mean: (sample_mean - gamma / 2.0) / (1.0 - gamma),
+                counts: None,
+                histogram: None,
+            }
+        } else {
+            // each category is expected to be output gamma * n / K times for random values
+            let counts = (1..=K)
+                .map(|category| values.iter().filter(|value| **value == category).count() as u64)
+                .collect::<Vec<_>>();
+            let histogram = counts
+                .iter()
+                .map(|count| (*count as f64 - gamma * n / K as f64) / (1.0 - gamma))
+                .collect::<Vec<_>>();
+            let mean = histogram
+                .iter()
+                .zip(1..=K)
+                .map(|(estimate, category)| estimate * category as f64)
+                .sum::<f64>()
+                / n;
+            Estimate {
+                mean,
+                counts: Some(counts),
+                histogram: Some(histogram),
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! R1CS constraint generation for the derivation of the permanent randomness.
+
+use crate::primitives::memoization::PERMANENT_KEY_EVAL_POINT;
+use ark_crypto_primitives::prf::{PRFGadget, PRF};
+use ark_ff::PrimeField;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+
+/// Gadget for the derivation of the permanent randomness used for memoization
+pub struct PermanentRandomnessGadget;
+
+impl PermanentRandomnessGadget {
+    /// Derive `RANDOMNESS_BYTES` bytes of permanent randomness for the given true value (given as
+    /// its `INPUT_BYTES` little-endian bytes) from the `nullifier_key` inside the ZKP circuit.
+    pub fn derive<
+        P: PRF,
+        PG: PRFGadget<P, ConstraintF>,
+        ConstraintF: PrimeField,
+        const INPUT_BYTES: usize,
+        const RANDOMNESS_BYTES: usize,
+    >(
+        nullifier_key: &[UInt8<ConstraintF>],
+        input_value: &[UInt8<ConstraintF>],
+    ) -> Result<Vec<UInt8<ConstraintF>>, SynthesisError> {
+        // such sizes are rejected by `PermanentRandomness::check`
+        if INPUT_BYTES >= 32 || RANDOMNESS_BYTES > 32 * (u8::MAX as usize + 1) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let key = PG::evaluate(
+            nullifier_key,
+            &UInt8::constant_vec(&PERMANENT_KEY_EVAL_POINT),
+        )?
+        .to_bytes()?;
+
+        let mut randomness = Vec::with_capacity(RANDOMNESS_BYTES);
+        for chunk_index in 0..((RANDOMNESS_BYTES - 1) / 32) + 1 {
+            let mut eval_point = input_value[0..INPUT_BYTES].to_vec();
+            eval_point.extend_from_slice(&UInt8::constant_vec(&[0; 32][INPUT_BYTES..31]));
+            eval_point.push(UInt8::constant(chunk_index as u8));
+            randomness.extend(PG::evaluate(&key, &eval_point)?.to_bytes()?);
+        }
+        randomness.truncate(RANDOMNESS_BYTES);
+        Ok(randomness)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::memoization::{PermanentRandomness, PermanentRandomnessGadget};
+    use ark_crypto_primitives::prf::blake2s::constraints::Blake2sGadget;
+    use ark_crypto_primitives::prf::Blake2s;
+    use ark_ed_on_bls12_381::Fq as Fr;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn permanent_randomness_gadget_test() {
+        const INPUT_BYTES: usize = 2;
+        const RANDOMNESS_BYTES: usize = 40;
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let nullifier_key = [7u8; 32];
+        let input_value = [3u8, 1u8];
+
+        let randomness = PermanentRandomness::derive::<Blake2s, INPUT_BYTES, RANDOMNESS_BYTES>(
+            &nullifier_key,
+            &input_value,
+        )
+        .unwrap();
+        // memoized: same value gives the same randomness, another value different randomness
+        assert_eq!(
+            randomness,
+            PermanentRandomness::derive::<Blake2s, INPUT_BYTES, RANDOMNESS_BYTES>(
+                &nullifier_key,
+                &input_value
+            )
+            .unwrap()
+        );
+        assert_ne!(
+            randomness,
+            PermanentRandomness::derive::<Blake2s, INPUT_BYTES, RANDOMNESS_BYTES>(
+                &nullifier_key,
+                &[4u8, 1u8]
+            )
+            .unwrap()
+        );
+
+        let nullifier_key_var = UInt8::new_witness_vec(cs.clone(), &nullifier_key).unwrap();
+        let input_value_var = UInt8::new_witness_vec(cs.clone(), &input_value).unwrap();
+        let randomness_var = PermanentRandomnessGadget::derive::<
+            Blake2s,
+            Blake2sGadget,
+            Fr,
+            INPUT_BYTES,
+            RANDOMNESS_BYTES,
+        >(&nullifier_key_var, &input_value_var)
+        .unwrap();
+
+        assert_eq!(randomness_var.value().unwrap(), randomness.to_vec());
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
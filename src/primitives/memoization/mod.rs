@@ -0,0 +1,79 @@
+//! Derivation of the permanent randomness for memoized (RAPPOR-style) randomization, also contains
+//! the constraints for usage inside ZKP circuits.
+//!
+//! The permanent randomness for a true value is derived from the nullifier key of the client as
+//! `PRF(PRF(nullifier_key, PERMANENT_KEY_EVAL_POINT), true_value || chunk_index)`. The nullifier key
+//! is committed in the first `Generate Randomness` step and is reused for all later steps (which the
+//! server enforces), so the permanent randomized response of a true value is the same every time it
+//! is computed, i.e., it is memoized without the client needing to store anything. Deriving a
+//! separate key first ensures that the permanent randomness is independent of the nullifiers, which
+//! are PRF evaluations of the nullifier key that are made public.
+
+use crate::prelude::*;
+
+// ZKP constraints
+pub mod constraints;
+pub use constraints::*;
+
+/// Evaluation point used to derive the key for the permanent randomness from the nullifier key,
+/// which is never the evaluation point of a nullifier (see `PermanentRandomness::check`).
+pub const PERMANENT_KEY_EVAL_POINT: [u8; 32] = [u8::MAX; 32];
+
+/// Derivation of the permanent randomness used for memoization
+pub struct PermanentRandomness;
+
+impl PermanentRandomness {
+    /// Check that permanent randomness can be derived for the given sizes: the true value has to
+    /// fit in an evaluation point next to the chunk index, there are at most 256 chunks, and the
+    /// time has to leave byte 30 of the evaluation point of a nullifier zero (see
+    /// `Nullifier::eval_point`), such that it never equals `PERMANENT_KEY_EVAL_POINT`.
+    pub fn check<
+        const INPUT_BYTES: usize,
+        const TIME_BYTES: usize,
+        const RANDOMNESS_BYTES: usize,
+    >() -> Result<(), Error> {
+        if !Self::fits::<INPUT_BYTES, RANDOMNESS_BYTES>() || TIME_BYTES > 30 {
+            Err("Memoization requires at most 31 input bytes, 30 time bytes and 8192 randomness bytes.")?
+        }
+        Ok(())
+    }
+
+    /// Whether the true value and the chunk indices fit in the evaluation points.
+    fn fits<const INPUT_BYTES: usize, const RANDOMNESS_BYTES: usize>() -> bool {
+        INPUT_BYTES < 32 && RANDOMNESS_BYTES <= 32 * (u8::MAX as usize + 1)
+    }
+
+    /// Derive `RANDOMNESS_BYTES` bytes of permanent randomness for the given true value from the
+    /// `nullifier_key` of the client.
+    pub fn derive<
+        P: PRF<Input = [u8; 32], Seed = [u8; 32], Output = [u8; 32]>,
+        const INPUT_BYTES: usize,
+        const RANDOMNESS_BYTES: usize,
+    >(
+        nullifier_key: &[u8; 32],
+        input_value: &[u8; INPUT_BYTES],
+    ) -> Result<[u8; RANDOMNESS_BYTES], Error> {
+        if !Self::fits::<INPUT_BYTES, RANDOMNESS_BYTES>() {
+            Err("The true value or the permanent randomness is too large for memoization.")?
+        }
+        let key = P::evaluate(nullifier_key, &PERMANENT_KEY_EVAL_POINT)?;
+        let mut randomness = [0; RANDOMNESS_BYTES];
+        for (chunk_index, chunk) in randomness.chunks_mut(32).enumerate() {
+            let eval_point = Self::eval_point::<INPUT_BYTES>(input_value, chunk_index);
+            chunk.copy_from_slice(&P::evaluate(&key, &eval_point)?[0..chunk.len()]);
+        }
+        Ok(randomness)
+    }
+
+    /// Evaluation point for the given chunk of permanent randomness: the true value followed by
+    /// zeros, with the index of the chunk as the last byte.
+    fn eval_point<const INPUT_BYTES: usize>(
+        input_value: &[u8; INPUT_BYTES],
+        chunk_index: usize,
+    ) -> [u8; 32] {
+        let mut eval_point = [0; 32];
+        eval_point[0..INPUT_BYTES].copy_from_slice(input_value);
+        eval_point[31] = chunk_index as u8;
+        eval_point
+    }
+}
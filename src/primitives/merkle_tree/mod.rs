@@ -0,0 +1,211 @@
+//! Merkle tree whose leaves are generated on demand, such that its root and authentication paths
+//! can be computed with memory that is linear in the height of the tree (instead of linear in the
+//! number of leaves as for `MerkleTree`).
+//!
+//! The resulting roots and paths are identical to those of `MerkleTree` for the same leaves, so
+//! they can be verified with the regular (native or R1CS) path verification.
+//!
+//! Leaves are requested in consecutive batches of at most `LEAF_BATCH_SIZE` leaves, such that the
+//! generator can compute the leaves of a batch in parallel.
+
+use crate::prelude::*;
+use ark_crypto_primitives::merkle_tree::{
+    Config as MerkleTreeConfig, DigestConverter, LeafParam, TwoToOneParam,
+};
+use std::borrow::Borrow;
+use std::ops::Range;
+
+/// Maximum number of leaves that are requested from the leaf generator at once.
+pub const LEAF_BATCH_SIZE: usize = 1 << 10;
+
+/// Merkle tree of the given `height` (i.e., with `2^(height - 1)` leaves) that does not store any
+/// nodes, leaves are obtained from a generator function whenever they are needed.
+///
+/// The generator function `leaves(range)` returns the leaves at the indices in `range`, in order.
+#[derive(Clone)]
+pub struct StreamingMerkleTree<P: MerkleTreeConfig> {
+    leaf_hash_param: LeafParam<P>,
+    two_to_one_hash_param: TwoToOneParam<P>,
+    height: usize,
+}
+
+impl<P: MerkleTreeConfig> StreamingMerkleTree<P> {
+    /// Create a streaming merkle tree of the given height.
+    pub fn new(
+        leaf_hash_param: &LeafParam<P>,
+        two_to_one_hash_param: &TwoToOneParam<P>,
+        height: usize,
+    ) -> Self {
+        assert!(
+            height >= 2,
+            "Merkle tree should contain at least two leaves."
+        );
+        Self {
+            leaf_hash_param: leaf_hash_param.clone(),
+            two_to_one_hash_param: two_to_one_hash_param.clone(),
+            height,
+        }
+    }
+
+    /// Number of leaves of the tree.
+    pub fn num_leaves(&self) -> usize {
+        1 << (self.height - 1)
+    }
+
+    /// Compute the root of the tree.
+    ///
+    /// Every leaf is generated once, while only `O(height)` digests (and a single batch of leaves)
+    /// are kept in memory.
+    pub fn root<L: Borrow<P::Leaf>>(
+        &self,
+        leaves: impl FnMut(Range<usize>) -> Result<Vec<L>, Error>,
+    ) -> Result<P::InnerDigest, Error> {
+        self.subtree_root(0, self.height, leaves)
+    }
+
+    /// Compute the authentication path of the leaf at `index`.
+    ///
+    /// Only the roots of the sibling subtrees along the path are computed, this requires generating
+    /// every leaf once, while only `O(height)` digests (and a single batch of leaves) are kept in
+    /// memory.
+    pub fn generate_proof<L: Borrow<P::Leaf>>(
+        &self,
+        index: usize,
+        mut leaves: impl FnMut(Range<usize>) -> Result<Vec<L>, Error>,
+    ) -> Result<Path<P>, Error> {
+        assert!(index < self.num_leaves(), "Leaf index is out of bounds.");
+        let leaf_sibling = leaves((index ^ 1)..(index ^ 1) + 1)?
+            .pop()
+            .ok_or("Leaf generator returned too few leaves.")?;
+        let leaf_sibling_hash = P::LeafHash::evaluate(&self.leaf_hash_param, leaf_sibling)?;
+
+        // siblings of the non-leaf nodes on the path, from the layer below the root to the layer
+        // above the leaves
+        let auth_path = (1..self.height - 1)
+            .map(|depth| {
+                let subtree_height = self.height - depth;
+                let sibling = (index >> (subtree_height - 1)) ^ 1;
+                self.subtree_root(sibling << (subtree_height - 1), subtree_height, &mut leaves)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Path {
+            leaf_sibling_hash,
+            auth_path,
+            leaf_index: index,
+        })
+    }
+
+    /// Compute the root of the subtree of the given height whose leftmost leaf is at `start`, by
+    /// hashing its leaves from left to right and merging nodes on a stack as soon as both children
+    /// of a node are known.
+    fn subtree_root<L: Borrow<P::Leaf>>(
+        &self,
+        start: usize,
+        height: usize,
+        mut leaves: impl FnMut(Range<usize>) -> Result<Vec<L>, Error>,
+    ) -> Result<P::InnerDigest, Error> {
+        let end = start + (1 << (height - 1));
+        // stack of (height, digest) of subtrees whose sibling is not yet known
+        let mut stack: Vec<(usize, P::InnerDigest)> = Vec::with_capacity(height);
+        for batch_start in (start..end).step_by(LEAF_BATCH_SIZE) {
+            let batch = batch_start..end.min(batch_start + LEAF_BATCH_SIZE);
+            let batch_leaves = leaves(batch.clone())?;
+            if batch_leaves.len() != batch.len() {
+                Err("Leaf generator returned a wrong number of leaves.")?
+            }
+            for pair in batch_leaves.chunks(2) {
+                self.push_leaves(&mut stack, &pair[0], &pair[1])?;
+            }
+        }
+        debug_assert!(stack.len() == 1 && stack[0].0 == height);
+        Ok(stack.pop().expect("Subtree should have a root.").1)
+    }
+
+    /// Push the parent node of two adjacent leaves onto the stack, and merge it with the nodes on
+    /// the stack as long as it is their sibling.
+    fn push_leaves<L: Borrow<P::Leaf>>(
+        &self,
+        stack: &mut Vec<(usize, P::InnerDigest)>,
+        left: &L,
+        right: &L,
+    ) -> Result<(), Error> {
+        let left = P::LeafHash::evaluate(&self.leaf_hash_param, left.borrow())?;
+        let right = P::LeafHash::evaluate(&self.leaf_hash_param, right.borrow())?;
+        let mut node = (
+            2,
+            P::TwoToOneHash::evaluate(
+                &self.two_to_one_hash_param,
+                P::LeafInnerDigestConverter::convert(left)?,
+                P::LeafInnerDigestConverter::convert(right)?,
+            )?,
+        );
+        while let Some((sibling_height, sibling)) = stack.pop() {
+            if sibling_height != node.0 {
+                stack.push((sibling_height, sibling));
+                break;
+            }
+            node = (
+                node.0 + 1,
+                P::TwoToOneHash::compress(&self.two_to_one_hash_param, sibling, node.1)?,
+            );
+        }
+        stack.push(node);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::BasicConfig;
+    use crate::prelude::*;
+    use crate::primitives::merkle_tree::LEAF_BATCH_SIZE;
+    use ark_bls12_381::Bls12_381;
+    use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
+    use ark_groth16::Groth16;
+    use astro_float::BigFloat;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::ops::Range;
+
+    type Conf = BasicConfig<JubJub, ChaChaRng, Groth16<Bls12_381>, 24>;
+
+    #[test]
+    fn streaming_merkle_tree_test() {
+        let rng = &mut ChaChaRng::seed_from_u64(0);
+        let params = ParametersExpand::<Conf, 8>::setup(BigFloat::from_f64(0.5, 64), rng).unwrap();
+        let leaf_params = &params.client_merkle_tree_scheme.leaf_crh_params;
+        let two_to_one_params = &params.client_merkle_tree_scheme.two_to_one_crh_params;
+
+        // the last tree spans multiple batches of leaves, for which only some paths are checked
+        for (height, indices) in [
+            (2, 0..2),
+            (3, 0..4),
+            (5, 0..16),
+            (12, LEAF_BATCH_SIZE - 1..LEAF_BATCH_SIZE + 1),
+        ] {
+            let leaves = (0..1 << (height - 1))
+                .map(|_| ClientCommitmentSchemeOutput::<Conf>::rand(rng))
+                .collect::<Vec<_>>();
+            let leaf_batch = |batch: Range<usize>| Ok(leaves[batch].to_vec());
+            let merkle_tree =
+                ClientMerkleTree::<Conf>::new(leaf_params, two_to_one_params, &leaves).unwrap();
+            let streaming_merkle_tree =
+                ClientStreamingMerkleTree::<Conf>::new(leaf_params, two_to_one_params, height);
+
+            let root = streaming_merkle_tree.root(leaf_batch).unwrap();
+            assert_eq!(root, merkle_tree.root());
+            for (index, leaf_value) in indices.map(|index| (index, &leaves[index])) {
+                let path = streaming_merkle_tree
+                    .generate_proof(index, leaf_batch)
+                    .unwrap();
+                let expected_path = merkle_tree.generate_proof(index).unwrap();
+                assert_eq!(path.leaf_sibling_hash, expected_path.leaf_sibling_hash);
+                assert_eq!(path.auth_path, expected_path.auth_path);
+                assert!(path
+                    .verify(leaf_params, two_to_one_params, &root, leaf_value)
+                    .unwrap());
+            }
+        }
+    }
+}
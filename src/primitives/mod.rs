@@ -1,6 +1,14 @@
 //! Cryptographic primitives for use in our schemes. Each primitive includes a regular usage
 //! implementation along with a circuit specification for use inside ZKP circuits.
 
+pub mod blanket_noise;
+pub mod comparison;
+pub mod components;
 pub mod crh;
+pub mod encryption;
+pub mod mechanism;
+pub mod memoization;
+pub mod merkle_tree;
+pub mod multi_message;
 pub mod parameters;
 pub mod signature;
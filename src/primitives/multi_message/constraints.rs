@@ -0,0 +1,115 @@
+//! R1CS constraint generation for the derivation of the messages of a client in the multi-message
+//! Shuffle scheme.
+
+use crate::primitives::comparison::BoundedFpVar;
+use crate::primitives::multi_message::REAL_INDEX_EVAL_POINT;
+use ark_crypto_primitives::prf::{PRFGadget, PRF};
+use ark_ff::PrimeField;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+use std::cmp::Ordering;
+
+/// Gadget for the derivation of the messages of a client in the multi-message Shuffle scheme
+pub struct MultiMessageGadget;
+
+impl MultiMessageGadget {
+    /// Evaluation points of the PRF for the message with the given index inside the ZKP circuit:
+    /// the given evaluation points with their last byte XOR-ed with the message index.
+    pub fn eval_points<ConstraintF: PrimeField>(
+        prf_eval_points: &[Vec<UInt8<ConstraintF>>],
+        message_index: &UInt8<ConstraintF>,
+    ) -> Result<Vec<Vec<UInt8<ConstraintF>>>, SynthesisError> {
+        prf_eval_points
+            .iter()
+            .map(|prf_eval_point| {
+                let mut eval_point = prf_eval_point.clone();
+                eval_point[31] = eval_point[31].xor(message_index)?;
+                Ok(eval_point)
+            })
+            .collect()
+    }
+
+    /// Compute whether the message with the given index is the real message out of the given
+    /// number of messages inside the ZKP circuit, i.e., whether `message_index` equals
+    /// `PRF(seed, REAL_INDEX_EVAL_POINT) mod messages`.
+    pub fn is_real<P: PRF, PG: PRFGadget<P, ConstraintF>, ConstraintF: PrimeField>(
+        seed: &[UInt8<ConstraintF>],
+        message_index: &UInt8<ConstraintF>,
+        messages: usize,
+    ) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        assert!((1..=u8::MAX as usize + 1).contains(&messages));
+        let key = PG::evaluate(seed, &UInt8::constant_vec(&REAL_INDEX_EVAL_POINT))?.to_bytes()?;
+        let value = BoundedFpVar::from_bytes_le(&key[0..2])?;
+        let cs = value.as_fp_var().cs();
+
+        // value = quotient * messages + remainder, with remainder < messages
+        let value_u16 = || {
+            value
+                .as_fp_var()
+                .value()
+                .map(|value| value.into_bigint().as_ref()[0] as usize)
+        };
+        let quotient = BoundedFpVar::new(
+            FpVar::new_witness(cs.clone(), || {
+                Ok(ConstraintF::from((value_u16()? / messages) as u64))
+            })?,
+            16,
+        )?;
+        let remainder = BoundedFpVar::new(
+            FpVar::new_witness(cs.clone(), || {
+                Ok(ConstraintF::from((value_u16()? % messages) as u64))
+            })?,
+            8,
+        )?;
+        (quotient.as_fp_var() * ConstraintF::from(messages as u64) + remainder.as_fp_var())
+            .enforce_equal(value.as_fp_var())?;
+        remainder
+            .is_cmp(
+                &BoundedFpVar::constant(ConstraintF::from(messages as u64)),
+                Ordering::Less,
+                false,
+            )?
+            .enforce_equal(&Boolean::TRUE)?;
+
+        remainder
+            .as_fp_var()
+            .is_eq(&Boolean::le_bits_to_fp_var(&message_index.to_bits_le()?)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::multi_message::{MultiMessage, MultiMessageGadget};
+    use ark_crypto_primitives::prf::blake2s::constraints::Blake2sGadget;
+    use ark_crypto_primitives::prf::Blake2s;
+    use ark_ed_on_bls12_381::Fq as Fr;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn multi_message_real_index_test() {
+        let rng = &mut ChaChaRng::seed_from_u64(0);
+        for messages in [2, 3, 7] {
+            let seed: [u8; 32] = rng.gen();
+            let real_index = MultiMessage::real_index::<Blake2s>(&seed, messages).unwrap();
+            assert!((real_index as usize) < messages);
+
+            for message_index in 0..messages as u8 {
+                let cs = ConstraintSystem::<Fr>::new_ref();
+                let seed_var = UInt8::new_witness_vec(cs.clone(), &seed).unwrap();
+                let message_index_var = UInt8::new_input(cs.clone(), || Ok(message_index)).unwrap();
+                let is_real = MultiMessageGadget::is_real::<Blake2s, Blake2sGadget, _>(
+                    &seed_var,
+                    &message_index_var,
+                    messages,
+                )
+                .unwrap();
+                assert_eq!(is_real.value().unwrap(), message_index == real_index);
+                assert!(cs.is_satisfied().unwrap());
+            }
+        }
+    }
+}
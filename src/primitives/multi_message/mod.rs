@@ -0,0 +1,151 @@
+//! Derivation of the messages of a client in the multi-message Shuffle scheme, also contains the
+//! constraints for usage inside ZKP circuits, and the privacy analysis and estimator of the scheme.
+//!
+//! In every epoch a client sends `m` messages, each with its own proof. The randomness of message
+//! `j` is derived from the seed with the PRF evaluation points of which the last byte is XOR-ed
+//! with `j`. Exactly one of these messages (the real message) applies the mechanism to the true
+//! value using gamma, all other messages use the blanket gamma of the mechanism, i.e., they are
+//! uniformly random blanket noise. The index of the real message is derived from the seed as
+//! `PRF(seed, REAL_INDEX_EVAL_POINT) mod m` (using its first two bytes), so it is hidden from the
+//! server, but cannot be chosen by the client.
+
+use crate::prelude::*;
+use crate::primitives::mechanism::{gamma_to_probability, probability_to_gamma};
+
+// ZKP constraints
+pub mod constraints;
+pub use constraints::*;
+
+/// Evaluation point used to derive the index of the real message from the seed.
+pub const REAL_INDEX_EVAL_POINT: [u8; 32] = [u8::MAX - 1; 32];
+
+/// Derivation of the messages of a client in the multi-message Shuffle scheme
+pub struct MultiMessage;
+
+impl MultiMessage {
+    /// Evaluation points of the PRF for the message with the given index: the given evaluation
+    /// points with their last byte XOR-ed with the message index.
+    pub fn eval_points(prf_eval_points: &[[u8; 32]], message_index: u8) -> Vec<[u8; 32]> {
+        prf_eval_points
+            .iter()
+            .map(|prf_eval_point| {
+                let mut eval_point = *prf_eval_point;
+                eval_point[31] ^= message_index;
+                eval_point
+            })
+            .collect()
+    }
+
+    /// Derive the index of the real message out of the given number of messages from the seed.
+    pub fn real_index<P: PRF<Input = [u8; 32], Seed = [u8; 32], Output = [u8; 32]>>(
+        seed: &[u8; 32],
+        messages: usize,
+    ) -> Result<u8, Error> {
+        assert!((1..=u8::MAX as usize + 1).contains(&messages));
+        let key = P::evaluate(seed, &REAL_INDEX_EVAL_POINT)?;
+        Ok((u16::from_le_bytes([key[0], key[1]]) as usize % messages) as u8)
+    }
+
+    /// Central privacy loss (epsilon, for the given delta) of an epoch in which the given number of
+    /// clients report, following the privacy blanket bound of Balle et al. ("The Privacy Blanket
+    /// of the Shuffle Model", CRYPTO 2019, Thm. 3.1) for randomized response: the messages of all
+    /// other clients contain `(clients - 1) * (gamma + messages - 1)` uniformly random values in
+    /// expectation, which hide the real message of a single client.
+    ///
+    /// Note: the bound only holds for an epsilon of at most 1.
+    pub fn central_epsilon<const GAMMA_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        gamma: &[u8; GAMMA_BYTES],
+        messages: usize,
+        clients: usize,
+        delta: f64,
+    ) -> f64 {
+        let levels = if IS_REAL_INPUT { K + 1 } else { K } as f64;
+        let blanket = clients.saturating_sub(1) as f64
+            * (gamma_to_probability(gamma) + messages.saturating_sub(1) as f64);
+        (14.0 * levels * (2.0 / delta).ln() / blanket)
+            .sqrt()
+            .max(27.0 * levels / blanket)
+    }
+
+    /// Estimate the population statistics from the accepted values of all messages of an epoch.
+    ///
+    /// A message is uniformly random with probability `(gamma + messages - 1) / messages`, so the
+    /// estimator of the mechanism is applied with this effective gamma, after which the estimated
+    /// histogram is scaled down to the number of clients (i.e., the number of real messages).
+    pub fn estimate<
+        M: Mechanism,
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        gamma: &[u8; GAMMA_BYTES],
+        messages: usize,
+        values: &[M::Value],
+    ) -> Estimate {
+        if messages == 1 {
+            return M::estimate::<INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(gamma, values);
+        }
+        let effective_gamma = probability_to_gamma::<GAMMA_BYTES>(
+            (gamma_to_probability(gamma) + (messages - 1) as f64) / messages as f64,
+        );
+        let mut estimate =
+            M::estimate::<INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(&effective_gamma, values);
+        if let Some(histogram) = &mut estimate.histogram {
+            histogram
+                .iter_mut()
+                .for_each(|estimate| *estimate /= messages as f64);
+        }
+        estimate
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::primitives::mechanism::probability_to_gamma;
+    use crate::primitives::multi_message::MultiMessage;
+    use num_bigint::BigUint;
+    use rand::{RngCore, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn multi_message_estimate_test() {
+        const K: u64 = 4;
+        const MESSAGES: usize = 3;
+        let rng = &mut ChaChaRng::seed_from_u64(0);
+        let gamma = probability_to_gamma::<4>(0.25);
+        let blanket_gamma = RandomizedResponse::blanket_gamma::<4>().unwrap();
+
+        // 20000 clients with true value 2, each sending one real message and blanket noise
+        let clients = 20000;
+        let mut values = vec![];
+        for _ in 0..clients {
+            for message_index in 0..MESSAGES {
+                let mut randomness = [0; 8];
+                rng.fill_bytes(&mut randomness);
+                let gamma = if message_index == 0 {
+                    &gamma
+                } else {
+                    &blanket_gamma
+                };
+                values.push(RandomizedResponse::apply::<4, 4, K, false>(
+                    gamma,
+                    &BigUint::from(2u8),
+                    &randomness,
+                ));
+            }
+        }
+
+        let estimate =
+            MultiMessage::estimate::<RandomizedResponse, 4, 4, K, false>(&gamma, MESSAGES, &values);
+        let histogram = estimate.histogram.unwrap();
+        assert!((histogram[1] / clients as f64 - 1.0).abs() < 0.05);
+        assert!((estimate.mean - 2.0).abs() < 0.05);
+
+        // blanket noise of the other clients reduces the central privacy loss
+        let single = MultiMessage::central_epsilon::<4, K, false>(&gamma, 1, clients, 1e-6);
+        let multi = MultiMessage::central_epsilon::<4, K, false>(&gamma, MESSAGES, clients, 1e-6);
+        assert!(multi < single && single < 1.0);
+    }
+}
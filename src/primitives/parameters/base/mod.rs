@@ -1,6 +1,7 @@
 //! Convenient struct for using the parameters of the Base VLDP scheme.
 
 use crate::prelude::*;
+use crate::primitives::parameters::ClientMerkleTreeParameters;
 use astro_float::{BigFloat, Consts, Radix, RoundingMode};
 use num_bigint::BigUint;
 use std::str::FromStr;
@@ -11,12 +12,15 @@ pub use constraints::*;
 /// All parameters needed for the shuffle model.
 /// Gamma is not directly accessible, as all logic for handling computations involving gamma has
 /// been implemented here.
+/// The parameters of the merkle tree are only used for batches of `Generate Randomness` steps (see
+/// `SignedBatch`).
 #[derive(Clone)]
 pub struct ParametersBase<Conf: Config, const GAMMA_BYTES: usize> {
     gamma: BigFloat,
     pub client_commitment_scheme: ClientCommitmentSchemeParameters<Conf>,
     pub server_signature_scheme: ServerSignatureSchemeParameters<Conf>,
     pub client_signature_scheme: ClientSignatureSchemeParameters<Conf>,
+    pub client_merkle_tree_scheme: ClientMerkleTreeParameters<Conf>,
 }
 
 impl<Conf: Config, const GAMMA_BYTES: usize> ParametersBase<Conf, GAMMA_BYTES> {
@@ -24,12 +28,17 @@ impl<Conf: Config, const GAMMA_BYTES: usize> ParametersBase<Conf, GAMMA_BYTES> {
     /// This simply generates parameters for all cryptographic primitives.
     pub fn setup<R: Rng + CryptoRng>(gamma: BigFloat, rng: &mut R) -> Result<Self, Error> {
         assert!(BigFloat::from(0) < gamma && gamma <= BigFloat::from(1));
-        Ok(Self {
+        let parameters = Self {
             gamma,
             client_commitment_scheme: Conf::ClientCommitmentScheme::setup(rng)?,
             server_signature_scheme: Conf::ServerSignatureScheme::setup(rng)?,
             client_signature_scheme: Conf::ClientSignatureScheme::setup(rng)?,
-        })
+            client_merkle_tree_scheme: ClientMerkleTreeParameters::setup(rng)?,
+        };
+        if !Conf::LdpMechanism::is_valid_gamma(&parameters.gamma_as_bytes()?) {
+            Err(GenericError::InvalidGamma)?
+        }
+        Ok(parameters)
     }
 
     /// Transform a floating point value of gamma to a byte array in a deterministic way, with
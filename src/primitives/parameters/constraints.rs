@@ -1,46 +1,67 @@
 //! R1CS constraint generation for parameters that are generic across each VLDP scheme.
 
-use crate::prelude::{constraints::*, *};
+use crate::primitives::comparison::BoundedFpVar;
 use ark_ff::PrimeField;
 use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
 use ark_relations::r1cs::{Namespace, SynthesisError};
 use std::borrow::Borrow;
 use std::cmp::Ordering;
 
 /// R1CS variable for storing the gamma variable (for the LDP randomizers, see paper Sec. 4.1)
-pub struct GammaVar<Conf: Config> {
-    gamma: FpVar<ConstraintField<Conf>>,
+#[derive(Clone)]
+pub struct GammaVar<ConstraintF: PrimeField> {
+    gamma: BoundedFpVar<ConstraintF>,
 }
 
-impl<Conf: Config> GammaVar<Conf> {
+impl<ConstraintF: PrimeField> GammaVar<ConstraintF> {
     /// Given an array of random bytes this computes Ber(gamma) inside the ZKP circuit.
     pub fn compute_ldp_bit(
         &self,
-        randomness: &[UInt8<ConstraintField<Conf>>],
-    ) -> Result<Boolean<ConstraintField<Conf>>, SynthesisError> {
-        let randomness = Boolean::le_bits_to_fp_var(&randomness.to_bits_le()?)?;
+        randomness: &[UInt8<ConstraintF>],
+    ) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        let randomness = BoundedFpVar::from_bytes_le(randomness)?;
         // randomness <= gamma
-        self.gamma
-            .is_cmp_unchecked(&randomness, Ordering::Greater, true)
+        self.gamma.is_cmp(&randomness, Ordering::Greater, true)
+    }
+
+    /// The (integer encoding of) gamma as a field element variable.
+    pub fn as_fp_var(&self) -> &FpVar<ConstraintF> {
+        self.gamma.as_fp_var()
+    }
+}
+
+// conditional selection between two values of gamma
+impl<ConstraintF: PrimeField> CondSelectGadget<ConstraintF> for GammaVar<ConstraintF> {
+    fn conditionally_select(
+        cond: &Boolean<ConstraintF>,
+        true_value: &Self,
+        false_value: &Self,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Self {
+            gamma: cond.select(&true_value.gamma, &false_value.gamma)?,
+        })
     }
 }
 
 // R1CS variable allocation for gamma
-impl<Conf: Config, const N: usize> AllocVar<[u8; N], ConstraintField<Conf>> for GammaVar<Conf> {
+impl<ConstraintF: PrimeField, const N: usize> AllocVar<[u8; N], ConstraintF>
+    for GammaVar<ConstraintF>
+{
     fn new_variable<T: Borrow<[u8; N]>>(
-        cs: impl Into<Namespace<ConstraintField<Conf>>>,
+        cs: impl Into<Namespace<ConstraintF>>,
         f: impl FnOnce() -> Result<T, SynthesisError>,
         mode: AllocationMode,
     ) -> Result<Self, SynthesisError> {
+        // gamma is encoded by N bytes
         Ok(Self {
-            gamma: FpVar::new_variable(
-                cs,
-                || {
-                    f().map(|gamma| {
-                        ConstraintField::<Conf>::from_le_bytes_mod_order(gamma.borrow())
-                    })
-                },
-                mode,
+            gamma: BoundedFpVar::new(
+                FpVar::new_variable(
+                    cs,
+                    || f().map(|gamma| ConstraintF::from_le_bytes_mod_order(gamma.borrow())),
+                    mode,
+                )?,
+                8 * N,
             )?,
         })
     }
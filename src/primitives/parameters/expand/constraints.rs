@@ -8,7 +8,7 @@ use std::borrow::Borrow;
 
 /// All R1CS variables for the parameters needed for the Expand scheme.
 pub struct ParametersExpandVar<Conf: Config, ConfG: ConfigGadget<Conf>> {
-    pub gamma: GammaVar<Conf>,
+    pub gamma: GammaVar<ConstraintField<Conf>>,
     pub client_commitment_scheme: ClientCommitmentSchemeParametersVar<Conf, ConfG>,
     #[allow(dead_code)]
     pub server_signature_scheme: ServerSignatureSchemeParametersVar<Conf, ConfG>,
@@ -28,7 +28,7 @@ impl<Conf: Config, ConfG: ConfigGadget<Conf>, const GAMMA_BYTES: usize>
     ) -> Result<Self, SynthesisError> {
         let cs = cs.into();
         f().and_then(|val| {
-            let gamma = GammaVar::<Conf>::new_variable(
+            let gamma = GammaVar::new_variable(
                 cs.clone(),
                 || {
                     Ok(val
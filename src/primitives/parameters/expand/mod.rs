@@ -12,9 +12,17 @@ pub use constraints::*;
 /// All parameters needed for the shuffle model.
 /// Gamma is not directly accessible, as all logic for handling computations involving gamma has
 /// been implemented here.
+/// If seeded leaves are used, every leaf of the client merkle tree commits to a 32 byte seed
+/// instead of to the full client randomness, which is then derived from this seed with the PRF
+/// inside the ZKP circuit. This makes the commitment independent of the number of randomness bytes.
+/// If the index is bound to the epoch, the leaf that is used in a report is not chosen by the
+/// client, but is enforced to be `epoch - start_epoch` inside the ZKP circuit, where the epoch is
+/// the upper time bound and the start epoch of the merkle tree is signed by the server.
 #[derive(Clone)]
 pub struct ParametersExpand<Conf: Config, const GAMMA_BYTES: usize> {
     gamma: BigFloat,
+    seeded_leaves: bool,
+    epoch_bound_index: bool,
     pub client_commitment_scheme: ClientCommitmentSchemeParameters<Conf>,
     pub server_signature_scheme: ServerSignatureSchemeParameters<Conf>,
     pub client_signature_scheme: ClientSignatureSchemeParameters<Conf>,
@@ -26,15 +34,51 @@ impl<Conf: Config, const GAMMA_BYTES: usize> ParametersExpand<Conf, GAMMA_BYTES>
     /// This simply generates parameters for all cryptographic primitives.
     pub fn setup<R: Rng + CryptoRng>(gamma: BigFloat, rng: &mut R) -> Result<Self, Error> {
         assert!(BigFloat::from(0) < gamma && gamma <= BigFloat::from(1));
-        Ok(Self {
+        let parameters = Self {
             gamma,
+            seeded_leaves: false,
+            epoch_bound_index: false,
             client_commitment_scheme: Conf::ClientCommitmentScheme::setup(rng)?,
             server_signature_scheme: Conf::ServerSignatureScheme::setup(rng)?,
             client_signature_scheme: Conf::ClientSignatureScheme::setup(rng)?,
             client_merkle_tree_scheme: ClientMerkleTreeParameters::setup(rng)?,
+        };
+        if !Conf::LdpMechanism::is_valid_gamma(&parameters.gamma_as_bytes()?) {
+            Err(GenericError::InvalidGamma)?
+        }
+        Ok(parameters)
+    }
+
+    /// Perform the setup of the Expand scheme with seeded leaves for the given value of gamma.
+    pub fn setup_seeded<R: Rng + CryptoRng>(gamma: BigFloat, rng: &mut R) -> Result<Self, Error> {
+        Ok(Self {
+            seeded_leaves: true,
+            ..Self::setup(gamma, rng)?
+        })
+    }
+
+    /// Perform the setup of the Expand scheme for the given value of gamma, where the index of the
+    /// leaf that is used in a report is bound to the epoch.
+    pub fn setup_epoch_bound<R: Rng + CryptoRng>(
+        gamma: BigFloat,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            epoch_bound_index: true,
+            ..Self::setup(gamma, rng)?
         })
     }
 
+    /// Whether the leaves of the client merkle tree commit to a seed instead of the randomness.
+    pub fn has_seeded_leaves(&self) -> bool {
+        self.seeded_leaves
+    }
+
+    /// Whether the index of the leaf that is used in a report is bound to the epoch.
+    pub fn has_epoch_bound_index(&self) -> bool {
+        self.epoch_bound_index
+    }
+
     /// Transform a floating point value of gamma to a byte array in a deterministic way, with
     /// as much precision as possible. This is needed for encoding inside the ZKP circuit.
     pub fn gamma_as_bytes(&self) -> Result<[u8; GAMMA_BYTES], Error> {
@@ -2,16 +2,19 @@
 //! scheme.
 
 use crate::prelude::{constraints::*, *};
-use crate::primitives::parameters::GammaVar;
+use crate::primitives::parameters::{ClientMerkleTreeParametersVar, GammaVar};
 use ark_relations::r1cs::{Namespace, SynthesisError};
 use std::borrow::Borrow;
 
 /// All R1CS variables for the parameters needed for the Shuffle scheme.
 pub struct ParametersShuffleVar<Conf: Config, ConfG: ConfigGadget<Conf>> {
-    pub gamma: GammaVar<Conf>,
+    pub gamma: GammaVar<ConstraintField<Conf>>,
+    pub permanent_gamma: Option<GammaVar<ConstraintField<Conf>>>,
+    pub blanket_gamma: Option<GammaVar<ConstraintField<Conf>>>,
     pub client_commitment_scheme: ClientCommitmentSchemeParametersVar<Conf, ConfG>,
     pub server_signature_scheme: ServerSignatureSchemeParametersVar<Conf, ConfG>,
     pub client_signature_scheme: ClientSignatureSchemeParametersVar<Conf, ConfG>,
+    pub client_merkle_tree_scheme: ClientMerkleTreeParametersVar<Conf, ConfG>,
 }
 
 // implement variable allocation of all parameters
@@ -26,7 +29,7 @@ impl<Conf: Config, ConfG: ConfigGadget<Conf>, const GAMMA_BYTES: usize>
     ) -> Result<Self, SynthesisError> {
         let cs = cs.into();
         f().and_then(|val| {
-            let gamma = GammaVar::<Conf>::new_variable(
+            let gamma = GammaVar::new_variable(
                 cs.clone(),
                 || {
                     Ok(val
@@ -36,6 +39,24 @@ impl<Conf: Config, ConfG: ConfigGadget<Conf>, const GAMMA_BYTES: usize>
                 },
                 mode,
             )?;
+            let permanent_gamma = val
+                .borrow()
+                .permanent_gamma_as_bytes()
+                .map_err(|_| SynthesisError::AssignmentMissing)?
+                .map(|permanent_gamma| {
+                    GammaVar::new_variable(cs.clone(), || Ok(permanent_gamma), mode)
+                })
+                .transpose()?;
+            // the blanket gamma is only used if clients send multiple messages
+            let blanket_gamma = if val.borrow().messages() > 1 {
+                Conf::LdpMechanism::blanket_gamma::<GAMMA_BYTES>()
+                    .map(|blanket_gamma| {
+                        GammaVar::new_variable(cs.clone(), || Ok(blanket_gamma), mode)
+                    })
+                    .transpose()?
+            } else {
+                None
+            };
             let client_commitment_scheme =
                 ClientCommitmentSchemeParametersVar::<Conf, ConfG>::new_variable(
                     cs.clone(),
@@ -54,11 +75,20 @@ impl<Conf: Config, ConfG: ConfigGadget<Conf>, const GAMMA_BYTES: usize>
                     || Ok(&val.borrow().client_signature_scheme),
                     mode,
                 )?;
+            let client_merkle_tree_scheme =
+                ClientMerkleTreeParametersVar::<_, ConfG>::new_variable(
+                    cs.clone(),
+                    || Ok(&val.borrow().client_merkle_tree_scheme),
+                    mode,
+                )?;
             return Ok(Self {
                 gamma,
+                permanent_gamma,
+                blanket_gamma,
                 client_commitment_scheme,
                 server_signature_scheme,
                 client_signature_scheme,
+                client_merkle_tree_scheme,
             });
         })
     }
@@ -1,6 +1,7 @@
 //! Convenient struct for using the parameters of the Shuffle VLDP scheme.
 
 use crate::prelude::*;
+use crate::primitives::parameters::ClientMerkleTreeParameters;
 use astro_float::{BigFloat, Consts, Radix, RoundingMode};
 use num_bigint::BigUint;
 use std::str::FromStr;
@@ -12,12 +13,31 @@ pub use constraints::*;
 /// All parameters needed for the shuffle model.
 /// Gamma is not directly accessible, as all logic for handling computations involving gamma has
 /// been implemented here.
+/// If a permanent gamma is set, the scheme uses memoization: the true value is first randomized
+/// with permanent randomness that only depends on the seed and the true value (using the
+/// permanent gamma), after which the result is randomized again using gamma for every report.
+/// If a registry depth is set, clients additionally prove that their signature public key is a
+/// leaf of the Merkle tree (of the given depth) of registered client keys.
+/// If more than one message is set, every client sends this number of messages per epoch, of which
+/// one is the real message and the others are blanket noise (see `MultiMessage`).
+/// If blanket noise is set, the shuffler adds dummy reports to every epoch with fewer reports than
+/// the minimum batch size (see `BlanketNoise`).
+/// If a batch depth is set, the server signs the root of a Merkle tree (of the given depth) over a
+/// batch of `Generate Randomness` steps, and clients prove that their signature input is a leaf of
+/// a signed root (see `SignedBatch`).
 #[derive(Clone)]
 pub struct ParametersShuffle<Conf: Config, const GAMMA_BYTES: usize> {
     gamma: BigFloat,
+    permanent_gamma: Option<BigFloat>,
+    registry_depth: Option<usize>,
+    batch_depth: Option<usize>,
+    messages: usize,
+    blanket_noise: Option<BlanketNoise>,
     pub client_commitment_scheme: ClientCommitmentSchemeParameters<Conf>,
     pub server_signature_scheme: ServerSignatureSchemeParameters<Conf>,
     pub client_signature_scheme: ClientSignatureSchemeParameters<Conf>,
+    pub client_merkle_tree_scheme: ClientMerkleTreeParameters<Conf>,
+    pub report_encryption_scheme: ReportEncryptionSchemeParameters<Conf>,
 }
 
 impl<Conf: Config, const GAMMA_BYTES: usize> ParametersShuffle<Conf, GAMMA_BYTES> {
@@ -25,19 +45,169 @@ impl<Conf: Config, const GAMMA_BYTES: usize> ParametersShuffle<Conf, GAMMA_BYTES
     /// This simply generates parameters for all cryptographic primitives.
     pub fn setup<R: Rng + CryptoRng>(gamma: BigFloat, rng: &mut R) -> Result<Self, Error> {
         assert!(BigFloat::from(0) < gamma && gamma <= BigFloat::from(1));
-        Ok(Self {
+        let parameters = Self {
             gamma,
+            permanent_gamma: None,
+            registry_depth: None,
+            batch_depth: None,
+            messages: 1,
+            blanket_noise: None,
             client_commitment_scheme: Conf::ClientCommitmentScheme::setup(rng)?,
             server_signature_scheme: Conf::ServerSignatureScheme::setup(rng)?,
             client_signature_scheme: Conf::ClientSignatureScheme::setup(rng)?,
+            client_merkle_tree_scheme: ClientMerkleTreeParameters::setup(rng)?,
+            report_encryption_scheme: Conf::ReportEncryptionScheme::setup(rng)?,
+        };
+        if !Conf::LdpMechanism::is_valid_gamma(&parameters.gamma_as_bytes()?) {
+            Err(GenericError::InvalidGamma)?
+        }
+        Ok(parameters)
+    }
+
+    /// Perform the setup of the Shuffle scheme with memoization for the given values of gamma
+    /// (used for every report) and the permanent gamma (used once per true value).
+    pub fn setup_memoized<R: Rng + CryptoRng>(
+        gamma: BigFloat,
+        permanent_gamma: BigFloat,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        assert!(BigFloat::from(0) < permanent_gamma && permanent_gamma <= BigFloat::from(1));
+        let parameters = Self {
+            permanent_gamma: Some(permanent_gamma),
+            ..Self::setup(gamma, rng)?
+        };
+        match parameters.permanent_gamma_as_bytes()? {
+            Some(permanent_gamma) if Conf::LdpMechanism::is_valid_gamma(&permanent_gamma) => {
+                Ok(parameters)
+            }
+            _ => Err(GenericError::InvalidGamma)?,
+        }
+    }
+
+    /// Perform the setup of the Shuffle scheme with a registry of client keys for the given value
+    /// of gamma, where the Merkle tree of registered keys has the given depth (i.e., it holds
+    /// `2^(registry_depth - 1)` keys).
+    pub fn setup_registered<R: Rng + CryptoRng>(
+        gamma: BigFloat,
+        registry_depth: usize,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        assert!(
+            registry_depth >= 2,
+            "Merkle tree should contain at least two leaves."
+        );
+        Ok(Self {
+            registry_depth: Some(registry_depth),
+            ..Self::setup(gamma, rng)?
+        })
+    }
+
+    /// Perform the setup of the Shuffle scheme with batches of `Generate Randomness` steps for the
+    /// given value of gamma, where the Merkle tree of a batch has the given depth (i.e., a batch
+    /// holds at most `2^(batch_depth - 1)` clients).
+    pub fn setup_batch<R: Rng + CryptoRng>(
+        gamma: BigFloat,
+        batch_depth: usize,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        assert!(
+            batch_depth >= 2,
+            "Merkle tree should contain at least two leaves."
+        );
+        Ok(Self {
+            batch_depth: Some(batch_depth),
+            ..Self::setup(gamma, rng)?
+        })
+    }
+
+    /// Perform the setup of the multi-message Shuffle scheme for the given value of gamma, where
+    /// every client sends the given number of messages per epoch. This requires a mechanism with
+    /// a blanket gamma.
+    pub fn setup_multi_message<R: Rng + CryptoRng>(
+        gamma: BigFloat,
+        messages: usize,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        assert!(
+            (2..=u8::MAX as usize + 1).contains(&messages),
+            "Clients should send at least two and at most 256 messages."
+        );
+        assert!(
+            Conf::LdpMechanism::blanket_gamma::<GAMMA_BYTES>().is_some(),
+            "Multiple messages require a mechanism with a blanket gamma."
+        );
+        Ok(Self {
+            messages,
+            ..Self::setup(gamma, rng)?
+        })
+    }
+
+    /// Perform the setup of the Shuffle scheme with blanket noise for the given value of gamma,
+    /// where the shuffler adds dummy reports up to the given minimum batch size per epoch. The
+    /// public seed of the dummy reports is generated as part of the setup. This requires a
+    /// mechanism with a blanket gamma.
+    pub fn setup_blanket_noise<R: Rng + CryptoRng>(
+        gamma: BigFloat,
+        min_batch_size: usize,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        assert!(
+            min_batch_size >= 1,
+            "Minimum batch size should be positive."
+        );
+        assert!(
+            Conf::LdpMechanism::blanket_gamma::<GAMMA_BYTES>().is_some(),
+            "Blanket noise requires a mechanism with a blanket gamma."
+        );
+        let parameters = Self::setup(gamma, rng)?;
+        Ok(Self {
+            blanket_noise: Some(BlanketNoise {
+                min_batch_size,
+                seed: rng.gen(),
+            }),
+            ..parameters
         })
     }
 
+    /// Depth of the Merkle tree of registered client keys, if a registry is used.
+    pub fn registry_depth(&self) -> Option<usize> {
+        self.registry_depth
+    }
+
+    /// Depth of the Merkle tree of a batch of `Generate Randomness` steps, if batches are used.
+    pub fn batch_depth(&self) -> Option<usize> {
+        self.batch_depth
+    }
+
+    /// Number of messages that every client sends per epoch.
+    pub fn messages(&self) -> usize {
+        self.messages
+    }
+
+    /// Blanket noise that is injected by the shuffler, if used.
+    pub fn blanket_noise(&self) -> Option<&BlanketNoise> {
+        self.blanket_noise.as_ref()
+    }
+
     /// Transform a floating point value of gamma to a byte array in a deterministic way, with
     /// as much precision as possible. This is needed for encoding inside the ZKP circuit.
     pub fn gamma_as_bytes(&self) -> Result<[u8; GAMMA_BYTES], Error> {
+        Self::float_as_bytes(&self.gamma)
+    }
+
+    /// Transform the floating point value of the permanent gamma (if memoization is used) to a
+    /// byte array, in the same way as `gamma_as_bytes`.
+    pub fn permanent_gamma_as_bytes(&self) -> Result<Option<[u8; GAMMA_BYTES]>, Error> {
+        self.permanent_gamma
+            .as_ref()
+            .map(Self::float_as_bytes)
+            .transpose()
+    }
+
+    /// Shared implementation of `gamma_as_bytes` and `permanent_gamma_as_bytes`.
+    fn float_as_bytes(value: &BigFloat) -> Result<[u8; GAMMA_BYTES], Error> {
         let precision = GAMMA_BYTES * 8 * 2;
-        let mut gamma = self.gamma.clone();
+        let mut gamma = value.clone();
         gamma.set_precision(precision, RoundingMode::Down)?;
         let gamma_as_int = gamma
             .mul_full_prec(
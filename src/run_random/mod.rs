@@ -1,5 +1,6 @@
 //! Functionality to run a server and client for any of our VLDP schemes (Base, Extend, Shuffle)
-//! locally on randomly generated inputs (trusted environment and communication are emulated).
+//! locally on randomly generated inputs (trusted environment and communication are emulated), and
+//! to simulate a population of clients reporting to a single server for several epochs.
 
 mod run_protocol_base;
 pub use run_protocol_base::*;
@@ -9,3 +10,24 @@ pub use run_protocol_expand::*;
 
 mod run_protocol_shuffle;
 pub use run_protocol_shuffle::*;
+
+mod simulation;
+pub use simulation::*;
+
+use crate::prelude::ProofSystemRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+/// RNGs for a protocol run (one for the protocol and one for the proof system). If a seed is
+/// given both are derived from it, such that the run can be replayed exactly, otherwise they are
+/// seeded from entropy.
+fn protocol_rngs<R: ProofSystemRng>(seed: Option<u64>) -> (ChaChaRng, R) {
+    match seed {
+        Some(seed) => {
+            let mut rng = ChaChaRng::seed_from_u64(seed);
+            let zkp_rng = R::with_seed(rng.next_u64());
+            (rng, zkp_rng)
+        }
+        None => (ChaChaRng::from_entropy(), R::new()),
+    }
+}
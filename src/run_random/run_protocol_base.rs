@@ -1,21 +1,21 @@
 //! Locally run a server and client for the Base scheme on randomly generated inputs (trusted
 //! environment and communication are emulated).
 
+use crate::beacon::LocalBeacon;
 use crate::circuits::CircuitBase;
 use crate::client::*;
 use crate::prelude::*;
+use crate::run_random::protocol_rngs;
 use crate::server::*;
 use ark_ff::{PrimeField, Zero};
 use ark_serialize::CanonicalDeserialize;
 use ark_std::{end_timer, start_timer};
 use astro_float::{BigFloat, Consts, Radix, RoundingMode};
 use num_bigint::BigUint;
-use rand::SeedableRng;
-use rand_chacha::ChaChaRng;
 use std::str::FromStr;
 
 /// Run the Base protocol for a given gamma on random inputs (trusted environment and
-/// communication are emulated).
+/// communication are emulated). If a seed is given, the run is fully determined by it.
 pub fn run_protocol_base<
     Conf: Config,
     ConfG: ConfigGadget<Conf>,
@@ -27,23 +27,185 @@ pub fn run_protocol_base<
     const IS_REAL_INPUT: bool,
 >(
     gamma: BigFloat,
+    seed: Option<u64>,
 ) -> Result<(), Error>
 where
     ClientSignatureSchemePublicKey<Conf>:
         ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
     ClientCommitmentSchemeOutput<Conf>:
         ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     Proof<Conf>: CanonicalDeserialize,
 {
-    assert!(IS_REAL_INPUT || INPUT_BYTES + GAMMA_BYTES <= RANDOMNESS_BYTES);
-    assert!(!IS_REAL_INPUT || 2 * INPUT_BYTES + GAMMA_BYTES <= RANDOMNESS_BYTES);
+    run_protocol_base_with_seed_source::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(gamma, seed, ServerSeedSource::Sampled)
+}
+
+/// Run the Base protocol with a stateless server, which derives its seeds deterministically, for
+/// a given gamma on random inputs (trusted environment and communication are emulated). If a seed
+/// is given, the run is fully determined by it.
+pub fn run_protocol_base_stateless<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientCommitmentSchemeOutput<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    run_protocol_base_with_seed_source::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(gamma, seed, ServerSeedSource::Derived)
+}
+
+/// Run the non-interactive Base protocol, in which the server seed is replaced by the output of a
+/// round of a local randomness beacon, for a given gamma on random inputs (trusted environment and
+/// communication are emulated). If a seed is given, the run is fully determined by it.
+pub fn run_protocol_base_beacon<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientCommitmentSchemeOutput<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    run_protocol_base_with_seed_source::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(gamma, seed, ServerSeedSource::Beacon)
+}
+
+/// Run the Base protocol, in which the server signs the `Generate Randomness` step in a batch (see
+/// `SignedBatch`), for a given gamma on random inputs (trusted environment and communication are
+/// emulated). If a seed is given, the run is fully determined by it.
+pub fn run_protocol_base_batch<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientCommitmentSchemeOutput<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    run_protocol_base_with_seed_source::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(gamma, seed, ServerSeedSource::Batch)
+}
+
+/// Source of the server seed in a run of the Base protocol
+#[derive(Clone, Copy, PartialEq)]
+enum ServerSeedSource {
+    /// Sampled by the server
+    Sampled,
+    /// Derived by the server from its seed key
+    Derived,
+    /// Output of a round of the randomness beacon
+    Beacon,
+    /// Sampled by the server, which signs it in a batch
+    Batch,
+}
+
+fn run_protocol_base_with_seed_source<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    seed: Option<u64>,
+    server_seed_source: ServerSeedSource,
+) -> Result<(), Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientCommitmentSchemeOutput<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    assert!(
+        Conf::LdpMechanism::randomness_bytes::<INPUT_BYTES, GAMMA_BYTES, IS_REAL_INPUT>()
+            <= RANDOMNESS_BYTES
+    );
     assert!(K >= 2 && (K.ilog2() + 1) as usize <= INPUT_BYTES * 8);
     assert!(INPUT_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
     assert!(GAMMA_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
     assert!(TIME_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
-    let mut rng = ChaChaRng::from_entropy();
-    let mut zkp_rng = Conf::ZKPRng::new();
+    let (mut rng, mut zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
 
     // setup
     let parameters = ParametersBase::setup(gamma, &mut rng)?;
@@ -61,7 +223,7 @@ where
         Conf::ClientSignatureScheme::keygen(&parameters.client_signature_scheme, &mut rng)?;
 
     // create server
-    let server = ServerBase::<
+    let mut server = ServerBase::<
         _,
         INPUT_BYTES,
         TIME_BYTES,
@@ -72,6 +234,20 @@ where
     >::new(parameters.clone(), verifying_key, &mut rng)?;
     let server_sig_pk = server.get_signature_public_key();
 
+    // let server derive its seeds
+    if server_seed_source == ServerSeedSource::Derived {
+        server.set_seed_key(rng.gen());
+    }
+
+    // create randomness beacon
+    let beacon = if server_seed_source == ServerSeedSource::Beacon {
+        let beacon = LocalBeacon::new(&parameters, &mut rng)?;
+        server.set_beacon_public_key(beacon.get_signature_public_key());
+        Some(beacon)
+    } else {
+        None
+    };
+
     // create client
     let mut client = ClientBase::<
         _,
@@ -88,22 +264,80 @@ where
         proving_key,
     )?;
 
+    let random_time_byte: u8 = rng.gen_range(1..u8::MAX - 1);
+    let mut input_value_time = [0; TIME_BYTES];
+    input_value_time[0] = random_time_byte;
+    let mut lower_bound_time = [0; TIME_BYTES];
+    lower_bound_time[0] = random_time_byte - 1;
+    let upper_bound_time = input_value_time.clone();
+    let time_bounds = (lower_bound_time, upper_bound_time);
+
     // actual protocol
     let timer_gen_rand = start_timer!(|| "Generate randomness");
 
-    // 1. generate randomness
-    let timer_gen_rand_client = start_timer!(|| "Client generation");
-    let gen_rand_client_message = client.generate_randomness_create([0; TIME_BYTES], &mut rng)?;
-    end_timer!(timer_gen_rand_client);
+    // 1. generate randomness (without contacting the server, if the beacon is used)
+    #[cfg_attr(not(feature = "print-trace"), allow(unused_variables))]
+    let (gen_rand_client_message, gen_rand_server_message, result) = match &beacon {
+        Some(beacon) => {
+            let timer_gen_rand_client = start_timer!(|| "Client commitment");
+            let commitment_message = client.generate_randomness_commit(time_bounds.0, &mut rng)?;
+            end_timer!(timer_gen_rand_client);
+
+            // -- START TRUSTED ENVIRONMENT --
+            let timer_te = start_timer!(|| "Trusted environment commitment attestation");
+            let commitment_signature = Conf::ClientSignatureScheme::sign(
+                &parameters.client_signature_scheme,
+                &client_sig_sk,
+                &commitment_message,
+                &mut rng,
+            )?;
+            end_timer!(timer_te);
+            // -- END TRUSTED ENVIRONMENT --
+
+            // the round of the current epoch is the first round after the commitment
+            let beacon_round = beacon.publish(time_bounds.1, &mut rng)?;
 
-    let timer_gen_rand_server = start_timer!(|| "Server generation");
-    let gen_rand_server_message =
-        server.generate_randomness_create(&gen_rand_client_message, &mut rng)?;
-    end_timer!(timer_gen_rand_server);
+            let timer_verify_rand_client = start_timer!(|| "Client verification");
+            let result = client.generate_randomness_beacon(
+                &beacon.get_signature_public_key(),
+                &beacon_round,
+                commitment_signature,
+            )?;
+            end_timer!(timer_verify_rand_client);
+            (vec![], beacon_round, result)
+        }
+        None => {
+            // a derived server seed is bound to the epoch in which the client reports
+            let time = if server_seed_source == ServerSeedSource::Derived {
+                time_bounds.1
+            } else {
+                [0; TIME_BYTES]
+            };
 
-    let timer_verify_rand_client = start_timer!(|| "Client verification");
-    let result = client.generate_randomness_verify(&gen_rand_server_message)?;
-    end_timer!(timer_verify_rand_client);
+            let timer_gen_rand_client = start_timer!(|| "Client generation");
+            let gen_rand_client_message = client.generate_randomness_create(time, &mut rng)?;
+            end_timer!(timer_gen_rand_client);
+
+            let timer_gen_rand_server = start_timer!(|| "Server generation");
+            let gen_rand_server_message = if server_seed_source == ServerSeedSource::Batch {
+                server
+                    .generate_randomness_create_batch(
+                        std::slice::from_ref(&gen_rand_client_message),
+                        &mut rng,
+                    )?
+                    .pop()
+                    .ok_or("The batch contains no server message.")?
+            } else {
+                server.generate_randomness_create(&gen_rand_client_message, &mut rng)?
+            };
+            end_timer!(timer_gen_rand_server);
+
+            let timer_verify_rand_client = start_timer!(|| "Client verification");
+            let result = client.generate_randomness_verify(&gen_rand_server_message)?;
+            end_timer!(timer_verify_rand_client);
+            (gen_rand_client_message, gen_rand_server_message, result)
+        }
+    };
 
     end_timer!(timer_gen_rand);
 
@@ -148,21 +382,12 @@ where
         BigUint::from(rng.gen_range(1..=K))
     };
 
-    let random_time_byte: u8 = rng.gen_range(1..u8::MAX - 1);
-    let mut input_value_time = [0; TIME_BYTES];
-    input_value_time[0] = random_time_byte;
-    let mut lower_bound_time = [0; TIME_BYTES];
-    lower_bound_time[0] = random_time_byte - 1;
-    let upper_bound_time = input_value_time.clone();
-    let time_bounds = (lower_bound_time, upper_bound_time);
-
     let mut input_value_bytes = [0; INPUT_BYTES];
     for (idx, byte) in input_value.to_bytes_le().iter().enumerate() {
         input_value_bytes[idx] = *byte;
     }
 
-    let mut message_bytes = input_value_bytes.to_vec();
-    message_bytes.extend_from_slice(&input_value_time);
+    let message_bytes = AuthenticatedInput::message(&input_value_bytes, &input_value_time);
 
     let timer_te = start_timer!(|| "Trusted environment computation");
     let input_value_signature = Conf::ClientSignatureScheme::sign(
@@ -4,18 +4,18 @@
 use crate::circuits::CircuitExpand;
 use crate::client::*;
 use crate::prelude::*;
+use crate::run_random::protocol_rngs;
 use crate::server::*;
 use ark_ff::PrimeField;
 use ark_serialize::CanonicalDeserialize;
 use ark_std::{end_timer, start_timer, Zero};
 use astro_float::{BigFloat, Consts, Radix, RoundingMode};
 use num_bigint::BigUint;
-use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
 use std::str::FromStr;
 
 /// Run the Expand protocol for a given gamma on random inputs (trusted environment and
-/// communication are emulated).
+/// communication are emulated). If a seed is given, the run is fully determined by it.
 pub fn run_protocol_expand<
     Conf: Config,
     ConfG: ConfigGadget<Conf>,
@@ -28,24 +28,194 @@ pub fn run_protocol_expand<
     const IS_REAL_INPUT: bool,
 >(
     gamma: BigFloat,
+    seed: Option<u64>,
 ) -> Result<(), Error>
 where
     ClientSignatureSchemePublicKey<Conf>:
         ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
     ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     Proof<Conf>: CanonicalDeserialize,
+    ClientCommitmentSchemeParameters<Conf>: Sync,
+    ClientCommitmentSchemeOutput<Conf>: Send,
 {
-    assert!(IS_REAL_INPUT || INPUT_BYTES + GAMMA_BYTES <= RANDOMNESS_BYTES);
-    assert!(!IS_REAL_INPUT || 2 * INPUT_BYTES + GAMMA_BYTES <= RANDOMNESS_BYTES);
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersExpand::setup(gamma, &mut rng)?;
+    run_protocol_expand_with_parameters::<
+        Conf,
+        ConfG,
+        MT_DEPTH,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, false, rng, zkp_rng)
+}
+
+/// Run the Expand protocol with seeded leaves for a given gamma on random inputs (trusted
+/// environment and communication are emulated). If a seed is given, the run is fully determined
+/// by it.
+pub fn run_protocol_expand_seeded<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const MT_DEPTH: usize,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+    ClientCommitmentSchemeParameters<Conf>: Sync,
+    ClientCommitmentSchemeOutput<Conf>: Send,
+{
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersExpand::setup_seeded(gamma, &mut rng)?;
+    run_protocol_expand_with_parameters::<
+        Conf,
+        ConfG,
+        MT_DEPTH,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, false, rng, zkp_rng)
+}
+
+/// Run the Expand protocol with an index that is bound to the epoch for a given gamma on random
+/// inputs (trusted environment and communication are emulated). If a seed is given, the run is
+/// fully determined by it.
+pub fn run_protocol_expand_epoch_bound<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const MT_DEPTH: usize,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+    ClientCommitmentSchemeParameters<Conf>: Sync,
+    ClientCommitmentSchemeOutput<Conf>: Send,
+{
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersExpand::setup_epoch_bound(gamma, &mut rng)?;
+    run_protocol_expand_with_parameters::<
+        Conf,
+        ConfG,
+        MT_DEPTH,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, false, rng, zkp_rng)
+}
+
+/// Run the Expand protocol, in which the server signs the `Generate Randomness` step in a batch
+/// (see `SignedBatch`), for a given gamma on random inputs (trusted environment and communication
+/// are emulated). If a seed is given, the run is fully determined by it.
+pub fn run_protocol_expand_batch<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const MT_DEPTH: usize,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+    ClientCommitmentSchemeParameters<Conf>: Sync,
+    ClientCommitmentSchemeOutput<Conf>: Send,
+{
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersExpand::setup(gamma, &mut rng)?;
+    run_protocol_expand_with_parameters::<
+        Conf,
+        ConfG,
+        MT_DEPTH,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, true, rng, zkp_rng)
+}
+
+fn run_protocol_expand_with_parameters<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const MT_DEPTH: usize,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    parameters: ParametersExpand<Conf, GAMMA_BYTES>,
+    batch: bool,
+    mut rng: ChaChaRng,
+    mut zkp_rng: ZKPRng<Conf>,
+) -> Result<(), Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+    ClientCommitmentSchemeParameters<Conf>: Sync,
+    ClientCommitmentSchemeOutput<Conf>: Send,
+{
+    assert!(
+        Conf::LdpMechanism::randomness_bytes::<INPUT_BYTES, GAMMA_BYTES, IS_REAL_INPUT>()
+            <= RANDOMNESS_BYTES
+    );
     assert!(K >= 2 && (K.ilog2() + 1) as usize <= INPUT_BYTES * 8);
     assert!(INPUT_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
     assert!(TIME_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
-    let mut rng = ChaChaRng::from_entropy();
-    let mut zkp_rng = Conf::ZKPRng::new();
 
     // setup
-    let parameters = ParametersExpand::setup(gamma, &mut rng)?;
     let (proving_key, verifying_key) = CircuitExpand::<
         _,
         ConfG,
@@ -65,7 +235,7 @@ where
     let index = 0;
 
     // create server
-    let server = ServerExpand::<
+    let mut server = ServerExpand::<
         _,
         MT_DEPTH,
         INPUT_BYTES,
@@ -94,17 +264,39 @@ where
         proving_key,
     )?;
 
+    let random_time_byte: u8 = rng.gen_range(1..u8::MAX - 1);
+    let mut input_value_time = [0; TIME_BYTES];
+    input_value_time[0] = random_time_byte;
+    let mut lower_bound_time = [0; TIME_BYTES];
+    lower_bound_time[0] = random_time_byte - 1;
+    let upper_bound_time = input_value_time.clone();
+    let time_bounds = (lower_bound_time, upper_bound_time);
+
+    // the merkle tree starts in the current epoch, if the index is bound to the epoch
+    if parameters.has_epoch_bound_index() {
+        client.set_start_epoch(time_bounds.1)?;
+    }
+
     // actual protocol
     let timer_gen_rand = start_timer!(|| "Generate randomness");
 
     // 1. generate randomness
     let timer_gen_rand_client = start_timer!(|| "Client generation");
-    let gen_rand_client_message = client.generate_randomness_create(&mut rng)?;
+    let gen_rand_client_message = client.generate_randomness_create(&client_sig_sk, &mut rng)?;
     end_timer!(timer_gen_rand_client);
 
     let timer_gen_rand_server = start_timer!(|| "Server generation");
-    let gen_rand_server_message =
-        server.generate_randomness_create(&gen_rand_client_message, &mut rng)?;
+    let gen_rand_server_message = if batch {
+        server
+            .generate_randomness_create_batch(
+                std::slice::from_ref(&gen_rand_client_message),
+                &mut rng,
+            )?
+            .pop()
+            .ok_or("The batch contains no server message.")?
+    } else {
+        server.generate_randomness_create(&gen_rand_client_message, &mut rng)?
+    };
     end_timer!(timer_gen_rand_server);
 
     let timer_verify_rand_client = start_timer!(|| "Client verification");
@@ -154,21 +346,12 @@ where
         BigUint::from(rng.gen_range(1..=K))
     };
 
-    let random_time_byte: u8 = rng.gen_range(1..u8::MAX - 1);
-    let mut input_value_time = [0; TIME_BYTES];
-    input_value_time[0] = random_time_byte;
-    let mut lower_bound_time = [0; TIME_BYTES];
-    lower_bound_time[0] = random_time_byte - 1;
-    let upper_bound_time = input_value_time.clone();
-    let time_bounds = (lower_bound_time, upper_bound_time);
-
     let mut input_value_bytes = [0; INPUT_BYTES];
     for (idx, byte) in input_value.to_bytes_le().iter().enumerate() {
         input_value_bytes[idx] = *byte;
     }
 
-    let mut message_bytes = input_value_bytes.to_vec();
-    message_bytes.extend_from_slice(&input_value_time);
+    let message_bytes = AuthenticatedInput::message(&input_value_bytes, &input_value_time);
 
     let timer_te = start_timer!(|| "Trusted environment computation");
     let input_value_signature = Conf::ClientSignatureScheme::sign(
@@ -4,18 +4,19 @@
 use crate::circuits::CircuitShuffle;
 use crate::client::*;
 use crate::prelude::*;
+use crate::run_random::protocol_rngs;
 use crate::server::*;
+use crate::shuffler::Shuffler;
 use ark_ff::PrimeField;
 use ark_serialize::CanonicalDeserialize;
 use ark_std::{end_timer, start_timer, Zero};
 use astro_float::{BigFloat, Consts, Radix, RoundingMode};
 use num_bigint::BigUint;
-use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
 use std::str::FromStr;
 
 /// Run the Shuffle protocol for a given gamma on random inputs (trusted environment and
-/// communication are emulated).
+/// communication are emulated). If a seed is given, the run is fully determined by it.
 pub fn run_protocol_shuffle<
     Conf: Config,
     ConfG: ConfigGadget<Conf>,
@@ -27,24 +28,256 @@ pub fn run_protocol_shuffle<
     const IS_REAL_INPUT: bool,
 >(
     gamma: BigFloat,
+    seed: Option<u64>,
 ) -> Result<(), Error>
 where
     ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
     ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
     ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
     ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     Proof<Conf>: CanonicalDeserialize,
 {
-    assert!(IS_REAL_INPUT || INPUT_BYTES + GAMMA_BYTES <= RANDOMNESS_BYTES);
-    assert!(!IS_REAL_INPUT || 2 * INPUT_BYTES + GAMMA_BYTES <= RANDOMNESS_BYTES);
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersShuffle::setup(gamma, &mut rng)?;
+    run_protocol_shuffle_with_parameters::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, rng, zkp_rng)
+}
+
+/// Run the Shuffle protocol with memoization for a given gamma and permanent gamma on random
+/// inputs (trusted environment and communication are emulated). If a seed is given, the run is
+/// fully determined by it.
+pub fn run_protocol_shuffle_memoized<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    permanent_gamma: BigFloat,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersShuffle::setup_memoized(gamma, permanent_gamma, &mut rng)?;
+    run_protocol_shuffle_with_parameters::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, rng, zkp_rng)
+}
+
+/// Run the Shuffle protocol with a registry of client keys of the given depth for a given gamma on
+/// random inputs (trusted environment and communication are emulated). If a seed is given, the run
+/// is fully determined by it.
+pub fn run_protocol_shuffle_registered<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    registry_depth: usize,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersShuffle::setup_registered(gamma, registry_depth, &mut rng)?;
+    run_protocol_shuffle_with_parameters::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, rng, zkp_rng)
+}
+
+/// Run the Shuffle protocol with batches of `Generate Randomness` steps, of which the merkle tree
+/// has the given depth, for a given gamma on random inputs (trusted environment and communication
+/// are emulated). If a seed is given, the run is fully determined by it.
+pub fn run_protocol_shuffle_batch<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    batch_depth: usize,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersShuffle::setup_batch(gamma, batch_depth, &mut rng)?;
+    run_protocol_shuffle_with_parameters::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, rng, zkp_rng)
+}
+
+/// Run the multi-message Shuffle protocol for a given gamma and number of messages per client on
+/// random inputs (trusted environment and communication are emulated). If a seed is given, the run
+/// is fully determined by it.
+pub fn run_protocol_shuffle_multi_message<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    messages: usize,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersShuffle::setup_multi_message(gamma, messages, &mut rng)?;
+    run_protocol_shuffle_with_parameters::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, rng, zkp_rng)
+}
+
+/// Run the Shuffle protocol with blanket noise for a given gamma and minimum batch size on random
+/// inputs (trusted environment and communication are emulated). If a seed is given, the run is
+/// fully determined by it.
+pub fn run_protocol_shuffle_blanket_noise<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    gamma: BigFloat,
+    min_batch_size: usize,
+    seed: Option<u64>,
+) -> Result<(), Error>
+where
+    ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    let (mut rng, zkp_rng) = protocol_rngs::<Conf::ZKPRng>(seed);
+    let parameters = ParametersShuffle::setup_blanket_noise(gamma, min_batch_size, &mut rng)?;
+    run_protocol_shuffle_with_parameters::<
+        Conf,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >(parameters, rng, zkp_rng)
+}
+
+fn run_protocol_shuffle_with_parameters<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    parameters: ParametersShuffle<Conf, GAMMA_BYTES>,
+    mut rng: ChaChaRng,
+    mut zkp_rng: ZKPRng<Conf>,
+) -> Result<(), Error>
+where
+    ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    assert!(
+        Conf::LdpMechanism::randomness_bytes::<INPUT_BYTES, GAMMA_BYTES, IS_REAL_INPUT>()
+            <= RANDOMNESS_BYTES
+    );
     assert!(K >= 2 && (K.ilog2() + 1) as usize <= INPUT_BYTES * 8);
     assert!(INPUT_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
     assert!(TIME_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
-    let mut rng = ChaChaRng::from_entropy();
-    let mut zkp_rng = Conf::ZKPRng::new();
 
     // setup
-    let parameters = ParametersShuffle::setup(gamma, &mut rng)?;
     let (proving_key, verifying_key) = CircuitShuffle::<
         _,
         ConfG,
@@ -62,7 +295,7 @@ where
         .collect::<Vec<_>>();
 
     // create server
-    let server = ServerShuffle::<
+    let mut server = ServerShuffle::<
         _,
         INPUT_BYTES,
         TIME_BYTES,
@@ -70,8 +303,13 @@ where
         RANDOMNESS_BYTES,
         K,
         IS_REAL_INPUT,
-    >::new(parameters.clone(), verifying_key, &mut rng)?;
+    >::new(parameters.clone(), verifying_key.clone(), &mut rng)?;
     let server_sig_pk = server.get_signature_public_key();
+    let server_enc_pk = server.get_encryption_public_key();
+
+    // create shuffler
+    let shuffler = Shuffler::new(&parameters, server_enc_pk.clone(), verifying_key, &mut rng)?;
+    let shuffler_enc_pk = shuffler.get_encryption_public_key();
 
     // create client
     let mut client = ClientShuffle::<
@@ -85,10 +323,21 @@ where
     >::new(
         parameters.clone(),
         server_sig_pk,
-        client_sig_pk,
+        server_enc_pk,
+        shuffler_enc_pk,
+        client_sig_pk.clone(),
         proving_key,
     )?;
 
+    // register client key
+    if parameters.registry_depth().is_some() {
+        server.register_client_key(&client_sig_pk)?;
+        let key_registry = server
+            .get_key_registry()
+            .ok_or(ClientError::UnobtainedValue)?;
+        client.set_registration(key_registry.root(), key_registry.path(&client_sig_pk)?);
+    }
+
     // actual protocol
     let timer_gen_rand = start_timer!(|| "Generate randomness");
 
@@ -99,7 +348,7 @@ where
 
     let timer_gen_rand_server = start_timer!(|| "Server generation");
     let gen_rand_server_message =
-        server.generate_randomness_create(&gen_rand_client_message, &mut rng)?;
+        server.generate_randomness_create(&gen_rand_client_message, [0; TIME_BYTES], &mut rng)?;
     end_timer!(timer_gen_rand_server);
 
     let timer_verify_rand_client = start_timer!(|| "Client verification");
@@ -162,8 +411,7 @@ where
         input_value_bytes[idx] = *byte;
     }
 
-    let mut message_bytes = input_value_bytes.to_vec();
-    message_bytes.extend_from_slice(&input_value_time);
+    let message_bytes = AuthenticatedInput::message(&input_value_bytes, &input_value_time);
 
     let timer_te = start_timer!(|| "Trusted environment computation");
     let input_value_signature = Conf::ClientSignatureScheme::sign(
@@ -178,7 +426,7 @@ where
     let timer_ver_rand = start_timer!(|| "Verifiable randomization");
 
     let timer_ver_rand_client = start_timer!(|| "Client generation");
-    let ver_rand_client_message = client.verifiable_randomization_create::<ConfG>(
+    let ver_rand_client_messages = client.verifiable_randomization_create_messages::<ConfG>(
         time_bounds,
         input_value_time,
         input_value,
@@ -189,30 +437,58 @@ where
     )?;
     end_timer!(timer_ver_rand_client);
 
+    let timer_ver_rand_shuffler = start_timer!(|| "Shuffler forwarding");
+    let ver_rand_shuffler_messages = shuffler.forward(&ver_rand_client_messages, &mut zkp_rng)?;
+    end_timer!(timer_ver_rand_shuffler);
+
+    assert_eq!(
+        ver_rand_shuffler_messages.len(),
+        ver_rand_client_messages.len()
+            + parameters
+                .blanket_noise()
+                .map_or(0, |blanket_noise| blanket_noise
+                    .dummies(ver_rand_client_messages.len())),
+        "Shuffler could not decrypt all client messages."
+    );
+
     let timer_ver_rand_server = start_timer!(|| "Server verification");
-    let result = server.verifiable_randomization_verify::<ConfG>(
-        &ver_rand_client_message,
-        time_bounds,
-        &prf_eval_points,
-        &mut zkp_rng,
-        false,
-    )?;
+    let mut result = true;
+    for ver_rand_shuffler_message in &ver_rand_shuffler_messages {
+        result &= server
+            .verifiable_randomization_verify::<ConfG>(
+                ver_rand_shuffler_message,
+                time_bounds,
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )?
+            .0;
+    }
     end_timer!(timer_ver_rand_server);
 
     end_timer!(timer_ver_rand);
 
     assert!(
-        result.0,
+        result,
         "Verification of verifiable randomization protocol failed."
     );
 
     #[cfg(feature = "print-trace")]
     {
+        let ver_rand_client_messages_len = ver_rand_client_messages
+            .iter()
+            .map(|message| message.len())
+            .sum::<usize>();
+        let ver_rand_shuffler_messages_len = ver_rand_shuffler_messages
+            .iter()
+            .map(|message| message.len())
+            .sum::<usize>();
         println!(
             "Messages sent: {}b",
             gen_rand_client_message.len()
                 + gen_rand_server_message.len()
-                + ver_rand_client_message.len()
+                + ver_rand_client_messages_len
+                + ver_rand_shuffler_messages_len
         );
         println!(
             "··Generate randomness: {}b",
@@ -222,8 +498,10 @@ where
         println!("····Server message: {}b", gen_rand_server_message.len());
         println!(
             "··Verifiable randomization: {}b",
-            ver_rand_client_message.len()
-        )
+            ver_rand_client_messages_len + ver_rand_shuffler_messages_len
+        );
+        println!("····Client messages: {}b", ver_rand_client_messages_len);
+        println!("····Shuffler messages: {}b", ver_rand_shuffler_messages_len);
     }
 
     Ok(())
@@ -0,0 +1,987 @@
+//! Simulation of a population of clients that report to a single server for several epochs, for
+//! any of our VLDP schemes (trusted environment, communication, and optionally the shuffler are
+//! emulated).
+//!
+//! In every epoch each client reports its true value of that epoch, after which the server
+//! verifies all reports of the epoch and the population statistics are estimated from the accepted
+//! LDP values. The clients are simulated one after the other, such that only a single client (and
+//! its copy of the proving key) is in memory at a time.
+
+use crate::circuits::{CircuitBase, CircuitExpand, CircuitShuffle};
+use crate::client::*;
+use crate::prelude::*;
+use crate::run_random::protocol_rngs;
+use crate::server::*;
+use crate::shuffler::Shuffler;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalDeserialize;
+use num_bigint::BigUint;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand_chacha::ChaChaRng;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Distribution of the true values of the clients
+#[derive(Clone, Debug)]
+pub enum TrueValueDistribution {
+    /// Uniformly random values: in `1..=K` for categorical inputs, and any `INPUT_BYTES` wide value
+    /// for real-valued inputs.
+    Uniform,
+    /// Categorical values in `1..=K`, drawn with the given (relative) weights.
+    Weighted(Vec<f64>),
+    /// Given true values per epoch and client, i.e., `values[epoch][client]`.
+    Data(Vec<Vec<BigUint>>),
+}
+
+impl TrueValueDistribution {
+    /// Get the true value of the given client in the given epoch.
+    fn sample<R: Rng, const INPUT_BYTES: usize, const K: u64, const IS_REAL_INPUT: bool>(
+        &self,
+        epoch: usize,
+        client: usize,
+        rng: &mut R,
+    ) -> Result<BigUint, Error> {
+        Ok(match self {
+            Self::Uniform if IS_REAL_INPUT => {
+                let mut input_value_bytes = [0; INPUT_BYTES];
+                rng.fill_bytes(&mut input_value_bytes);
+                BigUint::from_bytes_le(&input_value_bytes)
+            }
+            Self::Uniform => BigUint::from(rng.gen_range(1..=K)),
+            Self::Weighted(weights) => {
+                if IS_REAL_INPUT || weights.len() as u64 != K {
+                    Err("Weights must be given for each of the K categories of a categorical input.")?
+                }
+                BigUint::from(WeightedIndex::new(weights)?.sample(rng) as u64 + 1)
+            }
+            Self::Data(values) => values
+                .get(epoch)
+                .and_then(|values| values.get(client))
+                .cloned()
+                .ok_or_else(|| format!("No true value for client {client} in epoch {epoch}."))?,
+        })
+    }
+}
+
+/// Settings of a simulation
+#[derive(Clone, Debug)]
+pub struct SimulationSettings {
+    pub clients: usize,
+    pub epochs: usize,
+    pub distribution: TrueValueDistribution,
+    /// Whether to emulate a shuffler, which forwards the reports of each epoch in a random order.
+    pub shuffler: bool,
+    /// Whether to skip proof generation and verification (see `verifiable_randomization_create`).
+    pub skip_proof: bool,
+    /// Seed to make the simulation reproducible (see `run_protocol_base`).
+    pub seed: Option<u64>,
+}
+
+/// Results of a single epoch of a simulation
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EpochReport {
+    pub epoch: usize,
+    pub reports: usize,
+    pub accepted: usize,
+    /// Total time spent in the `Generate Randomness` steps (client and server).
+    pub gen_rand_time: Duration,
+    /// Total time spent by the clients in the `Randomize` step.
+    pub client_time: Duration,
+    /// Total time spent by the server in the `Verify` step.
+    pub server_time: Duration,
+    /// Total size of the messages of the `Generate Randomness` steps.
+    pub gen_rand_bytes: usize,
+    /// Total size of the reports (as sent by the clients).
+    pub report_bytes: usize,
+    /// Mean of the true values (in `[0, 1]` for real-valued inputs).
+    pub true_mean: f64,
+    /// Number of true values per category `1..=K`, if the mechanism has categorical outputs.
+    pub true_histogram: Option<Vec<u64>>,
+    /// Estimate from the accepted LDP values.
+    pub estimate: Estimate,
+}
+
+/// Results of a simulation
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimulationReport {
+    pub epochs: Vec<EpochReport>,
+}
+
+impl SimulationReport {
+    /// Export the results as CSV, with one row per epoch (times in milliseconds).
+    pub fn export_csv<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        let categories = self
+            .epochs
+            .first()
+            .and_then(|epoch| epoch.true_histogram.as_ref())
+            .map_or(0, Vec::len);
+        let mut header = [
+            "epoch",
+            "reports",
+            "accepted",
+            "gen_rand_time",
+            "client_time",
+            "server_time",
+            "gen_rand_bytes",
+            "report_bytes",
+            "true_mean",
+            "estimated_mean",
+        ]
+        .map(String::from)
+        .to_vec();
+        for prefix in ["true", "ldp", "estimated"] {
+            header.extend((1..=categories).map(|category| format!("{prefix}_count_{category}")));
+        }
+        writer.write_record(&header)?;
+        for epoch in &self.epochs {
+            let mut record = vec![
+                epoch.epoch.to_string(),
+                epoch.reports.to_string(),
+                epoch.accepted.to_string(),
+                (epoch.gen_rand_time.as_secs_f64() * 1000.0).to_string(),
+                (epoch.client_time.as_secs_f64() * 1000.0).to_string(),
+                (epoch.server_time.as_secs_f64() * 1000.0).to_string(),
+                epoch.gen_rand_bytes.to_string(),
+                epoch.report_bytes.to_string(),
+                epoch.true_mean.to_string(),
+                epoch.estimate.mean.to_string(),
+            ];
+            record.extend(epoch.true_histogram.iter().flatten().map(u64::to_string));
+            record.extend(epoch.estimate.counts.iter().flatten().map(u64::to_string));
+            record.extend(
+                epoch
+                    .estimate
+                    .histogram
+                    .iter()
+                    .flatten()
+                    .map(f64::to_string),
+            );
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reports of a single epoch that have been collected from the clients, but not yet verified
+#[derive(Default)]
+struct EpochCollector {
+    // (report, index of the merkle tree leaf)
+    reports: Vec<(Vec<u8>, usize)>,
+    true_values: Vec<BigUint>,
+    gen_rand_time: Duration,
+    client_time: Duration,
+    gen_rand_bytes: usize,
+    report_bytes: usize,
+}
+
+impl EpochCollector {
+    /// Emulate the trusted environment: get the true value of the client in this epoch, and sign
+    /// it along with its creation time.
+    #[allow(clippy::too_many_arguments)]
+    fn trusted_environment<
+        Conf: Config,
+        const INPUT_BYTES: usize,
+        const TIME_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        &mut self,
+        distribution: &TrueValueDistribution,
+        parameters: &ClientSignatureSchemeParameters<Conf>,
+        client_sig_sk: &ClientSignatureSchemeSecretKey<Conf>,
+        epoch: usize,
+        client: usize,
+        time: &[u8; TIME_BYTES],
+        rng: &mut ChaChaRng,
+    ) -> Result<(BigUint, ClientSignatureSchemeSignature<Conf>), Error> {
+        let input_value =
+            distribution.sample::<_, INPUT_BYTES, K, IS_REAL_INPUT>(epoch, client, rng)?;
+        let input_value_bytes =
+            AuthenticatedInput::encode::<Conf::LdpMechanism, INPUT_BYTES, K, IS_REAL_INPUT>(
+                &input_value,
+            )?;
+        let input_value_signature = Conf::ClientSignatureScheme::sign(
+            parameters,
+            client_sig_sk,
+            &AuthenticatedInput::message(&input_value_bytes, time),
+            rng,
+        )?;
+        self.true_values.push(input_value.clone());
+        Ok((input_value, input_value_signature))
+    }
+
+    /// Record a `Generate Randomness` step that was started at `start`, given its messages and
+    /// the verification result of the client.
+    fn generate_randomness(
+        &mut self,
+        start: Instant,
+        client_message: &[u8],
+        server_message: &[u8],
+        result: bool,
+    ) {
+        self.gen_rand_time += start.elapsed();
+        self.gen_rand_bytes += client_message.len() + server_message.len();
+        assert!(
+            result,
+            "Verification of generate randomness protocol failed."
+        );
+    }
+
+    /// Run the `Randomize` step of the client, and collect its report.
+    fn randomize(
+        &mut self,
+        index: usize,
+        client_create: impl FnOnce() -> Result<Vec<u8>, Error>,
+    ) -> Result<(), Error> {
+        self.randomize_messages(index, || Ok(vec![client_create()?]))
+    }
+
+    /// Run the `Randomize` step of a client that sends multiple messages, and collect all of them.
+    fn randomize_messages(
+        &mut self,
+        index: usize,
+        client_create: impl FnOnce() -> Result<Vec<Vec<u8>>, Error>,
+    ) -> Result<(), Error> {
+        let start = Instant::now();
+        let reports = client_create()?;
+        self.client_time += start.elapsed();
+        for report in reports {
+            self.report_bytes += report.len();
+            self.reports.push((report, index));
+        }
+        Ok(())
+    }
+
+    /// Let the shuffler process every collected report before it is forwarded to the server,
+    /// reports that are dropped by the shuffler are not forwarded.
+    fn forward(
+        &mut self,
+        mut shuffler_forward: impl FnMut(&[u8]) -> Result<Option<Vec<u8>>, Error>,
+    ) -> Result<(), Error> {
+        let mut reports = Vec::with_capacity(self.reports.len());
+        for (report, index) in &self.reports {
+            if let Some(report) = shuffler_forward(report)? {
+                reports.push((report, *index));
+            }
+        }
+        self.reports = reports;
+        Ok(())
+    }
+
+    /// Add the dummy reports of the shuffler for the forwarded reports.
+    fn inject_dummies(
+        &mut self,
+        shuffler_dummy_messages: impl FnOnce(&[Vec<u8>]) -> Result<Vec<Vec<u8>>, Error>,
+    ) -> Result<(), Error> {
+        let forwarded_messages = self
+            .reports
+            .iter()
+            .map(|(report, _)| report.clone())
+            .collect::<Vec<_>>();
+        self.reports.extend(
+            shuffler_dummy_messages(&forwarded_messages)?
+                .into_iter()
+                .map(|message| (message, 0)),
+        );
+        Ok(())
+    }
+
+    /// Verify the collected reports with the given server (in a random order if the shuffler is
+    /// emulated), and compare the estimated population statistics to the true ones, given the
+    /// number of messages that every client sends and the number of dummy reports of the shuffler
+    /// that the server accepted once all reports are verified.
+    #[allow(clippy::too_many_arguments)]
+    fn verify<
+        S,
+        M: Mechanism,
+        const INPUT_BYTES: usize,
+        const GAMMA_BYTES: usize,
+        const K: u64,
+        const IS_REAL_INPUT: bool,
+    >(
+        mut self,
+        epoch: usize,
+        gamma: &[u8; GAMMA_BYTES],
+        messages: usize,
+        shuffler: bool,
+        rng: &mut ChaChaRng,
+        server: &mut S,
+        mut server_verify: impl FnMut(&mut S, &[u8], usize) -> Result<(bool, M::Value), Error>,
+        server_dummies: impl FnOnce(&S) -> Result<usize, Error>,
+    ) -> Result<EpochReport, Error> {
+        if shuffler {
+            self.reports.shuffle(rng);
+        }
+        let mut server_time = Duration::ZERO;
+        let mut ldp_values = Vec::with_capacity(self.reports.len());
+        for (report, index) in &self.reports {
+            let start = Instant::now();
+            let (accepted, ldp_value) = server_verify(server, report, *index)?;
+            server_time += start.elapsed();
+            if accepted {
+                ldp_values.push(ldp_value);
+            }
+        }
+
+        let estimate = BlanketNoise::estimate::<M, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+            gamma,
+            messages,
+            &ldp_values,
+            server_dummies(server)?,
+        );
+        let true_histogram = estimate.histogram.as_ref().map(|_| {
+            (1..=K)
+                .map(|category| {
+                    let category = BigUint::from(category);
+                    self.true_values
+                        .iter()
+                        .filter(|value| **value == category)
+                        .count() as u64
+                })
+                .collect()
+        });
+        let true_mean = self
+            .true_values
+            .iter()
+            .map(true_value_to_f64::<INPUT_BYTES, IS_REAL_INPUT>)
+            .sum::<f64>()
+            / self.true_values.len() as f64;
+        Ok(EpochReport {
+            epoch,
+            reports: self.reports.len(),
+            accepted: ldp_values.len(),
+            gen_rand_time: self.gen_rand_time,
+            client_time: self.client_time,
+            server_time,
+            gen_rand_bytes: self.gen_rand_bytes,
+            report_bytes: self.report_bytes,
+            true_mean,
+            true_histogram,
+            estimate,
+        })
+    }
+}
+
+/// Decode a true value to the scale of the estimated mean, i.e., to `[0, 1]` for real-valued
+/// inputs.
+fn true_value_to_f64<const INPUT_BYTES: usize, const IS_REAL_INPUT: bool>(value: &BigUint) -> f64 {
+    let to_f64 = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .rev()
+            .fold(0.0, |acc, byte| acc * 256.0 + *byte as f64)
+    };
+    if IS_REAL_INPUT {
+        to_f64(&value.to_bytes_le()) / to_f64(&[u8::MAX; INPUT_BYTES])
+    } else {
+        to_f64(&value.to_bytes_le())
+    }
+}
+
+/// Time bounds and creation time of the true values of an epoch: the values of epoch `e` are
+/// created at time `e + 1`, and the time bounds are `(e, e + 1]`.
+#[allow(clippy::type_complexity)]
+fn epoch_time<const TIME_BYTES: usize>(
+    epoch: usize,
+) -> Result<(([u8; TIME_BYTES], [u8; TIME_BYTES]), [u8; TIME_BYTES]), Error> {
+    let to_time = |value: usize| {
+        let bytes = value.to_le_bytes();
+        if bytes.iter().skip(TIME_BYTES).any(|byte| *byte != 0) {
+            Err(format!(
+                "Epoch {epoch} does not fit in {TIME_BYTES} time bytes."
+            ))?
+        }
+        let mut time = [0; TIME_BYTES];
+        for (time_byte, byte) in time.iter_mut().zip(bytes) {
+            *time_byte = byte;
+        }
+        Ok::<_, Error>(time)
+    };
+    let time = to_time(epoch + 1)?;
+    Ok(((to_time(epoch)?, time), time))
+}
+
+/// Check that the protocol settings are supported by the configuration.
+fn assert_settings<
+    Conf: Config,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>() {
+    assert!(
+        Conf::LdpMechanism::randomness_bytes::<INPUT_BYTES, GAMMA_BYTES, IS_REAL_INPUT>()
+            <= RANDOMNESS_BYTES
+    );
+    assert!(K >= 2 && (K.ilog2() + 1) as usize <= INPUT_BYTES * 8);
+    assert!(INPUT_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
+    assert!(TIME_BYTES * 8 <= ConstraintField::<Conf>::MODULUS_BIT_SIZE as usize);
+}
+
+/// Simulate a population of clients in the Base scheme with the given parameters. Every client
+/// runs the `Generate Randomness` step in every epoch.
+pub fn simulate_base<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    parameters: ParametersBase<Conf, GAMMA_BYTES>,
+    settings: &SimulationSettings,
+) -> Result<SimulationReport, Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientCommitmentSchemeOutput<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    assert_settings::<Conf, INPUT_BYTES, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, K, IS_REAL_INPUT>(
+    );
+    let (mut rng, mut zkp_rng) = protocol_rngs::<Conf::ZKPRng>(settings.seed);
+    let gamma = parameters.gamma_as_bytes()?;
+
+    // setup
+    let (proving_key, verifying_key) = CircuitBase::<
+        _,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >::keygen(parameters.clone(), &mut zkp_rng)?;
+    let mut server = ServerBase::<
+        _,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >::new(parameters.clone(), verifying_key, &mut rng)?;
+    let server_sig_pk = server.get_signature_public_key();
+
+    // clients
+    let mut epochs = (0..settings.epochs)
+        .map(|_| EpochCollector::default())
+        .collect::<Vec<_>>();
+    for client_index in 0..settings.clients {
+        let (client_sig_pk, client_sig_sk) =
+            Conf::ClientSignatureScheme::keygen(&parameters.client_signature_scheme, &mut rng)?;
+        let mut client = ClientBase::<
+            _,
+            INPUT_BYTES,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >::new(
+            parameters.clone(),
+            server_sig_pk.clone(),
+            client_sig_pk,
+            proving_key.clone(),
+        )?;
+        for (epoch, collector) in epochs.iter_mut().enumerate() {
+            let (time_bounds, time) = epoch_time::<TIME_BYTES>(epoch)?;
+
+            // 1. generate randomness
+            let start = Instant::now();
+            let client_message = client.generate_randomness_create(time_bounds.0, &mut rng)?;
+            let server_message = server.generate_randomness_create(&client_message, &mut rng)?;
+            let result = client.generate_randomness_verify(&server_message)?;
+            collector.generate_randomness(start, &client_message, &server_message, result);
+
+            // 2. verifiable randomization
+            let (input_value, input_value_signature) = collector
+                .trusted_environment::<Conf, INPUT_BYTES, TIME_BYTES, K, IS_REAL_INPUT>(
+                    &settings.distribution,
+                    &parameters.client_signature_scheme,
+                    &client_sig_sk,
+                    epoch,
+                    client_index,
+                    &time,
+                    &mut rng,
+                )?;
+            collector.randomize(0, || {
+                client.verifiable_randomization_create::<ConfG>(
+                    time_bounds,
+                    time,
+                    input_value,
+                    input_value_signature,
+                    &mut zkp_rng,
+                    settings.skip_proof,
+                )
+            })?;
+        }
+    }
+
+    // 3. verification
+    let epochs = epochs
+        .into_iter()
+        .enumerate()
+        .map(|(epoch, collector)| {
+            let (time_bounds, _) = epoch_time::<TIME_BYTES>(epoch)?;
+            collector.verify::<_, Conf::LdpMechanism, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                epoch,
+                &gamma,
+                1,
+                settings.shuffler,
+                &mut rng,
+                &mut server,
+                |server, report, _| {
+                    server.verifiable_randomization_verify::<ConfG>(
+                        report,
+                        time_bounds,
+                        &mut zkp_rng,
+                        settings.skip_proof,
+                    )
+                },
+                |_| Ok(0),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(SimulationReport { epochs })
+}
+
+/// Simulate a population of clients in the Expand scheme with the given parameters. Every client
+/// uses a single leaf of its merkle tree per epoch, and renews its merkle tree once it is
+/// exhausted (such that every merkle tree starts in the epoch of its `Generate Randomness` step).
+pub fn simulate_expand<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const MT_DEPTH: usize,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    parameters: ParametersExpand<Conf, GAMMA_BYTES>,
+    settings: &SimulationSettings,
+) -> Result<SimulationReport, Error>
+where
+    ClientSignatureSchemePublicKey<Conf>:
+        ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+    ClientCommitmentSchemeParameters<Conf>: Sync,
+    ClientCommitmentSchemeOutput<Conf>: Send,
+{
+    assert_settings::<Conf, INPUT_BYTES, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, K, IS_REAL_INPUT>(
+    );
+    let (mut rng, mut zkp_rng) = protocol_rngs::<Conf::ZKPRng>(settings.seed);
+    let gamma = parameters.gamma_as_bytes()?;
+
+    // setup
+    let (proving_key, verifying_key) = CircuitExpand::<
+        _,
+        ConfG,
+        MT_DEPTH,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >::keygen(parameters.clone(), &mut zkp_rng)?;
+    let prf_eval_points = (0..((RANDOMNESS_BYTES - 1) / 32) + 1)
+        .map(|_| rng.gen::<PRFSchemeInput<Conf>>())
+        .collect::<Vec<_>>();
+    let mut server = ServerExpand::<
+        _,
+        MT_DEPTH,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >::new(parameters.clone(), verifying_key, &mut rng)?;
+    let server_sig_pk = server.get_signature_public_key();
+
+    // clients
+    let mut clients = (0..settings.clients)
+        .map(|_| {
+            let (client_sig_pk, client_sig_sk) =
+                Conf::ClientSignatureScheme::keygen(&parameters.client_signature_scheme, &mut rng)?;
+            let client = ClientExpand::<
+                _,
+                MT_DEPTH,
+                INPUT_BYTES,
+                TIME_BYTES,
+                GAMMA_BYTES,
+                RANDOMNESS_BYTES,
+                K,
+                IS_REAL_INPUT,
+            >::new(
+                parameters.clone(),
+                server_sig_pk.clone(),
+                client_sig_pk,
+                proving_key.clone(),
+            )?;
+            Ok::<_, Error>((client, client_sig_sk, 0))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // every epoch is verified before the next one, since the server only accepts the renewal of a
+    // merkle tree once all of its leaves are used
+    let epochs = (0..settings.epochs)
+        .map(|epoch| {
+            let (time_bounds, time) = epoch_time::<TIME_BYTES>(epoch)?;
+            let mut collector = EpochCollector::default();
+            for (client_index, (client, client_sig_sk, index)) in clients.iter_mut().enumerate() {
+                // 1. generate randomness (for the first merkle tree, or to renew an exhausted one)
+                if epoch == 0 || client.is_merkle_tree_exhausted() {
+                    let start = Instant::now();
+                    if parameters.has_epoch_bound_index() {
+                        client.set_start_epoch(time_bounds.1)?;
+                    }
+                    let client_message = if epoch == 0 {
+                        client.generate_randomness_create(client_sig_sk, &mut rng)?
+                    } else {
+                        client.generate_randomness_renew_create(client_sig_sk, &mut rng)?
+                    };
+                    let server_message =
+                        server.generate_randomness_create(&client_message, &mut rng)?;
+                    let result = client.generate_randomness_verify(&server_message)?;
+                    collector.generate_randomness(start, &client_message, &server_message, result);
+                    *index = 0;
+                }
+
+                // 2. verifiable randomization
+                let (input_value, input_value_signature) = collector
+                    .trusted_environment::<Conf, INPUT_BYTES, TIME_BYTES, K, IS_REAL_INPUT>(
+                        &settings.distribution,
+                        &parameters.client_signature_scheme,
+                        client_sig_sk,
+                        epoch,
+                        client_index,
+                        &time,
+                        &mut rng,
+                    )?;
+                collector.randomize(*index, || {
+                    client.verifiable_randomization_create::<ConfG>(
+                        time_bounds,
+                        time,
+                        input_value,
+                        input_value_signature,
+                        &prf_eval_points,
+                        *index,
+                        &mut zkp_rng,
+                        settings.skip_proof,
+                    )
+                })?;
+                *index += 1;
+            }
+
+            // 3. verification
+            collector.verify::<_, Conf::LdpMechanism, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                epoch,
+                &gamma,
+                1,
+                settings.shuffler,
+                &mut rng,
+                &mut server,
+                |server, report, index| {
+                    server.verifiable_randomization_verify::<ConfG>(
+                        report,
+                        time_bounds,
+                        &prf_eval_points,
+                        index,
+                        &mut zkp_rng,
+                        settings.skip_proof,
+                    )
+                },
+                |_| Ok(0),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(SimulationReport { epochs })
+}
+
+/// Simulate a population of clients in the Shuffle scheme with the given parameters. Every client
+/// runs the `Generate Randomness` step in every epoch, and sends all of its messages if the
+/// parameters use multiple messages per client.
+///
+/// The reports are always forwarded to the server by a shuffler, which rerandomizes the proofs and
+/// adds dummy reports if the parameters use blanket noise (the order of the reports is only
+/// randomized if the shuffler is emulated in the settings).
+pub fn simulate_shuffle<
+    Conf: Config,
+    ConfG: ConfigGadget<Conf>,
+    const INPUT_BYTES: usize,
+    const TIME_BYTES: usize,
+    const GAMMA_BYTES: usize,
+    const RANDOMNESS_BYTES: usize,
+    const K: u64,
+    const IS_REAL_INPUT: bool,
+>(
+    parameters: ParametersShuffle<Conf, GAMMA_BYTES>,
+    settings: &SimulationSettings,
+) -> Result<SimulationReport, Error>
+where
+    ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
+    ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+    ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    Proof<Conf>: CanonicalDeserialize,
+{
+    assert_settings::<Conf, INPUT_BYTES, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, K, IS_REAL_INPUT>(
+    );
+    let (mut rng, mut zkp_rng) = protocol_rngs::<Conf::ZKPRng>(settings.seed);
+    let gamma = parameters.gamma_as_bytes()?;
+
+    // setup
+    let (proving_key, verifying_key) = CircuitShuffle::<
+        _,
+        ConfG,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >::keygen(parameters.clone(), &mut zkp_rng)?;
+    let prf_eval_points = (0..((RANDOMNESS_BYTES - 1) / 32) + 1)
+        .map(|_| rng.gen::<PRFSchemeInput<Conf>>())
+        .collect::<Vec<_>>();
+    let mut server = ServerShuffle::<
+        _,
+        INPUT_BYTES,
+        TIME_BYTES,
+        GAMMA_BYTES,
+        RANDOMNESS_BYTES,
+        K,
+        IS_REAL_INPUT,
+    >::new(parameters.clone(), verifying_key.clone(), &mut rng)?;
+    let server_sig_pk = server.get_signature_public_key();
+    let server_enc_pk = server.get_encryption_public_key();
+    let shuffler = Shuffler::new(&parameters, server_enc_pk.clone(), verifying_key, &mut rng)?;
+    let shuffler_enc_pk = shuffler.get_encryption_public_key();
+
+    // client keys, which are all registered before the first report (if a registry is used), such
+    // that every client proves membership for the same root
+    let client_keys = (0..settings.clients)
+        .map(|_| Conf::ClientSignatureScheme::keygen(&parameters.client_signature_scheme, &mut rng))
+        .collect::<Result<Vec<_>, _>>()?;
+    if parameters.registry_depth().is_some() {
+        for (client_sig_pk, _) in &client_keys {
+            server.register_client_key(client_sig_pk)?;
+        }
+    }
+
+    // clients
+    let mut epochs = (0..settings.epochs)
+        .map(|_| EpochCollector::default())
+        .collect::<Vec<_>>();
+    for (client_index, (client_sig_pk, client_sig_sk)) in client_keys.into_iter().enumerate() {
+        let mut client = ClientShuffle::<
+            _,
+            INPUT_BYTES,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >::new(
+            parameters.clone(),
+            server_sig_pk.clone(),
+            server_enc_pk.clone(),
+            shuffler_enc_pk.clone(),
+            client_sig_pk.clone(),
+            proving_key.clone(),
+        )?;
+        if let Some(key_registry) = server.get_key_registry() {
+            client.set_registration(key_registry.root(), key_registry.path(&client_sig_pk)?);
+        }
+        for (epoch, collector) in epochs.iter_mut().enumerate() {
+            let (time_bounds, time) = epoch_time::<TIME_BYTES>(epoch)?;
+
+            // 1. generate randomness
+            let start = Instant::now();
+            let client_message = client.generate_randomness_create(&mut rng)?;
+            let server_message =
+                server.generate_randomness_create(&client_message, time, &mut rng)?;
+            let result = client.generate_randomness_verify(&server_message)?;
+            collector.generate_randomness(start, &client_message, &server_message, result);
+
+            // 2. verifiable randomization
+            let (input_value, input_value_signature) = collector
+                .trusted_environment::<Conf, INPUT_BYTES, TIME_BYTES, K, IS_REAL_INPUT>(
+                    &settings.distribution,
+                    &parameters.client_signature_scheme,
+                    &client_sig_sk,
+                    epoch,
+                    client_index,
+                    &time,
+                    &mut rng,
+                )?;
+            collector.randomize_messages(0, || {
+                client.verifiable_randomization_create_messages::<ConfG>(
+                    time_bounds,
+                    time,
+                    input_value,
+                    input_value_signature,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    settings.skip_proof,
+                )
+            })?;
+        }
+    }
+
+    // 3. verification
+    let epochs = epochs
+        .into_iter()
+        .enumerate()
+        .map(|(epoch, mut collector)| {
+            let (time_bounds, _) = epoch_time::<TIME_BYTES>(epoch)?;
+            collector.forward(|report| shuffler.forward_message(report, &mut zkp_rng))?;
+            collector.inject_dummies(|forwarded_messages| {
+                shuffler.dummy_messages(forwarded_messages, &mut zkp_rng)
+            })?;
+            collector.verify::<_, Conf::LdpMechanism, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(
+                epoch,
+                &gamma,
+                parameters.messages(),
+                settings.shuffler,
+                &mut rng,
+                &mut server,
+                |server, report, _| {
+                    server.verifiable_randomization_verify::<ConfG>(
+                        report,
+                        time_bounds,
+                        &prf_eval_points,
+                        &mut zkp_rng,
+                        settings.skip_proof,
+                    )
+                },
+                |server| server.get_dummy_count(time_bounds.1),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(SimulationReport { epochs })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::circuits::test::*;
+    use crate::prelude::*;
+    use crate::run_random::*;
+    use astro_float::BigFloat;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn simulation_test() {
+        let gamma = BigFloat::from_f64(0.25, 8 * GAMMA_BYTES);
+
+        // all reports are accepted, and exhausted merkle trees are renewed
+        let settings = SimulationSettings {
+            clients: 3,
+            epochs: 5,
+            distribution: TrueValueDistribution::Uniform,
+            shuffler: true,
+            skip_proof: false,
+            seed: Some(0),
+        };
+        let parameters =
+            ParametersExpand::setup(gamma.clone(), &mut ChaChaRng::seed_from_u64(1)).unwrap();
+        let report = simulate_expand::<
+            RrConf,
+            RrConfG,
+            2,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            7,
+            false,
+        >(parameters, &settings)
+        .unwrap();
+        for epoch in &report.epochs {
+            assert_eq!((epoch.reports, epoch.accepted), (3, 3));
+            assert_eq!(epoch.gen_rand_bytes > 0, epoch.epoch % 2 == 0);
+        }
+
+        // histogram estimate of categorical values
+        let settings = SimulationSettings {
+            clients: 400,
+            epochs: 1,
+            distribution: TrueValueDistribution::Weighted(vec![1.0, 2.0, 3.0, 4.0]),
+            shuffler: false,
+            skip_proof: true,
+            seed: Some(2),
+        };
+        let parameters =
+            ParametersBase::setup(gamma.clone(), &mut ChaChaRng::seed_from_u64(3)).unwrap();
+        let report = simulate_base::<
+            RrConf,
+            RrConfG,
+            1,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            4,
+            false,
+        >(parameters, &settings)
+        .unwrap();
+        let epoch = &report.epochs[0];
+        let true_histogram = epoch.true_histogram.as_ref().unwrap();
+        assert_eq!(true_histogram.iter().sum::<u64>(), 400);
+        for (true_count, estimate) in true_histogram
+            .iter()
+            .zip(epoch.estimate.histogram.as_ref().unwrap())
+        {
+            assert!((*true_count as f64 - estimate).abs() < 60.0);
+        }
+        let mut csv = Vec::new();
+        report.export_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().next().unwrap().ends_with("estimated_count_4"));
+
+        // mean estimate of real values
+        let settings = SimulationSettings {
+            clients: 400,
+            epochs: 1,
+            distribution: TrueValueDistribution::Uniform,
+            shuffler: true,
+            skip_proof: true,
+            seed: Some(4),
+        };
+        let parameters = ParametersShuffle::setup(gamma, &mut ChaChaRng::seed_from_u64(5)).unwrap();
+        let report = simulate_shuffle::<
+            RrConf,
+            RrConfG,
+            2,
+            TIME_BYTES,
+            GAMMA_BYTES,
+            RANDOMNESS_BYTES,
+            10,
+            true,
+        >(parameters, &settings)
+        .unwrap();
+        let epoch = &report.epochs[0];
+        assert!(epoch.true_histogram.is_none());
+        assert!((epoch.true_mean - epoch.estimate.mean).abs() < 0.1);
+    }
+}
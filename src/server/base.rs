@@ -2,7 +2,9 @@
 
 use crate::circuits::CircuitBase;
 use crate::messages::base::*;
+use crate::messages::verify_server_signature;
 use crate::prelude::*;
+use crate::server::{sign_batch, PrivacyBudgetLedger};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
 /// Base scheme server
@@ -19,6 +21,9 @@ pub struct ServerBase<
     sig_pk: ServerSignatureSchemePublicKey<Conf>,
     sig_sk: ServerSignatureSchemeSecretKey<Conf>,
     verifying_key: VerifyingKey<Conf>,
+    budget_ledger: Option<PrivacyBudgetLedger>,
+    beacon_sig_pk: Option<ServerSignatureSchemePublicKey<Conf>>,
+    seed_key: Option<PRFSchemeSeed<Conf>>,
 }
 
 impl<
@@ -44,6 +49,9 @@ impl<
             sig_pk,
             sig_sk,
             verifying_key,
+            budget_ledger: None,
+            beacon_sig_pk: None,
+            seed_key: None,
         })
     }
 
@@ -52,16 +60,72 @@ impl<
         self.sig_pk.clone()
     }
 
-    /// Given a client message perform the `Generate Randomness` step for the server.
-    pub fn generate_randomness_create<R: Rng + CryptoRng>(
+    /// Enable per-client privacy budget tracking with the given ledger.
+    pub fn set_budget_ledger(&mut self, budget_ledger: PrivacyBudgetLedger) {
+        self.budget_ledger = Some(budget_ledger);
+    }
+
+    /// Get the privacy budget ledger, if budget tracking is enabled.
+    pub fn get_budget_ledger(&self) -> Option<&PrivacyBudgetLedger> {
+        self.budget_ledger.as_ref()
+    }
+
+    /// Accept reports of which the server seed is the output of a round of the randomness beacon
+    /// with the given public key (non-interactive `Generate Randomness` step).
+    pub fn set_beacon_public_key(&mut self, beacon_sig_pk: ServerSignatureSchemePublicKey<Conf>) {
+        self.beacon_sig_pk = Some(beacon_sig_pk);
+    }
+
+    /// Derive the server seeds deterministically from the given secret key, the commitment and
+    /// public key of the client, and the epoch, instead of sampling them. Hence, the server issues
+    /// the same seed for the same commitment in an epoch, and recomputes the seed of a report in
+    /// the `Verify` step without storing anything. The `Generate Randomness` step must then be run
+    /// with the upper time bound of the epoch in which the client reports.
+    pub fn set_seed_key(&mut self, seed_key: PRFSchemeSeed<Conf>) {
+        self.seed_key = Some(seed_key);
+    }
+
+    /// Derive the server seed from the seed key, the commitment and public key of the client, and
+    /// the epoch (see `set_seed_key`).
+    fn derive_server_seed(
+        seed_key: &PRFSchemeSeed<Conf>,
+        client_randomness_commitment: &ClientCommitmentSchemeOutput<Conf>,
+        client_signature_public_key: &ClientSignatureSchemePublicKey<Conf>,
+        epoch: [u8; TIME_BYTES],
+    ) -> Result<PRFSchemeSeed<Conf>, Error>
+    where
+        ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+    {
+        RandomnessDerivation::derive_seed::<Conf::PRFScheme>(
+            seed_key,
+            &ServerSeedInputBase::<Conf, TIME_BYTES> {
+                client_randomness_commitment: client_randomness_commitment.clone(),
+                client_signature_public_key: client_signature_public_key.clone(),
+                epoch,
+            },
+        )
+    }
+
+    /// Epsilon that is charged to a client for a single report.
+    fn report_epsilon(&self) -> Result<f64, Error> {
+        Ok(Conf::LdpMechanism::epsilon::<
+            INPUT_BYTES,
+            GAMMA_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >(&self.parameters.gamma_as_bytes()?))
+    }
+
+    /// Given a client message compute the server seed, returns the input of the server signature.
+    fn generate_randomness_signature_input<R: Rng + CryptoRng>(
         &self,
         client_message: &[u8],
         rng: &mut R,
-    ) -> Result<Vec<u8>, Error>
+    ) -> Result<GenerateRandomnessSignatureInputBase<Conf>, Error>
     where
         ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
         ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
-        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     {
         // deserialize client message
         let client_message =
@@ -69,13 +133,43 @@ impl<
                 client_message,
             )?;
 
-        // compute server seed
-        let mut server_seed = PRFSchemeSeed::<Conf>::default();
-        rng.fill_bytes(&mut server_seed);
+        // compute server seed, deterministically if the server has a seed key
+        let server_seed = match &self.seed_key {
+            Some(seed_key) => Self::derive_server_seed(
+                seed_key,
+                &client_message.client_randomness_commitment,
+                &client_message.client_signature_public_key,
+                client_message.time,
+            )?,
+            None => {
+                let mut server_seed = PRFSchemeSeed::<Conf>::default();
+                rng.fill_bytes(&mut server_seed);
+                server_seed
+            }
+        };
+
+        // create signature input
+        Ok(GenerateRandomnessSignatureInputBase::new(
+            client_message,
+            server_seed,
+        ))
+    }
 
+    /// Given a client message perform the `Generate Randomness` step for the server.
+    ///
+    /// If the server has a seed key, the server seed is derived from it (see `set_seed_key`).
+    pub fn generate_randomness_create<R: Rng + CryptoRng>(
+        &self,
+        client_message: &[u8],
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Error>
+    where
+        ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    {
         // create signature input
-        let signature_input =
-            GenerateRandomnessSignatureInputBase::new(client_message, server_seed.clone());
+        let signature_input = self.generate_randomness_signature_input(client_message, rng)?;
         let mut signature_input_bytes = Vec::new();
         signature_input.serialize_uncompressed(&mut signature_input_bytes)?;
 
@@ -90,65 +184,213 @@ impl<
         // return message
         let mut serialized_message = vec![];
         GenerateRandomnessMessageServerBase::<Conf> {
-            server_seed,
+            server_seed: signature_input.server_seed,
             server_signature,
+            batch: None,
         }
         .serialize_compressed(&mut serialized_message)?;
         Ok(serialized_message)
     }
 
+    /// Given the client messages of a batch perform the `Generate Randomness` step for the server
+    /// for all of them at once, returns the server messages in the same order.
+    ///
+    /// Instead of signing the signature input of every client, the server signs the root of a
+    /// merkle tree over all signature inputs, and every client obtains the path of its leaf (see
+    /// `SignedBatch`).
+    pub fn generate_randomness_create_batch<R: Rng + CryptoRng>(
+        &self,
+        client_messages: &[Vec<u8>],
+        rng: &mut R,
+    ) -> Result<Vec<Vec<u8>>, Error>
+    where
+        ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    {
+        // create signature inputs
+        let signature_inputs = client_messages
+            .iter()
+            .map(|client_message| self.generate_randomness_signature_input(client_message, rng))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // sign the root of the merkle tree of the batch
+        let tree = SignedBatch::tree::<Conf>(
+            &self.parameters.client_commitment_scheme,
+            &self.parameters.client_merkle_tree_scheme,
+            &signature_inputs,
+            SignedBatch::height(signature_inputs.len()),
+        )?;
+        let (server_signature, batch_paths) = sign_batch::<Conf, _>(
+            &self.parameters.server_signature_scheme,
+            &self.sig_sk,
+            &tree,
+            signature_inputs.len(),
+            rng,
+        )?;
+
+        // return messages
+        signature_inputs
+            .into_iter()
+            .zip(batch_paths)
+            .map(|(signature_input, batch_path)| {
+                let mut serialized_message = vec![];
+                GenerateRandomnessMessageServerBase::<Conf> {
+                    server_seed: signature_input.server_seed,
+                    server_signature: server_signature.clone(),
+                    batch: Some(batch_path),
+                }
+                .serialize_compressed(&mut serialized_message)?;
+                Ok(serialized_message)
+            })
+            .collect()
+    }
+
+    /// Verify that the server seed of a client message is the output of a round of the randomness
+    /// beacon within the given time bounds, and that this is the first round after the time at
+    /// which the client randomness was committed to.
+    fn beacon_verify(
+        &self,
+        client_message: &VerifiableRandomizationMessageBase<Conf, INPUT_BYTES, TIME_BYTES>,
+        beacon: &BeaconAttestationBase<Conf, TIME_BYTES>,
+        time_bounds: &([u8; TIME_BYTES], [u8; TIME_BYTES]),
+    ) -> Result<bool, Error>
+    where
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+        Proof<Conf>: CanonicalDeserialize,
+        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    {
+        // only accept the beacon that is trusted by the server
+        let beacon_sig_pk = match &self.beacon_sig_pk {
+            Some(beacon_sig_pk) => beacon_sig_pk,
+            None => return Ok(false),
+        };
+
+        // check that the round is within the time bounds (i.e., not stale), and that it is the
+        // first round after the commitment to the client randomness (i.e., not chosen by the client)
+        if !TimeWindow::contains(time_bounds, &beacon.beacon_time)
+            || !TimeWindow::is_next(&beacon.commitment_time, &beacon.beacon_time)
+        {
+            return Ok(false);
+        }
+
+        // verify signature of the trusted environment on the commitment
+        let mut commitment_signature_input_bytes = Vec::new();
+        CommitmentSignatureInputBase::<Conf, TIME_BYTES> {
+            client_randomness_commitment: client_message.client_randomness_commitment.clone(),
+            time: beacon.commitment_time,
+        }
+        .serialize_uncompressed(&mut commitment_signature_input_bytes)?;
+        if !Conf::ClientSignatureScheme::verify(
+            &self.parameters.client_signature_scheme,
+            &client_message.client_sig_pk,
+            &commitment_signature_input_bytes,
+            &beacon.commitment_signature,
+        )? {
+            return Ok(false);
+        }
+
+        // verify signature of the beacon on the round
+        let mut beacon_signature_input_bytes = Vec::new();
+        BeaconSignatureInputBase::<Conf, TIME_BYTES> {
+            time: beacon.beacon_time,
+            output: client_message.server_seed,
+        }
+        .serialize_uncompressed(&mut beacon_signature_input_bytes)?;
+        Conf::ServerSignatureScheme::verify(
+            &self.parameters.server_signature_scheme,
+            beacon_sig_pk,
+            &beacon_signature_input_bytes,
+            &client_message.server_signature,
+        )
+    }
+
     /// Given a client message perform the `Verify` step for the server.
+    ///
+    /// If the server seed is the output of a round of the randomness beacon, the signature of the
+    /// beacon is verified instead of the server signature (see `set_beacon_public_key`), and the
+    /// round must be within the time bounds and directly follow the commitment of the client.
+    /// Otherwise, if the server has a seed key, the server seed must be the seed that is derived
+    /// for the current epoch (see `set_seed_key`). If the server seed was obtained in a batch, the
+    /// server signature is verified on the root of the batch that contains the signature input.
     pub fn verifiable_randomization_verify<ConfG: ConfigGadget<Conf>>(
-        &self,
+        &mut self,
         client_message: &[u8],
         time_bounds: ([u8; TIME_BYTES], [u8; TIME_BYTES]),
         zkp_rng: &mut ZKPRng<Conf>,
         skip_proof: bool,
-    ) -> Result<(bool, u64), Error>
+    ) -> Result<(bool, LdpValue<Conf>), Error>
     where
         ClientSignatureSchemePublicKey<Conf>:
             ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
         ClientCommitmentSchemeOutput<Conf>:
             ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
+        ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
         Proof<Conf>: CanonicalDeserialize,
         ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     {
         // deserialize client message
-        let client_message =
-            VerifiableRandomizationMessageBase::<Conf, INPUT_BYTES>::deserialize_compressed(
-                client_message,
-            )?;
+        let client_message = VerifiableRandomizationMessageBase::<
+            Conf,
+            INPUT_BYTES,
+            TIME_BYTES,
+        >::deserialize_compressed(client_message)?;
 
-        // reconstruct signature input
-        let signature_input = GenerateRandomnessSignatureInputBase::<Conf> {
-            client_randomness_commitment: client_message.client_randomness_commitment.clone(),
-            client_signature_public_key: client_message.client_sig_pk.clone(),
-            server_seed: client_message.server_seed,
-        };
-        let mut signature_input_bytes = Vec::new();
-        signature_input.serialize_uncompressed(&mut signature_input_bytes)?;
+        // check the privacy budget of the client
+        let epsilon = self.report_epsilon()?;
+        if let Some(budget_ledger) = &self.budget_ledger {
+            if !budget_ledger.can_charge(&client_message.client_sig_pk, &time_bounds.1, epsilon)? {
+                return Ok((false, LdpValue::<Conf>::default()));
+            }
+        }
 
-        // first verify signature
-        if Conf::ServerSignatureScheme::verify(
-            &self.parameters.server_signature_scheme,
-            &self.sig_pk,
-            &signature_input_bytes,
-            &client_message.server_signature,
-        )? {
-            // reconstruct server randomness
-            let mut server_randomness = [0; RANDOMNESS_BYTES];
-            for (index, chunk) in server_randomness.chunks_mut(32).enumerate() {
-                let mut eval_point = [0; 32];
-                for (new_byte, old_byte) in
-                    index.to_le_bytes().into_iter().zip(eval_point.iter_mut())
-                {
-                    *old_byte = new_byte;
-                }
-                chunk.copy_from_slice(
-                    &Conf::PRFScheme::evaluate(&client_message.server_seed, &eval_point)?
-                        [0..chunk.len()],
-                );
+        // first verify signature of the server, or of the randomness beacon
+        let signature_valid = match &client_message.beacon {
+            Some(beacon) => self.beacon_verify(&client_message, beacon, &time_bounds)?,
+            None => {
+                // recompute the server seed for the current epoch, if it is derived
+                let seed_valid = match &self.seed_key {
+                    Some(seed_key) => {
+                        Self::derive_server_seed(
+                            seed_key,
+                            &client_message.client_randomness_commitment,
+                            &client_message.client_sig_pk,
+                            time_bounds.1,
+                        )? == client_message.server_seed
+                    }
+                    None => true,
+                };
+
+                // reconstruct signature input
+                let signature_input = GenerateRandomnessSignatureInputBase::<Conf> {
+                    client_randomness_commitment: client_message
+                        .client_randomness_commitment
+                        .clone(),
+                    client_signature_public_key: client_message.client_sig_pk.clone(),
+                    server_seed: client_message.server_seed,
+                };
+
+                seed_valid
+                    && verify_server_signature::<Conf>(
+                        &self.parameters.client_commitment_scheme,
+                        &self.parameters.client_merkle_tree_scheme,
+                        &self.parameters.server_signature_scheme,
+                        &self.sig_pk,
+                        &signature_input,
+                        &client_message.server_signature,
+                        client_message.batch.as_ref(),
+                    )?
             }
+        };
+        let (accepted, ldp_value) = if signature_valid {
+            // reconstruct server randomness
+            let server_randomness =
+                RandomnessDerivation::expand::<Conf::PRFScheme, RANDOMNESS_BYTES>(
+                    &client_message.server_seed,
+                    &RandomnessDerivation::index_eval_points::<RANDOMNESS_BYTES>(0),
+                )?;
             // then verify proof
             if skip_proof {
                 Ok((true, client_message.ldp_value))
@@ -175,7 +417,385 @@ impl<
                 .map(|x| (x, client_message.ldp_value))
             }
         } else {
-            Ok((false, u64::MAX))
+            Ok((false, LdpValue::<Conf>::default()))
+        }?;
+
+        // charge the privacy budget of the client for an accepted report
+        if accepted {
+            if let Some(budget_ledger) = &mut self.budget_ledger {
+                budget_ledger.charge(&client_message.client_sig_pk, &time_bounds.1, epsilon)?;
+            }
+        }
+        Ok((accepted, ldp_value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::beacon::LocalBeacon;
+    use crate::circuits::test::*;
+    use crate::messages::base::{
+        CommitmentSignatureInputBase, GenerateRandomnessMessageServerBase,
+        VerifiableRandomizationMessageBase,
+    };
+    use crate::prelude::*;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use astro_float::BigFloat;
+    use num_bigint::BigUint;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn base_beacon_test() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+
+        // setup
+        let parameters = ParametersBase::<RrConf, GAMMA_BYTES>::setup(
+            BigFloat::from_f64(0.5, 8 * GAMMA_BYTES),
+            &mut rng,
+        )
+        .unwrap();
+        let BaseSetup {
+            mut server,
+            mut clients,
+            client_keys,
+        } = setup_base(&parameters, 1, &mut rng);
+        let (client_sig_pk, client_sig_sk) = client_keys[0].clone();
+        let mut client = clients.remove(0);
+        let beacon = LocalBeacon::new(&parameters, &mut rng).unwrap();
+
+        // commit at time 2, attested by the trusted environment
+        let commitment_message = client.generate_randomness_commit([2, 0], &mut rng).unwrap();
+        let commitment_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+            &parameters.client_signature_scheme,
+            &client_sig_sk,
+            &commitment_message,
+            &mut rng,
+        )
+        .unwrap();
+
+        // only the first round after the commitment replaces the server seed: an earlier round, a
+        // later round in the same window, and a second round for the same commitment are rejected
+        for (beacon_time, expected) in [
+            ([2, 0], false),
+            ([4, 0], false),
+            ([3, 0], true),
+            ([3, 0], false),
+        ] {
+            let beacon_round = beacon.publish(beacon_time, &mut rng).unwrap();
+            assert_eq!(
+                client
+                    .generate_randomness_beacon(
+                        &beacon.get_signature_public_key(),
+                        &beacon_round,
+                        commitment_signature.clone(),
+                    )
+                    .unwrap(),
+                expected
+            );
+        }
+
+        // report in the window (2, 4]
+        let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+            &parameters.client_signature_scheme,
+            &client_sig_sk,
+            &AuthenticatedInput::message(&[1], &[4, 0]),
+            &mut rng,
+        )
+        .unwrap();
+        let client_message = client
+            .verifiable_randomization_create::<RrConfG>(
+                ([2, 0], [4, 0]),
+                [4, 0],
+                BigUint::from(1u8),
+                input_value_signature.clone(),
+                &mut zkp_rng,
+                false,
+            )
+            .unwrap();
+
+        // the report is only accepted by a server that trusts the beacon
+        let mut verify = |server: &mut BaseServer, client_message: &[u8]| {
+            server
+                .verifiable_randomization_verify::<RrConfG>(
+                    client_message,
+                    ([2, 0], [4, 0]),
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap()
+                .0
+        };
+        assert!(!verify(&mut server, &client_message));
+        server.set_beacon_public_key(beacon.get_signature_public_key());
+        assert!(verify(&mut server, &client_message));
+
+        // a commitment time that is not before the round is rejected, and so is an earlier
+        // (attested) commitment time, for which the round is a later round than the first one
+        // after it
+        let mut tampered_message =
+            VerifiableRandomizationMessageBase::<RrConf, 1, TIME_BYTES>::deserialize_compressed(
+                client_message.as_slice(),
+            )
+            .unwrap();
+        tampered_message.beacon.as_mut().unwrap().commitment_time = [3, 0];
+        let mut tampered_client_message = vec![];
+        tampered_message
+            .serialize_compressed(&mut tampered_client_message)
+            .unwrap();
+        assert!(!verify(&mut server, &tampered_client_message));
+        let mut commitment_message = vec![];
+        CommitmentSignatureInputBase::<RrConf, TIME_BYTES> {
+            client_randomness_commitment: tampered_message.client_randomness_commitment,
+            time: [1, 0],
+        }
+        .serialize_uncompressed(&mut commitment_message)
+        .unwrap();
+        let beacon_attestation = tampered_message.beacon.as_mut().unwrap();
+        beacon_attestation.commitment_time = [1, 0];
+        beacon_attestation.commitment_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+            &parameters.client_signature_scheme,
+            &client_sig_sk,
+            &commitment_message,
+            &mut rng,
+        )
+        .unwrap();
+        let mut tampered_client_message = vec![];
+        tampered_message
+            .serialize_compressed(&mut tampered_client_message)
+            .unwrap();
+        assert!(!verify(&mut server, &tampered_client_message));
+
+        // a stale round that is published before the window is rejected
+        let mut stale_client = BaseClient::new(
+            parameters.clone(),
+            server.get_signature_public_key(),
+            client_sig_pk,
+            (),
+        )
+        .unwrap();
+        let commitment_message = stale_client
+            .generate_randomness_commit([1, 0], &mut rng)
+            .unwrap();
+        let commitment_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+            &parameters.client_signature_scheme,
+            &client_sig_sk,
+            &commitment_message,
+            &mut rng,
+        )
+        .unwrap();
+        let beacon_round = beacon.publish([2, 0], &mut rng).unwrap();
+        assert!(stale_client
+            .generate_randomness_beacon(
+                &beacon.get_signature_public_key(),
+                &beacon_round,
+                commitment_signature,
+            )
+            .unwrap());
+        let stale_client_message = stale_client
+            .verifiable_randomization_create::<RrConfG>(
+                ([2, 0], [4, 0]),
+                [4, 0],
+                BigUint::from(1u8),
+                input_value_signature,
+                &mut rng,
+                false,
+            )
+            .unwrap();
+        assert!(!verify(&mut server, &stale_client_message));
+    }
+
+    #[test]
+    fn base_stateless_test() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+
+        // setup
+        let parameters = ParametersBase::<RrConf, GAMMA_BYTES>::setup(
+            BigFloat::from_f64(0.5, 8 * GAMMA_BYTES),
+            &mut rng,
+        )
+        .unwrap();
+        let BaseSetup {
+            mut server,
+            mut clients,
+            client_keys,
+        } = setup_base(&parameters, 1, &mut rng);
+        let (_, client_sig_sk) = &client_keys[0];
+        let client = &mut clients[0];
+        server.set_seed_key([7; 32]);
+
+        // the server issues the same seed for the same commitment in the same epoch
+        let client_message = client.generate_randomness_create([4, 0], &mut rng).unwrap();
+        let server_seeds = (0..2)
+            .map(|_| {
+                let server_message = server
+                    .generate_randomness_create(&client_message, &mut rng)
+                    .unwrap();
+                GenerateRandomnessMessageServerBase::<RrConf>::deserialize_compressed(
+                    server_message.as_slice(),
+                )
+                .unwrap()
+                .server_seed
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(server_seeds[0], server_seeds[1]);
+        let server_message = server
+            .generate_randomness_create(&client_message, &mut rng)
+            .unwrap();
+        assert!(client.generate_randomness_verify(&server_message).unwrap());
+
+        // report in epoch 4
+        let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+            &parameters.client_signature_scheme,
+            client_sig_sk,
+            &AuthenticatedInput::message(&[1], &[4, 0]),
+            &mut rng,
+        )
+        .unwrap();
+        let client_message = client
+            .verifiable_randomization_create::<RrConfG>(
+                ([3, 0], [4, 0]),
+                [4, 0],
+                BigUint::from(1u8),
+                input_value_signature,
+                &mut zkp_rng,
+                false,
+            )
+            .unwrap();
+
+        // the seed is only recomputed for the epoch in which it was issued (even without proof)
+        for (time_bounds, skip_proof, expected) in [
+            (([3, 0], [4, 0]), false, true),
+            (([4, 0], [5, 0]), true, false),
+        ] {
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    &client_message,
+                    time_bounds,
+                    &mut zkp_rng,
+                    skip_proof,
+                )
+                .unwrap();
+            assert_eq!(accepted, expected);
         }
     }
+
+    #[test]
+    fn base_batch_test() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+
+        // setup, with three clients
+        let parameters = ParametersBase::<RrConf, GAMMA_BYTES>::setup(
+            BigFloat::from_f64(0.5, 8 * GAMMA_BYTES),
+            &mut rng,
+        )
+        .unwrap();
+        let BaseSetup {
+            mut server,
+            mut clients,
+            client_keys,
+        } = setup_base(&parameters, 3, &mut rng);
+
+        // the server signs a single root for all clients
+        let client_messages = clients
+            .iter_mut()
+            .map(|client| client.generate_randomness_create([0, 0], &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let server_messages = server
+            .generate_randomness_create_batch(&client_messages, &mut rng)
+            .unwrap();
+        assert_eq!(server_messages.len(), 3);
+        let server_signatures = server_messages
+            .iter()
+            .map(|server_message| {
+                let server_message =
+                    GenerateRandomnessMessageServerBase::<RrConf>::deserialize_compressed(
+                        server_message.as_slice(),
+                    )
+                    .unwrap();
+                let mut server_signature = vec![];
+                server_message
+                    .server_signature
+                    .serialize_compressed(&mut server_signature)
+                    .unwrap();
+                server_signature
+            })
+            .collect::<Vec<_>>();
+        assert!(server_signatures
+            .iter()
+            .all(|sig| *sig == server_signatures[0]));
+
+        // a client only accepts the path of its own leaf
+        assert!(!clients[0]
+            .generate_randomness_verify(&server_messages[1])
+            .unwrap());
+        for (client, server_message) in clients.iter_mut().zip(&server_messages) {
+            assert!(client.generate_randomness_verify(server_message).unwrap());
+        }
+
+        // every client reports in epoch 4
+        let time_bounds = ([3, 0], [4, 0]);
+        let client_messages = clients
+            .iter()
+            .zip(&client_keys)
+            .map(|(client, (_, client_sig_sk))| {
+                let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+                    &parameters.client_signature_scheme,
+                    client_sig_sk,
+                    &AuthenticatedInput::message(&[1], &time_bounds.1),
+                    &mut rng,
+                )
+                .unwrap();
+                client
+                    .verifiable_randomization_create::<RrConfG>(
+                        time_bounds,
+                        time_bounds.1,
+                        BigUint::from(1u8),
+                        input_value_signature,
+                        &mut zkp_rng,
+                        false,
+                    )
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        for client_message in &client_messages {
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    client_message,
+                    time_bounds,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            assert!(accepted);
+        }
+
+        // a report with the path of another client is rejected (even without proof)
+        let mut reports = client_messages
+            .iter()
+            .map(|client_message| {
+                VerifiableRandomizationMessageBase::<RrConf, 1, TIME_BYTES>::deserialize_compressed(
+                    client_message.as_slice(),
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        reports[1].batch = reports[0].batch.clone();
+        let mut client_message = vec![];
+        reports[1]
+            .serialize_compressed(&mut client_message)
+            .unwrap();
+        let (accepted, _) = server
+            .verifiable_randomization_verify::<RrConfG>(
+                &client_message,
+                time_bounds,
+                &mut zkp_rng,
+                true,
+            )
+            .unwrap();
+        assert!(!accepted);
+    }
 }
@@ -0,0 +1,188 @@
+//! Per-client privacy budget tracking for the servers of all three VLDP schemes
+//!
+//! Every accepted report of a client costs the epsilon of the LDP mechanism. The ledger keeps
+//! track of the total epsilon that each client (identified by its signature public key) has spent
+//! within a time window, and refuses further reports once the budget of that window is exhausted.
+
+use crate::prelude::*;
+use crate::server::client_key;
+use ark_serialize::CanonicalSerialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Slack used when comparing spent budgets, to prevent rounding errors from rejecting a report
+/// that exactly exhausts the budget.
+const BUDGET_TOLERANCE: f64 = 1e-9;
+
+/// Spent privacy budget of a single client within a single time window
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientBudgetSummary {
+    pub client_key: Vec<u8>,
+    pub window: u64,
+    pub reports: u64,
+    pub spent_epsilon: f64,
+    pub remaining_epsilon: f64,
+}
+
+/// Ledger of the privacy budget spent by each client, per time window.
+///
+/// Time steps (as used in the protocols) are interpreted as little-endian integers, and are
+/// grouped into windows of `window_length` consecutive time steps. Only time steps of at most 8
+/// bytes are supported, such that distinct time steps are never truncated into the same window.
+#[derive(Clone, Debug)]
+pub struct PrivacyBudgetLedger {
+    window_budget: f64,
+    window_length: u64,
+    // (serialized public key, window) -> (reports, spent epsilon)
+    spent: BTreeMap<(Vec<u8>, u64), (u64, f64)>,
+}
+
+impl PrivacyBudgetLedger {
+    /// Create a new ledger that allows each client to spend `window_budget` epsilon per window of
+    /// `window_length` time steps.
+    pub fn new(window_budget: f64, window_length: u64) -> Self {
+        assert!(window_length > 0, "The window length must be positive.");
+        Self {
+            window_budget,
+            window_length,
+            spent: BTreeMap::new(),
+        }
+    }
+
+    /// Get the budget per client per window.
+    pub fn window_budget(&self) -> f64 {
+        self.window_budget
+    }
+
+    /// Get the window that the given time step belongs to.
+    pub fn window<const TIME_BYTES: usize>(&self, time: &[u8; TIME_BYTES]) -> Result<u64, Error> {
+        if TIME_BYTES > 8 {
+            Err("The ledger only supports time steps of at most 8 bytes.")?
+        }
+        let mut time_bytes = [0; 8];
+        time_bytes[..TIME_BYTES].copy_from_slice(time);
+        Ok(u64::from_le_bytes(time_bytes) / self.window_length)
+    }
+
+    /// Get the epsilon spent by the client in the window of the given time step.
+    pub fn spent<PK: CanonicalSerialize, const TIME_BYTES: usize>(
+        &self,
+        client_pk: &PK,
+        time: &[u8; TIME_BYTES],
+    ) -> Result<f64, Error> {
+        let key = (client_key(client_pk)?, self.window(time)?);
+        Ok(self.spent.get(&key).map_or(0.0, |(_, spent)| *spent))
+    }
+
+    /// Check whether the client can still spend `epsilon` in the window of the given time step.
+    pub fn can_charge<PK: CanonicalSerialize, const TIME_BYTES: usize>(
+        &self,
+        client_pk: &PK,
+        time: &[u8; TIME_BYTES],
+        epsilon: f64,
+    ) -> Result<bool, Error> {
+        Ok(self.spent(client_pk, time)? + epsilon <= self.window_budget + BUDGET_TOLERANCE)
+    }
+
+    /// Charge `epsilon` to the client in the window of the given time step. Returns `false` (and
+    /// does not charge anything) if this would exceed the budget of the window.
+    pub fn charge<PK: CanonicalSerialize, const TIME_BYTES: usize>(
+        &mut self,
+        client_pk: &PK,
+        time: &[u8; TIME_BYTES],
+        epsilon: f64,
+    ) -> Result<bool, Error> {
+        if !self.can_charge(client_pk, time, epsilon)? {
+            return Ok(false);
+        }
+        let key = (client_key(client_pk)?, self.window(time)?);
+        let (reports, spent) = self.spent.entry(key).or_insert((0, 0.0));
+        *reports += 1;
+        *spent += epsilon;
+        Ok(true)
+    }
+
+    /// Summaries of the spent budget of all clients, ordered by client key and window.
+    pub fn summaries(&self) -> Vec<ClientBudgetSummary> {
+        self.spent
+            .iter()
+            .map(
+                |((client_key, window), (reports, spent))| ClientBudgetSummary {
+                    client_key: client_key.clone(),
+                    window: *window,
+                    reports: *reports,
+                    spent_epsilon: *spent,
+                    remaining_epsilon: (self.window_budget - spent).max(0.0),
+                },
+            )
+            .collect()
+    }
+
+    /// Export the summaries as CSV, with the client keys hex encoded.
+    pub fn export_csv<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record([
+            "client_key",
+            "window",
+            "reports",
+            "spent_epsilon",
+            "remaining_epsilon",
+        ])?;
+        for summary in self.summaries() {
+            writer.write_record([
+                summary
+                    .client_key
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>(),
+                summary.window.to_string(),
+                summary.reports.to_string(),
+                summary.spent_epsilon.to_string(),
+                summary.remaining_epsilon.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::server::PrivacyBudgetLedger;
+
+    #[test]
+    fn privacy_budget_ledger_test() {
+        // a budget of two reports of epsilon 0.5 per window of 10 time steps
+        let mut ledger = PrivacyBudgetLedger::new(1.0, 10);
+        let (client, other_client) = (1u64, 2u64);
+
+        // the budget is exhausted after two reports
+        assert!(ledger.charge(&client, &[3u8], 0.5).unwrap());
+        assert!(ledger.charge(&client, &[9u8], 0.5).unwrap());
+        assert!(!ledger.can_charge(&client, &[5u8], 0.5).unwrap());
+        assert!(!ledger.charge(&client, &[5u8], 0.5).unwrap());
+        assert_eq!(ledger.spent(&client, &[0u8]).unwrap(), 1.0);
+
+        // the budget is reset in the next window
+        assert_eq!(ledger.spent(&client, &[10u8]).unwrap(), 0.0);
+        assert!(ledger.charge(&client, &[10u8], 0.5).unwrap());
+
+        // clients have independent budgets
+        assert!(ledger.charge(&other_client, &[5u8], 1.0).unwrap());
+        assert!(!ledger.charge(&other_client, &[5u8], 0.5).unwrap());
+        assert_eq!(ledger.spent(&client, &[5u8]).unwrap(), 1.0);
+
+        // the summaries contain every client and window that was charged
+        let summaries = ledger.summaries();
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(summaries[0].reports, 2);
+        assert_eq!(summaries[0].remaining_epsilon, 0.0);
+        assert_eq!(summaries[1].window, 1);
+        let mut csv = vec![];
+        ledger.export_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 4);
+
+        // time steps that do not fit in a u64 are rejected
+        assert!(ledger.charge(&client, &[0u8; 9], 0.5).is_err());
+    }
+}
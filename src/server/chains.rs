@@ -0,0 +1,143 @@
+//! Chains of client merkle trees for the server of the Expand scheme
+//!
+//! Every merkle tree that the server signs for a client is added to the chain of that client. A
+//! client starts its chain once, after which every new merkle tree has to renew the latest merkle
+//! tree of the chain, which is only accepted once all leaves of the latest merkle tree are used.
+//! Without an epoch-bound index, every accepted report uses a leaf. With an epoch-bound index, a
+//! merkle tree covers the epochs from its start epoch up to the start epoch plus its number of
+//! leaves, and a renewal has to start after these epochs, such that every epoch is covered by at
+//! most one merkle tree of the client. Every leaf is then used by at most one accepted report, i.e.,
+//! a report cannot be replayed within its epoch.
+//!
+//! Since reports are only accepted for merkle trees in the chains, the chains have to be kept
+//! across restarts of the server, for which they can be serialized.
+
+use crate::prelude::*;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::collections::BTreeMap;
+
+/// Merkle tree in the chain of a client, along with the number of accepted reports that used it
+/// and (with an epoch-bound index) the indices of the epochs of these reports
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ClientMerkleTreeRecord<Conf: Config> {
+    pub root: ClientMerkleTreeRoot<Conf>,
+    pub start_epoch: Option<u64>,
+    pub reports: u64,
+    pub used_indices: Vec<u64>,
+}
+
+/// Chains of merkle trees of all clients
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ClientMerkleTreeChains<Conf: Config> {
+    leaves: u64,
+    // serialized public key -> chain of merkle trees (oldest first)
+    chains: BTreeMap<Vec<u8>, Vec<ClientMerkleTreeRecord<Conf>>>,
+}
+
+impl<Conf: Config> ClientMerkleTreeChains<Conf> {
+    /// Create empty chains for merkle trees with the given number of leaves.
+    pub fn new(leaves: u64) -> Self {
+        Self {
+            leaves,
+            chains: BTreeMap::new(),
+        }
+    }
+
+    /// Get the number of leaves of every merkle tree.
+    pub fn leaves(&self) -> u64 {
+        self.leaves
+    }
+
+    /// Get the chain of merkle trees of the client (oldest first).
+    pub fn chain(&self, client_key: &[u8]) -> &[ClientMerkleTreeRecord<Conf>] {
+        self.chains.get(client_key).map_or(&[], Vec::as_slice)
+    }
+
+    /// Add the merkle tree with the given root (and start epoch, if the index is bound to the
+    /// epoch) to the chain of the client, where `previous_root` is the root of the merkle tree that
+    /// it renews, if any.
+    ///
+    /// A new chain is only started if the client has none, and a renewal is only accepted if it
+    /// renews the latest merkle tree of the chain after all of its leaves have been used.
+    pub fn extend(
+        &mut self,
+        client_key: Vec<u8>,
+        root: &ClientMerkleTreeRoot<Conf>,
+        previous_root: Option<&ClientMerkleTreeRoot<Conf>>,
+        start_epoch: Option<u64>,
+    ) -> Result<(), Error> {
+        let chain = self.chains.entry(client_key).or_default();
+        match (previous_root, chain.last()) {
+            (None, None) => {}
+            (Some(previous_root), Some(previous)) if previous.root == *previous_root => {
+                match (previous.start_epoch, start_epoch) {
+                    (Some(previous_start_epoch), Some(start_epoch)) => {
+                        if start_epoch < previous_start_epoch.saturating_add(self.leaves) {
+                            Err(ServerError::InvalidStartEpoch)?
+                        }
+                    }
+                    _ => {
+                        if previous.reports < self.leaves {
+                            Err(ServerError::InvalidRenewal)?
+                        }
+                    }
+                }
+            }
+            _ => Err(ServerError::InvalidRenewal)?,
+        }
+        chain.push(ClientMerkleTreeRecord {
+            root: root.clone(),
+            start_epoch,
+            reports: 0,
+            used_indices: vec![],
+        });
+        Ok(())
+    }
+
+    /// Whether the merkle tree with the given root is in the chain of the client, and has a leaf
+    /// for a report in the epoch that ends at the given time: the epoch is covered by the merkle
+    /// tree and no report was accepted in it yet (with an epoch-bound index), or not all of its
+    /// leaves have been used (otherwise).
+    pub fn has_leaf<const TIME_BYTES: usize>(
+        &self,
+        client_key: &[u8],
+        root: &ClientMerkleTreeRoot<Conf>,
+        epoch: &[u8; TIME_BYTES],
+    ) -> bool {
+        self.chain(client_key)
+            .iter()
+            .find(|record| record.root == *root)
+            .is_some_and(|record| match record.start_epoch {
+                Some(start_epoch) => {
+                    TimeWindow::epoch_index(start_epoch, epoch).is_some_and(|index| {
+                        (index as u64) < self.leaves
+                            && !record.used_indices.contains(&(index as u64))
+                    })
+                }
+                None => record.reports < self.leaves,
+            })
+    }
+
+    /// Record an accepted report in the epoch that ends at the given time, which used a leaf of
+    /// the merkle tree with the given root.
+    pub fn use_leaf<const TIME_BYTES: usize>(
+        &mut self,
+        client_key: &[u8],
+        root: &ClientMerkleTreeRoot<Conf>,
+        epoch: &[u8; TIME_BYTES],
+    ) {
+        if let Some(record) = self
+            .chains
+            .get_mut(client_key)
+            .and_then(|chain| chain.iter_mut().find(|record| record.root == *root))
+        {
+            record.reports += 1;
+            if let Some(index) = record
+                .start_epoch
+                .and_then(|start_epoch| TimeWindow::epoch_index(start_epoch, epoch))
+            {
+                record.used_indices.push(index as u64);
+            }
+        }
+    }
+}
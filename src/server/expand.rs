@@ -2,7 +2,9 @@
 
 use crate::circuits::CircuitExpand;
 use crate::messages::expand::*;
+use crate::messages::verify_server_signature;
 use crate::prelude::*;
+use crate::server::{client_key, sign_batch, ClientMerkleTreeChains, PrivacyBudgetLedger};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
 /// Expand scheme server
@@ -20,6 +22,8 @@ pub struct ServerExpand<
     sig_pk: ServerSignatureSchemePublicKey<Conf>,
     sig_sk: ServerSignatureSchemeSecretKey<Conf>,
     verifying_key: VerifyingKey<Conf>,
+    budget_ledger: Option<PrivacyBudgetLedger>,
+    client_merkle_tree_chains: ClientMerkleTreeChains<Conf>,
 }
 
 impl<
@@ -56,6 +60,8 @@ impl<
             sig_pk,
             sig_sk,
             verifying_key,
+            budget_ledger: None,
+            client_merkle_tree_chains: ClientMerkleTreeChains::new(1 << (MT_DEPTH - 1)),
         })
     }
 
@@ -64,27 +70,122 @@ impl<
         self.sig_pk.clone()
     }
 
-    /// Given a client message perform the `Generate Randomness` step for the server.
-    pub fn generate_randomness_create<R: Rng + CryptoRng>(
-        &self,
+    /// Enable per-client privacy budget tracking with the given ledger.
+    pub fn set_budget_ledger(&mut self, budget_ledger: PrivacyBudgetLedger) {
+        self.budget_ledger = Some(budget_ledger);
+    }
+
+    /// Get the privacy budget ledger, if budget tracking is enabled.
+    pub fn get_budget_ledger(&self) -> Option<&PrivacyBudgetLedger> {
+        self.budget_ledger.as_ref()
+    }
+
+    /// Restore the chains of merkle trees of the clients (e.g., after a restart of the server),
+    /// which must be for merkle trees of the same depth.
+    pub fn set_client_merkle_tree_chains(
+        &mut self,
+        client_merkle_tree_chains: ClientMerkleTreeChains<Conf>,
+    ) -> Result<(), Error> {
+        if client_merkle_tree_chains.leaves() != self.client_merkle_tree_chains.leaves() {
+            Err("The Merkle trees of the chains do not have the depth of the parameters.")?
+        }
+        self.client_merkle_tree_chains = client_merkle_tree_chains;
+        Ok(())
+    }
+
+    /// Get the chains of merkle trees of the clients, which should be kept across restarts of the
+    /// server, since reports are only accepted for merkle trees in the chains.
+    pub fn get_client_merkle_tree_chains(&self) -> &ClientMerkleTreeChains<Conf> {
+        &self.client_merkle_tree_chains
+    }
+
+    /// Epsilon that is charged to a client for a single report.
+    fn report_epsilon(&self) -> Result<f64, Error> {
+        Ok(Conf::LdpMechanism::epsilon::<
+            INPUT_BYTES,
+            GAMMA_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >(&self.parameters.gamma_as_bytes()?))
+    }
+
+    /// Given a client message check the client signature and the (renewed) merkle tree of the
+    /// client, add it to the chain of the client and compute the server seed, returns the input of
+    /// the server signature.
+    fn generate_randomness_signature_input<R: Rng + CryptoRng>(
+        &mut self,
         client_message: &[u8],
         rng: &mut R,
-    ) -> Result<Vec<u8>, Error>
+    ) -> Result<GenerateRandomnessSignatureInputExpand<Conf>, Error>
     where
         ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
-        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     {
         // deserialize client message
         let client_message =
             GenerateRandomnessMessageClientExpand::<Conf>::deserialize_compressed(client_message)?;
+        if client_message.start_epoch.is_some() != self.parameters.has_epoch_bound_index() {
+            Err(ServerError::InvalidStartEpoch)?
+        }
+
+        // verify the client signature on the (renewed) merkle tree
+        let mut client_signature_input_bytes = Vec::new();
+        MerkleTreeSignatureInputExpand::<Conf> {
+            client_merkle_tree_root: client_message.client_merkle_tree_root.clone(),
+            previous_client_merkle_tree_root: client_message
+                .previous_client_merkle_tree_root
+                .clone(),
+            start_epoch: client_message.start_epoch,
+        }
+        .serialize_uncompressed(&mut client_signature_input_bytes)?;
+        if !Conf::ClientSignatureScheme::verify(
+            &self.parameters.client_signature_scheme,
+            &client_message.client_signature_public_key,
+            &client_signature_input_bytes,
+            &client_message.client_signature,
+        )? {
+            Err(ServerError::InvalidClientSignature)?
+        }
+
+        // extend the chain of merkle trees of the client, or start a new one
+        self.client_merkle_tree_chains.extend(
+            client_key(&client_message.client_signature_public_key)?,
+            &client_message.client_merkle_tree_root,
+            client_message.previous_client_merkle_tree_root.as_ref(),
+            client_message.start_epoch,
+        )?;
 
         // compute server seed
         let mut server_seed = PRFSchemeSeed::<Conf>::default();
         rng.fill_bytes(&mut server_seed);
 
         // create signature input
-        let signature_input =
-            GenerateRandomnessSignatureInputExpand::new(client_message, server_seed.clone());
+        Ok(GenerateRandomnessSignatureInputExpand::new(
+            client_message,
+            server_seed,
+        ))
+    }
+
+    /// Given a client message perform the `Generate Randomness` step for the server.
+    ///
+    /// The client message has to be signed by the client. A client without a merkle tree starts
+    /// its chain, after which a merkle tree is only accepted if it renews the latest merkle tree of
+    /// the client once all of its leaves are used (see `ClientMerkleTreeChains`), such that the
+    /// merkle trees of each client form a single chain.
+    /// If the parameters bind the index to the epoch, the client message should contain the start
+    /// epoch of the merkle tree, which is signed along with the merkle tree root.
+    pub fn generate_randomness_create<R: Rng + CryptoRng>(
+        &mut self,
+        client_message: &[u8],
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Error>
+    where
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    {
+        // create signature input
+        let signature_input = self.generate_randomness_signature_input(client_message, rng)?;
         let mut signature_input_bytes = Vec::new();
         signature_input.serialize_uncompressed(&mut signature_input_bytes)?;
 
@@ -99,28 +200,91 @@ impl<
         // return message
         let mut serialized_message = vec![];
         GenerateRandomnessMessageServerExpand::<Conf> {
-            server_seed,
+            server_seed: signature_input.server_seed,
             server_signature,
+            batch: None,
         }
         .serialize_compressed(&mut serialized_message)?;
         Ok(serialized_message)
     }
 
+    /// Given the client messages of a batch perform the `Generate Randomness` step for the server
+    /// for all of them at once, returns the server messages in the same order.
+    ///
+    /// Instead of signing the signature input of every client, the server signs the root of a
+    /// merkle tree over all signature inputs, and every client obtains the path of its leaf (see
+    /// `SignedBatch`).
+    /// Note: the client messages are handled in order, if one of them is rejected, the whole batch
+    /// is rejected, but the merkle trees of the client messages before it remain in their chains.
+    pub fn generate_randomness_create_batch<R: Rng + CryptoRng>(
+        &mut self,
+        client_messages: &[Vec<u8>],
+        rng: &mut R,
+    ) -> Result<Vec<Vec<u8>>, Error>
+    where
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    {
+        // create signature inputs
+        let signature_inputs = client_messages
+            .iter()
+            .map(|client_message| self.generate_randomness_signature_input(client_message, rng))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // sign the root of the merkle tree of the batch
+        let tree = SignedBatch::tree::<Conf>(
+            &self.parameters.client_commitment_scheme,
+            &self.parameters.client_merkle_tree_scheme,
+            &signature_inputs,
+            SignedBatch::height(signature_inputs.len()),
+        )?;
+        let (server_signature, batch_paths) = sign_batch::<Conf, _>(
+            &self.parameters.server_signature_scheme,
+            &self.sig_sk,
+            &tree,
+            signature_inputs.len(),
+            rng,
+        )?;
+
+        // return messages
+        signature_inputs
+            .into_iter()
+            .zip(batch_paths)
+            .map(|(signature_input, batch_path)| {
+                let mut serialized_message = vec![];
+                GenerateRandomnessMessageServerExpand::<Conf> {
+                    server_seed: signature_input.server_seed,
+                    server_signature: server_signature.clone(),
+                    batch: Some(batch_path),
+                }
+                .serialize_compressed(&mut serialized_message)?;
+                Ok(serialized_message)
+            })
+            .collect()
+    }
+
     /// Given a client message, current time (step) bounds, list of `prf_eval_points` (s in the
     /// paper) and current `index` (j in the paper) perform the `Verify` step for the server.
     ///
+    /// If the parameters bind the index to the epoch, the given index is ignored, and the proof is
+    /// verified for the start epoch that was signed in the `Generate Randomness` step instead, and
+    /// the merkle tree is only accepted in the epochs that it covers.
+    /// If the server seed was obtained in a batch, the server signature is verified on the root of
+    /// the batch that contains the signature input.
+    ///
     /// The `skip_proof` flag can be set to `true` to do a faster test run of this function that
     /// skips proof verification.
     /// Note: in actual usage this should be set to `false`.
     pub fn verifiable_randomization_verify<ConfG: ConfigGadget<Conf>>(
-        &self,
+        &mut self,
         client_message: &[u8],
         time_bounds: ([u8; TIME_BYTES], [u8; TIME_BYTES]),
         prf_eval_points: &[PRFSchemeInput<Conf>],
         index: usize,
         zkp_rng: &mut ZKPRng<Conf>,
         skip_proof: bool,
-    ) -> Result<(bool, u64), Error>
+    ) -> Result<(bool, LdpValue<Conf>), Error>
     where
         ClientSignatureSchemePublicKey<Conf>:
             ToConstraintField<ConstraintField<Conf>> + CanonicalDeserialize,
@@ -134,30 +298,46 @@ impl<
                 client_message,
             )?;
 
+        // check the privacy budget of the client
+        let epsilon = self.report_epsilon()?;
+        if let Some(budget_ledger) = &self.budget_ledger {
+            if !budget_ledger.can_charge(&client_message.client_sig_pk, &time_bounds.1, epsilon)? {
+                return Ok((false, LdpValue::<Conf>::default()));
+            }
+        }
+
         // reconstruct signature input
         let signature_input = GenerateRandomnessSignatureInputExpand::<Conf> {
             client_merkle_tree_root: client_message.client_merkle_tree_root.clone(),
             client_signature_public_key: client_message.client_sig_pk.clone(),
             server_seed: client_message.server_seed,
+            previous_client_merkle_tree_root: client_message
+                .previous_client_merkle_tree_root
+                .clone(),
+            start_epoch: client_message.start_epoch,
         };
-        let mut signature_input_bytes = Vec::new();
-        signature_input.serialize_uncompressed(&mut signature_input_bytes)?;
 
-        // first verify signature
-        if Conf::ServerSignatureScheme::verify(
+        // first verify signature and that the merkle tree in the chain of the client has a leaf left
+        let (accepted, ldp_value) = if verify_server_signature::<Conf>(
+            &self.parameters.client_commitment_scheme,
+            &self.parameters.client_merkle_tree_scheme,
             &self.parameters.server_signature_scheme,
             &self.sig_pk,
-            &signature_input_bytes,
+            &signature_input,
             &client_message.server_signature,
-        )? {
+            client_message.batch.as_ref(),
+        )? && client_message.start_epoch.is_some()
+            == self.parameters.has_epoch_bound_index()
+            && self.client_merkle_tree_chains.has_leaf(
+                &client_key(&client_message.client_sig_pk)?,
+                &client_message.client_merkle_tree_root,
+                &time_bounds.1,
+            ) {
             // reconstruct server randomness
-            let mut server_randomness = [0; RANDOMNESS_BYTES];
-            for (chunk, prf_eval_point) in server_randomness.chunks_mut(32).zip(prf_eval_points) {
-                chunk.copy_from_slice(
-                    &Conf::PRFScheme::evaluate(&client_message.server_seed, &prf_eval_point)?
-                        [0..chunk.len()],
-                );
-            }
+            let server_randomness = RandomnessDerivation::expand::<
+                Conf::PRFScheme,
+                RANDOMNESS_BYTES,
+            >(&client_message.server_seed, prf_eval_points)?;
             // then verify proof
             if skip_proof {
                 Ok((true, client_message.ldp_value))
@@ -180,13 +360,377 @@ impl<
                     &client_message.client_sig_pk,
                     &client_message.client_merkle_tree_root,
                     index,
+                    client_message.start_epoch,
                     server_randomness,
                     zkp_rng,
                 )
                 .map(|x| (x, client_message.ldp_value))
             }
         } else {
-            Ok((false, u64::MAX))
+            Ok((false, LdpValue::<Conf>::default()))
+        }?;
+
+        // use a leaf of the merkle tree and charge the privacy budget of the client for an accepted
+        // report
+        if accepted {
+            self.client_merkle_tree_chains.use_leaf(
+                &client_key(&client_message.client_sig_pk)?,
+                &client_message.client_merkle_tree_root,
+                &time_bounds.1,
+            );
+            if let Some(budget_ledger) = &mut self.budget_ledger {
+                budget_ledger.charge(&client_message.client_sig_pk, &time_bounds.1, epsilon)?;
+            }
+        }
+        Ok((accepted, ldp_value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::circuits::test::*;
+    use crate::messages::expand::{
+        GenerateRandomnessMessageClientExpand, MerkleTreeSignatureInputExpand,
+    };
+    use crate::prelude::*;
+    use crate::server::ClientMerkleTreeChains;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use astro_float::BigFloat;
+    use num_bigint::BigUint;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn expand_renewal_test() {
+        type Client = ExpandClient<2>;
+        type Server = ExpandServer<2>;
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+        let mut te_rng = ChaChaRng::seed_from_u64(2);
+
+        // setup
+        let parameters =
+            ParametersExpand::setup(BigFloat::from_f64(0.5, 8 * GAMMA_BYTES), &mut rng).unwrap();
+        let ExpandSetup {
+            mut server,
+            mut clients,
+            client_keys,
+            prf_eval_points,
+        } = setup_expand(&parameters, 1, &mut rng);
+        let (client_sig_pk, client_sig_sk) = client_keys[0].clone();
+        let mut client = clients.remove(0);
+
+        // report a value with the given index, returns whether the server accepted it
+        let mut report = |client: &mut Client, server: &mut Server, index: usize| {
+            let time = [1; TIME_BYTES];
+            let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+                &parameters.client_signature_scheme,
+                &client_sig_sk,
+                &AuthenticatedInput::message(&[3], &time),
+                &mut te_rng,
+            )
+            .unwrap();
+            let client_message = client.verifiable_randomization_create::<RrConfG>(
+                ([0; TIME_BYTES], time),
+                time,
+                BigUint::from(3u8),
+                input_value_signature,
+                &prf_eval_points,
+                index,
+                &mut zkp_rng,
+                false,
+            )?;
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    &client_message,
+                    ([0; TIME_BYTES], time),
+                    &prf_eval_points,
+                    index,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            Ok::<_, Error>(accepted)
+        };
+
+        // generate randomness and use the first leaf
+        let client_message = client
+            .generate_randomness_create(&client_sig_sk, &mut rng)
+            .unwrap();
+        let server_message = server
+            .generate_randomness_create(&client_message, &mut rng)
+            .unwrap();
+        assert!(client.generate_randomness_verify(&server_message).unwrap());
+        assert!(report(&mut client, &mut server, 0).unwrap());
+
+        // the chain of the client can neither be replaced by a message that is not signed by the
+        // client, nor by a replayed message of the client
+        let (_, other_sig_sk) = <RrConf as Config>::ClientSignatureScheme::keygen(
+            &parameters.client_signature_scheme,
+            &mut rng,
+        )
+        .unwrap();
+        let mut other_client = Client::new(
+            parameters.clone(),
+            server.get_signature_public_key(),
+            client_sig_pk,
+            (),
+        )
+        .unwrap();
+        let other_client_message = other_client
+            .generate_randomness_create(&other_sig_sk, &mut rng)
+            .unwrap();
+        assert_eq!(
+            server
+                .generate_randomness_create(&other_client_message, &mut rng)
+                .unwrap_err()
+                .to_string(),
+            ServerError::InvalidClientSignature.to_string()
+        );
+        assert_eq!(
+            server
+                .generate_randomness_create(&client_message, &mut rng)
+                .unwrap_err()
+                .to_string(),
+            ServerError::InvalidRenewal.to_string()
+        );
+
+        // the chains survive a restart of the server
+        let mut chains_bytes = Vec::new();
+        server
+            .get_client_merkle_tree_chains()
+            .serialize_compressed(&mut chains_bytes)
+            .unwrap();
+        server
+            .set_client_merkle_tree_chains(
+                ClientMerkleTreeChains::deserialize_compressed(&chains_bytes[..]).unwrap(),
+            )
+            .unwrap();
+        assert!(server
+            .set_client_merkle_tree_chains(ClientMerkleTreeChains::new(4))
+            .is_err());
+
+        // exhaust the merkle tree
+        assert!(report(&mut client, &mut server, 1).unwrap());
+        assert!(client.is_merkle_tree_exhausted());
+        assert_eq!(
+            report(&mut client, &mut server, 2).unwrap_err().to_string(),
+            ClientError::MerkleTreeExhausted.to_string()
+        );
+
+        // renew the merkle tree, which the server links to the previous one
+        let renewal_client_message = client
+            .generate_randomness_renew_create(&client_sig_sk, &mut rng)
+            .unwrap();
+        let server_message = server
+            .generate_randomness_create(&renewal_client_message, &mut rng)
+            .unwrap();
+        assert!(client.generate_randomness_verify(&server_message).unwrap());
+        assert!(!client.is_merkle_tree_exhausted());
+        assert!(report(&mut client, &mut server, 0).unwrap());
+
+        // the merkle tree can only be renewed once
+        assert_eq!(
+            server
+                .generate_randomness_create(&renewal_client_message, &mut rng)
+                .unwrap_err()
+                .to_string(),
+            ServerError::InvalidRenewal.to_string()
+        );
+
+        // the merkle tree can only be renewed once all of its leaves are used
+        let renewal_client_message = client
+            .generate_randomness_renew_create(&client_sig_sk, &mut rng)
+            .unwrap();
+        assert_eq!(
+            server
+                .generate_randomness_create(&renewal_client_message, &mut rng)
+                .unwrap_err()
+                .to_string(),
+            ServerError::InvalidRenewal.to_string()
+        );
+    }
+
+    #[test]
+    fn expand_epoch_bound_test() {
+        type Client = ExpandClient<3>;
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+        let mut te_rng = ChaChaRng::seed_from_u64(2);
+
+        // setup
+        let parameters =
+            ParametersExpand::setup_epoch_bound(BigFloat::from_f64(0.5, 8 * GAMMA_BYTES), &mut rng)
+                .unwrap();
+        let ExpandSetup {
+            mut server,
+            mut clients,
+            client_keys,
+            prf_eval_points,
+        } = setup_expand(&parameters, 1, &mut rng);
+        let (client_sig_pk, client_sig_sk) = client_keys[0].clone();
+        let mut client = clients.remove(0);
+
+        // create a report in the epoch that ends at the given time (the given index is ignored)
+        let mut report = |client: &mut Client, epoch: u8, zkp_rng: &mut ChaChaRng| {
+            let time = [epoch, 0];
+            let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+                &parameters.client_signature_scheme,
+                &client_sig_sk,
+                &AuthenticatedInput::message(&[3], &time),
+                &mut te_rng,
+            )
+            .unwrap();
+            client.verifiable_randomization_create::<RrConfG>(
+                ([epoch - 1, 0], time),
+                time,
+                BigUint::from(3u8),
+                input_value_signature,
+                &prf_eval_points,
+                0,
+                zkp_rng,
+                false,
+            )
+        };
+
+        // the start epoch is required before generating randomness
+        assert_eq!(
+            client
+                .generate_randomness_create(&client_sig_sk, &mut rng)
+                .unwrap_err()
+                .to_string(),
+            ClientError::UnobtainedValue.to_string()
+        );
+        client.set_start_epoch([5, 0]).unwrap();
+        let client_message = client
+            .generate_randomness_create(&client_sig_sk, &mut rng)
+            .unwrap();
+        let server_message = server
+            .generate_randomness_create(&client_message, &mut rng)
+            .unwrap();
+        assert!(client.generate_randomness_verify(&server_message).unwrap());
+        let previous_root =
+            GenerateRandomnessMessageClientExpand::<RrConf>::deserialize_compressed(
+                &client_message[..],
+            )
+            .unwrap()
+            .client_merkle_tree_root;
+
+        // the report of epoch 7 uses leaf 2, and is only accepted once in epoch 7, after which
+        // neither a replay nor another report of the client is accepted in that epoch
+        let client_message = report(&mut client, 7, &mut zkp_rng).unwrap();
+        let other_client_message = report(&mut client, 7, &mut zkp_rng).unwrap();
+        for (client_message, epoch, expected) in [
+            (&client_message, 8, false),
+            (&client_message, 7, true),
+            (&client_message, 7, false),
+            (&other_client_message, 7, false),
+        ] {
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    client_message,
+                    ([epoch - 1, 0], [epoch, 0]),
+                    &prf_eval_points,
+                    0,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            assert_eq!(accepted, expected);
+        }
+
+        // there is no leaf before the start epoch or after the last leaf
+        assert_eq!(
+            report(&mut client, 4, &mut zkp_rng)
+                .unwrap_err()
+                .to_string(),
+            ClientError::InvalidTime.to_string()
+        );
+        assert_eq!(
+            report(&mut client, 9, &mut zkp_rng)
+                .unwrap_err()
+                .to_string(),
+            ClientError::MerkleTreeExhausted.to_string()
+        );
+        let last_client_message = report(&mut client, 8, &mut zkp_rng).unwrap();
+
+        // a renewal that starts within the epochs of the renewed merkle tree (5 to 8) is rejected,
+        // even if it is signed by the client, such that no epoch can be reported under two merkle
+        // trees
+        let mut other_client = Client::new(
+            parameters.clone(),
+            server.get_signature_public_key(),
+            client_sig_pk,
+            (),
+        )
+        .unwrap();
+        other_client.set_start_epoch([8, 0]).unwrap();
+        let mut overlapping_client_message =
+            GenerateRandomnessMessageClientExpand::<RrConf>::deserialize_compressed(
+                &other_client
+                    .generate_randomness_create(&client_sig_sk, &mut rng)
+                    .unwrap()[..],
+            )
+            .unwrap();
+        overlapping_client_message.previous_client_merkle_tree_root = Some(previous_root);
+        let mut signature_input_bytes = Vec::new();
+        MerkleTreeSignatureInputExpand::<RrConf> {
+            client_merkle_tree_root: overlapping_client_message.client_merkle_tree_root,
+            previous_client_merkle_tree_root: overlapping_client_message
+                .previous_client_merkle_tree_root,
+            start_epoch: overlapping_client_message.start_epoch,
+        }
+        .serialize_uncompressed(&mut signature_input_bytes)
+        .unwrap();
+        overlapping_client_message.client_signature =
+            <RrConf as Config>::ClientSignatureScheme::sign(
+                &parameters.client_signature_scheme,
+                &client_sig_sk,
+                &signature_input_bytes,
+                &mut rng,
+            )
+            .unwrap();
+        let mut overlapping_client_message_bytes = Vec::new();
+        overlapping_client_message
+            .serialize_compressed(&mut overlapping_client_message_bytes)
+            .unwrap();
+        assert_eq!(
+            server
+                .generate_randomness_create(&overlapping_client_message_bytes, &mut rng)
+                .unwrap_err()
+                .to_string(),
+            ServerError::InvalidStartEpoch.to_string()
+        );
+
+        // a renewal that starts after these epochs is accepted
+        client.set_start_epoch([9, 0]).unwrap();
+        let client_message = client
+            .generate_randomness_renew_create(&client_sig_sk, &mut rng)
+            .unwrap();
+        let server_message = server
+            .generate_randomness_create(&client_message, &mut rng)
+            .unwrap();
+        assert!(client.generate_randomness_verify(&server_message).unwrap());
+        let client_message = report(&mut client, 9, &mut zkp_rng).unwrap();
+
+        // every merkle tree is only accepted in its own epochs (regardless of the proof)
+        for (client_message, epoch, expected) in [
+            (&last_client_message, 8, true),
+            (&last_client_message, 9, false),
+            (&client_message, 8, false),
+            (&client_message, 9, true),
+        ] {
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    client_message,
+                    ([epoch - 1, 0], [epoch, 0]),
+                    &prf_eval_points,
+                    0,
+                    &mut zkp_rng,
+                    true,
+                )
+                .unwrap();
+            assert_eq!(accepted, expected);
         }
     }
 }
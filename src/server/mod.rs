@@ -1,5 +1,18 @@
 //! All server functionalities for the three VLDP schemes (Base, Expand, and Shuffle)
 
+use crate::messages::BatchPath;
+use crate::prelude::*;
+use ark_serialize::CanonicalSerialize;
+
+pub mod budget;
+pub use budget::*;
+
+pub mod chains;
+pub use chains::*;
+
+pub mod registry;
+pub use registry::*;
+
 pub mod base;
 pub use base::*;
 
@@ -8,3 +21,37 @@ pub use expand::*;
 
 pub mod shuffle;
 pub use shuffle::*;
+
+/// Key under which the servers store state of a client: its serialized public key.
+pub(crate) fn client_key<PK: CanonicalSerialize>(client_pk: &PK) -> Result<Vec<u8>, Error> {
+    let mut client_key = Vec::new();
+    client_pk.serialize_compressed(&mut client_key)?;
+    Ok(client_key)
+}
+
+/// Sign the root of the merkle tree of a batch of `Generate Randomness` steps (see `SignedBatch`),
+/// returns the signature along with the root and path of each of the first `batch_size` leaves.
+pub(crate) fn sign_batch<Conf: Config, R: Rng + CryptoRng>(
+    server_signature_scheme: &ServerSignatureSchemeParameters<Conf>,
+    sig_sk: &ServerSignatureSchemeSecretKey<Conf>,
+    tree: &ClientMerkleTree<Conf>,
+    batch_size: usize,
+    rng: &mut R,
+) -> Result<(ServerSignatureSchemeSignature<Conf>, Vec<BatchPath<Conf>>), Error> {
+    let root = tree.root();
+    let server_signature = Conf::ServerSignatureScheme::sign(
+        server_signature_scheme,
+        sig_sk,
+        &SignedBatch::message::<Conf>(&root)?,
+        rng,
+    )?;
+    let batch_paths = (0..batch_size)
+        .map(|index| {
+            Ok(BatchPath {
+                root: root.clone(),
+                path: tree.generate_proof(index)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok((server_signature, batch_paths))
+}
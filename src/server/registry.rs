@@ -0,0 +1,98 @@
+//! Registry of client keys for the server of the Shuffle scheme
+//!
+//! Reports in the Shuffle scheme do not reveal the public key of the client, so the server cannot
+//! check whether a report was created by a known device. Instead, the server maintains a Merkle
+//! tree whose leaves are (commitments to) the registered client keys, and clients prove inside the
+//! ZKP that their key is a leaf of the current root of this tree.
+
+use crate::prelude::*;
+use crate::server::client_key;
+use std::collections::BTreeMap;
+
+/// Merkle tree of registered client signature public keys.
+///
+/// Keys are assigned to the leaves in order of registration, unused leaves contain the default
+/// leaf (which does not correspond to any public key).
+pub struct ClientKeyRegistry<Conf: Config> {
+    client_commitment_scheme: ClientCommitmentSchemeParameters<Conf>,
+    tree: ClientMerkleTree<Conf>,
+    // serialized public key -> index of its leaf
+    indices: BTreeMap<Vec<u8>, usize>,
+}
+
+impl<Conf: Config> ClientKeyRegistry<Conf> {
+    /// Create an empty registry for the given system parameters, which must use a registry.
+    pub fn new<const GAMMA_BYTES: usize>(
+        parameters: &ParametersShuffle<Conf, GAMMA_BYTES>,
+    ) -> Result<Self, Error> {
+        let registry_depth = parameters
+            .registry_depth()
+            .ok_or("The parameters do not use a registry of client keys.")?;
+        Ok(Self {
+            client_commitment_scheme: parameters.client_commitment_scheme.clone(),
+            tree: ClientMerkleTree::<Conf>::blank(
+                &parameters.client_merkle_tree_scheme.leaf_crh_params,
+                &parameters.client_merkle_tree_scheme.two_to_one_crh_params,
+                registry_depth,
+            )?,
+            indices: BTreeMap::new(),
+        })
+    }
+
+    /// Number of registered keys.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Whether no keys have been registered yet.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Whether the given client key is registered.
+    pub fn contains(
+        &self,
+        client_sig_pk: &ClientSignatureSchemePublicKey<Conf>,
+    ) -> Result<bool, Error> {
+        Ok(self.indices.contains_key(&client_key(client_sig_pk)?))
+    }
+
+    /// Register the given client key, returns the index of its leaf. Registering a key that is
+    /// already registered does not change the registry.
+    pub fn register(
+        &mut self,
+        client_sig_pk: &ClientSignatureSchemePublicKey<Conf>,
+    ) -> Result<usize, Error> {
+        let client_key = client_key(client_sig_pk)?;
+        if let Some(index) = self.indices.get(&client_key) {
+            return Ok(*index);
+        }
+        let index = self.indices.len();
+        if index >= 1 << (self.tree.height() - 1) {
+            Err(ServerError::RegistryFull)?
+        }
+        self.tree.update(
+            index,
+            &RegisteredKey::leaf::<Conf>(&self.client_commitment_scheme, client_sig_pk)?,
+        )?;
+        self.indices.insert(client_key, index);
+        Ok(index)
+    }
+
+    /// Current root of the registry, as used by the server to verify reports.
+    pub fn root(&self) -> ClientMerkleTreeRoot<Conf> {
+        self.tree.root()
+    }
+
+    /// Authentication path of the given client key for the current root.
+    pub fn path(
+        &self,
+        client_sig_pk: &ClientSignatureSchemePublicKey<Conf>,
+    ) -> Result<ClientMerkleTreePath<Conf>, Error> {
+        let index = self
+            .indices
+            .get(&client_key(client_sig_pk)?)
+            .ok_or(ServerError::UnregisteredKey)?;
+        self.tree.generate_proof(*index)
+    }
+}
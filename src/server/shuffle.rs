@@ -1,9 +1,12 @@
-//! All functionalities for a server in the Expand scheme
+//! All functionalities for a server in the Shuffle scheme
 
 use crate::circuits::CircuitShuffle;
 use crate::messages::shuffle::*;
 use crate::prelude::*;
+use crate::primitives::memoization::PermanentRandomness;
+use crate::server::{client_key, sign_batch, ClientKeyRegistry, PrivacyBudgetLedger};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Expand scheme server
 pub struct ServerShuffle<
@@ -18,7 +21,17 @@ pub struct ServerShuffle<
     parameters: ParametersShuffle<Conf, GAMMA_BYTES>,
     sig_pk: ServerSignatureSchemePublicKey<Conf>,
     sig_sk: ServerSignatureSchemeSecretKey<Conf>,
+    enc_pk: ReportEncryptionSchemePublicKey<Conf>,
+    enc_sk: ReportEncryptionSchemeSecretKey<Conf>,
     verifying_key: VerifyingKey<Conf>,
+    budget_ledger: Option<PrivacyBudgetLedger>,
+    key_registry: Option<ClientKeyRegistry<Conf>>,
+    // serialized public key -> commitment to the nullifier key
+    nullifier_key_commitments: BTreeMap<Vec<u8>, ClientCommitmentSchemeOutput<Conf>>,
+    // (epoch, nullifier) of all accepted reports
+    nullifiers: BTreeSet<([u8; TIME_BYTES], PRFSchemeOutput<Conf>)>,
+    // (epoch, index) of all accepted dummy reports
+    dummies: BTreeSet<([u8; TIME_BYTES], u32)>,
 }
 
 impl<
@@ -33,18 +46,35 @@ impl<
     ServerShuffle<Conf, INPUT_BYTES, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, K, IS_REAL_INPUT>
 {
     /// Create a new server with the given system parameters and proof verification key.
+    /// If the parameters use a registry of client keys, the server starts with an empty registry.
     pub fn new<R: Rng + CryptoRng>(
         parameters: ParametersShuffle<Conf, GAMMA_BYTES>,
         verifying_key: VerifyingKey<Conf>,
         rng: &mut R,
     ) -> Result<Self, Error> {
+        if parameters.permanent_gamma_as_bytes()?.is_some() {
+            PermanentRandomness::check::<INPUT_BYTES, TIME_BYTES, RANDOMNESS_BYTES>()?;
+        }
         let (sig_pk, sig_sk) =
             Conf::ServerSignatureScheme::keygen(&parameters.server_signature_scheme, rng)?;
+        let (enc_pk, enc_sk) =
+            Conf::ReportEncryptionScheme::keygen(&parameters.report_encryption_scheme, rng)?;
+        let key_registry = match parameters.registry_depth() {
+            Some(_) => Some(ClientKeyRegistry::new(&parameters)?),
+            None => None,
+        };
         Ok(Self {
             parameters,
             sig_pk,
             sig_sk,
+            enc_pk,
+            enc_sk,
             verifying_key,
+            budget_ledger: None,
+            key_registry,
+            nullifier_key_commitments: BTreeMap::new(),
+            nullifiers: BTreeSet::new(),
+            dummies: BTreeSet::new(),
         })
     }
 
@@ -53,28 +83,161 @@ impl<
         self.sig_pk.clone()
     }
 
-    /// Given a client message perform the `Generate Randomness` step for the server.
-    pub fn generate_randomness_create<R: Rng + CryptoRng>(
-        &self,
+    /// Get server's encryption public key, under which the clients encrypt their reports (inside
+    /// the message for the shuffler)
+    pub fn get_encryption_public_key(&self) -> ReportEncryptionSchemePublicKey<Conf> {
+        self.enc_pk.clone()
+    }
+
+    /// Enable per-client privacy budget tracking with the given ledger.
+    pub fn set_budget_ledger(&mut self, budget_ledger: PrivacyBudgetLedger) {
+        self.budget_ledger = Some(budget_ledger);
+    }
+
+    /// Get the privacy budget ledger, if budget tracking is enabled.
+    pub fn get_budget_ledger(&self) -> Option<&PrivacyBudgetLedger> {
+        self.budget_ledger.as_ref()
+    }
+
+    /// Register the signature public key of a client in the registry of client keys, returns the
+    /// index of its leaf.
+    pub fn register_client_key(
+        &mut self,
+        client_sig_pk: &ClientSignatureSchemePublicKey<Conf>,
+    ) -> Result<usize, Error> {
+        self.key_registry
+            .as_mut()
+            .ok_or("The parameters do not use a registry of client keys.")?
+            .register(client_sig_pk)
+    }
+
+    /// Get the registry of client keys, if the parameters use a registry.
+    pub fn get_key_registry(&self) -> Option<&ClientKeyRegistry<Conf>> {
+        self.key_registry.as_ref()
+    }
+
+    /// Get the number of accepted dummy reports of the given epoch (i.e., upper time bound), which
+    /// the estimator accounts for (see `BlanketNoise::estimate`). Returns an error if the shuffler
+    /// added more dummy reports than the minimum batch size minus the number of accepted client
+    /// reports of the epoch.
+    pub fn get_dummy_count(&self, epoch: [u8; TIME_BYTES]) -> Result<usize, Error> {
+        let dummies = self.dummies.range((epoch, 0)..=(epoch, u32::MAX)).count();
+        let reports = self
+            .nullifiers
+            .iter()
+            .filter(|(report_epoch, _)| *report_epoch == epoch)
+            .count();
+        if dummies
+            > self
+                .parameters
+                .blanket_noise()
+                .map_or(0, |blanket_noise| blanket_noise.dummies(reports))
+        {
+            Err(ServerError::ExcessDummyReports)?
+        }
+        Ok(dummies)
+    }
+
+    /// Epsilon that is charged to a client for a single report.
+    fn report_epsilon(&self) -> Result<f64, Error> {
+        Ok(Conf::LdpMechanism::epsilon::<
+            INPUT_BYTES,
+            GAMMA_BYTES,
+            K,
+            IS_REAL_INPUT,
+        >(&self.parameters.gamma_as_bytes()?))
+    }
+
+    /// Given a client message and the current time step check and charge the client, register its
+    /// nullifier key and compute the server seed, returns the input of the server signature.
+    fn generate_randomness_signature_input<R: Rng + CryptoRng>(
+        &mut self,
         client_message: &[u8],
+        time: [u8; TIME_BYTES],
         rng: &mut R,
-    ) -> Result<Vec<u8>, Error>
+    ) -> Result<GenerateRandomnessSignatureInputShuffle<Conf>, Error>
     where
         ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
         ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
-        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
     {
         // deserialize client message
         let client_message =
             GenerateRandomnessMessageClientShuffle::<Conf>::deserialize_compressed(client_message)?;
 
+        // check that the client key is registered
+        if let Some(key_registry) = &self.key_registry {
+            if !key_registry.contains(&client_message.client_signature_public_key)? {
+                Err(ServerError::UnregisteredKey)?
+            }
+        }
+
+        // check that the client uses its registered nullifier key
+        let client_key = client_key(&client_message.client_signature_public_key)?;
+        if self
+            .nullifier_key_commitments
+            .get(&client_key)
+            .is_some_and(|commitment| *commitment != client_message.nullifier_key_commitment)
+        {
+            Err(ServerError::InvalidNullifierKey)?
+        }
+
+        // charge the privacy budget of the client
+        let epsilon = self.report_epsilon()?;
+        if let Some(budget_ledger) = &mut self.budget_ledger {
+            if !budget_ledger.charge(&client_message.client_signature_public_key, &time, epsilon)? {
+                Err(ServerError::BudgetExhausted)?
+            }
+        }
+
         // compute server seed
         let mut server_seed = PRFSchemeSeed::<Conf>::default();
         rng.fill_bytes(&mut server_seed);
 
+        // register the nullifier key of the client
+        self.nullifier_key_commitments
+            .entry(client_key)
+            .or_insert_with(|| client_message.nullifier_key_commitment.clone());
+
+        // create signature input
+        Ok(GenerateRandomnessSignatureInputShuffle::new(
+            client_message,
+            server_seed,
+        ))
+    }
+
+    /// Given a client message and the current time step perform the `Generate Randomness` step for
+    /// the server.
+    ///
+    /// Note: reports in the Shuffle scheme cannot be linked to a client, so if budget tracking is
+    /// enabled the budget is charged here (when the client obtains its randomness) instead of in
+    /// the `Verify` step.
+    /// The commitment to the nullifier key of a client is registered in its first `Generate
+    /// Randomness` step, after which the client must always use the same commitment.
+    /// If a registry of client keys is used, only registered clients obtain randomness.
+    /// If the parameters use batches, the step is performed as a batch of a single client (see
+    /// `generate_randomness_create_batch`).
+    pub fn generate_randomness_create<R: Rng + CryptoRng>(
+        &mut self,
+        client_message: &[u8],
+        time: [u8; TIME_BYTES],
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Error>
+    where
+        ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    {
+        // the signature is on the root of a batch if the parameters use batches
+        if self.parameters.batch_depth().is_some() {
+            return Ok(self
+                .generate_randomness_create_batch(&[client_message.to_vec()], time, rng)?
+                .pop()
+                .ok_or("The batch contains no server message.")?);
+        }
+
         // create signature input
         let signature_input =
-            GenerateRandomnessSignatureInputShuffle::new(client_message, server_seed.clone());
+            self.generate_randomness_signature_input(client_message, time, rng)?;
         let mut signature_input_bytes = Vec::new();
         signature_input.serialize_uncompressed(&mut signature_input_bytes)?;
 
@@ -89,39 +252,147 @@ impl<
         // return message
         let mut serialized_message = vec![];
         GenerateRandomnessMessageServerShuffle::<Conf> {
-            server_seed,
+            server_seed: signature_input.server_seed,
             server_signature,
+            batch: None,
         }
         .serialize_compressed(&mut serialized_message)?;
         Ok(serialized_message)
     }
 
+    /// Given the client messages of a batch and the current time step perform the `Generate
+    /// Randomness` step for the server for all of them at once (see `generate_randomness_create`),
+    /// returns the server messages in the same order. The parameters must use batches.
+    ///
+    /// Instead of signing the signature input of every client, the server signs the root of the
+    /// merkle tree (of the batch depth) over all signature inputs, and every client obtains the
+    /// path of its leaf (see `SignedBatch`).
+    /// Note: the client messages are handled in order, if one of them is rejected, the whole batch
+    /// is rejected, but the clients before it remain charged and registered.
+    pub fn generate_randomness_create_batch<R: Rng + CryptoRng>(
+        &mut self,
+        client_messages: &[Vec<u8>],
+        time: [u8; TIME_BYTES],
+        rng: &mut R,
+    ) -> Result<Vec<Vec<u8>>, Error>
+    where
+        ClientCommitmentSchemeOutput<Conf>: CanonicalDeserialize,
+        ClientSignatureSchemePublicKey<Conf>: CanonicalDeserialize,
+        ServerSignatureSchemeSignature<Conf>: CanonicalDeserialize,
+    {
+        // check that the batch fits in the merkle tree, before any client is charged
+        let batch_depth = self
+            .parameters
+            .batch_depth()
+            .ok_or("The parameters do not use batches of `Generate Randomness` steps.")?;
+        if client_messages.len() > 1 << (batch_depth - 1) {
+            Err(ServerError::BatchTooLarge)?
+        }
+
+        // create signature inputs
+        let signature_inputs = client_messages
+            .iter()
+            .map(|client_message| {
+                self.generate_randomness_signature_input(client_message, time, rng)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // sign the root of the merkle tree of the batch
+        let tree = SignedBatch::tree::<Conf>(
+            &self.parameters.client_commitment_scheme,
+            &self.parameters.client_merkle_tree_scheme,
+            &signature_inputs,
+            batch_depth,
+        )?;
+        let (server_signature, batch_paths) = sign_batch::<Conf, _>(
+            &self.parameters.server_signature_scheme,
+            &self.sig_sk,
+            &tree,
+            signature_inputs.len(),
+            rng,
+        )?;
+
+        // return messages
+        signature_inputs
+            .into_iter()
+            .zip(batch_paths)
+            .map(|(signature_input, batch_path)| {
+                let mut serialized_message = vec![];
+                GenerateRandomnessMessageServerShuffle::<Conf> {
+                    server_seed: signature_input.server_seed,
+                    server_signature: server_signature.clone(),
+                    batch: Some(batch_path),
+                }
+                .serialize_compressed(&mut serialized_message)?;
+                Ok(serialized_message)
+            })
+            .collect()
+    }
+
     /// Given a client message, current time (step) bounds, list of `prf_eval_points` (s in the
     /// paper) and current `index` (j in the paper) perform the `Verify` step for the server.
     ///
+    /// The client message is the message as forwarded by the shuffler, of which the report is
+    /// decrypted first, a report that cannot be decrypted is rejected.
+    /// If the parameters use blanket noise, the report can also be a dummy report of the shuffler,
+    /// which is accepted once per index below the minimum batch size in every epoch, and of which
+    /// the LDP value is derived from the public seed (see `BlanketNoise`). The number of dummy
+    /// reports is checked once the epoch is complete (see `get_dummy_count`).
+    /// A report is rejected if a report with the same nullifier has already been accepted in the
+    /// same epoch (i.e., with the same upper time bound). If the parameters use multiple messages,
+    /// every message of a client is verified as a separate report.
+    /// If a registry of client keys is used, the proof is verified for the current root of the
+    /// registry.
+    ///
     /// The `skip_proof` flag can be set to `true` to do a faster test run of this function that
     /// skips proof verification.
     /// Note: in actual usage this should be set to `false`.
     pub fn verifiable_randomization_verify<ConfG: ConfigGadget<Conf>>(
-        &self,
+        &mut self,
         client_message: &[u8],
         time_bounds: ([u8; TIME_BYTES], [u8; TIME_BYTES]),
         prf_eval_point: &[PRFSchemeInput<Conf>],
         zkp_rng: &mut ZKPRng<Conf>,
         skip_proof: bool,
-    ) -> Result<(bool, u64), Error>
+    ) -> Result<(bool, LdpValue<Conf>), Error>
     where
         ServerSignatureSchemePublicKey<Conf>: ToConstraintField<ConstraintField<Conf>>,
+        ClientMerkleTreeRoot<Conf>: ToConstraintField<ConstraintField<Conf>>,
         Proof<Conf>: CanonicalDeserialize,
     {
-        // deserialize client message
+        // deserialize client message (as forwarded by the shuffler) and decrypt the report
         let client_message =
-            VerifiableRandomizationMessageShuffle::<Conf, INPUT_BYTES>::deserialize_compressed(
-                client_message,
-            )?;
+            VerifiableRandomizationMessageShuffle::<Conf>::deserialize_compressed(client_message)?;
+        let report = match client_message
+            .encrypted_report
+            .decrypt(&self.parameters.report_encryption_scheme, &self.enc_sk)?
+        {
+            Some(report) => report,
+            None => return Ok((false, LdpValue::<Conf>::default())),
+        };
+        let report = match ReportPayloadShuffle::<Conf, INPUT_BYTES>::deserialize_compressed(
+            report.as_slice(),
+        )? {
+            ReportPayloadShuffle::Client(report) => report,
+            ReportPayloadShuffle::Dummy(report) => {
+                return self.dummy_report_verify(report.index, time_bounds.1)
+            }
+        };
+
+        // reject a message index that is not used by the parameters
+        let messages = self.parameters.messages();
+        if report.message_index as usize >= messages {
+            return Ok((false, LdpValue::<Conf>::default()));
+        }
+
+        // reject a second report of the same client within the same epoch (and message index)
+        let nullifier = (time_bounds.1, report.nullifier);
+        if self.nullifiers.contains(&nullifier) {
+            return Ok((false, LdpValue::<Conf>::default()));
+        }
 
-        if skip_proof {
-            Ok((true, client_message.ldp_value))
+        let (accepted, ldp_value) = if skip_proof {
+            Ok((true, report.ldp_value))
         } else {
             CircuitShuffle::<
                 _,
@@ -135,13 +406,673 @@ impl<
             >::verify(
                 &self.verifying_key,
                 &client_message.proof,
-                client_message.ldp_value,
+                report.ldp_value,
                 time_bounds,
                 &self.sig_pk,
                 prf_eval_point,
+                &report.nullifier,
+                self.key_registry
+                    .as_ref()
+                    .map(|key_registry| key_registry.root())
+                    .as_ref(),
+                (messages > 1).then_some(report.message_index),
                 zkp_rng,
             )
-            .map(|x| (x, client_message.ldp_value))
+            .map(|x| (x, report.ldp_value))
+        }?;
+
+        if accepted {
+            self.nullifiers.insert(nullifier);
+        }
+        Ok((accepted, ldp_value))
+    }
+
+    /// Verify a dummy report with the given index in the given epoch: it is accepted if the
+    /// parameters use blanket noise, the index is below the minimum batch size, and no dummy report
+    /// with the same index has been accepted in the same epoch.
+    fn dummy_report_verify(
+        &mut self,
+        index: u32,
+        epoch: [u8; TIME_BYTES],
+    ) -> Result<(bool, LdpValue<Conf>), Error> {
+        let blanket_noise = match self.parameters.blanket_noise() {
+            Some(blanket_noise) => blanket_noise,
+            None => return Ok((false, LdpValue::<Conf>::default())),
+        };
+        if index as usize >= blanket_noise.min_batch_size || !self.dummies.insert((epoch, index)) {
+            return Ok((false, LdpValue::<Conf>::default()));
+        }
+        Ok((
+            true,
+            blanket_noise
+                .ldp_value::<Conf, INPUT_BYTES, GAMMA_BYTES, K, IS_REAL_INPUT>(&epoch, index)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::circuits::test::*;
+    use crate::client::ClientShuffle;
+    use crate::messages::shuffle::{
+        DummyReportShuffle, EncryptedReportShuffle, GenerateRandomnessMessageServerShuffle,
+        ReportPayloadShuffle, VerifiableRandomizationMessageShuffle,
+    };
+    use crate::prelude::*;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use astro_float::BigFloat;
+    use num_bigint::BigUint;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn shuffle_nullifier_test() {
+        type Server = ShuffleServer;
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+        let mut te_rng = ChaChaRng::seed_from_u64(2);
+
+        // setup
+        let parameters =
+            ParametersShuffle::setup(BigFloat::from_f64(0.5, 8 * GAMMA_BYTES), &mut rng).unwrap();
+        let setup = setup_shuffle(&parameters, 1, &mut rng);
+        let mut other_client = setup.new_client(&parameters, setup.client_keys[0].0);
+        let ShuffleSetup {
+            mut server,
+            shuffler,
+            mut clients,
+            client_keys,
+            prf_eval_points,
+        } = setup;
+        let (_, client_sig_sk) = &client_keys[0];
+        let mut client = clients.remove(0);
+        let mut report_lengths = vec![];
+
+        // generate randomness, and report a value in the given epoch, returns whether the server
+        // accepted it
+        let mut report = |client: &mut ShuffleClient, server: &mut Server, epoch: u8| {
+            let client_message = client.generate_randomness_create(&mut rng)?;
+            let server_message = server.generate_randomness_create(
+                &client_message,
+                [epoch; TIME_BYTES],
+                &mut rng,
+            )?;
+            assert!(client.generate_randomness_verify(&server_message)?);
+            let time_bounds = ([epoch - 1; TIME_BYTES], [epoch; TIME_BYTES]);
+            let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+                &parameters.client_signature_scheme,
+                client_sig_sk,
+                &AuthenticatedInput::message(&[3], &time_bounds.1),
+                &mut te_rng,
+            )?;
+            let client_message = client.verifiable_randomization_create::<RrConfG>(
+                time_bounds,
+                time_bounds.1,
+                BigUint::from(3u8),
+                input_value_signature,
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )?;
+            let shuffler_message = shuffler
+                .forward_message(&client_message, &mut zkp_rng)?
+                .unwrap();
+            report_lengths.push((client_message.len(), shuffler_message.len()));
+            let (accepted, _) = server.verifiable_randomization_verify::<RrConfG>(
+                &shuffler_message,
+                time_bounds,
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )?;
+            Ok::<_, Error>(accepted)
+        };
+
+        // only a single report per epoch is accepted, even with fresh randomness
+        assert!(report(&mut client, &mut server, 1).unwrap());
+        assert!(!report(&mut client, &mut server, 1).unwrap());
+        assert!(report(&mut client, &mut server, 2).unwrap());
+
+        // the client cannot switch to another nullifier key
+        assert_eq!(
+            report(&mut other_client, &mut server, 3)
+                .unwrap_err()
+                .to_string(),
+            ServerError::InvalidNullifierKey.to_string()
+        );
+
+        // the messages on either side of the shuffler have the same length
+        assert!(report_lengths
+            .iter()
+            .all(|length| *length == report_lengths[0]));
+    }
+
+    #[test]
+    fn shuffle_memoization_test() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+        let mut te_rng = ChaChaRng::seed_from_u64(2);
+
+        // setup
+        let parameters = ParametersShuffle::setup_memoized(
+            BigFloat::from_f64(0.5, 8 * GAMMA_BYTES),
+            BigFloat::from_f64(0.5, 8 * GAMMA_BYTES),
+            &mut rng,
+        )
+        .unwrap();
+        let ShuffleSetup {
+            mut server,
+            shuffler,
+            mut clients,
+            client_keys,
+            prf_eval_points,
+        } = setup_shuffle(&parameters, 1, &mut rng);
+        let (client_sig_pk, client_sig_sk) = &client_keys[0];
+        let client = &mut clients[0];
+
+        // the permanent value of a true value is the same after every `Generate Randomness` step
+        let mut permanent_values = vec![];
+        for epoch in 1..=2 {
+            let client_message = client.generate_randomness_create(&mut rng).unwrap();
+            let server_message = server
+                .generate_randomness_create(&client_message, [epoch; TIME_BYTES], &mut rng)
+                .unwrap();
+            assert!(client.generate_randomness_verify(&server_message).unwrap());
+            permanent_values.push(
+                client
+                    .permanent_value(&BigUint::from(3u8))
+                    .unwrap()
+                    .unwrap(),
+            );
+
+            let time_bounds = ([epoch - 1; TIME_BYTES], [epoch; TIME_BYTES]);
+            let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+                &parameters.client_signature_scheme,
+                client_sig_sk,
+                &AuthenticatedInput::message(&[3], &time_bounds.1),
+                &mut te_rng,
+            )
+            .unwrap();
+            let client_message = client
+                .verifiable_randomization_create::<RrConfG>(
+                    time_bounds,
+                    time_bounds.1,
+                    BigUint::from(3u8),
+                    input_value_signature,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            let shuffler_message = shuffler
+                .forward_message(&client_message, &mut zkp_rng)
+                .unwrap()
+                .unwrap();
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    &shuffler_message,
+                    time_bounds,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            assert!(accepted);
+        }
+        assert_eq!(permanent_values[0], permanent_values[1]);
+
+        // memoization is rejected if the time could overlap the permanent key evaluation point
+        assert!(
+            ClientShuffle::<RrConf, 1, 31, GAMMA_BYTES, RANDOMNESS_BYTES, 7, false>::new(
+                parameters.clone(),
+                server.get_signature_public_key(),
+                server.get_encryption_public_key(),
+                shuffler.get_encryption_public_key(),
+                *client_sig_pk,
+                (),
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn shuffle_registry_test() {
+        type Server = ShuffleServer;
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+        let mut te_rng = ChaChaRng::seed_from_u64(2);
+
+        // setup, with room for two registered keys
+        let parameters = ParametersShuffle::setup_registered(
+            BigFloat::from_f64(0.5, 8 * GAMMA_BYTES),
+            2,
+            &mut rng,
+        )
+        .unwrap();
+        let ShuffleSetup {
+            mut server,
+            shuffler,
+            mut clients,
+            client_keys,
+            prf_eval_points,
+        } = setup_shuffle(&parameters, 3, &mut rng);
+
+        // register the first client, which proves membership for the current root
+        assert_eq!(server.register_client_key(&client_keys[0].0).unwrap(), 0);
+        let key_registry = server.get_key_registry().unwrap();
+        let (root, path) = (
+            key_registry.root(),
+            key_registry.path(&client_keys[0].0).unwrap(),
+        );
+        clients[0].set_registration(root, path);
+
+        // generate randomness, and report a value in the given epoch, returns whether the server
+        // accepted it
+        let mut report = |client_index: usize, server: &mut Server, epoch: u8| {
+            let client = &mut clients[client_index];
+            let client_message = client.generate_randomness_create(&mut rng)?;
+            let server_message = server.generate_randomness_create(
+                &client_message,
+                [epoch; TIME_BYTES],
+                &mut rng,
+            )?;
+            assert!(client.generate_randomness_verify(&server_message)?);
+            let time_bounds = ([epoch - 1; TIME_BYTES], [epoch; TIME_BYTES]);
+            let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+                &parameters.client_signature_scheme,
+                &client_keys[client_index].1,
+                &AuthenticatedInput::message(&[3], &time_bounds.1),
+                &mut te_rng,
+            )?;
+            let client_message = client.verifiable_randomization_create::<RrConfG>(
+                time_bounds,
+                time_bounds.1,
+                BigUint::from(3u8),
+                input_value_signature,
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )?;
+            let shuffler_message = shuffler
+                .forward_message(&client_message, &mut zkp_rng)?
+                .unwrap();
+            let (accepted, _) = server.verifiable_randomization_verify::<RrConfG>(
+                &shuffler_message,
+                time_bounds,
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )?;
+            Ok::<_, Error>(accepted)
+        };
+
+        assert!(report(0, &mut server, 1).unwrap());
+
+        // unregistered clients do not obtain randomness
+        assert_eq!(
+            report(1, &mut server, 1).unwrap_err().to_string(),
+            ServerError::UnregisteredKey.to_string()
+        );
+
+        // after registering another key, proofs for the old root are rejected
+        assert_eq!(server.register_client_key(&client_keys[1].0).unwrap(), 1);
+        assert!(!report(0, &mut server, 2).unwrap());
+
+        // the registry is full
+        assert_eq!(
+            server
+                .register_client_key(&client_keys[2].0)
+                .unwrap_err()
+                .to_string(),
+            ServerError::RegistryFull.to_string()
+        );
+    }
+
+    #[test]
+    fn shuffle_batch_test() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+        let mut te_rng = ChaChaRng::seed_from_u64(2);
+
+        // setup, with batches of two clients
+        let parameters =
+            ParametersShuffle::setup_batch(BigFloat::from_f64(0.5, 8 * GAMMA_BYTES), 2, &mut rng)
+                .unwrap();
+        let ShuffleSetup {
+            mut server,
+            shuffler,
+            mut clients,
+            client_keys,
+            prf_eval_points,
+        } = setup_shuffle(&parameters, 3, &mut rng);
+        let client_messages = clients
+            .iter_mut()
+            .map(|client| client.generate_randomness_create(&mut rng).unwrap())
+            .collect::<Vec<_>>();
+
+        // a batch does not fit more clients than the merkle tree has leaves
+        assert_eq!(
+            server
+                .generate_randomness_create_batch(&client_messages, [1; TIME_BYTES], &mut rng)
+                .unwrap_err()
+                .to_string(),
+            ServerError::BatchTooLarge.to_string()
+        );
+
+        // the first two clients obtain randomness in a batch, the last one in a batch of its own
+        let mut server_messages = server
+            .generate_randomness_create_batch(&client_messages[0..2], [1; TIME_BYTES], &mut rng)
+            .unwrap();
+        server_messages.push(
+            server
+                .generate_randomness_create(&client_messages[2], [1; TIME_BYTES], &mut rng)
+                .unwrap(),
+        );
+
+        // a client rejects the path of another leaf
+        let mut server_message =
+            GenerateRandomnessMessageServerShuffle::<RrConf>::deserialize_compressed(
+                server_messages[1].as_slice(),
+            )
+            .unwrap();
+        server_message.batch =
+            GenerateRandomnessMessageServerShuffle::<RrConf>::deserialize_compressed(
+                server_messages[0].as_slice(),
+            )
+            .unwrap()
+            .batch;
+        let mut tampered_server_message = vec![];
+        server_message
+            .serialize_compressed(&mut tampered_server_message)
+            .unwrap();
+        assert!(!clients[1]
+            .generate_randomness_verify(&tampered_server_message)
+            .unwrap());
+
+        // every client proves that its signature input is in a signed batch
+        for (client_index, client) in clients.iter_mut().enumerate() {
+            assert!(client
+                .generate_randomness_verify(&server_messages[client_index])
+                .unwrap());
+            let time_bounds = ([0; TIME_BYTES], [1; TIME_BYTES]);
+            let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+                &parameters.client_signature_scheme,
+                &client_keys[client_index].1,
+                &AuthenticatedInput::message(&[3], &time_bounds.1),
+                &mut te_rng,
+            )
+            .unwrap();
+            let client_message = client
+                .verifiable_randomization_create::<RrConfG>(
+                    time_bounds,
+                    time_bounds.1,
+                    BigUint::from(3u8),
+                    input_value_signature,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            let shuffler_message = shuffler
+                .forward_message(&client_message, &mut zkp_rng)
+                .unwrap()
+                .unwrap();
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    &shuffler_message,
+                    time_bounds,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            assert!(accepted);
+        }
+    }
+
+    #[test]
+    fn shuffle_multi_message_test() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+        let mut te_rng = ChaChaRng::seed_from_u64(2);
+
+        // setup
+        let parameters = ParametersShuffle::setup_multi_message(
+            BigFloat::from_f64(0.5, 8 * GAMMA_BYTES),
+            3,
+            &mut rng,
+        )
+        .unwrap();
+        let ShuffleSetup {
+            mut server,
+            shuffler,
+            mut clients,
+            client_keys,
+            prf_eval_points,
+        } = setup_shuffle(&parameters, 1, &mut rng);
+        let (_, client_sig_sk) = &client_keys[0];
+        let client = &mut clients[0];
+        let time_bounds = ([0; TIME_BYTES], [1; TIME_BYTES]);
+
+        // generate randomness
+        let client_message = client.generate_randomness_create(&mut rng).unwrap();
+        let server_message = server
+            .generate_randomness_create(&client_message, time_bounds.1, &mut rng)
+            .unwrap();
+        assert!(client.generate_randomness_verify(&server_message).unwrap());
+
+        // a single message cannot be created
+        let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+            &parameters.client_signature_scheme,
+            client_sig_sk,
+            &AuthenticatedInput::message(&[3], &time_bounds.1),
+            &mut te_rng,
+        )
+        .unwrap();
+        assert!(client
+            .verifiable_randomization_create::<RrConfG>(
+                time_bounds,
+                time_bounds.1,
+                BigUint::from(3u8),
+                input_value_signature.clone(),
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )
+            .is_err());
+
+        // all messages are accepted, but only once
+        let client_messages = client
+            .verifiable_randomization_create_messages::<RrConfG>(
+                time_bounds,
+                time_bounds.1,
+                BigUint::from(3u8),
+                input_value_signature,
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )
+            .unwrap();
+        assert_eq!(client_messages.len(), 3);
+        let forwarded_messages = shuffler.forward(&client_messages, &mut zkp_rng).unwrap();
+        assert_eq!(forwarded_messages.len(), 3);
+        for forwarded_message in &forwarded_messages {
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    forwarded_message,
+                    time_bounds,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            assert!(accepted);
+        }
+        let (accepted, _) = server
+            .verifiable_randomization_verify::<RrConfG>(
+                &forwarded_messages[0],
+                time_bounds,
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )
+            .unwrap();
+        assert!(!accepted);
+    }
+
+    #[test]
+    fn shuffle_blanket_noise_test() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+        let mut te_rng = ChaChaRng::seed_from_u64(2);
+
+        // setup
+        let parameters = ParametersShuffle::setup_blanket_noise(
+            BigFloat::from_f64(0.5, 8 * GAMMA_BYTES),
+            5,
+            &mut rng,
+        )
+        .unwrap();
+        let ShuffleSetup {
+            mut server,
+            shuffler,
+            mut clients,
+            client_keys,
+            prf_eval_points,
+        } = setup_shuffle(&parameters, 1, &mut rng);
+        let (_, client_sig_sk) = &client_keys[0];
+        let client = &mut clients[0];
+        let time_bounds = ([0; TIME_BYTES], [1; TIME_BYTES]);
+
+        // report of a single client
+        let client_message = client.generate_randomness_create(&mut rng).unwrap();
+        let server_message = server
+            .generate_randomness_create(&client_message, time_bounds.1, &mut rng)
+            .unwrap();
+        assert!(client.generate_randomness_verify(&server_message).unwrap());
+        let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+            &parameters.client_signature_scheme,
+            client_sig_sk,
+            &AuthenticatedInput::message(&[3], &time_bounds.1),
+            &mut te_rng,
+        )
+        .unwrap();
+        let client_message = client
+            .verifiable_randomization_create::<RrConfG>(
+                time_bounds,
+                time_bounds.1,
+                BigUint::from(3u8),
+                input_value_signature,
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )
+            .unwrap();
+
+        // the shuffler adds dummy reports up to the minimum batch size, which have the same length
+        // as the client message and are all accepted
+        let forwarded_messages = shuffler.forward(&[client_message], &mut zkp_rng).unwrap();
+        assert_eq!(forwarded_messages.len(), 5);
+        assert!(forwarded_messages
+            .iter()
+            .all(|message| message.len() == forwarded_messages[0].len()));
+        let mut ldp_values = vec![];
+        for forwarded_message in &forwarded_messages {
+            let (accepted, ldp_value) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    forwarded_message,
+                    time_bounds,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            assert!(accepted);
+            ldp_values.push(ldp_value);
+        }
+
+        // the values of the dummy reports can be recomputed by anyone from the public seed
+        let blanket_noise = parameters.blanket_noise().unwrap();
+        for index in 0..4 {
+            assert!(ldp_values.contains(
+                &blanket_noise
+                    .ldp_value::<RrConf, 1, GAMMA_BYTES, 7, false>(&time_bounds.1, index)
+                    .unwrap()
+            ));
+        }
+
+        // the dummy reports are only accounted for by their number in the estimate
+        let dummies = server.get_dummy_count(time_bounds.1).unwrap();
+        assert_eq!(dummies, 4);
+        let estimate = BlanketNoise::estimate::<RandomizedResponse, 1, GAMMA_BYTES, 7, false>(
+            &parameters.gamma_as_bytes().unwrap(),
+            1,
+            &ldp_values,
+            dummies,
+        );
+        assert_eq!(estimate.counts.unwrap().iter().sum::<u64>(), 5);
+        assert!((estimate.histogram.unwrap().iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        // a dummy report is only accepted once, and only below the minimum batch size
+        let mut dummy_messages = shuffler.dummy_messages(&[], &mut zkp_rng).unwrap();
+        let mut serialized_report = vec![];
+        ReportPayloadShuffle::<RrConf, 1>::Dummy(DummyReportShuffle { index: 5 })
+            .serialize_compressed(&mut serialized_report)
+            .unwrap();
+        let mut invalid_dummy_message = vec![];
+        VerifiableRandomizationMessageShuffle::<RrConf> {
+            proof: Default::default(),
+            encrypted_report: EncryptedReportShuffle::encrypt(
+                &parameters.report_encryption_scheme,
+                &server.get_encryption_public_key(),
+                &serialized_report,
+                &mut rng,
+            )
+            .unwrap(),
+        }
+        .serialize_compressed(&mut invalid_dummy_message)
+        .unwrap();
+        dummy_messages.push(invalid_dummy_message);
+        for (index, dummy_message) in dummy_messages.iter().enumerate() {
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    dummy_message,
+                    time_bounds,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            assert_eq!(accepted, index == 4);
+        }
+
+        // more dummy reports than the minimum batch size minus the client reports are detected
+        assert!(server.get_dummy_count(time_bounds.1).is_err());
+
+        // an epoch with only dummy reports has an empty estimate
+        let other_time_bounds = ([1; TIME_BYTES], [2; TIME_BYTES]);
+        let mut ldp_values = vec![];
+        for dummy_message in shuffler.forward(&[], &mut zkp_rng).unwrap() {
+            let (accepted, ldp_value) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    &dummy_message,
+                    other_time_bounds,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            assert!(accepted);
+            ldp_values.push(ldp_value);
         }
+        let dummies = server.get_dummy_count(other_time_bounds.1).unwrap();
+        assert_eq!(dummies, 5);
+        let estimate = BlanketNoise::estimate::<RandomizedResponse, 1, GAMMA_BYTES, 7, false>(
+            &parameters.gamma_as_bytes().unwrap(),
+            1,
+            &ldp_values,
+            dummies,
+        );
+        assert_eq!(estimate, Estimate::default());
     }
 }
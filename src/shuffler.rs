@@ -0,0 +1,396 @@
+//! All functionalities for the shuffler in the Shuffle scheme
+//!
+//! Clients send their messages to the shuffler, encrypted under the encryption public key of the
+//! shuffler. The shuffler decrypts each message, rerandomizes its proof, and forwards the messages
+//! to the server in a random order. The report itself stays encrypted under the encryption public
+//! key of the server, and the proof is zero-knowledge, so the shuffler learns nothing about the
+//! reports. Since the encryption and the proof are both fresh, a message that is forwarded by the
+//! shuffler cannot be linked to the message that was received from the client.
+//!
+//! If the parameters use blanket noise, the shuffler adds dummy reports to every epoch in which
+//! fewer messages than the minimum batch size are forwarded (see `BlanketNoise`). A dummy report
+//! is encrypted under the encryption public key of the server and has a rerandomized proof, such
+//! that it has the same shape as a forwarded client message.
+
+use crate::messages::shuffle::*;
+use crate::prelude::*;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::seq::SliceRandom;
+
+/// Shuffler of the Shuffle scheme
+pub struct Shuffler<Conf: Config> {
+    report_encryption_scheme: ReportEncryptionSchemeParameters<Conf>,
+    enc_pk: ReportEncryptionSchemePublicKey<Conf>,
+    enc_sk: ReportEncryptionSchemeSecretKey<Conf>,
+    server_enc_pk: ReportEncryptionSchemePublicKey<Conf>,
+    verifying_key: VerifyingKey<Conf>,
+    blanket_noise: Option<BlanketNoise>,
+}
+
+impl<Conf: Config> Shuffler<Conf> {
+    /// Create a new shuffler with the given system parameters, encryption public key of the server
+    /// and proof verification key.
+    pub fn new<R: Rng + CryptoRng, const GAMMA_BYTES: usize>(
+        parameters: &ParametersShuffle<Conf, GAMMA_BYTES>,
+        server_enc_pk: ReportEncryptionSchemePublicKey<Conf>,
+        verifying_key: VerifyingKey<Conf>,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        let (enc_pk, enc_sk) =
+            Conf::ReportEncryptionScheme::keygen(&parameters.report_encryption_scheme, rng)?;
+        Ok(Self {
+            report_encryption_scheme: parameters.report_encryption_scheme.clone(),
+            enc_pk,
+            enc_sk,
+            server_enc_pk,
+            verifying_key,
+            blanket_noise: parameters.blanket_noise().cloned(),
+        })
+    }
+
+    /// Get shuffler's encryption public key, under which the clients encrypt their messages
+    pub fn get_encryption_public_key(&self) -> ReportEncryptionSchemePublicKey<Conf> {
+        self.enc_pk.clone()
+    }
+
+    /// Given a client message, decrypt it and rerandomize its proof, returns the message that is
+    /// to be forwarded to the server, or `None` if the client message cannot be decrypted.
+    pub fn forward_message(
+        &self,
+        client_message: &[u8],
+        zkp_rng: &mut ZKPRng<Conf>,
+    ) -> Result<Option<Vec<u8>>, Error>
+    where
+        Proof<Conf>: CanonicalDeserialize,
+    {
+        // decrypt and deserialize client message
+        let client_message =
+            match EncryptedReportShuffle::<Conf>::deserialize_compressed(client_message)?
+                .decrypt(&self.report_encryption_scheme, &self.enc_sk)?
+            {
+                Some(client_message) => client_message,
+                None => return Ok(None),
+            };
+        let client_message = VerifiableRandomizationMessageShuffle::<Conf>::deserialize_compressed(
+            client_message.as_slice(),
+        )?;
+
+        // rerandomize proof and return message
+        let mut serialized_message = vec![];
+        VerifiableRandomizationMessageShuffle::<Conf> {
+            proof: Conf::ZKPScheme::rerandomize_proof(
+                &self.verifying_key,
+                &client_message.proof,
+                zkp_rng,
+            )?,
+            encrypted_report: client_message.encrypted_report,
+        }
+        .serialize_compressed(&mut serialized_message)?;
+        Ok(Some(serialized_message))
+    }
+
+    /// Create the dummy reports that are to be added to the given forwarded messages of an epoch,
+    /// which is empty if the parameters do not use blanket noise.
+    ///
+    /// Every dummy report is encrypted under the encryption public key of the server, and gets a
+    /// rerandomized proof of a random forwarded message (or a default proof if there are none).
+    pub fn dummy_messages(
+        &self,
+        forwarded_messages: &[Vec<u8>],
+        zkp_rng: &mut ZKPRng<Conf>,
+    ) -> Result<Vec<Vec<u8>>, Error>
+    where
+        Proof<Conf>: CanonicalDeserialize,
+    {
+        let dummies = self.blanket_noise.as_ref().map_or(0, |blanket_noise| {
+            blanket_noise.dummies(forwarded_messages.len())
+        });
+        (0..dummies as u32)
+            .map(|index| {
+                // encrypt dummy report for the server (independent of the input size)
+                let mut serialized_report = vec![];
+                ReportPayloadShuffle::<Conf, 0>::Dummy(DummyReportShuffle { index })
+                    .serialize_compressed(&mut serialized_report)?;
+                let encrypted_report = EncryptedReportShuffle::<Conf>::encrypt(
+                    &self.report_encryption_scheme,
+                    &self.server_enc_pk,
+                    &serialized_report,
+                    zkp_rng,
+                )?;
+
+                // rerandomize the proof of a random forwarded message
+                let proof = match forwarded_messages.choose(zkp_rng) {
+                    Some(forwarded_message) => Conf::ZKPScheme::rerandomize_proof(
+                        &self.verifying_key,
+                        &VerifiableRandomizationMessageShuffle::<Conf>::deserialize_compressed(
+                            forwarded_message.as_slice(),
+                        )?
+                        .proof,
+                        zkp_rng,
+                    )?,
+                    None => Proof::<Conf>::default(),
+                };
+
+                let mut serialized_message = vec![];
+                VerifiableRandomizationMessageShuffle::<Conf> {
+                    proof,
+                    encrypted_report,
+                }
+                .serialize_compressed(&mut serialized_message)?;
+                Ok(serialized_message)
+            })
+            .collect()
+    }
+
+    /// Given the client messages of an epoch, forward all messages that can be decrypted (see
+    /// `forward_message`) along with the dummy reports (see `dummy_messages`) in a random order.
+    pub fn forward(
+        &self,
+        client_messages: &[Vec<u8>],
+        zkp_rng: &mut ZKPRng<Conf>,
+    ) -> Result<Vec<Vec<u8>>, Error>
+    where
+        Proof<Conf>: CanonicalDeserialize,
+    {
+        let mut messages = client_messages
+            .iter()
+            .filter_map(|client_message| self.forward_message(client_message, zkp_rng).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+        messages.extend(self.dummy_messages(&messages, zkp_rng)?);
+        messages.shuffle(zkp_rng);
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circuits::test::{setup_shuffle, RrConf, RrConfG, ShuffleSetup};
+    use crate::circuits::CircuitShuffle;
+    use crate::client::ClientShuffle;
+    use crate::config::{BasicConfig, BasicConfigGadget};
+    use crate::server::ServerShuffle;
+    use ark_bls12_381::Bls12_381;
+    use ark_ed_on_bls12_381::constraints::EdwardsVar as JubJubVar;
+    use ark_ed_on_bls12_381::EdwardsProjective as JubJub;
+    use ark_groth16::Groth16;
+    use astro_float::BigFloat;
+    use num_bigint::BigUint;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    type Conf = BasicConfig<JubJub, ChaChaRng, Groth16<Bls12_381>, 32>;
+    type ConfG = BasicConfigGadget<JubJub, JubJubVar>;
+    const TIME_BYTES: usize = 2;
+    const GAMMA_BYTES: usize = 4;
+    const RANDOMNESS_BYTES: usize = 32;
+    type Circuit =
+        CircuitShuffle<Conf, ConfG, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 2, false>;
+
+    #[test]
+    fn groth16_forward_message_test() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+
+        // setup, where the encryption key pair of the server is generated here such that the
+        // forwarded report can be decrypted
+        let parameters = ParametersShuffle::<Conf, GAMMA_BYTES>::setup(
+            BigFloat::from_f64(0.25, 8 * GAMMA_BYTES),
+            &mut rng,
+        )
+        .unwrap();
+        let (proving_key, verifying_key) =
+            Circuit::keygen(parameters.clone(), &mut zkp_rng).unwrap();
+        let prf_eval_points = vec![rng.gen::<PRFSchemeInput<Conf>>()];
+        let (server_enc_pk, server_enc_sk) = <Conf as Config>::ReportEncryptionScheme::keygen(
+            &parameters.report_encryption_scheme,
+            &mut rng,
+        )
+        .unwrap();
+        let mut server =
+            ServerShuffle::<Conf, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 2, false>::new(
+                parameters.clone(),
+                verifying_key.clone(),
+                &mut rng,
+            )
+            .unwrap();
+        let shuffler =
+            Shuffler::new(&parameters, server_enc_pk, verifying_key.clone(), &mut rng).unwrap();
+        let (client_sig_pk, client_sig_sk) = <Conf as Config>::ClientSignatureScheme::keygen(
+            &parameters.client_signature_scheme,
+            &mut rng,
+        )
+        .unwrap();
+        let mut client =
+            ClientShuffle::<Conf, 1, TIME_BYTES, GAMMA_BYTES, RANDOMNESS_BYTES, 2, false>::new(
+                parameters.clone(),
+                server.get_signature_public_key(),
+                server_enc_pk,
+                shuffler.get_encryption_public_key(),
+                client_sig_pk,
+                proving_key,
+            )
+            .unwrap();
+
+        // client report with a Groth16 proof
+        let time_bounds = ([0; TIME_BYTES], [1; TIME_BYTES]);
+        let client_message = client.generate_randomness_create(&mut rng).unwrap();
+        let server_message = server
+            .generate_randomness_create(&client_message, time_bounds.1, &mut rng)
+            .unwrap();
+        assert!(client.generate_randomness_verify(&server_message).unwrap());
+        let input_value_signature = <Conf as Config>::ClientSignatureScheme::sign(
+            &parameters.client_signature_scheme,
+            &client_sig_sk,
+            &AuthenticatedInput::message(&[1], &time_bounds.1),
+            &mut rng,
+        )
+        .unwrap();
+        let client_message = client
+            .verifiable_randomization_create::<ConfG>(
+                time_bounds,
+                time_bounds.1,
+                BigUint::from(1u8),
+                input_value_signature,
+                &prf_eval_points,
+                &mut zkp_rng,
+                false,
+            )
+            .unwrap();
+
+        // the shuffler forwards the report with a different proof
+        let forwarded_message = shuffler
+            .forward_message(&client_message, &mut zkp_rng)
+            .unwrap()
+            .unwrap();
+        let client_message =
+            EncryptedReportShuffle::<Conf>::deserialize_compressed(client_message.as_slice())
+                .unwrap()
+                .decrypt(&shuffler.report_encryption_scheme, &shuffler.enc_sk)
+                .unwrap()
+                .unwrap();
+        let client_message = VerifiableRandomizationMessageShuffle::<Conf>::deserialize_compressed(
+            client_message.as_slice(),
+        )
+        .unwrap();
+        let forwarded_message =
+            VerifiableRandomizationMessageShuffle::<Conf>::deserialize_compressed(
+                forwarded_message.as_slice(),
+            )
+            .unwrap();
+        let mut client_proof = vec![];
+        client_message
+            .proof
+            .serialize_compressed(&mut client_proof)
+            .unwrap();
+        let mut forwarded_proof = vec![];
+        forwarded_message
+            .proof
+            .serialize_compressed(&mut forwarded_proof)
+            .unwrap();
+        assert_ne!(client_proof, forwarded_proof);
+
+        // the rerandomized proof is accepted for the report, but not for another LDP value
+        let report = match ReportPayloadShuffle::<Conf, 1>::deserialize_compressed(
+            forwarded_message
+                .encrypted_report
+                .decrypt(&parameters.report_encryption_scheme, &server_enc_sk)
+                .unwrap()
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap()
+        {
+            ReportPayloadShuffle::Client(report) => report,
+            ReportPayloadShuffle::Dummy(_) => panic!("The forwarded report should not be a dummy."),
+        };
+        for (ldp_value, accepted) in [(report.ldp_value, true), (3 - report.ldp_value, false)] {
+            assert_eq!(
+                Circuit::verify(
+                    &verifying_key,
+                    &forwarded_message.proof,
+                    ldp_value,
+                    time_bounds,
+                    &server.get_signature_public_key(),
+                    &prf_eval_points,
+                    &report.nullifier,
+                    None,
+                    None,
+                    &mut zkp_rng,
+                )
+                .unwrap(),
+                accepted
+            );
+        }
+    }
+
+    #[test]
+    fn shuffle_shuffler_test() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut zkp_rng = ChaChaRng::seed_from_u64(1);
+        let mut te_rng = ChaChaRng::seed_from_u64(2);
+
+        // setup
+        let parameters =
+            ParametersShuffle::setup(BigFloat::from_f64(0.5, 8 * GAMMA_BYTES), &mut rng).unwrap();
+        let ShuffleSetup {
+            mut server,
+            shuffler,
+            clients,
+            client_keys,
+            prf_eval_points,
+        } = setup_shuffle(&parameters, 3, &mut rng);
+        let time_bounds = ([0; TIME_BYTES], [1; TIME_BYTES]);
+
+        // reports of several clients in the same epoch
+        let mut client_messages = clients
+            .into_iter()
+            .zip(&client_keys)
+            .map(|(mut client, (_, client_sig_sk))| {
+                let client_message = client.generate_randomness_create(&mut rng)?;
+                let server_message =
+                    server.generate_randomness_create(&client_message, time_bounds.1, &mut rng)?;
+                assert!(client.generate_randomness_verify(&server_message)?);
+                let input_value_signature = <RrConf as Config>::ClientSignatureScheme::sign(
+                    &parameters.client_signature_scheme,
+                    client_sig_sk,
+                    &AuthenticatedInput::message(&[3], &time_bounds.1),
+                    &mut te_rng,
+                )?;
+                client.verifiable_randomization_create::<RrConfG>(
+                    time_bounds,
+                    time_bounds.1,
+                    BigUint::from(3u8),
+                    input_value_signature,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+
+        // a message that cannot be decrypted is dropped by the shuffler
+        let mut tampered_message = client_messages[0].clone();
+        *tampered_message.last_mut().unwrap() ^= 1;
+        client_messages.push(tampered_message);
+        let forwarded_messages = shuffler.forward(&client_messages, &mut zkp_rng).unwrap();
+        assert_eq!(forwarded_messages.len(), 3);
+
+        // the forwarded messages (with rerandomized proofs) are accepted by the server
+        for forwarded_message in &forwarded_messages {
+            assert!(!client_messages.contains(forwarded_message));
+            assert_eq!(forwarded_message.len(), forwarded_messages[0].len());
+            let (accepted, _) = server
+                .verifiable_randomization_verify::<RrConfG>(
+                    forwarded_message,
+                    time_bounds,
+                    &prf_eval_points,
+                    &mut zkp_rng,
+                    false,
+                )
+                .unwrap();
+            assert!(accepted);
+        }
+    }
+}